@@ -0,0 +1,23 @@
+//! Inference on wasm32, from an image embedded with `include_net!`.
+//!
+//! ```sh
+//! cargo build --release --example wasm --target wasm32-unknown-unknown
+//! ```
+//!
+//! The resulting module exports `evaluate`, which can be called from
+//! JavaScript once instantiated with no imports.
+
+use goober::{activation::ReLU, include_net, layer::DenseConnected, FeedForwardNetwork, Vector};
+
+type Net = DenseConnected<ReLU, 4, 2>;
+
+/// Output `idx` of the network on the input `[a, b, c, d]`.
+#[no_mangle]
+pub extern "C" fn evaluate(a: f32, b: f32, c: f32, d: f32, idx: usize) -> f32 {
+    let net: &Net = unsafe { include_net!(Net, "../tests/data/dense.bin") };
+    net.out(&Vector::from_raw([a, b, c, d]))[idx]
+}
+
+fn main() {
+    println!("{}", evaluate(1.0, 1.0, 1.0, 1.0, 1));
+}
@@ -1,33 +1,43 @@
+use crate::float::Real;
+
 pub trait Activation: Copy {
-    fn activate(x: f32) -> f32;
+    /// Identifies the activation in exported networks.
+    /// - Custom activations are `u8::MAX` unless specified.
+    const ID: u8 = u8::MAX;
+
+    fn activate<R: Real>(x: R) -> R;
 
-    fn derivative(x: f32) -> f32;
+    fn derivative<R: Real>(x: R) -> R;
 }
 
 #[derive(Clone, Copy)]
 pub struct Identity;
 impl Activation for Identity {
-    fn activate(x: f32) -> f32 {
+    const ID: u8 = 0;
+
+    fn activate<R: Real>(x: R) -> R {
         x
     }
 
-    fn derivative(_: f32) -> f32 {
-        1.0
+    fn derivative<R: Real>(_: R) -> R {
+        R::ONE
     }
 }
 
 #[derive(Clone, Copy)]
 pub struct ReLU;
 impl Activation for ReLU {
-    fn activate(x: f32) -> f32 {
-        x.max(0.0)
+    const ID: u8 = 1;
+
+    fn activate<R: Real>(x: R) -> R {
+        x.max(R::ZERO)
     }
 
-    fn derivative(x: f32) -> f32 {
-        if x > 0.0 {
-            1.0
+    fn derivative<R: Real>(x: R) -> R {
+        if x > R::ZERO {
+            R::ONE
         } else {
-            0.0
+            R::ZERO
         }
     }
 }
@@ -35,16 +45,18 @@ impl Activation for ReLU {
 #[derive(Clone, Copy)]
 pub struct SCReLU;
 impl Activation for SCReLU {
-    fn activate(x: f32) -> f32 {
-        let clamped = x.clamp(0.0, 1.0);
+    const ID: u8 = 2;
+
+    fn activate<R: Real>(x: R) -> R {
+        let clamped = x.clamp(R::ZERO, R::ONE);
         clamped * clamped
     }
 
-    fn derivative(x: f32) -> f32 {
-        if 0.0 < x && x < 1.0 {
-            2.0 * x
+    fn derivative<R: Real>(x: R) -> R {
+        if R::ZERO < x && x < R::ONE {
+            (R::ONE + R::ONE) * x
         } else {
-            0.0
+            R::ZERO
         }
     }
 }
@@ -52,12 +64,14 @@ impl Activation for SCReLU {
 #[derive(Clone, Copy)]
 pub struct Tanh;
 impl Activation for Tanh {
-    fn activate(x: f32) -> f32 {
+    const ID: u8 = 3;
+
+    fn activate<R: Real>(x: R) -> R {
         x.tanh()
     }
 
-    fn derivative(x: f32) -> f32 {
+    fn derivative<R: Real>(x: R) -> R {
         let t = x.tanh();
-        1.0 - t * t
+        R::ONE - t * t
     }
 }
@@ -0,0 +1,168 @@
+//! Augmentations of the samples of each batch, applied by the
+//! [`Trainer`](crate::trainer::Trainer) as each batch is assembled, see
+//! [`with_augmentation`](crate::trainer::Trainer::with_augmentation).
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh},
+//! #     augment::{FeatureDropout, Mixup},
+//! #     layer::{DenseConnected, SparseConnected},
+//! #     trainer::Trainer,
+//! #     FeedForwardNetwork,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # let trainer = Trainer::new(Net::boxed_and_zeroed(), Vec::new());
+//! // drop 10% of the features of each position
+//! let trainer = trainer.with_augmentation(FeatureDropout::new(0.1));
+//!
+//! // or blend each sample of dense inputs with another
+//! # let trainer = Trainer::new(DenseConnected::<Tanh, 8, 1>::boxed_and_zeroed(), Vec::new());
+//! let trainer = trainer.with_augmentation(Mixup::new(0.2));
+//! ```
+
+use alloc::vec::Vec;
+
+use libm::{logf, powf, sqrtf};
+
+use crate::{Rand, SparseVector, Vector, WeightedSparseVector};
+
+/// Changes the samples of a batch, of inputs of type `I` and targets of
+/// type `O`, in place, such as to make training more robust.
+/// - Implemented by closures taking the batch and a generator, and by
+///   pairs of augmentations, applied one after the other.
+pub trait Augment<I, O> {
+    /// Augments the samples of `batch`, drawing from `rng`.
+    fn augment(&mut self, batch: &mut [(I, O)], rng: &mut Rand);
+}
+
+impl<I, O, F: FnMut(&mut [(I, O)], &mut Rand)> Augment<I, O> for F {
+    fn augment(&mut self, batch: &mut [(I, O)], rng: &mut Rand) {
+        self(batch, rng)
+    }
+}
+
+impl<I, O, A: Augment<I, O>, B: Augment<I, O>> Augment<I, O> for (A, B) {
+    fn augment(&mut self, batch: &mut [(I, O)], rng: &mut Rand) {
+        self.0.augment(batch, rng);
+        self.1.augment(batch, rng);
+    }
+}
+
+/// Replaces each sample with a blend of it and another sample of the
+/// batch, of both inputs and targets, by a fraction drawn from the
+/// symmetric beta distribution of parameter `alpha`, as in mixup.
+/// - Small `alpha`s keep samples close to one of the pair.
+#[derive(Clone, Copy, Debug)]
+pub struct Mixup {
+    pub alpha: f32,
+}
+
+impl Mixup {
+    pub fn new(alpha: f32) -> Self {
+        Self { alpha }
+    }
+}
+
+impl<const M: usize, const N: usize> Augment<Vector<M>, Vector<N>> for Mixup {
+    fn augment(&mut self, batch: &mut [(Vector<M>, Vector<N>)], rng: &mut Rand) {
+        let mut partners: Vec<usize> = (0..batch.len()).collect();
+        for i in (1..partners.len()).rev() {
+            let j = (rng.rand_u64() % (i as u64 + 1)) as usize;
+            partners.swap(i, j);
+        }
+
+        let original = batch.to_vec();
+        for (sample, &j) in batch.iter_mut().zip(&partners) {
+            let t = beta(rng, self.alpha);
+            let (input, target) = original[j];
+            sample.0 = t * sample.0 + (1.0 - t) * input;
+            sample.1 = t * sample.1 + (1.0 - t) * target;
+        }
+    }
+}
+
+/// Removes each feature of sparse inputs with probability `rate`,
+/// without rescaling the rest, so that the network does not come to
+/// rely on any few features.
+#[derive(Clone, Copy, Debug)]
+pub struct FeatureDropout {
+    pub rate: f32,
+}
+
+impl FeatureDropout {
+    pub fn new(rate: f32) -> Self {
+        Self { rate }
+    }
+}
+
+impl<O> Augment<SparseVector, O> for FeatureDropout {
+    fn augment(&mut self, batch: &mut [(SparseVector, O)], rng: &mut Rand) {
+        for (input, _) in batch {
+            let mut kept = SparseVector::with_capacity(input.len());
+            for &feature in input.iter() {
+                if rng.rand_f32() >= self.rate {
+                    kept.push(feature);
+                }
+            }
+            if input.is_sorted() {
+                kept.sort_dedup();
+            }
+            *input = kept;
+        }
+    }
+}
+
+impl<O> Augment<WeightedSparseVector, O> for FeatureDropout {
+    fn augment(&mut self, batch: &mut [(WeightedSparseVector, O)], rng: &mut Rand) {
+        for (input, _) in batch {
+            let mut kept = WeightedSparseVector::with_capacity(input.len());
+            for &(feature, value) in input.iter() {
+                if rng.rand_f32() >= self.rate {
+                    kept.push(feature, value);
+                }
+            }
+            *input = kept;
+        }
+    }
+}
+
+/// Drawn from the gamma distribution of shape `shape` and scale 1,
+/// by the method of Marsaglia and Tsang.
+fn gamma(rng: &mut Rand, shape: f32) -> f32 {
+    if shape < 1.0 {
+        let u = 1.0 - rng.rand_f32();
+        return gamma(rng, shape + 1.0) * powf(u, 1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / sqrtf(9.0 * d);
+    loop {
+        let x = rng.rand_normal();
+        let v = 1.0 + c * x;
+        if v <= 0.0 {
+            continue;
+        }
+
+        let v = v * v * v;
+        let u = 1.0 - rng.rand_f32();
+        if logf(u) < 0.5 * x * x + d - d * v + d * logf(v) {
+            return d * v;
+        }
+    }
+}
+
+/// Drawn from the beta distribution of parameters `alpha` and `alpha`.
+fn beta(rng: &mut Rand, alpha: f32) -> f32 {
+    let x = gamma(rng, alpha);
+    let y = gamma(rng, alpha);
+    // both underflow for tiny `alpha`
+    if x + y > 0.0 {
+        x / (x + y)
+    } else {
+        0.5
+    }
+}
@@ -0,0 +1,483 @@
+//! Hooks into the [`Trainer`](crate::trainer::Trainer) loop, so that
+//! logging, snapshots and evaluation can be composed without forking it.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+};
+
+use crate::{checkpoint::write_atomic, metrics::Metrics, FeedForwardNetwork};
+
+/// State of training when a [`Callback`] is invoked.
+pub struct Progress<'a, T> {
+    /// Number of optimiser steps taken.
+    pub step: u64,
+    /// Number of complete epochs taken.
+    pub epoch: u64,
+    pub batches_per_epoch: u64,
+    /// Number of samples in each full batch.
+    pub batch_size: usize,
+    /// Learning rate for the next step.
+    pub lr: f32,
+    /// Mean loss of the batch or epoch just completed.
+    pub loss: f32,
+    /// Evaluation on the validation set, at the end of an epoch
+    /// of a trainer which has one.
+    pub validation: Option<Metrics>,
+    pub net: &'a T,
+}
+
+/// Whether training should carry on after a [`Callback`] returns.
+#[must_use]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Control {
+    #[default]
+    Continue,
+    /// Stops training at the end of the current batch, after
+    /// the remaining callbacks for it have been invoked.
+    Stop,
+}
+
+/// Invoked by the trainer at each stage of training, in the order
+/// the callbacks were added.
+pub trait Callback<T> {
+    fn on_batch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        let _ = progress;
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        let _ = progress;
+        Control::Continue
+    }
+
+    /// Called after a checkpoint has been written to `path`.
+    fn on_checkpoint(&mut self, progress: &Progress<'_, T>, path: &Path) {
+        let _ = (progress, path);
+    }
+
+    /// Called once [`run`](crate::trainer::Trainer::run) finishes or is
+    /// stopped, with the network so that it can be adjusted before
+    /// being handed back.
+    fn on_train_end(&mut self, net: &mut T) {
+        let _ = net;
+    }
+}
+
+/// The loss on the validation set of the trainer, see
+/// [`with_validation`](crate::trainer::Trainer::with_validation), as the
+/// metric of [`EarlyStopping`] and [`BestModel`] unless overridden.
+/// - Computed once at the end of each epoch, and shared with every
+///   other callback, rather than running the network again.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Validation;
+
+/// Stops training once a validation metric, where lower is better, has
+/// not improved on the best so far by more than `min_delta` for more
+/// than `patience` epochs, then restores the weights with the best
+/// metric.
+/// - The metric is the [`Validation`] loss, or is computed from the
+///   network by the closure passed to [`with_metric`](Self::with_metric),
+///   at the end of each epoch.
+/// - Epochs without a validation loss are not counted.
+pub struct EarlyStopping<M = Validation> {
+    pub patience: u32,
+    pub min_delta: f32,
+    /// Whether to restore the best weights when training ends.
+    pub restore_best: bool,
+    metric: M,
+    best: f32,
+    best_epoch: u64,
+    best_weights: Option<Vec<u8>>,
+    bad: u32,
+}
+
+impl EarlyStopping {
+    pub fn new(patience: u32) -> Self {
+        Self::with_metric(patience, Validation)
+    }
+}
+
+impl<M> EarlyStopping<M> {
+    /// Stops on `metric`, computed from the network, in
+    /// place of the [`Validation`] loss.
+    pub fn with_metric(patience: u32, metric: M) -> Self {
+        Self {
+            patience,
+            min_delta: 0.0,
+            restore_best: true,
+            metric,
+            best: f32::INFINITY,
+            best_epoch: 0,
+            best_weights: None,
+            bad: 0,
+        }
+    }
+
+    /// Best metric observed so far.
+    pub fn best(&self) -> f32 {
+        self.best
+    }
+
+    /// Epoch at the end of which the best metric was observed.
+    pub fn best_epoch(&self) -> u64 {
+        self.best_epoch
+    }
+
+    fn observe<T: FeedForwardNetwork>(
+        &mut self,
+        metric: f32,
+        progress: &Progress<'_, T>,
+    ) -> Control {
+        if metric < self.best - self.min_delta {
+            self.best = metric;
+            self.best_epoch = progress.epoch;
+            self.bad = 0;
+            if self.restore_best {
+                self.best_weights = Some(progress.net.save_bytes());
+            }
+            return Control::Continue;
+        }
+
+        self.bad += 1;
+        if self.bad > self.patience {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+
+    fn restore<T: FeedForwardNetwork>(&self, net: &mut T) {
+        if let Some(weights) = self.best_weights.as_deref() {
+            net.load_bytes(weights)
+                .expect("weights were saved from the same network");
+        }
+    }
+}
+
+impl<T: FeedForwardNetwork> Callback<T> for EarlyStopping {
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        match progress.validation {
+            Some(metrics) => self.observe(metrics.loss, progress),
+            None => Control::Continue,
+        }
+    }
+
+    fn on_train_end(&mut self, net: &mut T) {
+        self.restore(net);
+    }
+}
+
+impl<T: FeedForwardNetwork, M: FnMut(&T) -> f32> Callback<T> for EarlyStopping<M> {
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        let metric = (self.metric)(progress.net);
+        self.observe(metric, progress)
+    }
+
+    fn on_train_end(&mut self, net: &mut T) {
+        self.restore(net);
+    }
+}
+
+/// Saves the network to `path` with [`save`](FeedForwardNetwork::save)
+/// whenever a validation metric, where lower is better, improves on the
+/// best so far, so that the best network survives however training ends.
+/// - The metric is the [`Validation`] loss, at the end of each epoch, or
+///   is computed from the network by the closure passed to
+///   [`with_metric`](Self::with_metric), every `every` steps.
+/// - Saves are atomic, see [`write_atomic`].
+/// - Failing to save panics, rather than silently losing the best network.
+pub struct BestModel<M = Validation> {
+    pub path: PathBuf,
+    pub every: u64,
+    metric: M,
+    best: f32,
+    best_step: u64,
+}
+
+impl BestModel {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self::with_metric(path, 1, Validation)
+    }
+}
+
+impl<M> BestModel<M> {
+    /// Saves on `metric`, computed from the network every `every`
+    /// steps, in place of the [`Validation`] loss.
+    pub fn with_metric(path: impl Into<PathBuf>, every: u64, metric: M) -> Self {
+        Self {
+            path: path.into(),
+            every: every.max(1),
+            metric,
+            best: f32::INFINITY,
+            best_step: 0,
+        }
+    }
+
+    /// Best metric observed so far.
+    pub fn best(&self) -> f32 {
+        self.best
+    }
+
+    /// Step at which the best network was saved.
+    pub fn best_step(&self) -> u64 {
+        self.best_step
+    }
+
+    fn observe<T: FeedForwardNetwork>(&mut self, metric: f32, progress: &Progress<'_, T>) {
+        if metric < self.best {
+            self.best = metric;
+            self.best_step = progress.step;
+
+            if let Err(err) = write_atomic(&self.path, &progress.net.save_bytes()) {
+                panic!(
+                    "failed to save best network to {}: {err}",
+                    self.path.display()
+                );
+            }
+        }
+    }
+}
+
+impl<T: FeedForwardNetwork> Callback<T> for BestModel {
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        if let Some(metrics) = progress.validation {
+            self.observe(metrics.loss, progress);
+        }
+
+        Control::Continue
+    }
+}
+
+impl<T: FeedForwardNetwork, M: FnMut(&T) -> f32> Callback<T> for BestModel<M> {
+    fn on_batch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        if progress.step.is_multiple_of(self.every) {
+            let metric = (self.metric)(progress.net);
+            self.observe(metric, progress);
+        }
+
+        Control::Continue
+    }
+}
+
+/// Sets a parameter of the data, such as the difficulty of the samples
+/// kept by a filter, from how far training has come, so that training
+/// starts on easier samples and moves on to harder ones.
+/// - `schedule` maps the number of epochs taken, counting each batch as a
+///   fraction of an epoch, to the parameter, updated after every batch.
+/// - The parameter is read through a [`Stage`], which can be moved into
+///   the filter of a [`Loader`](crate::stream::Loader) on another thread,
+///   or the weights of a [`Weighted`](crate::dataset::Weighted) dataset.
+///
+/// ```no_run
+/// # use goober::{
+/// #     activation::{ReLU, Tanh}, callback::Curriculum, dataset::{FeatureEncoder, Record},
+/// #     layer::{DenseConnected, SparseConnected}, stream::Loader, FeedForwardNetwork,
+/// #     SparseVector, Vector,
+/// # };
+/// # #[derive(FeedForwardNetwork)]
+/// # pub struct Net {
+/// #     l1: SparseConnected<ReLU, 768, 32>,
+/// #     l2: DenseConnected<Tanh, 32, 1>,
+/// # }
+/// # struct Board;
+/// # impl Board {
+/// #     fn in_check(&self) -> bool { false }
+/// #     fn ply(&self) -> u32 { 0 }
+/// #     fn tactics(&self) -> f32 { 0.0 }
+/// #     fn game_id(&self) -> u64 { 0 }
+/// # }
+/// # struct PackedBoard;
+/// # impl Record for PackedBoard {
+/// #     type Input = Board;
+/// #     type Output = Vector<1>;
+/// #     const SIZE: usize = 32;
+/// #     fn decode(_: &[u8]) -> (Board, Vector<1>) { (Board, Vector::from_raw([0.0])) }
+/// # }
+/// # struct HalfKp;
+/// # impl FeatureEncoder<Board> for HalfKp {
+/// #     type Input = SparseVector;
+/// #     fn encode(&self, _: &Board) -> SparseVector { SparseVector::with_capacity(0) }
+/// # }
+/// # fn main() -> Result<(), std::io::Error> {
+/// # let mut trainer = goober::trainer::Trainer::new(Net::boxed_and_zeroed(), Vec::new());
+/// # let epochs = 10;
+/// let curriculum = Curriculum::new(|epochs| (epochs / 10.0).min(1.0));
+/// let stage = curriculum.stage();
+/// let mut trainer = trainer.with_callback(curriculum);
+///
+/// for epoch in 0..epochs {
+///     // quiet positions first, then more and more tactical ones
+///     let stage = stage.clone();
+///     let stream = Loader::<PackedBoard>::new(16)
+///         .with_encoder(HalfKp)
+///         .with_filter(move |board, _| board.tactics() <= stage.get())
+///         .open("positions.bin")?;
+///     trainer.run_stream(stream)?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Curriculum<F> {
+    schedule: F,
+    stage: Stage,
+}
+
+/// The parameter set by a [`Curriculum`], shared between threads.
+#[derive(Clone, Debug)]
+pub struct Stage(Arc<AtomicU32>);
+
+impl Stage {
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl<F: FnMut(f32) -> f32> Curriculum<F> {
+    /// Sets the parameter to `schedule(0.0)` until the first batch ends.
+    pub fn new(mut schedule: F) -> Self {
+        let stage = Stage(Arc::new(AtomicU32::new(schedule(0.0).to_bits())));
+        Self { schedule, stage }
+    }
+
+    /// The parameter, as it is updated.
+    pub fn stage(&self) -> Stage {
+        self.stage.clone()
+    }
+
+    fn update<T>(&mut self, progress: &Progress<'_, T>) {
+        let epochs = match progress.batches_per_epoch {
+            0 => progress.epoch as f32,
+            batches => progress.step as f32 / batches as f32,
+        };
+        self.stage.set((self.schedule)(epochs));
+    }
+}
+
+impl<T, F: FnMut(f32) -> f32> Callback<T> for Curriculum<F> {
+    fn on_batch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        self.update(progress);
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        self.update(progress);
+        Control::Continue
+    }
+}
+
+type Column<T> = (String, Box<dyn FnMut(&T) -> f32>);
+
+/// Appends a row of `step`, `epoch`, `lr`, `loss` and `val_loss`, along
+/// with any extra columns, to a CSV file at the end of each epoch, and
+/// optionally every so many steps, so runs can be plotted.
+/// - A header is written when the file is empty, so a resumed run
+///   continues the same file.
+/// - Columns which are not computed for a row, such as `val_loss` for
+///   a trainer without a validation set, are left empty.
+/// - Failing to write panics.
+pub struct CsvLogger<T> {
+    out: BufWriter<File>,
+    delimiter: char,
+    every: Option<u64>,
+    columns: Vec<Column<T>>,
+    header: bool,
+}
+
+impl<T> CsvLogger<T> {
+    pub fn new(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let header = file.metadata()?.len() == 0;
+
+        Ok(Self {
+            out: BufWriter::new(file),
+            delimiter: ',',
+            every: None,
+            columns: Vec::new(),
+            header,
+        })
+    }
+
+    /// Separates columns with `delimiter`, such as `'\t'` for TSV.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Also logs a row after every `every` steps.
+    pub fn with_every(mut self, every: u64) -> Self {
+        self.every = Some(every.max(1));
+        self
+    }
+
+    /// Adds a column named `name`, computed from the
+    /// network by `metric` at the end of each epoch.
+    pub fn with_column<M: FnMut(&T) -> f32 + 'static>(mut self, name: &str, metric: M) -> Self {
+        self.columns.push((name.to_string(), Box::new(metric)));
+        self
+    }
+
+    fn write_row(&mut self, progress: &Progress<'_, T>, epoch_end: bool) -> io::Result<()> {
+        let d = self.delimiter;
+
+        if self.header {
+            self.header = false;
+            write!(self.out, "step{d}epoch{d}lr{d}loss{d}val_loss")?;
+            for (name, _) in &self.columns {
+                write!(self.out, "{d}{name}")?;
+            }
+            writeln!(self.out)?;
+        }
+
+        write!(
+            self.out,
+            "{}{d}{}{d}{}{d}{}{d}",
+            progress.step, progress.epoch, progress.lr, progress.loss
+        )?;
+        if let Some(validation) = progress.validation {
+            write!(self.out, "{}", validation.loss)?;
+        }
+
+        for (_, metric) in &mut self.columns {
+            write!(self.out, "{d}")?;
+            if epoch_end {
+                write!(self.out, "{}", metric(progress.net))?;
+            }
+        }
+
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+
+    fn log(&mut self, progress: &Progress<'_, T>, epoch_end: bool) {
+        if let Err(err) = self.write_row(progress, epoch_end) {
+            panic!("failed to write metrics: {err}");
+        }
+    }
+}
+
+impl<T> Callback<T> for CsvLogger<T> {
+    fn on_batch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        if self
+            .every
+            .is_some_and(|every| progress.step.is_multiple_of(every))
+        {
+            self.log(progress, false);
+        }
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        self.log(progress, true);
+        Control::Continue
+    }
+}
@@ -0,0 +1,294 @@
+//! Checkpoints of training, holding everything needed to resume it
+//! exactly as if it had never been interrupted.
+//!
+//! All values are little-endian, and a checkpoint is laid out as:
+//! - magic `b"GBCK"`
+//! - `u32` format version
+//! - `u64` number of optimiser steps taken
+//! - `u64` state of the data order generator, see [`Rand::state`]
+//! - `u32` length of the scheduler state, then the state itself,
+//!   see [`Scheduler::state`]
+//! - the network, then the Adam momentum and velocity, each as a
+//!   `u64` length followed by a network saved with
+//!   [`save_bytes`](FeedForwardNetwork::save_bytes)
+//! - `u32` CRC-32 of everything before it
+//!
+//! Checkpoints are written atomically, so a crash while saving leaves
+//! any previous checkpoint at the same path intact, and a
+//! [`CheckpointManager`] keeps a rolling window of them in a directory.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{crc32, schedule::Scheduler, FeedForwardNetwork, LoadError, Rand};
+
+const MAGIC: [u8; 4] = *b"GBCK";
+
+const VERSION: u32 = 2;
+
+/// Network along with the state of its optimiser and learning rate schedule.
+pub struct Checkpoint<T, S> {
+    pub net: Box<T>,
+    pub momentum: Box<T>,
+    pub velocity: Box<T>,
+    /// Number of optimiser steps taken.
+    pub step: u64,
+    pub scheduler: S,
+    /// Generator for shuffling and sampling training data, so that
+    /// a resumed run sees the data in the same order.
+    pub rng: Rand,
+}
+
+impl<T: FeedForwardNetwork, S: Scheduler> Checkpoint<T, S> {
+    /// Starts training `net` from scratch.
+    pub fn new(net: Box<T>, scheduler: S) -> Self {
+        Self {
+            net,
+            momentum: T::boxed_and_zeroed(),
+            velocity: T::boxed_and_zeroed(),
+            step: 0,
+            scheduler,
+            rng: Rand::default(),
+        }
+    }
+
+    /// Learning rate for the next step.
+    pub fn lr(&self) -> f32 {
+        self.scheduler.lr(self.step)
+    }
+
+    /// Takes one step of Adam with the scheduled learning rate.
+    pub fn adam(&mut self, grad: &T::Grad, adj: f32) {
+        let lr = self.lr();
+        self.net
+            .adam(grad, &mut self.momentum, &mut self.velocity, adj, lr);
+        self.step += 1;
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        span!(DEBUG, "checkpoint_bytes", step = self.step);
+        let mut out = Vec::new();
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&self.step.to_le_bytes());
+        out.extend_from_slice(&self.rng.state().to_le_bytes());
+
+        let state = self.scheduler.state();
+        out.extend_from_slice(&(state.len() as u32).to_le_bytes());
+        out.extend_from_slice(&state);
+
+        for net in [&self.net, &self.momentum, &self.velocity] {
+            let bytes = net.save_bytes();
+            out.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            out.extend_from_slice(&bytes);
+        }
+
+        out.extend_from_slice(&crc32(&out).to_le_bytes());
+        out
+    }
+
+    /// Writes the checkpoint atomically, see [`write_atomic`].
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        write_atomic(Path::new(path), &self.to_bytes())
+    }
+
+    /// Restores a checkpoint written by [`to_bytes`](Self::to_bytes),
+    /// leaving `self` unchanged if it fails.
+    pub fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), LoadError> {
+        if bytes.len() < 32 {
+            return Err(LoadError::Truncated);
+        }
+
+        let (body, crc) = bytes.split_at(bytes.len() - 4);
+
+        if body[..4] != MAGIC {
+            return Err(LoadError::BadMagic);
+        }
+
+        let version = u32::from_le_bytes(body[4..8].try_into().unwrap());
+        if version != VERSION {
+            return Err(LoadError::UnsupportedVersion(version));
+        }
+
+        if crc32(body).to_le_bytes() != crc {
+            return Err(LoadError::BadChecksum);
+        }
+
+        let mut reader = Reader {
+            bytes: body,
+            pos: 8,
+        };
+        let step = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+        let rng = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+
+        let len = u32::from_le_bytes(reader.take(4)?.try_into().unwrap());
+        let state = reader.take(len as usize)?;
+
+        let mut nets = [(); 3].map(|_| T::boxed_and_zeroed());
+        for net in &mut nets {
+            let len = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+            net.load_bytes(reader.take(len as usize)?)?;
+        }
+
+        if reader.pos != body.len() {
+            return Err(LoadError::Malformed("trailing bytes".to_string()));
+        }
+
+        self.scheduler
+            .set_state(state)
+            .map_err(LoadError::Malformed)?;
+
+        let [net, momentum, velocity] = nets;
+        self.net = net;
+        self.momentum = momentum;
+        self.velocity = velocity;
+        self.step = step;
+        self.rng = Rand::with_seed(rng);
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, path: &str) -> Result<(), LoadError> {
+        self.load_bytes(&std::fs::read(path)?)
+    }
+}
+
+/// Writes `bytes` to a temporary file alongside `path`, syncs it to
+/// disk, then renames it over `path`, so that `path` always holds
+/// either its previous contents or all of `bytes`.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(&tmp, path)?;
+
+    // persist the rename itself, where directories can be synced
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+const BEST: &str = "best.ckpt";
+
+/// Saves checkpoints of a run into a directory as `step-<step>.ckpt`,
+/// keeping only the most recent `keep`, along with a separately tagged
+/// `best.ckpt` which is never rotated out.
+pub struct CheckpointManager {
+    dir: PathBuf,
+    keep: usize,
+}
+
+impl CheckpointManager {
+    /// Manages checkpoints in `dir`, creating it if needed.
+    /// - `keep` is clamped to at least one.
+    pub fn new(dir: impl Into<PathBuf>, keep: usize) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            keep: keep.max(1),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Saves `ckpt` under its step, then deletes all but the most
+    /// recent `keep` checkpoints, returning the path written.
+    pub fn save<T: FeedForwardNetwork, S: Scheduler>(
+        &self,
+        ckpt: &Checkpoint<T, S>,
+    ) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!("step-{}.ckpt", ckpt.step));
+        write_atomic(&path, &ckpt.to_bytes())?;
+
+        let saved = self.checkpoints()?;
+        let stale = saved.len().saturating_sub(self.keep);
+        for (_, old) in &saved[..stale] {
+            fs::remove_file(old)?;
+        }
+
+        Ok(path)
+    }
+
+    /// Saves `ckpt` as the best checkpoint, replacing any previous one.
+    pub fn save_best<T: FeedForwardNetwork, S: Scheduler>(
+        &self,
+        ckpt: &Checkpoint<T, S>,
+    ) -> io::Result<PathBuf> {
+        let path = self.dir.join(BEST);
+        write_atomic(&path, &ckpt.to_bytes())?;
+        Ok(path)
+    }
+
+    /// Saves `net` alone, with [`save`](FeedForwardNetwork::save), to
+    /// `name` in the directory, replacing any earlier network there,
+    /// such as for weights kept alongside the checkpoints.
+    pub fn save_net<T: FeedForwardNetwork>(&self, name: &str, net: &T) -> io::Result<PathBuf> {
+        let path = self.dir.join(name);
+        write_atomic(&path, &net.save_bytes())?;
+        Ok(path)
+    }
+
+    /// Step and path of every rotated checkpoint, oldest first.
+    pub fn checkpoints(&self) -> io::Result<Vec<(u64, PathBuf)>> {
+        let mut saved = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let step = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("step-")?.strip_suffix(".ckpt"))
+                .and_then(|step| step.parse().ok());
+
+            if let Some(step) = step {
+                saved.push((step, path));
+            }
+        }
+
+        saved.sort_unstable();
+        Ok(saved)
+    }
+
+    /// Most recent rotated checkpoint, if any, to resume from.
+    pub fn latest(&self) -> io::Result<Option<PathBuf>> {
+        Ok(self.checkpoints()?.pop().map(|(_, path)| path))
+    }
+
+    /// The best checkpoint, if one has been saved.
+    pub fn best(&self) -> Option<PathBuf> {
+        let path = self.dir.join(BEST);
+        path.exists().then_some(path)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or(LoadError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+}
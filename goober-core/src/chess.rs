@@ -0,0 +1,169 @@
+//! Records of the chess training data formats of existing tools, to train
+//! on their datasets directly, read as [`Records`](crate::dataset::Records)
+//! or [`Stream`](crate::stream::Stream)s of 32 byte positions.
+//! - [`BulletFormat`], written by `bullet` and its data tools, with each
+//!   position seen by the side to move.
+//! - [`MarlinFormat`], written by `marlinflow` and many engines'
+//!   data generators, with each position seen by white.
+//!
+//! Positions are decoded from the view of the side to move into the usual
+//! 768 features, `384 * side + 64 * piece + square`, where `side` is 0 for
+//! the pieces of the side to move, `piece` counts from pawn to king, and
+//! squares count from a1 to h8, flipped vertically when black is to move.
+//!
+//! ```no_run
+//! # use goober_core::{chess::BulletFormat, dataset::Records};
+//! # #[cfg(not(feature = "mmap"))]
+//! # fn main() {}
+//! # #[cfg(feature = "mmap")]
+//! # fn main() -> Result<(), std::io::Error> {
+//! // blend a sigmoid of the score, with a scale of 400, with 25% of the result
+//! let data = unsafe { Records::<BulletFormat<400, 25>, _>::map("positions.data")? };
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::vec::Vec;
+
+use libm::expf;
+
+use crate::{dataset::Record, SparseVector, Vector};
+
+/// Number of features of a position.
+pub const FEATURES: usize = 768;
+
+/// Size of a record of either format.
+const SIZE: usize = 32;
+
+/// A position decoded from the view of the side to move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    pub features: SparseVector,
+    /// Evaluation in centipawns.
+    pub score: i16,
+    /// Result of the game, 0 for a loss, 0.5 for a draw, and 1 for a win.
+    pub result: f32,
+}
+
+impl Position {
+    /// Sigmoid of the score divided by `scale`, blended with the result
+    /// by the fraction `wdl`.
+    pub fn target(&self, scale: f32, wdl: f32) -> f32 {
+        let score = 1.0 / (1.0 + expf(-f32::from(self.score) / scale));
+        wdl * self.result + (1.0 - wdl) * score
+    }
+}
+
+/// Squares of the pieces of the record `bytes`, from its occupancy
+/// bitboard, with the nibble describing each piece, low nibble first.
+fn pieces(bytes: &[u8]) -> impl Iterator<Item = (usize, u8)> + '_ {
+    let mut occupancy = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    (0..occupancy.count_ones() as usize).map(move |i| {
+        let square = occupancy.trailing_zeros() as usize;
+        occupancy &= occupancy - 1;
+        (square, (bytes[8 + i / 2] >> (4 * (i % 2))) & 15)
+    })
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+/// Target of [`Position::target`] with a scale of `SCALE`
+/// and `WDL` percent of the result.
+fn target<const SCALE: u32, const WDL: u32>(position: &Position) -> Vector<1> {
+    Vector::from_raw([position.target(SCALE as f32, WDL as f32 / 100.0)])
+}
+
+/// The position records of `bullet`, as `bulletformat::ChessBoard`,
+/// decoded into [`Record`]s with targets of [`Position::target`]
+/// with a scale of `SCALE` and `WDL` percent of the result.
+/// - An occupancy bitboard, then a nibble for each piece, then the
+///   score as an `i16`, and the result as 0, 1 or 2, then the squares
+///   of the kings, and padding.
+/// - Pieces are numbered from pawn to king, plus 8 for the pieces
+///   of the side not to move.
+pub struct BulletFormat<const SCALE: u32 = 400, const WDL: u32 = 0>;
+
+impl<const SCALE: u32, const WDL: u32> BulletFormat<SCALE, WDL> {
+    /// Decodes the record `bytes`.
+    /// - Panics if `bytes` is shorter than a record.
+    pub fn position(bytes: &[u8]) -> Position {
+        let features = pieces(bytes)
+            .map(|(square, piece)| {
+                384 * usize::from(piece >> 3) + 64 * usize::from(piece & 7) + square
+            })
+            .collect::<Vec<_>>();
+
+        Position {
+            features: SparseVector::from_slice(&features),
+            score: u16_at(bytes, 24) as i16,
+            result: f32::from(bytes[26]) / 2.0,
+        }
+    }
+}
+
+impl<const SCALE: u32, const WDL: u32> Record for BulletFormat<SCALE, WDL> {
+    type Input = SparseVector;
+    type Output = Vector<1>;
+
+    const SIZE: usize = SIZE;
+
+    fn decode(bytes: &[u8]) -> (SparseVector, Vector<1>) {
+        let position = Self::position(bytes);
+        let target = target::<SCALE, WDL>(&position);
+        (position.features, target)
+    }
+}
+
+/// The position records of `marlinflow`, as its `PackedBoard`, decoded
+/// into [`Record`]s with targets of [`Position::target`] with a scale
+/// of `SCALE` and `WDL` percent of the result.
+/// - An occupancy bitboard, then a nibble for each piece, then the side
+///   to move in the top bit of the en passant square, the halfmove clock
+///   and fullmove number, then the score of white as an `i16`, and the
+///   result for white as 0, 1 or 2, and padding.
+/// - Pieces are numbered from pawn to king, then 6 for rooks that may
+///   still castle, plus 8 for black pieces.
+pub struct MarlinFormat<const SCALE: u32 = 400, const WDL: u32 = 0>;
+
+impl<const SCALE: u32, const WDL: u32> MarlinFormat<SCALE, WDL> {
+    /// Decodes the record `bytes`, from the view of the side to move.
+    /// - Panics if `bytes` is shorter than a record.
+    pub fn position(bytes: &[u8]) -> Position {
+        let black = bytes[24] >> 7 == 1;
+        let flip = if black { 56 } else { 0 };
+
+        let features = pieces(bytes)
+            .map(|(square, piece)| {
+                let side = usize::from((piece >> 3 == 1) != black);
+                let kind = match piece & 7 {
+                    6 => 3,
+                    kind => usize::from(kind),
+                };
+                384 * side + 64 * kind + (square ^ flip)
+            })
+            .collect::<Vec<_>>();
+
+        let score = u16_at(bytes, 28) as i16;
+        let result = f32::from(bytes[30]) / 2.0;
+        Position {
+            features: SparseVector::from_slice(&features),
+            score: if black { score.saturating_neg() } else { score },
+            result: if black { 1.0 - result } else { result },
+        }
+    }
+}
+
+impl<const SCALE: u32, const WDL: u32> Record for MarlinFormat<SCALE, WDL> {
+    type Input = SparseVector;
+    type Output = Vector<1>;
+
+    const SIZE: usize = SIZE;
+
+    fn decode(bytes: &[u8]) -> (SparseVector, Vector<1>) {
+        let position = Self::position(bytes);
+        let target = target::<SCALE, WDL>(&position);
+        (position.features, target)
+    }
+}
@@ -0,0 +1,266 @@
+//! Generation of Rust source code embedding a trained network, with its
+//! parameters as `static` arrays and a minimal `evaluate` function, for
+//! projects which want no deserialization and no dependency on goober.
+//! - Parameters of every storage type are emitted as `f32`, unless the
+//!   network stores any as `f64`, in which case all are emitted as `f64`.
+
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{FeedForwardNetwork, Float, Graph, Op, Param, ParamVisitor, Unsupported};
+
+struct Tensor {
+    shape: Vec<usize>,
+    values: Vec<f64>,
+}
+
+struct Collect {
+    tensors: HashMap<String, Tensor>,
+    order: Vec<String>,
+    wide: bool,
+}
+
+impl ParamVisitor for Collect {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.wide |= F::BYTES == 8;
+        self.order.push(param.name.to_string());
+        self.tensors.insert(
+            param.name.to_string(),
+            Tensor {
+                shape: param.shape.to_vec(),
+                values: param.values.iter().map(|x| x.to_f64()).collect(),
+            },
+        );
+    }
+}
+
+/// Name of the `static` holding the tensor `name`.
+fn ident(name: &str) -> String {
+    let ident = name.replace('.', "_").to_uppercase();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{ident}")
+    } else {
+        ident
+    }
+}
+
+/// Type of a tensor of `shape`, as nested arrays.
+fn array_type(scalar: &str, shape: &[usize]) -> String {
+    shape
+        .iter()
+        .rev()
+        .fold(scalar.to_string(), |ty, dim| format!("[{ty}; {dim}]"))
+}
+
+fn literal(scalar: &str, x: f64, out: &mut String) {
+    match x {
+        x if x.is_nan() => write!(out, "{scalar}::NAN"),
+        x if x == f64::INFINITY => write!(out, "{scalar}::INFINITY"),
+        x if x == f64::NEG_INFINITY => write!(out, "{scalar}::NEG_INFINITY"),
+        x if scalar == "f32" => write!(out, "{:?}", x as f32),
+        x => write!(out, "{x:?}"),
+    }
+    .unwrap();
+}
+
+fn array(scalar: &str, shape: &[usize], values: &[f64], indent: usize, out: &mut String) {
+    match shape {
+        [] | [_] => {
+            out.push('[');
+            for (i, &x) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                literal(scalar, x, out);
+            }
+            out.push(']');
+        }
+        [_, rest @ ..] => {
+            let stride = rest.iter().product::<usize>().max(1);
+            out.push_str("[\n");
+            for row in values.chunks(stride) {
+                out.extend(std::iter::repeat_n(' ', indent + 4));
+                array(scalar, rest, row, indent + 4, out);
+                out.push_str(",\n");
+            }
+            out.extend(std::iter::repeat_n(' ', indent));
+            out.push(']');
+        }
+    }
+}
+
+const LINEAR: &str = "
+fn linear<const M: usize, const N: usize>(w: &[[T; M]; N], b: &[T; N], x: &[T; M]) -> [T; N] {
+    let mut out = *b;
+    for (out, row) in out.iter_mut().zip(w) {
+        for (w, x) in row.iter().zip(x) {
+            *out += w * x;
+        }
+    }
+    out
+}
+";
+
+const SPARSE_LINEAR: &str = "
+fn sparse_linear<const M: usize, const N: usize>(w: &[[T; N]; M], b: &[T; N], x: &[usize]) -> [T; N] {
+    let mut out = *b;
+    for &feat in x {
+        for (out, w) in out.iter_mut().zip(&w[feat]) {
+            *out += w;
+        }
+    }
+    out
+}
+";
+
+const CONV1D: &str = "
+fn conv1d<const M: usize, const N: usize>(w: &[T; M], b: &[T; N], x: &[T; M]) -> [T; N] {
+    let mut out = *b;
+    for (i, out) in out.iter_mut().enumerate() {
+        for j in 0..M - N + 1 {
+            *out += x[i + j] * w[j];
+        }
+    }
+    out
+}
+";
+
+const ADD: &str = "
+fn add<const N: usize>(a: &[T; N], b: &[T; N]) -> [T; N] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+";
+
+const RELU: &str = "
+fn relu<const N: usize>(x: [T; N]) -> [T; N] {
+    x.map(|x| x.max(0.0))
+}
+";
+
+const SCRELU: &str = "
+fn screlu<const N: usize>(x: [T; N]) -> [T; N] {
+    x.map(|x| x.clamp(0.0, 1.0).powi(2))
+}
+";
+
+const TANH: &str = "
+fn tanh<const N: usize>(x: [T; N]) -> [T; N] {
+    x.map(T::tanh)
+}
+";
+
+/// Generates a Rust source file embedding `net`, in which
+/// `evaluate` computes the output of the network.
+/// - Sparse inputs are passed as a slice of active feature indices,
+///   and dense inputs as an array.
+pub fn to_rust<T: FeedForwardNetwork>(net: &T) -> Result<String, Unsupported> {
+    let mut graph = Graph::default();
+    net.trace("", Graph::INPUT, &mut graph)?;
+
+    let mut collect = Collect {
+        tensors: HashMap::new(),
+        order: Vec::new(),
+        wide: false,
+    };
+    net.visit_params("", &mut collect);
+
+    let scalar = if collect.wide { "f64" } else { "f32" };
+    let tensor = |name: &str| &collect.tensors[name];
+
+    let input = match graph.nodes().first().map(|node| &node.op) {
+        Some(Op::SparseLinear { .. }) => "&[usize]".to_string(),
+        Some(Op::Linear { weight, .. }) => format!("&[T; {}]", tensor(weight).shape[1]),
+        Some(Op::Conv1D { weight, .. }) => format!("&[T; {}]", tensor(weight).shape[0]),
+        _ => return Err(Unsupported("input to non-parametric layer".to_string())),
+    };
+
+    let mut helpers = Vec::new();
+    let mut body = Vec::new();
+    let value = |name: &str| match name {
+        Graph::INPUT => "input".to_string(),
+        name => format!("&{name}"),
+    };
+
+    for node in graph.nodes() {
+        let inputs: Vec<String> = node.inputs.iter().map(|x| value(x)).collect();
+
+        let (expr, helper) = match &node.op {
+            Op::Linear { weight, bias } => {
+                let expr = format!(
+                    "linear(&{}, &{}, {})",
+                    ident(weight),
+                    ident(bias),
+                    inputs[0]
+                );
+                (expr, LINEAR)
+            }
+            Op::SparseLinear { weight, bias } => {
+                let expr = format!(
+                    "sparse_linear(&{}, &{}, {})",
+                    ident(weight),
+                    ident(bias),
+                    inputs[0]
+                );
+                (expr, SPARSE_LINEAR)
+            }
+            Op::Conv1D { weight, bias, .. } => {
+                let expr = format!(
+                    "conv1d(&{}, &{}, {})",
+                    ident(weight),
+                    ident(bias),
+                    inputs[0]
+                );
+                (expr, CONV1D)
+            }
+            Op::Add => (format!("add({}, {})", inputs[0], inputs[1]), ADD),
+            Op::Activation(0) => (node.inputs[0].clone(), ""),
+            Op::Activation(id) => {
+                let (name, helper) = match id {
+                    1 => ("relu", RELU),
+                    2 => ("screlu", SCRELU),
+                    3 => ("tanh", TANH),
+                    _ => return Err(Unsupported(format!("activation with id {id}"))),
+                };
+                (format!("{name}({})", node.inputs[0]), helper)
+            }
+        };
+
+        if !helper.is_empty() && !helpers.contains(&helper) {
+            helpers.push(helper);
+        }
+
+        body.push((&node.output, expr));
+    }
+
+    let mut out = String::new();
+    writeln!(out, "// Generated by goober, do not edit.").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "type T = {scalar};").unwrap();
+
+    for name in &collect.order {
+        let tensor = tensor(name);
+        let ty = array_type("T", &tensor.shape);
+        write!(out, "\nstatic {}: {ty} = ", ident(name)).unwrap();
+        array(scalar, &tensor.shape, &tensor.values, 0, &mut out);
+        out.push_str(";\n");
+    }
+
+    let size = graph.nodes().last().map_or(0, |node| node.size);
+    writeln!(out, "\npub fn evaluate(input: {input}) -> [T; {size}] {{").unwrap();
+    let (_, tail) = body.pop().unwrap();
+    for (name, expr) in body {
+        writeln!(out, "    let {name} = {expr};").unwrap();
+    }
+    writeln!(out, "    {tail}").unwrap();
+    writeln!(out, "}}").unwrap();
+
+    for helper in helpers {
+        out.push_str(helper);
+    }
+
+    Ok(out)
+}
+
+pub fn save<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    let source = to_rust(net).map_err(std::io::Error::other)?;
+    std::fs::write(path, source)
+}
@@ -0,0 +1,482 @@
+//! Training configuration read from a TOML file, so that a run is
+//! reproducible from a single file kept alongside its results.
+//!
+//! ```toml
+//! epochs = 10
+//! batch_size = 16384
+//! seed = 42
+//!
+//! [optimizer]
+//! kind = "adam"
+//! ema = 0.999
+//!
+//! [schedule]
+//! kind = "cosine"
+//! lr = 0.001
+//! min_lr = 0.00001
+//! warmup = 100
+//! steps = 100000
+//!
+//! [loss]
+//! kind = "mse"
+//!
+//! [data]
+//! train = ["data/train-1.bin", "data/train-2.bin"]
+//! validation = "data/validation.bin"
+//!
+//! [checkpoint]
+//! dir = "checkpoints"
+//! every = 1
+//! keep = 3
+//! ```
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, config::Config, layer::{DenseConnected, SparseConnected},
+//! #     FeedForwardNetwork, SparseVector, Vector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let net = Net::boxed_and_zeroed();
+//! # let data: Vec<(SparseVector, Vector<1>)> = Vec::new();
+//! let config = Config::load("run.toml")?;
+//! let mut trainer = config.trainer(net, data)?;
+//! trainer.run(config.epochs)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt, io,
+    ops::Mul,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    checkpoint::CheckpointManager,
+    dataset::Dataset,
+    loss::{Loss, Mse},
+    schedule::{Constant, Cosine, Plateau, Scheduler, StepDecay},
+    toml::{self, Entry, Toml},
+    trainer::Trainer,
+    FeedForwardNetwork,
+};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    /// The file is not valid TOML, or uses TOML beyond what is supported.
+    Syntax {
+        line: usize,
+        message: String,
+    },
+    /// A key is unknown, missing, or has a value of the wrong type.
+    Invalid {
+        key: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Syntax { line, message } => write!(f, "line {line}: {message}"),
+            Self::Invalid { key, message } => write!(f, "`{key}` {message}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+fn invalid(key: &str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.into(),
+        message: message.into(),
+    }
+}
+
+/// Learning rate schedule chosen at runtime, by `schedule.kind`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Schedule {
+    /// `"constant"`, with `lr`.
+    Constant(Constant),
+    /// `"step"`, with `lr`, `gamma` and `every`.
+    StepDecay(StepDecay),
+    /// `"cosine"`, with `lr`, `min_lr`, `warmup` and `steps`.
+    Cosine(Cosine),
+    /// `"plateau"`, with `lr`, `factor`, `patience`,
+    /// and optionally `min_delta` and `min_lr`.
+    Plateau(Plateau),
+}
+
+impl Scheduler for Schedule {
+    fn lr(&self, step: u64) -> f32 {
+        match self {
+            Self::Constant(s) => s.lr(step),
+            Self::StepDecay(s) => s.lr(step),
+            Self::Cosine(s) => s.lr(step),
+            Self::Plateau(s) => s.lr(step),
+        }
+    }
+
+    fn observe(&mut self, metric: f32) {
+        match self {
+            Self::Constant(s) => s.observe(metric),
+            Self::StepDecay(s) => s.observe(metric),
+            Self::Cosine(s) => s.observe(metric),
+            Self::Plateau(s) => s.observe(metric),
+        }
+    }
+
+    fn state(&self) -> Vec<u8> {
+        match self {
+            Self::Constant(s) => s.state(),
+            Self::StepDecay(s) => s.state(),
+            Self::Cosine(s) => s.state(),
+            Self::Plateau(s) => s.state(),
+        }
+    }
+
+    fn set_state(&mut self, state: &[u8]) -> Result<(), String> {
+        match self {
+            Self::Constant(s) => s.set_state(state),
+            Self::StepDecay(s) => s.set_state(state),
+            Self::Cosine(s) => s.set_state(state),
+            Self::Plateau(s) => s.set_state(state),
+        }
+    }
+}
+
+/// Optimiser, by `optimizer.kind`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Optimizer {
+    /// `"adam"`, the only optimiser networks implement.
+    #[default]
+    Adam,
+}
+
+/// Loss function, by `loss.kind`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LossKind {
+    /// `"mse"`, see [`Mse`].
+    #[default]
+    Mse,
+}
+
+/// Paths to the data, from the `[data]` table, for the
+/// program driving training to read in its own format.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DataConfig {
+    /// `train`, a single path or an array of them.
+    pub train: Vec<PathBuf>,
+    /// `validation`, optional.
+    pub validation: Option<PathBuf>,
+}
+
+/// Where and how often to save checkpoints, from the `[checkpoint]` table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointConfig {
+    pub dir: PathBuf,
+    /// Epochs between checkpoints, by default one.
+    pub every: u64,
+    /// Number of checkpoints to keep, by default three.
+    pub keep: usize,
+}
+
+/// Settings of a training run.
+/// - Top-level keys are `epochs`, `batch_size`, `seed`, `deterministic`,
+///   `shuffle` and `nan_guard`, setting the fields of the same name.
+/// - Unknown keys are rejected, so that typos are not silently ignored.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// By default one.
+    pub epochs: usize,
+    /// By default 1024, as [`Trainer::new`].
+    pub batch_size: usize,
+    pub seed: Option<u64>,
+    /// Whether to make training reproducible from `seed`, see
+    /// [`with_deterministic`](Trainer::with_deterministic).
+    pub deterministic: bool,
+    /// By default true.
+    pub shuffle: bool,
+    pub nan_guard: bool,
+    pub optimizer: Optimizer,
+    /// `optimizer.ema`, see [`with_ema`](Trainer::with_ema).
+    pub ema: Option<f32>,
+    /// `optimizer.swa`, see [`with_swa`](Trainer::with_swa).
+    pub swa: Option<u64>,
+    /// By default a constant learning rate of `0.001`, as [`Trainer::new`].
+    pub schedule: Schedule,
+    pub loss: LossKind,
+    pub data: DataConfig,
+    pub checkpoint: Option<CheckpointConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            epochs: 1,
+            batch_size: 1024,
+            seed: None,
+            deterministic: false,
+            shuffle: true,
+            nan_guard: false,
+            optimizer: Optimizer::Adam,
+            ema: None,
+            swa: None,
+            schedule: Schedule::Constant(Constant(0.001)),
+            loss: LossKind::Mse,
+            data: DataConfig::default(),
+            checkpoint: None,
+        }
+    }
+}
+
+impl Config {
+    /// Reads a configuration from the file at `path`, with relative
+    /// paths within it taken relative to the directory of the file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let mut config = Self::parse(&std::fs::read_to_string(path)?)?;
+
+        if let Some(dir) = path.parent() {
+            let resolve = |p: &mut PathBuf| *p = dir.join(&*p);
+            config.data.train.iter_mut().for_each(resolve);
+            config.data.validation.iter_mut().for_each(resolve);
+            if let Some(ckpt) = &mut config.checkpoint {
+                resolve(&mut ckpt.dir);
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Reads a configuration from TOML, leaving relative paths as they are.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let entries =
+            toml::parse(text).map_err(|(line, message)| ConfigError::Syntax { line, message })?;
+        Self::from_entries(entries)
+    }
+
+    pub(crate) fn from_entries(entries: Vec<Entry>) -> Result<Self, ConfigError> {
+        let mut keys = Keys {
+            used: vec![false; entries.len()],
+            entries,
+        };
+        let default = Self::default();
+
+        let config = Self {
+            epochs: keys.usize("epochs")?.unwrap_or(default.epochs),
+            batch_size: keys.usize("batch_size")?.unwrap_or(default.batch_size),
+            seed: keys.u64("seed")?,
+            deterministic: keys.bool("deterministic")?.unwrap_or(false),
+            shuffle: keys.bool("shuffle")?.unwrap_or(default.shuffle),
+            nan_guard: keys.bool("nan_guard")?.unwrap_or(false),
+            optimizer: match keys.str("optimizer.kind")?.as_deref() {
+                None | Some("adam") => Optimizer::Adam,
+                Some(kind) => return Err(invalid("optimizer.kind", unknown(kind, "adam"))),
+            },
+            ema: keys.f32("optimizer.ema")?,
+            swa: keys.u64("optimizer.swa")?,
+            schedule: keys.schedule()?.unwrap_or(default.schedule),
+            loss: match keys.str("loss.kind")?.as_deref() {
+                None | Some("mse") => LossKind::Mse,
+                Some(kind) => return Err(invalid("loss.kind", unknown(kind, "mse"))),
+            },
+            data: DataConfig {
+                train: keys.paths("data.train")?,
+                validation: keys.str("data.validation")?.map(PathBuf::from),
+            },
+            checkpoint: match keys.str("checkpoint.dir")? {
+                Some(dir) => Some(CheckpointConfig {
+                    dir: dir.into(),
+                    every: keys.u64("checkpoint.every")?.unwrap_or(1),
+                    keep: keys.usize("checkpoint.keep")?.unwrap_or(3),
+                }),
+                None => None,
+            },
+        };
+
+        if config.deterministic && config.seed.is_none() {
+            return Err(invalid("deterministic", "requires `seed`"));
+        }
+
+        keys.finish()?;
+        Ok(config)
+    }
+
+    /// Trains `net` on `data` with these settings, creating the
+    /// checkpoint directory if checkpoints are configured.
+    /// - The data is read by the caller, from the paths in
+    ///   [`data`](Self::data), as is any validation data, to add with
+    ///   [`with_validation`](Trainer::with_validation).
+    pub fn trainer<T, D>(&self, net: Box<T>, data: D) -> io::Result<Trainer<T, Schedule>>
+    where
+        T: FeedForwardNetwork,
+        D: Dataset<T::InputType, T::OutputType> + 'static,
+        Mse: Loss<T::OutputType>,
+        f32: Mul<T::OutputType, Output = T::OutputType>,
+    {
+        let loss = match self.loss {
+            LossKind::Mse => Mse,
+        };
+
+        let mut trainer = Trainer::from_dataset(net, data)
+            .with_scheduler(self.schedule)
+            .with_loss(loss)
+            .with_batch_size(self.batch_size)
+            .with_shuffle(self.shuffle)
+            .with_nan_guard(self.nan_guard);
+
+        match self.seed {
+            Some(seed) if self.deterministic => trainer = trainer.with_deterministic(seed),
+            Some(seed) => trainer = trainer.with_seed(seed),
+            None => {}
+        }
+        if let Some(decay) = self.ema {
+            trainer = trainer.with_ema(decay);
+        }
+        if let Some(start) = self.swa {
+            trainer = trainer.with_swa(start);
+        }
+        if let Some(ckpt) = &self.checkpoint {
+            let manager = CheckpointManager::new(&ckpt.dir, ckpt.keep)?;
+            trainer = trainer.with_checkpoints(manager, ckpt.every);
+        }
+
+        Ok(trainer)
+    }
+}
+
+fn unknown(kind: &str, expected: &str) -> String {
+    format!("is `{kind}`, expected one of {expected}")
+}
+
+/// Entries of a file, marking those which are read.
+struct Keys {
+    entries: Vec<Entry>,
+    used: Vec<bool>,
+}
+
+impl Keys {
+    fn get(&mut self, key: &str) -> Option<&Toml> {
+        let i = self.entries.iter().position(|(k, _, _)| k == key)?;
+        self.used[i] = true;
+        Some(&self.entries[i].1)
+    }
+
+    fn typed<T>(
+        &mut self,
+        key: &str,
+        expected: &str,
+        f: impl FnOnce(&Toml) -> Option<T>,
+    ) -> Result<Option<T>, ConfigError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => f(value)
+                .map(Some)
+                .ok_or_else(|| invalid(key, format!("should be {expected}"))),
+        }
+    }
+
+    fn str(&mut self, key: &str) -> Result<Option<String>, ConfigError> {
+        self.typed(key, "a string", |v| v.as_str().map(String::from))
+    }
+
+    fn f32(&mut self, key: &str) -> Result<Option<f32>, ConfigError> {
+        self.typed(key, "a number", |v| v.as_f64().map(|x| x as f32))
+    }
+
+    fn u64(&mut self, key: &str) -> Result<Option<u64>, ConfigError> {
+        self.typed(key, "a non-negative integer", Toml::as_u64)
+    }
+
+    fn usize(&mut self, key: &str) -> Result<Option<usize>, ConfigError> {
+        self.typed(key, "a non-negative integer", |v| {
+            v.as_u64().and_then(|x| usize::try_from(x).ok())
+        })
+    }
+
+    fn bool(&mut self, key: &str) -> Result<Option<bool>, ConfigError> {
+        self.typed(key, "a boolean", Toml::as_bool)
+    }
+
+    /// A single path, or an array of them.
+    fn paths(&mut self, key: &str) -> Result<Vec<PathBuf>, ConfigError> {
+        let paths = self.typed(key, "a string or an array of strings", |v| match v {
+            Toml::String(s) => Some(vec![PathBuf::from(s)]),
+            _ => v
+                .as_array()?
+                .iter()
+                .map(|v| v.as_str().map(PathBuf::from))
+                .collect(),
+        })?;
+        Ok(paths.unwrap_or_default())
+    }
+
+    fn required_f32(&mut self, key: &str) -> Result<f32, ConfigError> {
+        self.f32(key)?.ok_or_else(|| invalid(key, "is missing"))
+    }
+
+    fn required_u64(&mut self, key: &str) -> Result<u64, ConfigError> {
+        self.u64(key)?.ok_or_else(|| invalid(key, "is missing"))
+    }
+
+    fn schedule(&mut self) -> Result<Option<Schedule>, ConfigError> {
+        let Some(kind) = self.str("schedule.kind")? else {
+            return Ok(None);
+        };
+
+        let lr = self.required_f32("schedule.lr")?;
+        let schedule = match kind.as_str() {
+            "constant" => Schedule::Constant(Constant(lr)),
+            "step" => Schedule::StepDecay(StepDecay {
+                lr,
+                gamma: self.required_f32("schedule.gamma")?,
+                every: self.required_u64("schedule.every")?,
+            }),
+            "cosine" => Schedule::Cosine(Cosine {
+                lr,
+                min_lr: self.f32("schedule.min_lr")?.unwrap_or(0.0),
+                warmup: self.u64("schedule.warmup")?.unwrap_or(0),
+                steps: self.required_u64("schedule.steps")?,
+            }),
+            "plateau" => {
+                let factor = self.required_f32("schedule.factor")?;
+                let patience = self.required_u64("schedule.patience")?;
+                let patience = u32::try_from(patience)
+                    .map_err(|_| invalid("schedule.patience", "is too large"))?;
+
+                let mut plateau = Plateau::new(lr, factor, patience);
+                plateau.min_delta = self.f32("schedule.min_delta")?.unwrap_or(0.0);
+                plateau.min_lr = self.f32("schedule.min_lr")?.unwrap_or(0.0);
+                Schedule::Plateau(plateau)
+            }
+            kind => {
+                let expected = "constant, step, cosine, plateau";
+                return Err(invalid("schedule.kind", unknown(kind, expected)));
+            }
+        };
+
+        Ok(Some(schedule))
+    }
+
+    /// Fails on the first key which was not read.
+    fn finish(self) -> Result<(), ConfigError> {
+        match self.entries.iter().zip(&self.used).find(|(_, &used)| !used) {
+            Some(((key, _, line), _)) => Err(invalid(key, format!("on line {line} is unknown"))),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Reading tabular datasets from CSV files with a header row, into dense
+//! inputs and targets, selecting columns by their names.
+//!
+//! ```no_run
+//! # use goober::{csv::Csv, Vector};
+//! # fn main() -> Result<(), std::io::Error> {
+//! // predict `price` from three of the columns
+//! let data: Vec<(Vector<3>, Vector<1>)> = Csv::new()
+//!     .with_inputs(&["rooms", "area", "age"])
+//!     .with_targets(&["price"])
+//!     .load("houses.csv")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! - Fields may be quoted with `"`, and quotes within them doubled.
+//! - Blank lines are skipped.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::Vector;
+
+/// Selection of the columns of a CSV file read as inputs and targets.
+#[derive(Clone, Debug)]
+pub struct Csv {
+    inputs: Option<Vec<String>>,
+    targets: Option<Vec<String>>,
+    delimiter: char,
+}
+
+impl Default for Csv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Csv {
+    /// Reads the last columns as the targets, and the rest as inputs.
+    pub fn new() -> Self {
+        Self {
+            inputs: None,
+            targets: None,
+            delimiter: ',',
+        }
+    }
+
+    /// Reads the columns named `columns` as inputs, in that order.
+    pub fn with_inputs(mut self, columns: &[&str]) -> Self {
+        self.inputs = Some(columns.iter().map(|&column| column.into()).collect());
+        self
+    }
+
+    /// Reads the columns named `columns` as targets, in that order.
+    /// - Unless inputs are chosen, every other column is an input.
+    pub fn with_targets(mut self, columns: &[&str]) -> Self {
+        self.targets = Some(columns.iter().map(|&column| column.into()).collect());
+        self
+    }
+
+    /// Separates fields with `delimiter`, rather than `,`.
+    pub fn with_delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Reads every row of `reader` as a sample of `M` inputs and `N` targets.
+    /// - Fails if the columns chosen are missing, or their number is not
+    ///   `M` and `N`, or if a value is not a number.
+    pub fn read<const M: usize, const N: usize>(
+        &self,
+        reader: impl BufRead,
+    ) -> io::Result<Vec<(Vector<M>, Vector<N>)>> {
+        let invalid = |line: usize, message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {line}: {message}"),
+            )
+        };
+
+        let mut lines = reader.lines();
+        let header = lines
+            .next()
+            .transpose()?
+            .ok_or_else(|| invalid(1, "expected a header".into()))?;
+        let header = self.split(&header).map_err(|message| invalid(1, message))?;
+        let (inputs, targets) = self
+            .columns::<M, N>(&header)
+            .map_err(|message| invalid(1, message))?;
+
+        let mut data = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let number = i + 2;
+            let fields = self
+                .split(&line)
+                .map_err(|message| invalid(number, message))?;
+            if fields.len() != header.len() {
+                let message = format!("expected {} fields, found {}", header.len(), fields.len());
+                return Err(invalid(number, message));
+            }
+
+            let value = |column: usize| {
+                let field = fields[column].trim();
+                field.parse::<f32>().map_err(|_| {
+                    let message = format!("invalid value `{field}` in column `{}`", header[column]);
+                    invalid(number, message)
+                })
+            };
+
+            let mut input = Vector::zeroed();
+            for (j, &column) in inputs.iter().enumerate() {
+                input[j] = value(column)?;
+            }
+            let mut target = Vector::zeroed();
+            for (j, &column) in targets.iter().enumerate() {
+                target[j] = value(column)?;
+            }
+            data.push((input, target));
+        }
+
+        Ok(data)
+    }
+
+    /// Reads every row of the file at `path`, as [`read`](Self::read).
+    pub fn load<const M: usize, const N: usize>(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> io::Result<Vec<(Vector<M>, Vector<N>)>> {
+        let path = path.as_ref();
+        self.read(BufReader::new(File::open(path)?))
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))
+    }
+
+    /// Indices of the input and target columns in `header`.
+    fn columns<const M: usize, const N: usize>(
+        &self,
+        header: &[String],
+    ) -> Result<(Vec<usize>, Vec<usize>), String> {
+        let find = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| {
+                    header
+                        .iter()
+                        .position(|column| column.trim() == name)
+                        .ok_or_else(|| format!("no column `{name}`"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        let targets = match &self.targets {
+            Some(names) => find(names)?,
+            None => (header.len().saturating_sub(N)..header.len()).collect(),
+        };
+        let inputs = match &self.inputs {
+            Some(names) => find(names)?,
+            None => (0..header.len()).filter(|i| !targets.contains(i)).collect(),
+        };
+
+        if inputs.len() != M {
+            return Err(format!(
+                "expected {M} input columns, found {}",
+                inputs.len()
+            ));
+        }
+        if targets.len() != N {
+            return Err(format!(
+                "expected {N} target columns, found {}",
+                targets.len()
+            ));
+        }
+        Ok((inputs, targets))
+    }
+
+    /// Fields of `line`, with quotes removed.
+    fn split(&self, line: &str) -> Result<Vec<String>, String> {
+        let mut fields = vec![String::new()];
+        let mut chars = line.chars().peekable();
+        let mut quoted = false;
+
+        while let Some(c) = chars.next() {
+            let field = fields.last_mut().unwrap();
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    field.push('"');
+                    chars.next();
+                }
+                '"' if quoted => quoted = false,
+                '"' if field.trim().is_empty() => {
+                    field.clear();
+                    quoted = true;
+                }
+                c if c == self.delimiter && !quoted => fields.push(String::new()),
+                c => field.push(c),
+            }
+        }
+
+        match quoted {
+            true => Err("unterminated quote".into()),
+            false => Ok(fields),
+        }
+    }
+}
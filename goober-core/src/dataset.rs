@@ -0,0 +1,563 @@
+//! Sources of training samples, read by index, so that the
+//! [`Trainer`](crate::trainer::Trainer) can train on data held
+//! in memory or read from elsewhere alike.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh},
+//! #     dataset::{split_by_key, Dataset, Encoded, FeatureEncoder, Record, Records},
+//! #     layer::{DenseConnected, SparseConnected}, trainer::Trainer, FeedForwardNetwork,
+//! #     SparseVector, Vector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # #[derive(Clone)]
+//! # struct Board;
+//! # impl Board {
+//! #     fn in_check(&self) -> bool { false }
+//! #     fn ply(&self) -> u32 { 0 }
+//! #     fn tactics(&self) -> f32 { 0.0 }
+//! #     fn game_id(&self) -> u64 { 0 }
+//! # }
+//! # struct PackedBoard;
+//! # impl Record for PackedBoard {
+//! #     type Input = Board;
+//! #     type Output = Vector<1>;
+//! #     const SIZE: usize = 32;
+//! #     fn decode(_: &[u8]) -> (Board, Vector<1>) { (Board, Vector::from_raw([0.0])) }
+//! # }
+//! # struct HalfKp;
+//! # impl FeatureEncoder<Board> for HalfKp {
+//! #     type Input = SparseVector;
+//! #     fn encode(&self, _: &Board) -> SparseVector { SparseVector::with_capacity(0) }
+//! # }
+//! # struct Position;
+//! # impl Record for Position {
+//! #     type Input = SparseVector;
+//! #     type Output = Vector<1>;
+//! #     const SIZE: usize = 32;
+//! #     fn decode(_: &[u8]) -> (SparseVector, Vector<1>) { unimplemented!() }
+//! # }
+//! # fn read_samples() -> std::io::Result<Vec<(SparseVector, Vector<1>)>> { Ok(Vec::new()) }
+//! # #[cfg(not(feature = "mmap"))]
+//! # fn main() {}
+//! # #[cfg(feature = "mmap")]
+//! # fn main() -> Result<(), std::io::Error> {
+//! # let net = || Net::boxed_and_zeroed();
+//! let data: Vec<(SparseVector, Vector<1>)> = read_samples()?;
+//! // `Vec::get` is found first, returning an `Option`
+//! let sample = Dataset::get(&data, 0);
+//! let mut trainer = Trainer::new(net(), data);
+//!
+//! // or records of a fixed size in a file too large to read into memory
+//! let data = unsafe { Records::<Position, _>::map("positions.bin")? };
+//! let mut trainer = Trainer::from_dataset(net(), data);
+//!
+//! // or records decoded into boards, with the features of each computed
+//! // from its board only once it is read
+//! let data = unsafe { Records::<PackedBoard, _>::map("positions.bin")? };
+//! let mut trainer = Trainer::from_dataset(net(), Encoded::new(data, HalfKp));
+//!
+//! // or with 5% of the games held out for validation
+//! let data = unsafe { Records::<PackedBoard, _>::map("positions.bin")? };
+//! let (train, validation) = split_by_key(data, 0.05, |board, _| board.game_id());
+//! let validation = validation
+//!     .to_vec()
+//!     .into_iter()
+//!     .map(|(board, target)| (HalfKp.encode(&board), target))
+//!     .collect();
+//! let data = Encoded::new(train, HalfKp);
+//! let mut trainer = Trainer::from_dataset(net(), data).with_validation(validation);
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
+
+use crate::Rand;
+
+/// Input and target of a sample, borrowed from the dataset
+/// holding it, or decoded on demand.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sample<'a, I, O> {
+    Borrowed(&'a I, &'a O),
+    Owned(I, O),
+}
+
+impl<I, O> Sample<'_, I, O> {
+    pub fn input(&self) -> &I {
+        self.parts().0
+    }
+
+    pub fn target(&self) -> &O {
+        self.parts().1
+    }
+
+    pub fn parts(&self) -> (&I, &O) {
+        match self {
+            Self::Borrowed(input, target) => (input, target),
+            Self::Owned(input, target) => (input, target),
+        }
+    }
+}
+
+impl<I: Clone, O: Clone> Sample<'_, I, O> {
+    pub fn into_owned(self) -> (I, O) {
+        match self {
+            Self::Borrowed(input, target) => (input.clone(), target.clone()),
+            Self::Owned(input, target) => (input, target),
+        }
+    }
+}
+
+/// Samples of inputs of type `I` and targets of type `O`, by index.
+pub trait Dataset<I, O> {
+    /// Number of samples.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The sample at `index`.
+    /// - Panics if `index` is not less than [`len`](Self::len).
+    fn get(&self, index: usize) -> Sample<'_, I, O>;
+
+    /// Weight of the sample at `index` in the loss, 1 unless overridden.
+    /// - See [`Trainer::from_dataset`](crate::trainer::Trainer::from_dataset).
+    fn weight(&self, index: usize) -> f32 {
+        let _ = index;
+        1.0
+    }
+}
+
+/// Samples held in memory, borrowed without copying.
+impl<I, O> Dataset<I, O> for Vec<(I, O)> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, I, O> {
+        let (input, target) = &self[index];
+        Sample::Borrowed(input, target)
+    }
+}
+
+/// Samples held in memory with their weights.
+impl<I, O> Dataset<I, O> for Vec<(I, O, f32)> {
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, I, O> {
+        let (input, target, _) = &self[index];
+        Sample::Borrowed(input, target)
+    }
+
+    fn weight(&self, index: usize) -> f32 {
+        self[index].2
+    }
+}
+
+/// The samples of a dataset `D`, weighted by `W`, from each input and target,
+/// such as by the phase of a chess position, or the rarity of its class.
+pub struct Weighted<D, W> {
+    data: D,
+    weigh: W,
+}
+
+impl<D, W> Weighted<D, W> {
+    pub fn new(data: D, weigh: W) -> Self {
+        Self { data, weigh }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+}
+
+impl<D, W, I, O> Dataset<I, O> for Weighted<D, W>
+where
+    D: Dataset<I, O>,
+    W: Fn(&I, &O) -> f32,
+{
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, I, O> {
+        self.data.get(index)
+    }
+
+    fn weight(&self, index: usize) -> f32 {
+        let sample = self.data.get(index);
+        let (input, target) = sample.parts();
+        self.data.weight(index) * (self.weigh)(input, target)
+    }
+}
+
+/// Samples shared between several datasets, such as both sides of a split.
+impl<I, O, D: Dataset<I, O>> Dataset<I, O> for Arc<D> {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, I, O> {
+        (**self).get(index)
+    }
+
+    fn weight(&self, index: usize) -> f32 {
+        (**self).weight(index)
+    }
+}
+
+/// The samples of a dataset `D` at some of its indices, in their order.
+pub struct Subset<D> {
+    data: D,
+    indices: Vec<usize>,
+}
+
+impl<D> Subset<D> {
+    pub fn new(data: D, indices: Vec<usize>) -> Self {
+        Self { data, indices }
+    }
+
+    /// Indices of the samples in the whole dataset.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+
+    /// Copies the samples into memory, such as for
+    /// [`Trainer::with_validation`](crate::trainer::Trainer::with_validation).
+    pub fn to_vec<I: Clone, O: Clone>(&self) -> Vec<(I, O)>
+    where
+        D: Dataset<I, O>,
+    {
+        self.indices
+            .iter()
+            .map(|&index| self.data.get(index).into_owned())
+            .collect()
+    }
+}
+
+impl<D, I, O> Dataset<I, O> for Subset<D>
+where
+    D: Dataset<I, O>,
+{
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, I, O> {
+        self.data.get(self.indices[index])
+    }
+
+    fn weight(&self, index: usize) -> f32 {
+        self.data.weight(self.indices[index])
+    }
+}
+
+/// Training and validation samples of a dataset `D`.
+pub type Split<D> = (Subset<Arc<D>>, Subset<Arc<D>>);
+
+/// Splits `data` into training samples and a `validation` fraction of
+/// them, chosen at random by a generator seeded with `seed`.
+/// - The split depends only on the seed and the number of samples.
+/// - Samples of each side are in the order of `data`.
+pub fn split<D, I, O>(data: D, validation: f32, seed: u64) -> Split<D>
+where
+    D: Dataset<I, O>,
+{
+    let len = data.len();
+    let mut order: Vec<usize> = (0..len).collect();
+    let mut rng = Rand::with_seed(seed);
+    for i in (1..len).rev() {
+        let j = (rng.rand_u64() % (i as u64 + 1)) as usize;
+        order.swap(i, j);
+    }
+
+    let held = ((len as f64 * f64::from(validation.clamp(0.0, 1.0))) as usize).min(len);
+    let mut train = order.split_off(held);
+    train.sort_unstable();
+    order.sort_unstable();
+
+    let data = Arc::new(data);
+    (
+        Subset::new(Arc::clone(&data), train),
+        Subset::new(data, order),
+    )
+}
+
+/// Splits `data` into training samples and about a `validation` fraction
+/// of them, by a hash of the `key` of each sample, such as the game of a
+/// position, so that samples of the same key are never on both sides.
+/// - A key is always on the same side, whatever the other samples, so
+///   the split is kept as the data grows, and across platforms.
+/// - Samples of each side are in the order of `data`.
+pub fn split_by_key<D, I, O, K, H>(data: D, validation: f32, key: K) -> Split<D>
+where
+    D: Dataset<I, O>,
+    K: Fn(&I, &O) -> H,
+    H: Hash,
+{
+    let (mut train, mut held) = (Vec::new(), Vec::new());
+    for index in 0..data.len() {
+        let sample = data.get(index);
+        let (input, target) = sample.parts();
+        let mut hasher = Fnv::default();
+        key(input, target).hash(&mut hasher);
+
+        match unit(hasher.finish()) < f64::from(validation) {
+            true => held.push(index),
+            false => train.push(index),
+        }
+    }
+
+    let data = Arc::new(data);
+    (
+        Subset::new(Arc::clone(&data), train),
+        Subset::new(data, held),
+    )
+}
+
+/// Maps a hash to `[0, 1)`, mixing its bits first.
+fn unit(mut hash: u64) -> f64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xC4CE_B9FE_1A85_EC53);
+    hash ^= hash >> 33;
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// 64-bit FNV-1a, writing integers as little endian 64-bit integers, so
+/// hashes are the same on every platform.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write_u64(i.into());
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write_u64(i.into());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i.into());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write_i64(i.into());
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_i64(i.into());
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_i64(i.into());
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// Encoding of samples as records of a fixed number of bytes.
+pub trait Record {
+    type Input;
+    type Output;
+
+    /// Number of bytes of each record.
+    const SIZE: usize;
+
+    /// Decodes the sample held in `bytes`, of length [`SIZE`](Self::SIZE).
+    fn decode(bytes: &[u8]) -> (Self::Input, Self::Output);
+}
+
+/// Samples stored back to back as records in the format `R`, read in
+/// place from `B`, such as a file mapped with [`Records::map`], for
+/// datasets too large to hold in memory.
+pub struct Records<R, B = Vec<u8>> {
+    bytes: B,
+    phantom: PhantomData<fn() -> R>,
+}
+
+impl<R: Record, B: AsRef<[u8]>> Records<R, B> {
+    /// Reads records from `bytes`.
+    /// - Panics if the length of `bytes` is not a multiple of the size
+    ///   of a record.
+    pub fn new(bytes: B) -> Self {
+        let len = bytes.as_ref().len();
+        assert!(
+            len.is_multiple_of(R::SIZE),
+            "{len} bytes is not a whole number of records of {} bytes",
+            R::SIZE
+        );
+
+        Self {
+            bytes,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Bytes of the record at `index`, without copying.
+    pub fn record(&self, index: usize) -> &[u8] {
+        &self.bytes.as_ref()[index * R::SIZE..(index + 1) * R::SIZE]
+    }
+
+    pub fn into_inner(self) -> B {
+        self.bytes
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl<R: Record> Records<R, memmap2::Mmap> {
+    /// Memory-maps the file at `path`, so that only the pages holding
+    /// the records read are loaded, and shared between processes.
+    /// - Fails if the file is not a whole number of records.
+    ///
+    /// # Safety
+    /// The file must not be modified while mapped.
+    pub unsafe fn map(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+
+        // SAFETY: the caller guarantees the file is not modified while mapped
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        if !mmap.len().is_multiple_of(R::SIZE) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                alloc::format!(
+                    "{} bytes is not a whole number of records of {} bytes",
+                    mmap.len(),
+                    R::SIZE
+                ),
+            ));
+        }
+
+        // samples are read in a shuffled order, so reading ahead is wasted
+        #[cfg(unix)]
+        let _ = mmap.advise(memmap2::Advice::Random);
+
+        Ok(Self::new(mmap))
+    }
+}
+
+impl<R: Record, B: AsRef<[u8]>> Dataset<R::Input, R::Output> for Records<R, B> {
+    fn len(&self) -> usize {
+        self.bytes.as_ref().len() / R::SIZE
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, R::Input, R::Output> {
+        let (input, target) = R::decode(self.record(index));
+        Sample::Owned(input, target)
+    }
+}
+
+/// Computes the input of a network from a raw sample of type `R`, such as a
+/// board, so that the features of a network can be changed without changing
+/// how its data is stored, and computed as the data is read, with
+/// [`Encoded`], or on the reading thread of a
+/// [`Stream`](crate::stream::Stream::encoded).
+/// - Implemented by closures taking `&R`.
+pub trait FeatureEncoder<R> {
+    type Input;
+
+    fn encode(&self, raw: &R) -> Self::Input;
+}
+
+impl<R, I, F: Fn(&R) -> I> FeatureEncoder<R> for F {
+    type Input = I;
+
+    fn encode(&self, raw: &R) -> I {
+        self(raw)
+    }
+}
+
+/// The samples of a dataset `D` of raw inputs of type `R`,
+/// with their inputs encoded by `E` as they are read.
+pub struct Encoded<D, E, R> {
+    data: D,
+    encoder: E,
+    phantom: PhantomData<fn(&R)>,
+}
+
+impl<D, E, R> Encoded<D, E, R> {
+    pub fn new(data: D, encoder: E) -> Self {
+        Self {
+            data,
+            encoder,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn encoder(&self) -> &E {
+        &self.encoder
+    }
+
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+}
+
+impl<D, E, R, O> Dataset<E::Input, O> for Encoded<D, E, R>
+where
+    D: Dataset<R, O>,
+    E: FeatureEncoder<R>,
+    O: Clone,
+{
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, E::Input, O> {
+        let sample = self.data.get(index);
+        let (raw, target) = sample.parts();
+        Sample::Owned(self.encoder.encode(raw), target.clone())
+    }
+
+    fn weight(&self, index: usize) -> f32 {
+        self.data.weight(index)
+    }
+}
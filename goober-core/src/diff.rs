@@ -0,0 +1,173 @@
+//! Comparison of two networks of the same architecture, tensor by
+//! tensor, such as before and after fine-tuning.
+
+use alloc::{string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use crate::LoadError;
+use crate::{FeedForwardNetwork, Float, Param, ParamVisitor};
+
+/// Differences between the values of a single tensor of parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TensorDiff {
+    pub name: String,
+    pub shape: Vec<usize>,
+    /// L2 norm of the difference.
+    pub l2: f32,
+    /// Largest absolute difference.
+    pub linf: f32,
+    /// `l2` relative to the L2 norm of the tensor in the first network,
+    /// or infinite if that is zero and the tensors differ.
+    pub rel_l2: f32,
+    /// Index and L2 norm of the difference of the rows, along the first
+    /// dimension, which changed the most, largest first.
+    /// - Empty for tensors with a single dimension.
+    pub rows: Vec<(usize, f32)>,
+}
+
+impl TensorDiff {
+    fn new(name: String, shape: Vec<usize>, a: &[f32], b: &[f32], top: usize) -> Self {
+        let mut l2 = 0.0;
+        let mut norm = 0.0;
+        let mut linf = 0.0f32;
+
+        for (&x, &y) in a.iter().zip(b) {
+            let d = y - x;
+            l2 += f64::from(d) * f64::from(d);
+            norm += f64::from(x) * f64::from(x);
+            linf = linf.max(libm::fabsf(d));
+        }
+
+        let rel_l2 = if norm > 0.0 {
+            libm::sqrt(l2 / norm) as f32
+        } else if l2 > 0.0 {
+            f32::INFINITY
+        } else {
+            0.0
+        };
+
+        let mut rows = Vec::new();
+        if shape.len() > 1 && shape[0] > 0 {
+            let width = a.len() / shape[0];
+            rows = a
+                .chunks(width)
+                .zip(b.chunks(width))
+                .map(|(x, y)| {
+                    let sq = x
+                        .iter()
+                        .zip(y)
+                        .map(|(&x, &y)| (y - x) * (y - x))
+                        .sum::<f32>();
+                    libm::sqrtf(sq)
+                })
+                .enumerate()
+                .filter(|&(_, d)| d > 0.0)
+                .collect();
+            rows.sort_by(|x, y| y.1.total_cmp(&x.1).then(x.0.cmp(&y.0)));
+            rows.truncate(top);
+        }
+
+        Self {
+            name,
+            shape,
+            l2: libm::sqrt(l2) as f32,
+            linf,
+            rel_l2,
+            rows,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Collect(Vec<(String, Vec<usize>, Vec<f32>)>);
+
+impl ParamVisitor for Collect {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let values = param.values.iter().map(|x| x.to_f32()).collect();
+        let shape = param.shape.to_vec();
+        self.0.push((param.name.into(), shape, values));
+    }
+}
+
+/// Differences between every tensor of parameters of two networks,
+/// in the order they are visited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diff {
+    tensors: Vec<TensorDiff>,
+}
+
+impl Diff {
+    /// Compares `a` against `b`, keeping the `top` most
+    /// changed rows of each tensor.
+    pub fn of<T: FeedForwardNetwork>(a: &T, b: &T, top: usize) -> Self {
+        let mut xs = Collect::default();
+        a.visit_params("", &mut xs);
+        let mut ys = Collect::default();
+        b.visit_params("", &mut ys);
+        let (Collect(xs), Collect(ys)) = (xs, ys);
+
+        let tensors = xs
+            .into_iter()
+            .zip(ys)
+            .map(|((name, shape, x), (_, _, y))| TensorDiff::new(name, shape, &x, &y, top))
+            .collect();
+
+        Self { tensors }
+    }
+
+    #[cfg(feature = "std")]
+    /// Loads networks of type `T` saved with
+    /// [`save`](FeedForwardNetwork::save) at `a` and `b`, and compares them.
+    pub fn load<T: FeedForwardNetwork>(a: &str, b: &str, top: usize) -> Result<Self, LoadError> {
+        let mut x = T::boxed_and_zeroed();
+        x.load(a)?;
+        let mut y = T::boxed_and_zeroed();
+        y.load(b)?;
+        Ok(Self::of(&*x, &*y, top))
+    }
+
+    pub fn tensors(&self) -> &[TensorDiff] {
+        &self.tensors
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TensorDiff> {
+        self.tensors.iter().find(|diff| diff.name == name)
+    }
+
+    /// Whether every parameter is identical.
+    pub fn is_identical(&self) -> bool {
+        self.tensors.iter().all(|diff| diff.linf == 0.0)
+    }
+}
+
+impl core::fmt::Display for Diff {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let width = self
+            .tensors
+            .iter()
+            .map(|diff| diff.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+
+        writeln!(
+            f,
+            "{:width$}  {:>11} {:>11} {:>11}  top rows",
+            "name", "l2", "linf", "rel l2"
+        )?;
+
+        for diff in &self.tensors {
+            write!(
+                f,
+                "{:width$}  {:>11.4e} {:>11.4e} {:>11.4e} ",
+                diff.name, diff.l2, diff.linf, diff.rel_l2
+            )?;
+            for (row, d) in &diff.rows {
+                write!(f, " {row}:{d:.3e}")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! Export of the topology of a network to [Graphviz](https://graphviz.org)
+//! DOT, for documentation and reviewing architectures.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, dot, layer::{DenseConnected, SparseConnected},
+//! #     FeedForwardNetwork,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let net = Net::boxed_and_zeroed();
+//! std::fs::write("net.dot", dot::to_dot(&*net)?)?;
+//! // dot -Tsvg net.dot -o net.svg
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use core::fmt::Write;
+
+use crate::{FeedForwardNetwork, Float, Graph, Op, Param, ParamVisitor, Unsupported};
+
+struct Shapes(BTreeMap<String, Vec<usize>>);
+
+impl ParamVisitor for Shapes {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.0.insert(param.name.into(), param.shape.to_vec());
+    }
+}
+
+fn activation_name(id: u8) -> String {
+    match id {
+        0 => "Identity".into(),
+        1 => "ReLU".into(),
+        2 => "SCReLU".into(),
+        3 => "Tanh".into(),
+        _ => format!("activation {id}"),
+    }
+}
+
+/// Name of the layer owning the tensor of parameters `param`.
+fn layer_name(param: &str) -> &str {
+    param.rsplit_once('.').map_or("", |(layer, _)| layer)
+}
+
+/// A node of the DOT graph, being either a layer with its
+/// activation, or an operation without parameters.
+struct Block {
+    label: String,
+    inputs: Vec<String>,
+}
+
+/// Describes the layers of `net` as a DOT `digraph`, with a node for
+/// each layer, labelled with its name, operation, activation and output
+/// size, and an edge for each flow of data between them, including
+/// those skipping over layers.
+/// - Fails if any layer does not support [`trace`](FeedForwardNetwork::trace).
+pub fn to_dot<T: FeedForwardNetwork>(net: &T) -> Result<String, Unsupported> {
+    let mut graph = Graph::default();
+    net.trace("", Graph::INPUT, &mut graph)?;
+
+    let mut shapes = Shapes(BTreeMap::new());
+    net.visit_params("", &mut shapes);
+
+    let mut blocks: Vec<Block> = Vec::new();
+    // tensor name -> (index of block producing it, size)
+    let mut produced: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+    let mut input = String::from("input");
+
+    for node in graph.nodes() {
+        if let Op::Activation(id) = node.op {
+            let fused = produced
+                .get(node.inputs[0].as_str())
+                .copied()
+                .filter(|&(block, _)| !blocks[block].label.contains(" + "));
+
+            if let Some((block, size)) = fused {
+                if id != 0 {
+                    let label = &mut blocks[block].label;
+                    let (head, tail) = label.rsplit_once("\\n").unwrap_or((label, ""));
+                    *label = format!("{head} + {}\\n{tail}", activation_name(id));
+                }
+                produced.insert(&node.output, (block, size));
+                continue;
+            }
+        }
+
+        let (layer, op) = match &node.op {
+            Op::Linear { weight, .. } => (layer_name(weight), String::from("Linear")),
+            Op::SparseLinear { weight, .. } => (layer_name(weight), String::from("SparseLinear")),
+            Op::Conv1D { weight, kernel, .. } => (layer_name(weight), format!("Conv1D k={kernel}")),
+            Op::Activation(id) => ("", activation_name(*id)),
+            Op::Add => ("", String::from("Add")),
+        };
+
+        if node.inputs.iter().any(|x| x == Graph::INPUT) {
+            input = match &node.op {
+                Op::SparseLinear { weight, .. } => {
+                    format!("input\\nsparse [{}]", shapes.0[weight][0])
+                }
+                Op::Linear { weight, .. } => format!("input\\n[{}]", shapes.0[weight][1]),
+                Op::Conv1D { weight, .. } => format!("input\\n[{}]", shapes.0[weight][0]),
+                _ => input,
+            };
+        }
+
+        let label = if layer.is_empty() {
+            format!("{op}\\n[{}]", node.size)
+        } else {
+            format!("{layer}\\n{op}\\n[{}]", node.size)
+        };
+
+        produced.insert(&node.output, (blocks.len(), node.size));
+        blocks.push(Block {
+            label,
+            inputs: node.inputs.clone(),
+        });
+    }
+
+    let mut out = String::new();
+    let id = |tensor: &str| match produced.get(tensor) {
+        Some((block, _)) => format!("n{block}"),
+        None => String::from("input"),
+    };
+    let size = |tensor: &str| produced.get(tensor).map(|&(_, size)| size);
+
+    writeln!(out, "digraph network {{").unwrap();
+    writeln!(out, "    node [shape=box];").unwrap();
+    writeln!(out, "    input [shape=ellipse, label=\"{input}\"];").unwrap();
+
+    for (i, block) in blocks.iter().enumerate() {
+        writeln!(out, "    n{i} [label=\"{}\"];", block.label).unwrap();
+    }
+
+    for (i, block) in blocks.iter().enumerate() {
+        for tensor in &block.inputs {
+            match size(tensor) {
+                Some(size) => writeln!(out, "    {} -> n{i} [label=\"[{size}]\"];", id(tensor)),
+                None => writeln!(out, "    input -> n{i};"),
+            }
+            .unwrap();
+        }
+    }
+
+    writeln!(out, "    output [shape=ellipse];").unwrap();
+    match size(graph.output()) {
+        Some(size) => writeln!(
+            out,
+            "    {} -> output [label=\"[{size}]\"];",
+            id(graph.output())
+        ),
+        None => writeln!(out, "    input -> output;"),
+    }
+    .unwrap();
+    writeln!(out, "}}").unwrap();
+
+    Ok(out)
+}
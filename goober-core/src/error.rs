@@ -0,0 +1,86 @@
+use core::alloc::Layout;
+
+#[cfg(feature = "std")]
+use crate::LoadError;
+use crate::{FeatureOutOfBounds, LengthMismatch, Unsupported};
+
+/// Any error returned by the fallible APIs of the crate, for callers,
+/// such as engines, which handle every failure alike, converted from
+/// each of the more specific errors by `?`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum GooberError {
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Loading a saved network failed.
+    #[cfg(feature = "std")]
+    Load(LoadError),
+    /// Data was not of the shape expected.
+    LengthMismatch(LengthMismatch),
+    FeatureOutOfBounds(FeatureOutOfBounds),
+    Unsupported(Unsupported),
+    /// Allocating memory of the given layout failed.
+    Alloc(Layout),
+}
+
+impl core::fmt::Display for GooberError {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => write!(f, "{err}"),
+            #[cfg(feature = "std")]
+            Self::Load(err) => write!(f, "{err}"),
+            Self::LengthMismatch(err) => write!(f, "{err}"),
+            Self::FeatureOutOfBounds(err) => write!(f, "{err}"),
+            Self::Unsupported(err) => write!(f, "{err}"),
+            Self::Alloc(layout) => write!(f, "failed to allocate {} bytes", layout.size()),
+        }
+    }
+}
+
+impl core::error::Error for GooberError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(err) => Some(err),
+            #[cfg(feature = "std")]
+            Self::Load(err) => Some(err),
+            Self::LengthMismatch(err) => Some(err),
+            Self::FeatureOutOfBounds(err) => Some(err),
+            Self::Unsupported(err) => Some(err),
+            Self::Alloc(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for GooberError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<LoadError> for GooberError {
+    fn from(err: LoadError) -> Self {
+        Self::Load(err)
+    }
+}
+
+impl From<LengthMismatch> for GooberError {
+    fn from(err: LengthMismatch) -> Self {
+        Self::LengthMismatch(err)
+    }
+}
+
+impl From<FeatureOutOfBounds> for GooberError {
+    fn from(err: FeatureOutOfBounds) -> Self {
+        Self::FeatureOutOfBounds(err)
+    }
+}
+
+impl From<Unsupported> for GooberError {
+    fn from(err: Unsupported) -> Self {
+        Self::Unsupported(err)
+    }
+}
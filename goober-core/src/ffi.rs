@@ -0,0 +1,245 @@
+//! Inference from C, through functions generated for a concrete network
+//! by [`export_c_api!`](crate::export_c_api), suitable for `cbindgen`.
+//! - Networks are created from bytes written by
+//!   [`save`](FeedForwardNetwork::save), and passed to C as opaque pointers.
+//! - Forward passes return the number of outputs written, or `-1` if the
+//!   input is invalid for the network or the output buffer is too small.
+
+use std::{ffi::c_void, panic::AssertUnwindSafe};
+
+use crate::{FeedForwardNetwork, Float, SparseVector, Vector};
+
+/// Input to a network which can be read from C buffers.
+pub trait CInput: Sized {
+    /// Reads an input from `len` floats at `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads of `len` floats.
+    unsafe fn from_dense(ptr: *const f32, len: usize) -> Option<Self> {
+        let _ = (ptr, len);
+        None
+    }
+
+    /// Reads an input from `len` active feature indices at `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads of `len` indices.
+    unsafe fn from_sparse(ptr: *const usize, len: usize) -> Option<Self> {
+        let _ = (ptr, len);
+        None
+    }
+}
+
+/// Output of a network which can be written to a C buffer.
+pub trait COutput {
+    /// Writes the output to the start of `out`, returning the number
+    /// of floats written, or `None` if `out` is too small.
+    fn write(&self, out: &mut [f32]) -> Option<usize>;
+}
+
+/// # Safety
+/// `ptr` must be null or valid for reads of `len` elements.
+unsafe fn slice<'a, T>(ptr: *const T, len: usize) -> Option<&'a [T]> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Some(&[]),
+        (true, _) => None,
+        // SAFETY: guaranteed by the caller
+        (false, _) => Some(unsafe { std::slice::from_raw_parts(ptr, len) }),
+    }
+}
+
+impl<const N: usize, F: Float> CInput for Vector<N, F> {
+    unsafe fn from_dense(ptr: *const f32, len: usize) -> Option<Self> {
+        // SAFETY: guaranteed by the caller
+        let input = unsafe { slice(ptr, len)? };
+        (len == N).then(|| Self::from_fn(|i| F::from_f32(input[i])))
+    }
+}
+
+impl CInput for SparseVector {
+    unsafe fn from_sparse(ptr: *const usize, len: usize) -> Option<Self> {
+        // SAFETY: guaranteed by the caller
+        unsafe { slice(ptr, len) }.map(Self::from_slice)
+    }
+}
+
+impl<const N: usize, F: Float> COutput for Vector<N, F> {
+    fn write(&self, out: &mut [f32]) -> Option<usize> {
+        let out = out.get_mut(..N)?;
+        for (out, x) in out.iter_mut().zip(self.as_slice()) {
+            *out = x.to_f32();
+        }
+        Some(N)
+    }
+}
+
+/// Creates a network from `len` bytes at `bytes`,
+/// returning null if they are not a valid save of it.
+///
+/// # Safety
+/// `bytes` must be valid for reads of `len` bytes.
+pub unsafe fn create<T: FeedForwardNetwork>(bytes: *const u8, len: usize) -> *mut c_void {
+    // SAFETY: guaranteed by the caller
+    let Some(bytes) = (unsafe { slice(bytes, len) }) else {
+        return std::ptr::null_mut();
+    };
+
+    let mut net = T::boxed_and_zeroed();
+    match net.load_bytes(bytes) {
+        Ok(()) => Box::into_raw(net).cast(),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// # Safety
+/// `net` must have been returned by [`create`] for `T`, and `out`
+/// must be null or valid for writes of `out_len` floats.
+unsafe fn forward<T: FeedForwardNetwork>(
+    net: *const c_void,
+    input: Option<T::InputType>,
+    out: *mut f32,
+    out_len: usize,
+) -> isize
+where
+    T::OutputType: COutput,
+{
+    let (Some(input), false) = (input, net.is_null()) else {
+        return -1;
+    };
+
+    // SAFETY: guaranteed by the caller
+    let net = unsafe { &*net.cast::<T>() };
+    let out = match (out.is_null(), out_len) {
+        (_, 0) => &mut [],
+        (true, _) => return -1,
+        // SAFETY: guaranteed by the caller
+        (false, _) => unsafe { std::slice::from_raw_parts_mut(out, out_len) },
+    };
+
+    // panics cannot unwind into C, so report them as invalid inputs
+    std::panic::catch_unwind(AssertUnwindSafe(|| net.out(&input)))
+        .ok()
+        .and_then(|output| output.write(out))
+        .map_or(-1, |written| written as isize)
+}
+
+/// Computes the output of `net` on `len` floats at `input`.
+///
+/// # Safety
+/// `net` must have been returned by [`create`] for `T`, `input` must be
+/// valid for reads of `len` floats and `out` for writes of `out_len`.
+pub unsafe fn forward_dense<T: FeedForwardNetwork>(
+    net: *const c_void,
+    input: *const f32,
+    len: usize,
+    out: *mut f32,
+    out_len: usize,
+) -> isize
+where
+    T::InputType: CInput,
+    T::OutputType: COutput,
+{
+    // SAFETY: guaranteed by the caller
+    unsafe {
+        let input = T::InputType::from_dense(input, len);
+        forward::<T>(net, input, out, out_len)
+    }
+}
+
+/// Computes the output of `net` on `len` active feature indices at `input`.
+///
+/// # Safety
+/// As for [`forward_dense`], with `input` valid for reads of `len` indices.
+pub unsafe fn forward_sparse<T: FeedForwardNetwork>(
+    net: *const c_void,
+    input: *const usize,
+    len: usize,
+    out: *mut f32,
+    out_len: usize,
+) -> isize
+where
+    T::InputType: CInput,
+    T::OutputType: COutput,
+{
+    // SAFETY: guaranteed by the caller
+    unsafe {
+        let input = T::InputType::from_sparse(input, len);
+        forward::<T>(net, input, out, out_len)
+    }
+}
+
+/// Destroys a network returned by [`create`].
+///
+/// # Safety
+/// `net` must be null or have been returned by [`create`] for `T`,
+/// and must not be used afterwards.
+pub unsafe fn destroy<T: FeedForwardNetwork>(net: *mut c_void) {
+    if !net.is_null() {
+        // SAFETY: guaranteed by the caller
+        drop(unsafe { Box::from_raw(net.cast::<T>()) });
+    }
+}
+
+/// Exports the C inference API for the network `$t`, as the functions
+/// `goober_create`, `goober_forward_dense`, `goober_forward_sparse`
+/// and `goober_destroy`, so can be invoked once per library.
+#[macro_export]
+macro_rules! export_c_api {
+    ($t:ty) => {
+        /// Creates a network from a save of it, returning null if invalid.
+        ///
+        /// # Safety
+        /// `bytes` must be valid for reads of `len` bytes.
+        #[no_mangle]
+        pub unsafe extern "C" fn goober_create(
+            bytes: *const u8,
+            len: usize,
+        ) -> *mut ::std::ffi::c_void {
+            unsafe { $crate::ffi::create::<$t>(bytes, len) }
+        }
+
+        /// Computes the output on a dense input, returning the
+        /// number of outputs written, or `-1` on failure.
+        ///
+        /// # Safety
+        /// `net` must have been returned by `goober_create`, `input` must
+        /// be valid for reads of `len` floats and `out` for writes of `out_len`.
+        #[no_mangle]
+        pub unsafe extern "C" fn goober_forward_dense(
+            net: *const ::std::ffi::c_void,
+            input: *const f32,
+            len: usize,
+            out: *mut f32,
+            out_len: usize,
+        ) -> isize {
+            unsafe { $crate::ffi::forward_dense::<$t>(net, input, len, out, out_len) }
+        }
+
+        /// Computes the output on active feature indices, returning
+        /// the number of outputs written, or `-1` on failure.
+        ///
+        /// # Safety
+        /// As for `goober_forward_dense`, with `input`
+        /// valid for reads of `len` indices.
+        #[no_mangle]
+        pub unsafe extern "C" fn goober_forward_sparse(
+            net: *const ::std::ffi::c_void,
+            input: *const usize,
+            len: usize,
+            out: *mut f32,
+            out_len: usize,
+        ) -> isize {
+            unsafe { $crate::ffi::forward_sparse::<$t>(net, input, len, out, out_len) }
+        }
+
+        /// Destroys a network returned by `goober_create`.
+        ///
+        /// # Safety
+        /// `net` must be null or have been returned by
+        /// `goober_create`, and must not be used afterwards.
+        #[no_mangle]
+        pub unsafe extern "C" fn goober_destroy(net: *mut ::std::ffi::c_void) {
+            unsafe { $crate::ffi::destroy::<$t>(net) }
+        }
+    };
+}
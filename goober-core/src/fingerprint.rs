@@ -0,0 +1,48 @@
+use alloc::vec::Vec;
+
+use crate::{FeedForwardNetwork, Float, Param, ParamVisitor};
+
+/// 64-bit FNV-1a, which is stable across platforms and releases.
+struct Fnv {
+    hash: u64,
+    buf: Vec<u8>,
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= u64::from(byte);
+            self.hash = self.hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+impl ParamVisitor for Fnv {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.write(&(param.name.len() as u64).to_le_bytes());
+        self.write(param.name.as_bytes());
+        self.write(&[F::ID]);
+
+        self.write(&(param.shape.len() as u64).to_le_bytes());
+        for &dim in param.shape {
+            self.write(&(dim as u64).to_le_bytes());
+        }
+
+        self.buf.clear();
+        for &x in param.values {
+            x.write_le(&mut self.buf);
+        }
+        let buf = core::mem::take(&mut self.buf);
+        self.write(&buf);
+        self.buf = buf;
+    }
+}
+
+pub fn fingerprint<T: FeedForwardNetwork>(net: &T) -> u64 {
+    let mut fnv = Fnv {
+        hash: 0xcbf2_9ce4_8422_2325,
+        buf: Vec::new(),
+    };
+    net.visit_params("", &mut fnv);
+    fnv.hash
+}
@@ -0,0 +1,331 @@
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::Rand;
+
+pub use half::{bf16, f16};
+
+/// Scalar type that weights, activations and optimiser
+/// state can be stored as.
+/// - All arithmetic is performed in `Self::Compute`, with values
+///   converted to and from the storage type inside each op.
+pub trait Float: Copy + core::fmt::Debug + PartialEq + 'static {
+    type Compute: Real;
+
+    const ZERO: Self;
+
+    /// Identifies the storage format when serialized.
+    const ID: u8;
+
+    /// Size in bytes when serialized.
+    const BYTES: usize;
+
+    fn from_compute(x: Self::Compute) -> Self;
+
+    fn to_compute(self) -> Self::Compute;
+
+    fn from_f32(x: f32) -> Self {
+        Self::from_compute(Self::Compute::from_f32(x))
+    }
+
+    fn to_f32(self) -> f32 {
+        self.to_compute().to_f32()
+    }
+
+    fn to_f64(self) -> f64 {
+        f64::from(self.to_f32())
+    }
+
+    /// Rounds `x` to one of its two nearest representable neighbours,
+    /// with probability proportional to its proximity to each.
+    /// - `rand` is uniformly distributed in `[0, 1)`.
+    fn from_compute_stochastic(x: Self::Compute, _rand: f32) -> Self {
+        Self::from_compute(x)
+    }
+
+    /// Converts a weight or optimiser state after it has been
+    /// updated by the optimiser.
+    fn from_update(x: Self::Compute) -> Self {
+        Self::from_compute(x)
+    }
+
+    fn write_le(self, out: &mut Vec<u8>);
+
+    /// Reads a value from the first `Self::BYTES` bytes of `bytes`.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+/// Scalar type that arithmetic can be performed in.
+pub trait Real:
+    Float<Compute = Self>
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    const ONE: Self;
+
+    fn from_f64(x: f64) -> Self;
+
+    fn sqrt(self) -> Self;
+
+    fn tanh(self) -> Self;
+
+    fn max(self, other: Self) -> Self;
+
+    fn clamp(self, min: Self, max: Self) -> Self;
+}
+
+/// Functions of floats which are only inherent with `std`,
+/// and are otherwise provided by `libm`.
+trait Math {
+    fn sqrt(self) -> Self;
+
+    fn tanh(self) -> Self;
+}
+
+macro_rules! impl_math {
+    ($t:ty, $sqrt:path, $tanh:path) => {
+        impl Math for $t {
+            #[cfg(feature = "std")]
+            fn sqrt(self) -> Self {
+                <$t>::sqrt(self)
+            }
+
+            #[cfg(not(feature = "std"))]
+            fn sqrt(self) -> Self {
+                $sqrt(self)
+            }
+
+            #[cfg(feature = "std")]
+            fn tanh(self) -> Self {
+                <$t>::tanh(self)
+            }
+
+            #[cfg(not(feature = "std"))]
+            fn tanh(self) -> Self {
+                $tanh(self)
+            }
+        }
+    };
+}
+
+impl_math!(f32, libm::sqrtf, libm::tanhf);
+impl_math!(f64, libm::sqrt, libm::tanh);
+
+macro_rules! impl_real {
+    ($t:ty, $id:expr) => {
+        impl Float for $t {
+            type Compute = $t;
+
+            const ZERO: Self = 0.0;
+
+            const ID: u8 = $id;
+
+            const BYTES: usize = core::mem::size_of::<$t>();
+
+            #[inline]
+            fn from_compute(x: Self) -> Self {
+                x
+            }
+
+            #[inline]
+            fn to_compute(self) -> Self {
+                self
+            }
+
+            #[inline]
+            fn from_f32(x: f32) -> Self {
+                x as $t
+            }
+
+            #[inline]
+            fn to_f32(self) -> f32 {
+                self as f32
+            }
+
+            #[inline]
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn write_le(self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_le(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes(bytes[..Self::BYTES].try_into().unwrap())
+            }
+        }
+
+        impl Real for $t {
+            const ONE: Self = 1.0;
+
+            #[inline]
+            fn from_f64(x: f64) -> Self {
+                x as $t
+            }
+
+            #[inline]
+            fn sqrt(self) -> Self {
+                Math::sqrt(self)
+            }
+
+            #[inline]
+            fn tanh(self) -> Self {
+                Math::tanh(self)
+            }
+
+            #[inline]
+            fn max(self, other: Self) -> Self {
+                <$t>::max(self, other)
+            }
+
+            #[inline]
+            fn clamp(self, min: Self, max: Self) -> Self {
+                <$t>::clamp(self, min, max)
+            }
+        }
+    };
+}
+
+impl_real!(f32, 0);
+impl_real!(f64, 1);
+
+macro_rules! impl_half {
+    ($t:ty, $id:expr) => {
+        impl Float for $t {
+            type Compute = f32;
+
+            const ZERO: Self = <$t>::ZERO;
+
+            const ID: u8 = $id;
+
+            const BYTES: usize = 2;
+
+            #[inline]
+            fn from_compute(x: f32) -> Self {
+                <$t>::from_f32(x)
+            }
+
+            #[inline]
+            fn to_compute(self) -> f32 {
+                <$t>::to_f32(self)
+            }
+
+            fn from_compute_stochastic(x: f32, rand: f32) -> Self {
+                let abs = x.abs();
+                let nearest = <$t>::from_f32(abs);
+                let bits = nearest.to_bits();
+
+                let (lo, hi) = match nearest.to_f32() {
+                    y if y == abs => return <$t>::from_f32(x),
+                    y if y > abs => (<$t>::from_bits(bits - 1), nearest),
+                    _ => (nearest, <$t>::from_bits(bits + 1)),
+                };
+
+                let p = (abs - lo.to_f32()) / (hi.to_f32() - lo.to_f32());
+                let res = if rand < p { hi } else { lo };
+
+                if x < 0.0 {
+                    -res
+                } else {
+                    res
+                }
+            }
+
+            fn write_le(self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+
+            fn read_le(bytes: &[u8]) -> Self {
+                <$t>::from_le_bytes([bytes[0], bytes[1]])
+            }
+        }
+    };
+}
+
+impl_half!(f16, 2);
+impl_half!(bf16, 3);
+
+#[cfg(feature = "std")]
+thread_local! {
+    static ROUNDING_RNG: std::cell::Cell<Rand> = std::cell::Cell::new(Rand::default());
+}
+
+/// Seeds the generator used by [`Stochastic`] on the current thread.
+#[cfg(feature = "std")]
+pub fn seed_stochastic_rounding(seed: u64) {
+    ROUNDING_RNG.with(|rng| rng.set(Rand::with_seed(seed)));
+}
+
+/// Storage type wrapper which stochastically rounds weights and
+/// optimiser state after each update, avoiding the systematic bias
+/// of round-to-nearest when updates are small relative to the
+/// precision of `T`.
+/// - Without the `std` feature, there is no generator to round
+///   with, so updates are rounded to nearest.
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Stochastic<T>(pub T);
+
+impl<T: Float> Float for Stochastic<T> {
+    type Compute = T::Compute;
+
+    const ZERO: Self = Self(T::ZERO);
+
+    const ID: u8 = T::ID;
+
+    const BYTES: usize = T::BYTES;
+
+    #[inline]
+    fn from_compute(x: Self::Compute) -> Self {
+        Self(T::from_compute(x))
+    }
+
+    #[inline]
+    fn to_compute(self) -> Self::Compute {
+        self.0.to_compute()
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0.to_f64()
+    }
+
+    fn from_compute_stochastic(x: Self::Compute, rand: f32) -> Self {
+        Self(T::from_compute_stochastic(x, rand))
+    }
+
+    #[cfg(feature = "std")]
+    fn from_update(x: Self::Compute) -> Self {
+        let rand = ROUNDING_RNG.with(|rng| {
+            let mut state = rng.get();
+            let rand = state.rand_f32();
+            rng.set(state);
+            rand
+        });
+
+        Self::from_compute_stochastic(x, rand)
+    }
+
+    fn write_le(self, out: &mut Vec<u8>) {
+        self.0.write_le(out);
+    }
+
+    fn read_le(bytes: &[u8]) -> Self {
+        Self(T::read_le(bytes))
+    }
+}
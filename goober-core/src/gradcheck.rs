@@ -0,0 +1,236 @@
+//! Checking [`backprop`](FeedForwardNetwork::backprop) against gradients
+//! estimated by finite differences, for testing new layers.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, gradcheck::GradCheck, layer::{DenseConnected, SparseConnected},
+//! #     loss::Mse, FeedForwardNetwork, SparseVector, Vector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # let mut net = Net::boxed_and_zeroed();
+//! # let (input, target) = (SparseVector::from_slice(&[0]), Vector::from_raw([0.5]));
+//! let report = GradCheck::default().check(&mut *net, &input, &target, &Mse);
+//! assert!(report.passed(), "{report}");
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{
+    loss::Loss, FeedForwardNetwork, Float, Gradient, OutputLayer, Param, ParamMut, ParamVisitor,
+    ParamVisitorMut,
+};
+
+/// Settings of a gradient check.
+/// - Each checked weight is perturbed by `epsilon` either way, and the
+///   gradient estimated by the central difference of the loss.
+/// - A weight passes if either the absolute or the relative error of
+///   its gradient is within `tolerance`.
+/// - Activations with kinks, such as ReLU, can fail spuriously when a
+///   perturbation crosses the kink, so smooth activations make for
+///   more reliable checks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradCheck {
+    pub epsilon: f32,
+    pub tolerance: f32,
+    /// Maximum number of weights checked in each tensor, spread
+    /// evenly over it, so that large layers stay quick to check.
+    pub max_per_tensor: usize,
+}
+
+impl Default for GradCheck {
+    fn default() -> Self {
+        Self {
+            epsilon: 1e-3,
+            tolerance: 1e-2,
+            max_per_tensor: 64,
+        }
+    }
+}
+
+/// Result of checking a single tensor of parameters.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TensorCheck {
+    pub name: String,
+    /// Number of weights checked.
+    pub checked: usize,
+    /// Number of weights checked which failed.
+    pub failed: usize,
+    pub max_abs_error: f32,
+    pub max_rel_error: f32,
+}
+
+impl TensorCheck {
+    pub fn passed(&self) -> bool {
+        self.failed == 0
+    }
+}
+
+/// Result of [`GradCheck::check`], for every tensor of parameters
+/// in the order they are visited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradReport {
+    tensors: Vec<TensorCheck>,
+}
+
+impl GradReport {
+    pub fn tensors(&self) -> &[TensorCheck] {
+        &self.tensors
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TensorCheck> {
+        self.tensors.iter().find(|check| check.name == name)
+    }
+
+    pub fn passed(&self) -> bool {
+        self.tensors.iter().all(TensorCheck::passed)
+    }
+}
+
+impl core::fmt::Display for GradReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let width = self
+            .tensors
+            .iter()
+            .map(|check| check.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+
+        writeln!(
+            f,
+            "{:width$}  {:>7} {:>7} {:>11} {:>11}",
+            "name", "checked", "failed", "abs err", "rel err"
+        )?;
+
+        for check in &self.tensors {
+            writeln!(
+                f,
+                "{:width$}  {:>7} {:>7} {:>11.4e} {:>11.4e}",
+                check.name, check.checked, check.failed, check.max_abs_error, check.max_rel_error
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Copies the name and values of each tensor.
+#[derive(Default)]
+struct Collect(Vec<(String, Vec<f32>)>);
+
+impl ParamVisitor for Collect {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let values = param.values.iter().map(|x| x.to_f32()).collect();
+        self.0.push((param.name.into(), values));
+    }
+}
+
+/// Sets a single weight, returning the value it replaced.
+struct Set {
+    tensor: usize,
+    index: usize,
+    value: f32,
+    seen: usize,
+    old: f32,
+}
+
+impl ParamVisitorMut for Set {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        if self.seen == self.tensor {
+            self.old = param.values[self.index].to_f32();
+            param.values[self.index] = F::from_f32(self.value);
+        }
+        self.seen += 1;
+    }
+}
+
+fn set<T: FeedForwardNetwork>(net: &mut T, tensor: usize, index: usize, value: f32) -> f32 {
+    let mut set = Set {
+        tensor,
+        index,
+        value,
+        seen: 0,
+        old: 0.0,
+    };
+    net.visit_params_mut("", &mut set);
+    set.old
+}
+
+impl GradCheck {
+    /// Compares the gradient of `loss` of `net` on `input` against
+    /// `target`, as computed by `backprop`, with that estimated by
+    /// perturbing each checked weight of `net` in turn.
+    /// - `net` is left as it was.
+    pub fn check<T, L>(
+        &self,
+        net: &mut T,
+        input: &T::InputType,
+        target: &T::OutputType,
+        loss: &L,
+    ) -> GradReport
+    where
+        T: FeedForwardNetwork,
+        L: Loss<T::OutputType>,
+    {
+        let mut grad = T::zeroed_grad();
+        let layers = net.out_with_layers(input);
+        let (_, err) = loss.loss(&layers.output_layer(), target);
+        net.backprop(input, &mut grad, err, &layers);
+
+        let mut analytic = Collect::default();
+        grad.visit_grads("", &mut analytic);
+
+        let mut weights = Collect::default();
+        net.visit_params("", &mut weights);
+
+        let eval = |net: &T| loss.loss(&net.out(input), target).0;
+
+        let tensors = analytic
+            .0
+            .into_iter()
+            .zip(weights.0)
+            .enumerate()
+            .map(|(tensor, ((name, analytic), (_, weights)))| {
+                let len = weights.len();
+                let checked = len.min(self.max_per_tensor);
+                let mut check = TensorCheck {
+                    name,
+                    checked,
+                    failed: 0,
+                    max_abs_error: 0.0,
+                    max_rel_error: 0.0,
+                };
+
+                for i in 0..checked {
+                    let index = i * len / checked;
+                    let weight = weights[index];
+
+                    set(net, tensor, index, weight + self.epsilon);
+                    let plus = eval(net);
+                    set(net, tensor, index, weight - self.epsilon);
+                    let minus = eval(net);
+                    set(net, tensor, index, weight);
+
+                    let numeric = (plus - minus) / (2.0 * self.epsilon);
+                    let abs = libm::fabsf(analytic[index] - numeric);
+                    let scale = libm::fabsf(analytic[index]).max(libm::fabsf(numeric));
+                    let rel = if scale > 0.0 { abs / scale } else { 0.0 };
+
+                    check.max_abs_error = check.max_abs_error.max(abs);
+                    check.max_rel_error = check.max_rel_error.max(rel);
+                    if abs > self.tolerance && rel > self.tolerance {
+                        check.failed += 1;
+                    }
+                }
+
+                check
+            })
+            .collect();
+
+        GradReport { tensors }
+    }
+}
@@ -0,0 +1,83 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Operation in a traced [`Graph`], referring to tensors of
+/// parameters by the names they are visited with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Op {
+    /// `W x + b`, with `W` of shape `[N, M]`.
+    Linear { weight: String, bias: String },
+    /// Sum of the rows of `W`, of shape `[M, N]`, selected
+    /// by the active feature indices of the input, plus `b`.
+    SparseLinear { weight: String, bias: String },
+    /// Valid 1D convolution of the input with the first
+    /// `kernel` elements of `weight`, plus `bias`.
+    Conv1D {
+        weight: String,
+        bias: String,
+        kernel: usize,
+    },
+    /// Activation function with the given
+    /// [`ID`](crate::activation::Activation::ID).
+    Activation(u8),
+    /// Elementwise sum of the two inputs.
+    Add,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub op: Op,
+    pub inputs: Vec<String>,
+    pub output: String,
+    /// Size of the output vector.
+    pub size: usize,
+}
+
+/// Sequence of operations computing the output of a network,
+/// recorded by [`FeedForwardNetwork::trace`](crate::FeedForwardNetwork::trace).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Graph {
+    nodes: Vec<Node>,
+}
+
+/// Error returned when tracing a layer
+/// which does not support it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unsupported(pub String);
+
+impl core::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "unsupported: {}", self.0)
+    }
+}
+
+impl core::error::Error for Unsupported {}
+
+impl Graph {
+    /// Name of the input to the network.
+    pub const INPUT: &'static str = "input";
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Name of the output of the network.
+    pub fn output(&self) -> &str {
+        self.nodes.last().map_or(Self::INPUT, |node| &node.output)
+    }
+
+    /// Appends an operation, returning the name of its output.
+    pub fn push(&mut self, op: Op, inputs: &[&str], size: usize) -> String {
+        let output = format!("t{}", self.nodes.len());
+        self.nodes.push(Node {
+            op,
+            inputs: inputs.iter().map(|x| x.to_string()).collect(),
+            output: output.clone(),
+            size,
+        });
+        output
+    }
+}
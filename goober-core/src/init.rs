@@ -0,0 +1,419 @@
+//! Distributions of the initial weights of layers, which break the
+//! symmetry between their units, as all-zero weights do not, so that
+//! layers other than the last can learn.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh},
+//! #     init::Lsuv,
+//! #     layer::{DenseConnected, SparseConnected},
+//! #     FeedForwardNetwork, Rand, SparseVector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<ReLU, 32, 32>,
+//! #     l3: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # let inputs: Vec<SparseVector> = Vec::new();
+//! let mut rng = Rand::with_seed(42);
+//! let mut net = Net::boxed_and_zeroed();
+//! net.l1 = SparseConnected::glorot_uniform(&mut rng);
+//! net.l2 = DenseConnected::glorot_normal(&mut rng);
+//! net.l3 = DenseConnected::orthogonal(&mut rng, 1.0);
+//! Lsuv::default().run(&mut *net, &inputs);
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{
+    ActivationVisitor, FeedForwardNetwork, Float, Param, ParamMut, ParamVisitor, ParamVisitorMut,
+    Rand,
+};
+
+/// Distribution drawn from for each weight.
+pub trait Distribution {
+    fn sample(&self, rng: &mut Rand) -> f32;
+}
+
+impl<D: Distribution + ?Sized> Distribution for &D {
+    fn sample(&self, rng: &mut Rand) -> f32 {
+        (**self).sample(rng)
+    }
+}
+
+/// Uniform distribution over `[low, high)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Uniform {
+    pub low: f32,
+    pub high: f32,
+}
+
+impl Uniform {
+    pub fn new(low: f32, high: f32) -> Self {
+        Self { low, high }
+    }
+
+    /// Uniform distribution over `[-limit, limit)`.
+    pub fn symmetric(limit: f32) -> Self {
+        Self::new(-limit, limit)
+    }
+}
+
+impl Distribution for Uniform {
+    fn sample(&self, rng: &mut Rand) -> f32 {
+        self.low + (self.high - self.low) * rng.rand_f32()
+    }
+}
+
+/// Normal distribution of mean `mean` and standard deviation `std`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Normal {
+    pub mean: f32,
+    pub std: f32,
+}
+
+impl Normal {
+    pub fn new(mean: f32, std: f32) -> Self {
+        Self { mean, std }
+    }
+}
+
+impl Distribution for Normal {
+    fn sample(&self, rng: &mut Rand) -> f32 {
+        self.mean + self.std * rng.rand_normal()
+    }
+}
+
+/// Glorot, or Xavier, uniform distribution for a layer of `fan_in` inputs
+/// and `fan_out` outputs, keeping the variance of activations and their
+/// gradients alike through layers without activations or with `tanh`.
+/// - Bounded by `sqrt(6 / (fan_in + fan_out))`.
+pub fn glorot_uniform(fan_in: usize, fan_out: usize) -> Uniform {
+    Uniform::symmetric(libm::sqrtf(6.0 / (fan_in + fan_out) as f32))
+}
+
+/// Glorot, or Xavier, normal distribution, as [`glorot_uniform`].
+/// - Of standard deviation `sqrt(2 / (fan_in + fan_out))`.
+pub fn glorot_normal(fan_in: usize, fan_out: usize) -> Normal {
+    Normal::new(0.0, libm::sqrtf(2.0 / (fan_in + fan_out) as f32))
+}
+
+/// He, or Kaiming, uniform distribution for a layer of `fan_in` inputs,
+/// keeping the variance of activations alike through layers with `ReLU`
+/// and its relatives, which zero half of their inputs.
+/// - Bounded by `sqrt(6 / fan_in)`.
+pub fn he_uniform(fan_in: usize) -> Uniform {
+    Uniform::symmetric(libm::sqrtf(6.0 / fan_in as f32))
+}
+
+/// He, or Kaiming, normal distribution, as [`he_uniform`].
+/// - Of standard deviation `sqrt(2 / fan_in)`.
+pub fn he_normal(fan_in: usize) -> Normal {
+    Normal::new(0.0, libm::sqrtf(2.0 / fan_in as f32))
+}
+
+/// Random `rows` by `cols` matrix, in row-major order, with orthonormal
+/// rows, or orthonormal columns if there are more rows than columns, so
+/// that multiplying by it preserves the lengths of vectors, as far as
+/// its shape allows, however many layers it is stacked through.
+/// - Made by Gram-Schmidt orthogonalisation of normally distributed
+///   vectors, so drawn uniformly among such matrices.
+pub fn orthogonal(rng: &mut Rand, rows: usize, cols: usize) -> Vec<f32> {
+    let (count, len) = (rows.min(cols), rows.max(cols));
+    let mut basis: Vec<Vec<f64>> = Vec::with_capacity(count);
+
+    while basis.len() < count {
+        let mut v: Vec<f64> = (0..len).map(|_| f64::from(rng.rand_normal())).collect();
+        // orthogonalising twice keeps rounding errors from accumulating
+        for _ in 0..2 {
+            for u in &basis {
+                let dot: f64 = u.iter().zip(&v).map(|(a, b)| a * b).sum();
+                for (x, a) in v.iter_mut().zip(u) {
+                    *x -= dot * a;
+                }
+            }
+        }
+
+        let norm = libm::sqrt(v.iter().map(|x| x * x).sum());
+        // draw again in the unlikely case `v` was nearly dependent
+        if norm > 1e-6 {
+            v.iter_mut().for_each(|x| *x /= norm);
+            basis.push(v);
+        }
+    }
+
+    let mut matrix = vec![0.0; rows * cols];
+    for (i, u) in basis.iter().enumerate() {
+        for (j, &x) in u.iter().enumerate() {
+            match rows <= cols {
+                true => matrix[i * cols + j] = x as f32,
+                false => matrix[j * cols + i] = x as f32,
+            }
+        }
+    }
+    matrix
+}
+
+/// `k` distinct indices less than `n`, chosen uniformly, in no particular
+/// order, such as the inputs of a unit given non-zero weights by a sparse
+/// initialisation, or all `n` if `k` is at least `n`.
+/// - By Floyd's algorithm, taking time quadratic in `k`, for small `k`.
+pub fn choose(rng: &mut Rand, n: usize, k: usize) -> Vec<usize> {
+    let mut chosen = Vec::with_capacity(k.min(n));
+    for j in n - k.min(n)..n {
+        let t = (rng.rand_u64() % (j as u64 + 1)) as usize;
+        chosen.push(if chosen.contains(&t) { j } else { t });
+    }
+    chosen
+}
+
+/// Visitor setting every parameter of a network, biases included, to
+/// a draw from `dist`, such as a quick start for networks small enough
+/// not to need an initialisation chosen per layer.
+///
+/// ```no_run
+/// # use goober::{
+/// #     activation::{ReLU, Tanh}, init::{Randomize, Uniform}, layer::{DenseConnected, SparseConnected},
+/// #     FeedForwardNetwork, Rand,
+/// # };
+/// # #[derive(FeedForwardNetwork)]
+/// # pub struct Net {
+/// #     l1: SparseConnected<ReLU, 768, 32>,
+/// #     l2: DenseConnected<Tanh, 32, 1>,
+/// # }
+/// let mut net = Net::boxed_and_zeroed();
+/// let mut init = Randomize::new(Rand::with_seed(42), Uniform::symmetric(0.1));
+/// net.visit_params_mut("", &mut init);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Randomize<D> {
+    pub rng: Rand,
+    pub dist: D,
+}
+
+impl<D: Distribution> Randomize<D> {
+    pub fn new(rng: Rand, dist: D) -> Self {
+        Self { rng, dist }
+    }
+}
+
+impl<D: Distribution> ParamVisitorMut for Randomize<D> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        for x in param.values {
+            *x = F::from_f32(self.dist.sample(&mut self.rng));
+        }
+    }
+}
+
+/// Settings of layer-sequential unit-variance initialisation, which
+/// rescales the weights of each layer in turn, from the first, until its
+/// outputs over sample inputs have unit variance, so that a deep network
+/// starts neither vanishing nor exploding, whatever its activations.
+/// - Run after initialising the weights, such as [`orthogonal`]ly.
+/// - The variance is of the outputs of each layer after its activation,
+///   as seen by [`visit_activations`](FeedForwardNetwork::visit_activations),
+///   so layers which do not report their outputs are left unchanged.
+/// - Biases are not rescaled, so the rescaling is exact only for layers
+///   with zero biases and activations such as `ReLU`, which commute with
+///   it, and otherwise converges over iterations, if the activation can
+///   reach unit variance at all.
+/// - A rescale which would move the variance away from 1, as when
+///   activating many more units of a layer with negative biases, or
+///   saturating a clipped activation such as `Tanh`, which never
+///   reaches unit variance, is undone, and retried at half the size.
+/// - The output layer is left unchanged, as the scale of its outputs is
+///   set by the targets, rather than by the layers after it.
+///
+/// ```no_run
+/// # use goober::{
+/// #     activation::{ReLU, Tanh}, init::Lsuv, layer::{DenseConnected, SparseConnected},
+/// #     FeedForwardNetwork, SparseVector, Vector,
+/// # };
+/// # #[derive(FeedForwardNetwork)]
+/// # pub struct Net {
+/// #     l1: SparseConnected<ReLU, 768, 32>,
+/// #     l2: DenseConnected<Tanh, 32, 1>,
+/// # }
+/// # let mut net = Net::boxed_and_zeroed();
+/// # let data: Vec<(SparseVector, Vector<1>)> = Vec::new();
+/// let inputs: Vec<_> = data[..1024].iter().map(|(input, _)| input.clone()).collect();
+/// for (layer, variance) in Lsuv::default().run(&mut *net, &inputs) {
+///     println!("{layer}: {variance}");
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lsuv {
+    /// Stops rescaling a layer once its variance is within this of 1.
+    pub tolerance: f32,
+    /// Most times to rescale each layer.
+    pub max_iterations: usize,
+}
+
+impl Default for Lsuv {
+    fn default() -> Self {
+        Self {
+            tolerance: 0.05,
+            max_iterations: 10,
+        }
+    }
+}
+
+impl Lsuv {
+    /// Rescales the weights of each layer of `net` in turn, measuring the
+    /// variance of its outputs over `inputs`, returning the name of each
+    /// layer with the variance of its outputs once done.
+    /// - Layers of no variance, such as those whose units are all dead,
+    ///   are left as they are, as is the output layer, whose variance is
+    ///   still returned.
+    pub fn run<T: FeedForwardNetwork>(
+        &self,
+        net: &mut T,
+        inputs: &[T::InputType],
+    ) -> Vec<(String, f32)> {
+        let Some(first) = inputs.first() else {
+            return Vec::new();
+        };
+
+        let mut layers = LayerNames(Vec::new());
+        net.visit_activations("", &net.out_with_layers(first), &mut layers);
+        let mut params = ParamNames(Vec::new());
+        net.visit_params("", &mut params);
+
+        let output = layers
+            .0
+            .last()
+            .and_then(|layer| owner(&params.0, layer))
+            .map(String::from);
+
+        let mut variances = Vec::with_capacity(layers.0.len());
+        for layer in layers.0 {
+            let mut variance = output_variance(net, inputs, &layer);
+            let prefix =
+                owner(&params.0, &layer).filter(|&prefix| Some(prefix) != output.as_deref());
+            if let Some(prefix) = prefix {
+                // fraction of the rescale towards unit variance to take,
+                // halved whenever a rescale moves away from it
+                let mut step = 1.0;
+                for _ in 0..self.max_iterations {
+                    let distance = libm::fabsf(variance - 1.0);
+                    if distance <= self.tolerance || !variance.is_normal() {
+                        break;
+                    }
+
+                    let scale = libm::powf(variance, -0.5 * step);
+                    net.visit_params_mut("", &mut Rescale { prefix, scale });
+                    let rescaled = output_variance(net, inputs, &layer);
+
+                    if libm::fabsf(rescaled - 1.0) < distance {
+                        variance = rescaled;
+                    } else {
+                        let scale = 1.0 / scale;
+                        net.visit_params_mut("", &mut Rescale { prefix, scale });
+                        step /= 2.0;
+                    }
+                }
+            }
+            variances.push((layer, variance));
+        }
+        variances
+    }
+}
+
+/// Names of the layers reporting their outputs, in order.
+struct LayerNames(Vec<String>);
+
+impl ActivationVisitor for LayerNames {
+    fn visit<F: Float>(&mut self, name: &str, _: &[F]) {
+        self.0.push(name.to_string());
+    }
+}
+
+/// Names of the tensors of parameters.
+struct ParamNames(Vec<String>);
+
+impl ParamVisitor for ParamNames {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.0.push(param.name.to_string());
+    }
+}
+
+/// Sums of the outputs of the layer named `layer`, and their squares.
+struct Moments<'a> {
+    layer: &'a str,
+    sum: f64,
+    sum_sq: f64,
+    count: usize,
+}
+
+impl ActivationVisitor for Moments<'_> {
+    fn visit<F: Float>(&mut self, name: &str, values: &[F]) {
+        if name == self.layer {
+            for x in values {
+                let x = x.to_f64();
+                self.sum += x;
+                self.sum_sq += x * x;
+            }
+            self.count += values.len();
+        }
+    }
+}
+
+/// Multiplies every tensor of weights, but not biases, within `prefix`.
+struct Rescale<'a> {
+    prefix: &'a str,
+    scale: f32,
+}
+
+impl ParamVisitorMut for Rescale<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        let bias = param.name.rsplit('.').next() == Some("bias");
+        if within(param.name, self.prefix) && !bias {
+            for x in param.values.iter_mut() {
+                *x = F::from_f32(x.to_f32() * self.scale);
+            }
+        }
+    }
+}
+
+/// Variance of the outputs of the layer named `layer` over `inputs`.
+fn output_variance<T: FeedForwardNetwork>(net: &T, inputs: &[T::InputType], layer: &str) -> f32 {
+    let mut moments = Moments {
+        layer,
+        sum: 0.0,
+        sum_sq: 0.0,
+        count: 0,
+    };
+    for input in inputs {
+        net.visit_activations("", &net.out_with_layers(input), &mut moments);
+    }
+
+    let count = moments.count.max(1) as f64;
+    let mean = moments.sum / count;
+    (moments.sum_sq / count - mean * mean).max(0.0) as f32
+}
+
+/// Whether the parameter named `name` belongs to the layer named `prefix`.
+fn within(name: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || name
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Name of the innermost layer enclosing the outputs named `layer` that
+/// has parameters, such as `l1` for the outputs `l1.stm` of a
+/// perspective layer, which shares its weights between both sides.
+fn owner<'a>(params: &[String], layer: &'a str) -> Option<&'a str> {
+    let mut prefix = layer;
+    loop {
+        if params.iter().any(|name| within(name, prefix)) {
+            return Some(prefix);
+        }
+        prefix = prefix.rsplit_once('.')?.0;
+    }
+}
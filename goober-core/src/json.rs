@@ -0,0 +1,476 @@
+//! Human-readable dumps of networks as JSON, for debugging and
+//! diffing small networks rather than for performance.
+//! - Tensors are nested into objects by the components of their names,
+//!   so `l1.weight` is found at `{"l1": {"weight": ...}}`.
+//! - Values are nested arrays following the shape of each tensor.
+
+use crate::{
+    params::{load_named, Tensors},
+    FeedForwardNetwork, Float, LoadError, Param, ParamVisitor,
+};
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub(crate) fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Self::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_usize(&self) -> Option<usize> {
+        self.as_f64()
+            .filter(|x| x.fract() == 0.0 && *x >= 0.0)
+            .map(|x| x as usize)
+    }
+
+    pub(crate) fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            bytes: text.as_bytes(),
+            pos: 0,
+        };
+
+        let value = parser.value()?;
+        parser.whitespace();
+
+        if parser.pos != parser.bytes.len() {
+            return Err(format!("trailing characters at {}", parser.pos));
+        }
+
+        Ok(value)
+    }
+
+    pub(crate) fn write(&self, out: &mut String) {
+        match self {
+            Self::Null => out.push_str("null"),
+            Self::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Self::Number(x) if x.is_finite() => out.push_str(&format!("{x}")),
+            Self::Number(_) => out.push_str("null"),
+            Self::String(s) => write_string(s, out),
+            Self::Array(values) => {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    value.write(out);
+                }
+                out.push(']');
+            }
+            Self::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    write_string(key, out);
+                    out.push(':');
+                    value.write(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    /// As [`write`](Self::write), with each field of an object and
+    /// each array which is not a row of numbers on its own line.
+    pub(crate) fn write_pretty(&self, indent: usize, out: &mut String) {
+        let newline = |out: &mut String, indent: usize| {
+            out.push('\n');
+            out.extend(std::iter::repeat_n(' ', indent));
+        };
+
+        match self {
+            Self::Array(values)
+                if values
+                    .iter()
+                    .any(|v| matches!(v, Self::Array(_) | Self::Object(_))) =>
+            {
+                out.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 2);
+                    value.write_pretty(indent + 2, out);
+                }
+                newline(out, indent);
+                out.push(']');
+            }
+            Self::Object(fields) if !fields.is_empty() => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 2);
+                    write_string(key, out);
+                    out.push_str(": ");
+                    value.write_pretty(indent + 2, out);
+                }
+                newline(out, indent);
+                out.push('}');
+            }
+            _ => self.write(out),
+        }
+    }
+}
+
+impl std::fmt::Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out);
+        f.write_str(&out)
+    }
+}
+
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\n' | b'\r' | b'\t') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.whitespace();
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected `{}` at {}", byte as char, self.pos))
+        }
+    }
+
+    fn literal(&mut self, lit: &str, value: Json) -> Result<Json, String> {
+        if self.bytes[self.pos..].starts_with(lit.as_bytes()) {
+            self.pos += lit.len();
+            Ok(value)
+        } else {
+            Err(format!("invalid literal at {}", self.pos))
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Json::String),
+            Some(b'n') => self.literal("null", Json::Null),
+            Some(b't') => self.literal("true", Json::Bool(true)),
+            Some(b'f') => self.literal("false", Json::Bool(false)),
+            Some(_) => self.number(),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+
+        self.whitespace();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(fields));
+        }
+
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+
+            self.whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(fields));
+                }
+                _ => return Err(format!("expected `,` or `}}` at {}", self.pos)),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect(b'[')?;
+        let mut values = Vec::new();
+
+        self.whitespace();
+        if self.bytes.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(Json::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(values));
+                }
+                _ => return Err(format!("expected `,` or `]` at {}", self.pos)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect(b'"')?;
+        let mut res = Vec::new();
+
+        loop {
+            match self.bytes.get(self.pos) {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return String::from_utf8(res).map_err(|err| err.to_string());
+                }
+                Some(b'\\') => {
+                    let escaped = match self.bytes.get(self.pos + 1) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => {
+                            let hex = self
+                                .bytes
+                                .get(self.pos + 2..self.pos + 6)
+                                .and_then(|hex| std::str::from_utf8(hex).ok())
+                                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                                .ok_or(format!("invalid escape at {}", self.pos))?;
+                            self.pos += 4;
+                            char::from_u32(hex).unwrap_or('\u{fffd}')
+                        }
+                        _ => return Err(format!("invalid escape at {}", self.pos)),
+                    };
+
+                    self.pos += 2;
+                    let mut buf = [0; 4];
+                    res.extend_from_slice(escaped.encode_utf8(&mut buf).as_bytes());
+                }
+                Some(&byte) => {
+                    self.pos += 1;
+                    res.push(byte);
+                }
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.pos;
+        while let Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .ok()
+            .and_then(|num| num.parse().ok())
+            .map(Json::Number)
+            .ok_or(format!("invalid value at {start}"))
+    }
+}
+
+/// Inserts `value` into the nested objects of `root` at the path
+/// given by the components of `name`.
+fn insert(root: &mut Vec<(String, Json)>, name: &str, value: Json) {
+    let mut fields = root;
+    let mut parts = name.split('.').peekable();
+
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            fields.push((part.to_string(), value));
+            return;
+        }
+
+        let idx = match fields.iter().position(|(k, _)| k == part) {
+            Some(idx) => idx,
+            None => {
+                fields.push((part.to_string(), Json::Object(Vec::new())));
+                fields.len() - 1
+            }
+        };
+
+        fields = match &mut fields[idx].1 {
+            Json::Object(inner) => inner,
+            _ => unreachable!("`{part}` is both a tensor and a parent"),
+        };
+    }
+}
+
+/// Nests `values` into arrays following `shape`.
+fn nest(shape: &[usize], values: &[f64]) -> Json {
+    match shape {
+        [] | [_] => Json::Array(values.iter().map(|&x| Json::Number(x)).collect()),
+        [_, rest @ ..] => {
+            let stride = rest.iter().product::<usize>().max(1);
+            Json::Array(values.chunks(stride).map(|row| nest(rest, row)).collect())
+        }
+    }
+}
+
+/// Flattens nested arrays of numbers into `values`, returning
+/// their shape, or `None` if they are not rectangular.
+fn flatten(value: &Json, values: &mut Vec<f64>) -> Option<Vec<usize>> {
+    let items = value.as_array()?;
+
+    if items.iter().all(|x| matches!(x, Json::Number(_))) {
+        values.extend(items.iter().filter_map(Json::as_f64));
+        return Some(vec![items.len()]);
+    }
+
+    let mut inner = None;
+    for item in items {
+        let shape = flatten(item, values)?;
+        if inner.get_or_insert_with(|| shape.clone()) != &shape {
+            return None;
+        }
+    }
+
+    let mut shape = vec![items.len()];
+    shape.extend(inner?);
+    Some(shape)
+}
+
+struct Writer(Vec<(String, Json)>);
+
+impl ParamVisitor for Writer {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        // print the shortest decimal which rounds back to the stored value
+        let values: Vec<f64> = param
+            .values
+            .iter()
+            .map(|&x| match F::BYTES {
+                8 => x.to_f64(),
+                _ => x.to_f32().to_string().parse().unwrap(),
+            })
+            .collect();
+
+        insert(&mut self.0, param.name, nest(param.shape, &values));
+    }
+}
+
+struct Reader<'a> {
+    root: &'a Json,
+    tensors: Tensors,
+    err: Option<LoadError>,
+}
+
+impl ParamVisitor for Reader<'_> {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let Some(value) = param
+            .name
+            .split('.')
+            .try_fold(self.root, |node, part| node.get(part))
+        else {
+            return;
+        };
+
+        let mut values = Vec::new();
+        match flatten(value, &mut values) {
+            Some(shape) => {
+                self.tensors.insert(param.name.to_string(), (shape, values));
+            }
+            None => {
+                let reason = format!("`{}` is not a rectangular array of numbers", param.name);
+                self.err.get_or_insert(LoadError::Malformed(reason));
+            }
+        }
+    }
+}
+
+/// Dumps every tensor of parameters of `net` as indented JSON.
+pub fn to_string<T: FeedForwardNetwork>(net: &T) -> String {
+    let mut writer = Writer(Vec::new());
+    net.visit_params("", &mut writer);
+
+    let mut out = String::new();
+    Json::Object(writer.0).write_pretty(0, &mut out);
+    out.push('\n');
+    out
+}
+
+pub fn save<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    std::fs::write(path, to_string(net))
+}
+
+/// Restores a network dumped by [`to_string`], failing without
+/// modifying `net` if any tensor is missing or has the wrong shape.
+/// Fields which are not part of the network are ignored.
+pub fn from_str<T: FeedForwardNetwork>(net: &mut T, text: &str) -> Result<(), LoadError> {
+    let root = Json::parse(text).map_err(LoadError::Malformed)?;
+
+    let mut reader = Reader {
+        root: &root,
+        tensors: Tensors::new(),
+        err: None,
+    };
+    net.visit_params("", &mut reader);
+
+    if let Some(err) = reader.err {
+        return Err(err);
+    }
+
+    load_named(net, &reader.tensors)
+}
+
+pub fn load<T: FeedForwardNetwork>(net: &mut T, path: &str) -> Result<(), LoadError> {
+    from_str(net, &std::fs::read_to_string(path)?)
+}
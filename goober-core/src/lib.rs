@@ -1,12 +1,189 @@
+//! Without the default `std` feature, builds under `no_std` with `alloc`,
+//! leaving out reading and writing networks to files and in interchange
+//! formats.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+/// Enters a `tracing` span at `$level` until the end of the
+/// enclosing block, if the `tracing` feature is enabled.
+#[allow(unused_macros)]
+macro_rules! span {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($args)*).entered();
+    };
+}
+
+/// Emits a `tracing` event at `$level`, if the `tracing` feature is enabled.
+#[allow(unused_macros)]
+macro_rules! event {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($args)*);
+    };
+}
+
 pub mod activation;
+pub mod augment;
+#[cfg(feature = "std")]
+pub mod callback;
+#[cfg(feature = "std")]
+pub mod checkpoint;
+#[cfg(feature = "chess")]
+pub mod chess;
+#[cfg(feature = "std")]
+pub mod codegen;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod csv;
+pub mod dataset;
+pub mod diff;
+pub mod dot;
+mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod fingerprint;
+mod float;
+pub mod gradcheck;
+mod graph;
+pub mod init;
+#[cfg(feature = "std")]
+pub mod json;
+#[cfg(feature = "std")]
+pub mod libsvm;
+pub mod loss;
+#[cfg(feature = "std")]
+pub mod lr_finder;
 mod matrix;
+pub mod merge;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod mmap;
+#[cfg(feature = "std")]
+pub mod numpy;
+#[cfg(feature = "std")]
+pub mod onnx;
+mod params;
+#[cfg(feature = "progress")]
+pub mod progress;
+pub mod prune;
+pub mod qat;
+mod rand;
+#[cfg(feature = "std")]
+pub mod safetensors;
+#[cfg(feature = "std")]
+mod save;
+pub mod schedule;
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub mod serde_array;
+pub mod stats;
+#[cfg(feature = "std")]
+pub mod stream;
+pub mod summary;
+#[cfg(feature = "std")]
+pub mod sweep;
+#[cfg(feature = "tensorboard")]
+pub mod tensorboard;
+#[cfg(feature = "std")]
+mod toml;
+#[cfg(feature = "std")]
+pub mod trainer;
 mod vector;
+#[cfg(feature = "zstd")]
+pub mod zstd;
+
+use alloc::{boxed::Box, format, string::String};
+use qat::Qat;
+use summary::{LayerSummary, Summary};
 
+pub use error::GooberError;
+#[cfg(feature = "std")]
+pub use float::seed_stochastic_rounding;
+pub use float::{bf16, f16, Float, Real, Stochastic};
+pub use graph::{Graph, Node, Op, Unsupported};
 pub use matrix::Matrix;
-pub use vector::{SparseVector, Vector};
+pub use params::{
+    param_name, param_name_cow, ActivationVisitor, Param, ParamMut, ParamVisitor, ParamVisitorMut,
+};
+pub use rand::Rand;
+#[cfg(feature = "std")]
+pub use save::{crc32, LoadError};
+pub use vector::{FeatureOutOfBounds, LengthMismatch, SparseVector, Vector, WeightedSparseVector};
 
 pub trait OutputLayer<OutputType> {
-    fn output_layer(&self) -> OutputType;
+    /// Output of the last layer, borrowed, so that it can be passed on
+    /// as the input of the next layer without being copied.
+    fn output_layer_ref(&self) -> &OutputType;
+
+    fn output_layer(&self) -> OutputType
+    where
+        OutputType: Clone,
+    {
+        self.output_layer_ref().clone()
+    }
+}
+
+/// Allocates a `T` on the heap with every byte zero, without building
+/// it on the stack first, aborting if the allocation fails.
+///
+/// # Safety
+/// All zero bits must be a valid `T`.
+pub unsafe fn boxed_zeroed<T>() -> Box<T> {
+    // SAFETY: upheld by the caller
+    unsafe { try_boxed_zeroed() }
+        .unwrap_or_else(|_| alloc::alloc::handle_alloc_error(core::alloc::Layout::new::<T>()))
+}
+
+/// As [`boxed_zeroed`], failing rather than aborting if the
+/// allocation fails.
+///
+/// # Safety
+/// All zero bits must be a valid `T`.
+pub unsafe fn try_boxed_zeroed<T>() -> Result<Box<T>, GooberError> {
+    let layout = core::alloc::Layout::new::<T>();
+    if layout.size() == 0 {
+        // allocating zero bytes is undefined behaviour
+        let ptr = core::ptr::NonNull::<T>::dangling().as_ptr();
+        // SAFETY: `T` is zero-sized, so any aligned non-null pointer is valid
+        return Ok(unsafe { Box::from_raw(ptr) });
+    }
+
+    // SAFETY: `layout` is not zero-sized, and the caller guarantees
+    // that all zero bits is a valid `T`
+    unsafe {
+        let ptr = alloc::alloc::alloc_zeroed(layout);
+        if ptr.is_null() {
+            return Err(GooberError::Alloc(layout));
+        }
+        Ok(Box::from_raw(ptr.cast()))
+    }
+}
+
+/// Gradients of the parameters of a network, as accumulated by
+/// [`backprop`](FeedForwardNetwork::backprop).
+/// - A layer's gradients are the same layer stored as its compute type,
+///   and a derived network's are a struct of those of its fields.
+pub trait Gradient: Sized {
+    /// Visits each tensor of gradients, named as the parameters
+    /// they are of, in the same order.
+    fn visit_grads<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V);
+
+    fn visit_grads_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V);
+}
+
+impl<T: FeedForwardNetwork> Gradient for T {
+    fn visit_grads<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.visit_params(prefix, visitor);
+    }
+
+    fn visit_grads_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.visit_params_mut(prefix, visitor);
+    }
 }
 
 pub trait FeedForwardNetwork: Sized {
@@ -14,34 +191,167 @@ pub trait FeedForwardNetwork: Sized {
     type OutputType: Clone;
     type Layers: OutputLayer<Self::OutputType>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32);
+    /// Gradients of the parameters, stored in the compute type of each
+    /// layer rather than its storage type, so that summing many small
+    /// updates over a batch does not round them away.
+    type Grad: Gradient;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32);
 
+    /// Allocates the network on the heap with every parameter zero,
+    /// without building it on the stack first, as networks are often
+    /// too large for it.
+    /// - Aborts if the allocation fails, see
+    ///   [`try_boxed_and_zeroed`](Self::try_boxed_and_zeroed).
     fn boxed_and_zeroed() -> Box<Self> {
-        unsafe {
-            let layout = std::alloc::Layout::new::<Self>();
-            let ptr = std::alloc::alloc_zeroed(layout);
-            if ptr.is_null() {
-                std::alloc::handle_alloc_error(layout);
-            }
-            Box::from_raw(ptr.cast())
-        }
+        Self::try_boxed_and_zeroed().unwrap_or_else(|_| {
+            alloc::alloc::handle_alloc_error(core::alloc::Layout::new::<Self>())
+        })
     }
 
+    /// As [`boxed_and_zeroed`](Self::boxed_and_zeroed), failing
+    /// rather than aborting if the allocation fails.
+    fn try_boxed_and_zeroed() -> Result<Box<Self>, GooberError> {
+        // SAFETY: every layer is made of floats, for which all zero bits is zero
+        unsafe { try_boxed_zeroed() }
+    }
+
+    /// Allocates gradients for the network on the heap, all zero, as
+    /// [`boxed_and_zeroed`](Self::boxed_and_zeroed) does the network.
+    fn zeroed_grad() -> Box<Self::Grad> {
+        // SAFETY: gradients are made of floats, for which all zero bits is zero
+        unsafe { boxed_zeroed() }
+    }
+
+    #[cfg(feature = "std")]
+    /// Writes the raw parameters in little-endian order, with no
+    /// header, regardless of the endianness of the host.
+    /// - Panics if the file cannot be written, see
+    ///   [`try_write_to_bin`](Self::try_write_to_bin).
     fn write_to_bin(&self, path: &str) {
-        use std::io::Write;
+        self.try_write_to_bin(path)
+            .unwrap_or_else(|err| panic!("failed to write `{path}`: {err}"));
+    }
 
-        let mut file = std::fs::File::create(path).unwrap();
+    #[cfg(feature = "std")]
+    /// As [`write_to_bin`](Self::write_to_bin), failing rather than
+    /// panicking if the file cannot be written.
+    fn try_write_to_bin(&self, path: &str) -> Result<(), GooberError> {
+        Ok(std::fs::write(path, save::to_raw_bytes(self))?)
+    }
 
-        unsafe {
-            let ptr: *const Self = self;
-            let slice_ptr: *const u8 = std::mem::transmute(ptr);
-            let slice = std::slice::from_raw_parts(slice_ptr, std::mem::size_of_val(self));
-            file.write_all(slice).unwrap();
-        }
+    #[cfg(feature = "std")]
+    /// Reads parameters written by [`write_to_bin`](Self::write_to_bin),
+    /// panicking if the file cannot be read or is not the expected size,
+    /// see [`try_read_from_bin`](Self::try_read_from_bin).
+    fn read_from_bin(&mut self, path: &str) {
+        self.try_read_from_bin(path)
+            .unwrap_or_else(|err| panic!("failed to read `{path}`: {err}"));
+    }
+
+    #[cfg(feature = "std")]
+    /// As [`read_from_bin`](Self::read_from_bin), failing rather than
+    /// panicking, and leaving the network unchanged, if the file cannot
+    /// be read or is not the expected size.
+    fn try_read_from_bin(&mut self, path: &str) -> Result<(), GooberError> {
+        Ok(save::from_raw_bytes(self, &std::fs::read(path)?)?)
+    }
+
+    #[cfg(feature = "std")]
+    /// Saves the network in a versioned format, which records the name,
+    /// storage type, activation and shape of every tensor of parameters
+    /// alongside its little-endian values, followed by a CRC-32.
+    ///
+    /// # Panics
+    /// If a parameter name is longer than `u16::MAX` bytes, or a
+    /// parameter has more than `u8::MAX` dimensions.
+    fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.save_bytes())
+    }
+
+    #[cfg(feature = "std")]
+    /// As [`save`](Self::save), returning the bytes rather than writing them.
+    ///
+    /// # Panics
+    /// As [`save`](Self::save).
+    fn save_bytes(&self) -> Vec<u8> {
+        save::to_bytes(self)
+    }
+
+    #[cfg(feature = "std")]
+    /// Loads a network written by [`save`](Self::save), failing if the
+    /// file is corrupt or was saved from a different architecture.
+    fn load(&mut self, path: &str) -> Result<(), LoadError> {
+        self.load_bytes(&std::fs::read(path)?)
+    }
+
+    #[cfg(feature = "std")]
+    /// As [`load`](Self::load), reading from `bytes`.
+    fn load_bytes(&mut self, bytes: &[u8]) -> Result<(), LoadError> {
+        save::from_bytes(self, bytes)
+    }
+
+    /// Hash of the name, storage type, shape and value of every tensor
+    /// of parameters, which is stable across platforms, for checking
+    /// that two networks are identical without comparing their files.
+    fn fingerprint(&self) -> u64 {
+        fingerprint::fingerprint(self)
+    }
+
+    /// [`fingerprint`](Self::fingerprint) as 16 hex digits.
+    fn fingerprint_hex(&self) -> String {
+        format!("{:016x}", self.fingerprint())
+    }
+
+    /// Visits each tensor of parameters, with names prefixed by `prefix`.
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V);
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V);
+
+    /// Appends the operations computing the output from the value
+    /// named `input` to `graph`, returning the name of the output.
+    fn trace(&self, prefix: &str, input: &str, graph: &mut Graph) -> Result<String, Unsupported> {
+        let _ = (input, graph);
+        Err(Unsupported(format!("tracing layer `{prefix}`")))
+    }
+
+    /// Appends a row describing each layer to `summary`, named by `prefix`.
+    /// - By default, a single row of the type and number of parameters.
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        summary.push(LayerSummary::new(self, prefix));
+    }
+
+    /// Table of the type, shapes, activation and number of parameters
+    /// of each layer, which can be printed, similar to Keras'
+    /// `model.summary()`.
+    fn summary(&self) -> Summary {
+        Summary::of(self)
+    }
+
+    /// Visits the output of each layer in `layers`, as computed by
+    /// [`out_with_layers`](Self::out_with_layers), with names prefixed by
+    /// `prefix`.
+    /// - Layers which do not override this are skipped.
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        let _ = (prefix, layers, visitor);
     }
 
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers;
 
+    /// As [`out_with_layers`](Self::out_with_layers), but overwrites
+    /// `layers`, from an earlier sample, rather than returning new ones,
+    /// so that a buffer allocated once is reused for every sample.
+    /// - Layers which do not override this compute new outputs and move
+    ///   them into `layers`.
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        *layers = self.out_with_layers(input);
+    }
+
     fn out(&self, input: &Self::InputType) -> Self::OutputType {
         self.out_with_layers(input).output_layer()
     }
@@ -49,8 +359,55 @@ pub trait FeedForwardNetwork: Sized {
     fn backprop(
         &self,
         input: &Self::InputType,
-        grad: &mut Self,
+        grad: &mut Self::Grad,
         out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType;
+
+    /// As [`backprop`](Self::backprop), but overwrites `input_err` with
+    /// the error of the input, rather than returning it.
+    /// - `layers` are mutable so that networks of several layers can keep
+    ///   the error of each layer in them, written in place for every sample.
+    /// - Layers which do not override this compute a new error and move
+    ///   it into `input_err`.
+    fn backprop_into(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        out_err: &Self::OutputType,
+        layers: &mut Self::Layers,
+        input_err: &mut Self::InputType,
+    ) {
+        *input_err = self.backprop(input, grad, out_err.clone(), layers);
+    }
+
+    /// As [`out_into`](Self::out_into), simulating quantizing each layer
+    /// in `qat`, named by `prefix`, for quantization-aware training.
+    /// - Layers which do not override this are not quantized.
+    fn out_into_qat(
+        &self,
+        prefix: &str,
+        input: &Self::InputType,
+        layers: &mut Self::Layers,
+        qat: &Qat,
+    ) {
+        let _ = (prefix, qat);
+        self.out_into(input, layers);
+    }
+
+    /// Checks that `input` can be run through the network, such as that
+    /// the indices of its sparse features are in bounds, for inputs from
+    /// outside of Rust which would otherwise cause a panic.
+    /// - Layers which do not override this accept any input.
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        let _ = input;
+        Ok(())
+    }
+
+    /// Clamps the weights of each layer in `qat`, named by `prefix`,
+    /// to the range representable once quantized.
+    /// - Layers which do not override this are left unchanged.
+    fn clamp_qat(&mut self, prefix: &str, qat: &Qat) {
+        let _ = (prefix, qat);
+    }
 }
@@ -0,0 +1,79 @@
+//! Reading sparse datasets in the text format of libsvm, as used by
+//! many public classification and regression datasets.
+//!
+//! ```text
+//! # label index:value ...
+//! +1 3:0.5 10:1 2048:-0.25
+//! -1 7:1 # comments run to the end of the line
+//! ```
+//!
+//! - Indices count from 1, as written by libsvm, so are read as features
+//!   counting from 0, one less than written.
+//! - Query ids of ranking datasets, `qid:1`, are ignored.
+
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use crate::{Vector, WeightedSparseVector};
+
+/// Reads every sample of `reader`, with the value of each feature as its
+/// weight, and the label as the target.
+pub fn read(reader: impl BufRead) -> io::Result<Vec<(WeightedSparseVector, Vector<1>)>> {
+    let mut data = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let sample = parse_line(&line).map_err(|message| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {message}", i + 1),
+            )
+        })?;
+        data.extend(sample);
+    }
+
+    Ok(data)
+}
+
+/// Reads every sample of the file at `path`, as [`read`].
+pub fn load(path: impl AsRef<Path>) -> io::Result<Vec<(WeightedSparseVector, Vector<1>)>> {
+    let path = path.as_ref();
+    read(BufReader::new(File::open(path)?))
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))
+}
+
+/// Sample of a line, or `None` if it holds only whitespace or a comment.
+fn parse_line(line: &str) -> Result<Option<(WeightedSparseVector, Vector<1>)>, String> {
+    let line = line.split_once('#').map_or(line, |(line, _)| line);
+    let mut tokens = line.split_whitespace();
+    let Some(label) = tokens.next() else {
+        return Ok(None);
+    };
+    let label = label
+        .parse::<f32>()
+        .map_err(|_| format!("invalid label `{label}`"))?;
+
+    let mut features = WeightedSparseVector::with_capacity(tokens.size_hint().0);
+    for token in tokens {
+        let (index, value) = token
+            .split_once(':')
+            .ok_or_else(|| format!("expected `index:value`, found `{token}`"))?;
+        if index == "qid" {
+            continue;
+        }
+
+        let index = match index.parse::<usize>() {
+            Ok(0) => return Err("index 0, though indices count from 1".into()),
+            Ok(index) => index - 1,
+            Err(_) => return Err(format!("invalid index `{index}`")),
+        };
+        let value = value
+            .parse::<f32>()
+            .map_err(|_| format!("invalid value `{value}`"))?;
+        features.push(index, value);
+    }
+
+    Ok(Some((features, Vector::from_raw([label]))))
+}
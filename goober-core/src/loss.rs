@@ -0,0 +1,45 @@
+//! Loss functions, giving the error of an output against its target
+//! and the gradient to backpropagate from it.
+
+use crate::{Float, Vector};
+
+/// Loss of a network output of type `O` against a target of the same type.
+pub trait Loss<O> {
+    /// Loss of `out` against `target`, along with
+    /// its gradient with respect to `out`.
+    fn loss(&self, out: &O, target: &O) -> (f32, O);
+}
+
+/// Mean squared error over the elements of the output.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mse;
+
+impl<const N: usize, F: Float> Loss<Vector<N, F>> for Mse {
+    fn loss(&self, out: &Vector<N, F>, target: &Vector<N, F>) -> (f32, Vector<N, F>) {
+        let scale = 1.0 / N as f32;
+        let mut loss = 0.0;
+
+        let grad = Vector::from_fn(|i| {
+            let err = out[i].to_f32() - target[i].to_f32();
+            loss += scale * err * err;
+            F::from_f32(2.0 * scale * err)
+        });
+
+        (loss, grad)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mse() {
+        let (loss, grad) = Mse.loss(
+            &Vector::<2>::from_raw([1.0, 0.0]),
+            &Vector::from_raw([0.0, 0.0]),
+        );
+        assert_eq!(loss, 0.5);
+        assert_eq!(grad, Vector::from_raw([1.0, 0.0]));
+    }
+}
@@ -0,0 +1,124 @@
+//! Learning rate range test, training briefly with a learning rate growing
+//! exponentially each step and recording the loss, to choose a learning
+//! rate for a new architecture without guessing.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, layer::{DenseConnected, SparseConnected}, lr_finder::LrFinder,
+//! #     FeedForwardNetwork,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # let mut trainer = goober::trainer::Trainer::new(Net::boxed_and_zeroed(), Vec::new());
+//! let sweep = trainer.find_lr(&LrFinder::default());
+//! println!("{sweep}");
+//! let (_, max_lr) = sweep.suggested().unwrap();
+//! ```
+
+use std::fmt;
+
+/// Settings of a learning rate range test, run with
+/// [`Trainer::find_lr`](crate::trainer::Trainer::find_lr).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LrFinder {
+    /// Learning rate of the first step.
+    pub min_lr: f32,
+    /// Learning rate of the last step.
+    pub max_lr: f32,
+    /// Number of batches to train on.
+    pub steps: u64,
+    /// Factor of the exponential moving average smoothing the loss.
+    pub smoothing: f32,
+    /// Stops once the smoothed loss exceeds the lowest seen by this factor.
+    pub divergence: f32,
+}
+
+impl Default for LrFinder {
+    fn default() -> Self {
+        Self {
+            min_lr: 1e-7,
+            max_lr: 1.0,
+            steps: 300,
+            smoothing: 0.98,
+            divergence: 4.0,
+        }
+    }
+}
+
+impl LrFinder {
+    /// Learning rate of the step after `step` steps have been taken.
+    pub fn lr(&self, step: u64) -> f32 {
+        let t = step as f32 / self.steps.saturating_sub(1).max(1) as f32;
+        self.min_lr * libm::powf(self.max_lr / self.min_lr, t)
+    }
+}
+
+/// Smoothed loss at each learning rate of a range test,
+/// up to where the loss diverged.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LrSweep {
+    points: Vec<(f32, f32)>,
+}
+
+impl LrSweep {
+    pub(crate) fn push(&mut self, lr: f32, loss: f32) {
+        self.points.push((lr, loss));
+    }
+
+    /// Learning rate and smoothed loss of each step, in order.
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// Learning rate at which the smoothed loss was lowest.
+    pub fn min_loss_lr(&self) -> Option<f32> {
+        self.min_loss().map(|i| self.points[i].0)
+    }
+
+    /// Learning rate, before the lowest loss, at which the smoothed loss
+    /// fell fastest against the logarithm of the learning rate, measured
+    /// across a tenth of the steps to see past noise, and skipping the
+    /// first tenth, while the average is still noisy.
+    pub fn steepest_lr(&self) -> Option<f32> {
+        let end = self.min_loss()?;
+        let span = (self.points.len() / 10).max(1);
+        (2 * span..=end)
+            .map(|i| {
+                let ((lr0, loss0), (lr1, loss1)) = (self.points[i - span], self.points[i]);
+                (lr1, (loss1 - loss0) / libm::logf(lr1 / lr0))
+            })
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .map(|(lr, _)| lr)
+    }
+
+    /// Range of learning rates to train with, from a hundredth to a tenth
+    /// of that at which the smoothed loss was lowest, such as for the
+    /// minimum and maximum of a one-cycle or cosine schedule.
+    pub fn suggested(&self) -> Option<(f32, f32)> {
+        let lr = self.min_loss_lr()?;
+        Some((lr / 100.0, lr / 10.0))
+    }
+
+    fn min_loss(&self) -> Option<usize> {
+        (0..self.points.len()).min_by(|&i, &j| self.points[i].1.total_cmp(&self.points[j].1))
+    }
+}
+
+impl fmt::Display for LrSweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (Some(min), Some((lo, hi))) = (self.min_loss(), self.suggested()) else {
+            return write!(f, "no steps taken");
+        };
+
+        let (lr, loss) = self.points[min];
+        writeln!(f, "steps:          {}", self.points.len())?;
+        writeln!(f, "lowest loss:    {loss:.6} at lr {lr:.3e}")?;
+        if let Some(lr) = self.steepest_lr() {
+            writeln!(f, "steepest:       lr {lr:.3e}")?;
+        }
+        write!(f, "suggested:      lr {lo:.3e} to {hi:.3e}")
+    }
+}
@@ -1,50 +1,225 @@
-use crate::Vector;
+use alloc::vec::Vec;
 
-/// `M`x`N` Matrix Type.
+use crate::{init::Distribution, vector::shown, Float, LengthMismatch, Rand, Vector};
+
+/// `M`x`N` Matrix Type, with elements stored as `T`.
+/// - Formatted with only the first and last few rows and columns if
+///   large, as [`Vector`]s are.
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Matrix<const M: usize, const N: usize> {
-    inner: [Vector<N>; M],
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>"
+    ))
+)]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Matrix<const M: usize, const N: usize, T: Float = f32> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    inner: [Vector<N, T>; M],
+}
+
+impl<const M: usize, const N: usize, T: Float> core::fmt::Debug for Matrix<M, N, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "Matrix<{M}, {N}> [")?;
+        for (k, i) in shown(M).enumerate() {
+            if k > 0 {
+                f.write_str(", ")?;
+            }
+            match i {
+                Some(i) => self.inner[i].fmt_elements(f, |f, x| write!(f, "{x:?}"))?,
+                None => f.write_str("...")?,
+            }
+        }
+        f.write_str("]")
+    }
+}
+
+/// One row to a line, below a line of the shape, with the elements of
+/// every column aligned.
+impl<const M: usize, const N: usize, T: Float> core::fmt::Display for Matrix<M, N, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let precision = f.precision().unwrap_or(4);
+        let width = shown(M)
+            .flatten()
+            .map(|i| self.inner[i].shown_width(precision))
+            .max()
+            .unwrap_or(0);
+
+        write!(f, "Matrix<{M}, {N}>")?;
+        for i in shown(M) {
+            f.write_str("\n")?;
+            match i {
+                Some(i) => {
+                    self.inner[i].fmt_elements(f, |f, x| write!(f, "{x:>width$.precision$}"))?
+                }
+                None => f.write_str("...")?,
+            }
+        }
+        Ok(())
+    }
 }
 
-impl<const M: usize, const N: usize> std::ops::AddAssign<&Matrix<M, N>> for Matrix<M, N> {
-    fn add_assign(&mut self, rhs: &Matrix<M, N>) {
+impl<const M: usize, const N: usize, T: Float> core::ops::AddAssign<&Matrix<M, N, T>>
+    for Matrix<M, N, T>
+{
+    fn add_assign(&mut self, rhs: &Matrix<M, N, T>) {
         for (u, v) in self.inner.iter_mut().zip(rhs.inner.iter()) {
             *u += *v;
         }
     }
 }
 
-impl<const M: usize, const N: usize> std::ops::Deref for Matrix<M, N> {
-    type Target = [Vector<N>; M];
+impl<const M: usize, const N: usize, T: Float> core::ops::Deref for Matrix<M, N, T> {
+    type Target = [Vector<N, T>; M];
     fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
 
-impl<const M: usize, const N: usize> std::ops::DerefMut for Matrix<M, N> {
+impl<const M: usize, const N: usize, T: Float> core::ops::DerefMut for Matrix<M, N, T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.inner
     }
 }
 
-impl<const M: usize, const N: usize> std::ops::Mul<Vector<N>> for Matrix<M, N> {
-    type Output = Vector<M>;
-    fn mul(self, rhs: Vector<N>) -> Self::Output {
-        Vector::<M>::from_fn(|i| self.inner[i].dot(&rhs))
+/// Elements in row-major order.
+impl<const M: usize, const N: usize, T: Float> AsRef<[T]> for Matrix<M, N, T> {
+    fn as_ref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<const M: usize, const N: usize, T: Float> AsMut<[T]> for Matrix<M, N, T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+// SAFETY: `#[repr(C)]` arrays of `T`s, without padding, so any
+// bytes which are valid `T`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<const M: usize, const N: usize, T: Float + bytemuck::Zeroable> bytemuck::Zeroable
+    for Matrix<M, N, T>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<const M: usize, const N: usize, T: Float + bytemuck::Pod> bytemuck::Pod
+    for Matrix<M, N, T>
+where
+    Self: Copy + 'static,
+{
+}
+
+/// Elements in row-major order.
+impl<const M: usize, const N: usize, T: Float> TryFrom<&[T]> for Matrix<M, N, T> {
+    type Error = LengthMismatch;
+
+    fn try_from(slice: &[T]) -> Result<Self, Self::Error> {
+        if slice.len() != M * N {
+            return Err(LengthMismatch {
+                expected: M * N,
+                found: slice.len(),
+            });
+        }
+        Ok(Self::from_fn(|i, j| slice[i * N + j]))
+    }
+}
+
+/// Elements in row-major order.
+impl<const M: usize, const N: usize, T: Float> TryFrom<Vec<T>> for Matrix<M, N, T> {
+    type Error = LengthMismatch;
+
+    fn try_from(vec: Vec<T>) -> Result<Self, Self::Error> {
+        Self::try_from(vec.as_slice())
+    }
+}
+
+impl<const M: usize, const N: usize, T: Float> From<[[T; N]; M]> for Matrix<M, N, T> {
+    fn from(rows: [[T; N]; M]) -> Self {
+        Self::from_rows(rows)
+    }
+}
+
+impl<const M: usize, const N: usize, T: Float> From<[Vector<N, T>; M]> for Matrix<M, N, T> {
+    fn from(rows: [Vector<N, T>; M]) -> Self {
+        Self::from_raw(rows)
+    }
+}
+
+/// Row `i`.
+/// - Implemented explicitly, as once `Index` is implemented for `(i, j)`,
+///   `matrix[i]` no longer reaches the rows through `Deref`.
+impl<const M: usize, const N: usize, T: Float> core::ops::Index<usize> for Matrix<M, N, T> {
+    type Output = Vector<N, T>;
+    fn index(&self, i: usize) -> &Self::Output {
+        &self.inner[i]
+    }
+}
+
+impl<const M: usize, const N: usize, T: Float> core::ops::IndexMut<usize> for Matrix<M, N, T> {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
+        &mut self.inner[i]
+    }
+}
+
+/// Element in row `i` and column `j`.
+impl<const M: usize, const N: usize, T: Float> core::ops::Index<(usize, usize)>
+    for Matrix<M, N, T>
+{
+    type Output = T;
+    fn index(&self, (i, j): (usize, usize)) -> &Self::Output {
+        &self.inner[i][j]
     }
 }
 
-impl<const M: usize, const N: usize> Matrix<M, N> {
+impl<const M: usize, const N: usize, T: Float> core::ops::IndexMut<(usize, usize)>
+    for Matrix<M, N, T>
+{
+    fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Self::Output {
+        &mut self.inner[i][j]
+    }
+}
+
+impl<const M: usize, const N: usize, T: Float> core::ops::Mul<Vector<N, T>> for Matrix<M, N, T> {
+    type Output = Vector<M, T>;
+    fn mul(self, rhs: Vector<N, T>) -> Self::Output {
+        Vector::<M, T>::from_fn(|i| T::from_compute(self.inner[i].dot(&rhs)))
+    }
+}
+
+impl<const M: usize, const N: usize, T: Float> Matrix<M, N, T> {
     pub const fn zeroed() -> Self {
         Self::from_raw([Vector::zeroed(); M])
     }
 
-    pub const fn from_raw(inner: [Vector<N>; M]) -> Self {
+    pub const fn from_raw(inner: [Vector<N, T>; M]) -> Self {
         Self { inner }
     }
 
-    pub fn from_fn<F: FnMut(usize, usize) -> f32>(mut f: F) -> Self {
+    /// Matrix of the rows `rows`, written out as nested arrays,
+    /// such as `[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]`.
+    pub fn from_rows(rows: [[T; N]; M]) -> Self {
+        Self::from_raw(rows.map(Vector::from_raw))
+    }
+
+    /// Elements in row-major order.
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: `Vector<N, T>` is a `#[repr(C)]` wrapper around
+        // `[T; N]`, so the rows are laid out contiguously.
+        unsafe { core::slice::from_raw_parts(self.inner.as_ptr().cast(), M * N) }
+    }
+
+    /// Elements in row-major order.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        // SAFETY: see `as_slice`.
+        unsafe { core::slice::from_raw_parts_mut(self.inner.as_mut_ptr().cast(), M * N) }
+    }
+
+    pub fn from_fn<F: FnMut(usize, usize) -> T>(mut f: F) -> Self {
         let mut rows = [Vector::zeroed(); M];
 
         for (i, row) in rows.iter_mut().enumerate() {
@@ -55,19 +230,62 @@ impl<const M: usize, const N: usize> Matrix<M, N> {
         Self::from_raw(rows)
     }
 
-    pub fn transpose_mul(&self, out: Vector<M>) -> Vector<N> {
+    /// Elements drawn from `dist`, in row-major order, such as
+    /// [`Normal`](crate::init::Normal).
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_fn(|_, _| T::from_f32(dist.sample(rng)))
+    }
+
+    /// Each row, in order.
+    pub fn rows(&self) -> core::slice::Iter<'_, Vector<N, T>> {
+        self.inner.iter()
+    }
+
+    /// Each row, in order.
+    pub fn rows_mut(&mut self) -> core::slice::IterMut<'_, Vector<N, T>> {
+        self.inner.iter_mut()
+    }
+
+    /// Elements of column `j`, from the first row to the last.
+    /// - Panics if `j` is not less than `N`.
+    pub fn col(&self, j: usize) -> impl Iterator<Item = T> + '_ {
+        assert!(j < N, "column {j} out of bounds for matrix of {N} columns");
+        self.inner.iter().map(move |row| row[j])
+    }
+
+    /// `N`x`M` matrix whose rows are the columns of this one.
+    pub fn transpose(&self) -> Matrix<N, M, T> {
+        Matrix::from_fn(|i, j| self.inner[j][i])
+    }
+
+    pub fn transpose_mul(&self, out: Vector<M, T>) -> Vector<N, T> {
         Vector::from_fn(|i| {
-            let mut v = 0.0;
+            let mut v = T::Compute::ZERO;
             for j in 0..M {
-                v += self.inner[j][i] * out[j];
+                v += self.inner[j][i].to_compute() * out[j].to_compute();
             }
-            v
+            T::from_compute(v)
         })
     }
 
-    pub fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+    pub fn adam(
+        &mut self,
+        g: &Matrix<M, N, T::Compute>,
+        m: &mut Self,
+        v: &mut Self,
+        adj: f32,
+        lr: f32,
+    ) {
         for i in 0..M {
             self.inner[i].adam(g.inner[i], &mut m.inner[i], &mut v.inner[i], adj, lr);
         }
     }
 }
+
+impl<const N: usize, T: Float> Matrix<N, N, T> {
+    /// `N`x`N` matrix of ones on the diagonal and zeros elsewhere.
+    pub fn identity() -> Self {
+        let one = T::from_f32(1.0);
+        Self::from_fn(|i, j| if i == j { one } else { T::ZERO })
+    }
+}
@@ -0,0 +1,106 @@
+//! Interpolating and averaging the parameters of networks of the same
+//! architecture, such as to merge several runs into a "model soup".
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{FeedForwardNetwork, Float, Param, ParamMut, ParamVisitor, ParamVisitorMut};
+
+/// Adds the parameters of a network, scaled by `weight`, to `sums`.
+struct Accumulate<'a> {
+    sums: &'a mut Vec<Vec<f64>>,
+    weight: f64,
+    tensor: usize,
+}
+
+impl ParamVisitor for Accumulate<'_> {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        if self.sums.len() == self.tensor {
+            self.sums.push(alloc::vec![0.0; param.values.len()]);
+        }
+
+        let sums = &mut self.sums[self.tensor];
+        for (sum, x) in sums.iter_mut().zip(param.values) {
+            *sum += self.weight * x.to_f64();
+        }
+        self.tensor += 1;
+    }
+}
+
+struct Assign {
+    sums: Vec<Vec<f64>>,
+    tensor: usize,
+}
+
+impl ParamVisitorMut for Assign {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        for (x, &sum) in param.values.iter_mut().zip(&self.sums[self.tensor]) {
+            *x = F::from_f32(sum as f32);
+        }
+        self.tensor += 1;
+    }
+}
+
+/// Sum of the parameters of each network scaled by its weight.
+/// - Weights are used as given, so should usually sum to one.
+/// - Panics if `nets` is empty.
+pub fn weighted_average<T: FeedForwardNetwork>(nets: &[(&T, f32)]) -> Box<T> {
+    assert!(!nets.is_empty(), "no networks to average");
+
+    let mut sums = Vec::new();
+    for &(net, weight) in nets {
+        let mut acc = Accumulate {
+            sums: &mut sums,
+            weight: f64::from(weight),
+            tensor: 0,
+        };
+        net.visit_params("", &mut acc);
+    }
+
+    let mut out = T::boxed_and_zeroed();
+    out.visit_params_mut("", &mut Assign { sums, tensor: 0 });
+    out
+}
+
+/// Mean of the parameters of `nets`.
+/// - Panics if `nets` is empty.
+pub fn average<T: FeedForwardNetwork>(nets: &[&T]) -> Box<T> {
+    let weight = 1.0 / nets.len() as f32;
+    let nets: Vec<_> = nets.iter().map(|&net| (net, weight)).collect();
+    weighted_average(&nets)
+}
+
+/// Linear interpolation `a + t (b - a)` of the parameters of `a` and
+/// `b`, so `a` at `t = 0` and `b` at `t = 1`.
+pub fn lerp<T: FeedForwardNetwork>(a: &T, b: &T, t: f32) -> Box<T> {
+    weighted_average(&[(a, 1.0 - t), (b, t)])
+}
+
+/// Moves the parameters of `a` towards those of `b`, to
+/// `a + t (b - a)`, without allocating another network.
+pub fn lerp_in_place<T: FeedForwardNetwork>(a: &mut T, b: &T, t: f32) {
+    let mut sums = Vec::new();
+    let mut acc = Accumulate {
+        sums: &mut sums,
+        weight: 1.0,
+        tensor: 0,
+    };
+    b.visit_params("", &mut acc);
+
+    a.visit_params_mut("", &mut Towards { sums, t, tensor: 0 });
+}
+
+struct Towards {
+    sums: Vec<Vec<f64>>,
+    t: f32,
+    tensor: usize,
+}
+
+impl ParamVisitorMut for Towards {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        for (x, &y) in param.values.iter_mut().zip(&self.sums[self.tensor]) {
+            let a = x.to_f32();
+            *x = F::from_f32(a + self.t * (y as f32 - a));
+        }
+        self.tensor += 1;
+    }
+}
@@ -0,0 +1,258 @@
+//! Evaluation of networks on held-out data, and streaming accumulators
+//! of metrics comparing outputs against targets.
+
+use crate::{loss::Loss, FeedForwardNetwork, Float, Vector};
+
+/// Aggregate results of [`evaluate`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Mean loss over the samples.
+    pub loss: f32,
+    pub samples: usize,
+}
+
+/// Runs `net` forward over every pair of input and target in `data`,
+/// split into batches across all available threads.
+pub fn evaluate<T, L>(net: &T, data: &[(T::InputType, T::OutputType)], loss: &L) -> Metrics
+where
+    T: FeedForwardNetwork + Sync,
+    T::InputType: Sync,
+    T::OutputType: Sync,
+    L: Loss<T::OutputType> + Sync + ?Sized,
+{
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+    evaluate_with_threads(net, data, loss, threads)
+}
+
+/// Number of samples whose losses are summed together
+/// before being added to the total, in [`evaluate`].
+const CHUNK: usize = 256;
+
+/// As [`evaluate`], using at most `threads` threads.
+/// - Losses are summed in fixed chunks of samples, whose sums are then
+///   added in order, so results are identical for any number of threads.
+pub fn evaluate_with_threads<T, L>(
+    net: &T,
+    data: &[(T::InputType, T::OutputType)],
+    loss: &L,
+    threads: usize,
+) -> Metrics
+where
+    T: FeedForwardNetwork + Sync,
+    T::InputType: Sync,
+    T::OutputType: Sync,
+    L: Loss<T::OutputType> + Sync + ?Sized,
+{
+    span!(DEBUG, "evaluate", samples = data.len(), threads);
+
+    if data.is_empty() {
+        return Metrics::default();
+    }
+
+    let chunk = |chunk: &[(T::InputType, T::OutputType)]| {
+        chunk
+            .iter()
+            .map(|(input, target)| f64::from(loss.loss(&net.out(input), target).0))
+            .sum::<f64>()
+    };
+
+    let chunks = data.len().div_ceil(CHUNK);
+    let size = chunks.div_ceil(threads.max(1)) * CHUNK;
+    let sums: Vec<f64> = std::thread::scope(|s| {
+        let handles: Vec<_> = data
+            .chunks(size)
+            .map(|part| s.spawn(move || part.chunks(CHUNK).map(chunk).collect::<Vec<_>>()))
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect()
+    });
+
+    Metrics {
+        loss: (sums.iter().sum::<f64>() / data.len() as f64) as f32,
+        samples: data.len(),
+    }
+}
+
+/// Accumulates a metric over pairs of outputs and targets of type `O`.
+pub trait Metric<O> {
+    fn record(&mut self, out: &O, target: &O);
+
+    /// Value of the metric over everything recorded,
+    /// or zero if nothing has been.
+    fn value(&self) -> f32;
+}
+
+fn ratio(num: u64, den: u64) -> f32 {
+    if den == 0 {
+        0.0
+    } else {
+        (num as f64 / den as f64) as f32
+    }
+}
+
+/// Indices of the elements of `x`, largest first.
+fn ranked<const N: usize, F: Float>(x: &Vector<N, F>) -> [usize; N] {
+    let mut idx = core::array::from_fn(|i| i);
+    idx.sort_by(|&i, &j| x[j].to_f32().total_cmp(&x[i].to_f32()));
+    idx
+}
+
+/// Fraction of samples where the largest output
+/// is at the same index as the largest target.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Accuracy {
+    correct: u64,
+    count: u64,
+}
+
+impl<const N: usize, F: Float> Metric<Vector<N, F>> for Accuracy {
+    fn record(&mut self, out: &Vector<N, F>, target: &Vector<N, F>) {
+        self.correct += u64::from(ranked(out)[0] == ranked(target)[0]);
+        self.count += 1;
+    }
+
+    fn value(&self) -> f32 {
+        ratio(self.correct, self.count)
+    }
+}
+
+/// Fraction of samples where the index of the largest target is
+/// among the indices of the `k` largest outputs, as for policy heads.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TopK {
+    pub k: usize,
+    correct: u64,
+    count: u64,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            correct: 0,
+            count: 0,
+        }
+    }
+}
+
+impl<const N: usize, F: Float> Metric<Vector<N, F>> for TopK {
+    fn record(&mut self, out: &Vector<N, F>, target: &Vector<N, F>) {
+        let best = ranked(target)[0];
+        let top = &ranked(out)[..self.k.min(N)];
+        self.correct += u64::from(top.contains(&best));
+        self.count += 1;
+    }
+
+    fn value(&self) -> f32 {
+        ratio(self.correct, self.count)
+    }
+}
+
+/// Mean absolute error over every element of the outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Mae {
+    total: f64,
+    count: u64,
+}
+
+impl<const N: usize, F: Float> Metric<Vector<N, F>> for Mae {
+    fn record(&mut self, out: &Vector<N, F>, target: &Vector<N, F>) {
+        for i in 0..N {
+            self.total += f64::from((out[i].to_f32() - target[i].to_f32()).abs());
+        }
+        self.count += N as u64;
+    }
+
+    fn value(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.total / self.count as f64) as f32
+        }
+    }
+}
+
+/// Pearson correlation between every element of the
+/// outputs and the corresponding target, such as the
+/// evaluation of a position against its search score.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Correlation {
+    count: f64,
+    x: f64,
+    y: f64,
+    xx: f64,
+    yy: f64,
+    xy: f64,
+}
+
+impl<const N: usize, F: Float> Metric<Vector<N, F>> for Correlation {
+    fn record(&mut self, out: &Vector<N, F>, target: &Vector<N, F>) {
+        for i in 0..N {
+            let x = out[i].to_f64();
+            let y = target[i].to_f64();
+            self.count += 1.0;
+            self.x += x;
+            self.y += y;
+            self.xx += x * x;
+            self.yy += y * y;
+            self.xy += x * y;
+        }
+    }
+
+    fn value(&self) -> f32 {
+        let n = self.count;
+        let cov = n * self.xy - self.x * self.y;
+        let var = (n * self.xx - self.x * self.x) * (n * self.yy - self.y * self.y);
+
+        if var > 0.0 {
+            (cov / var.sqrt()) as f32
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record<M: Metric<Vector<3>>>(metric: &mut M, pairs: &[([f32; 3], [f32; 3])]) -> f32 {
+        for &(out, target) in pairs {
+            metric.record(&Vector::from_raw(out), &Vector::from_raw(target));
+        }
+        metric.value()
+    }
+
+    #[test]
+    fn classification() {
+        let pairs = [
+            ([0.1, 0.7, 0.2], [0.0, 1.0, 0.0]),
+            ([0.5, 0.3, 0.2], [0.0, 1.0, 0.0]),
+            ([0.1, 0.2, 0.7], [1.0, 0.0, 0.0]),
+            ([0.3, 0.3, 0.4], [0.0, 0.0, 1.0]),
+        ];
+
+        assert_eq!(record(&mut Accuracy::default(), &pairs), 0.5);
+        assert_eq!(record(&mut TopK::new(2), &pairs), 0.75);
+        assert_eq!(record(&mut TopK::new(5), &pairs), 1.0);
+        assert_eq!(Metric::<Vector<3>>::value(&Accuracy::default()), 0.0);
+    }
+
+    #[test]
+    fn regression() {
+        let pairs = [
+            ([1.0, 2.0, 3.0], [2.0, 4.0, 6.0]),
+            ([0.0, -1.0, 0.5], [0.0, -2.0, 1.0]),
+        ];
+
+        assert!((record(&mut Mae::default(), &pairs) - 7.5 / 6.0).abs() < 1e-6);
+        assert!((record(&mut Correlation::default(), &pairs) - 1.0).abs() < 1e-6);
+
+        let anti = [([1.0, 2.0, 3.0], [3.0, 2.0, 1.0])];
+        assert!((record(&mut Correlation::default(), &anti) + 1.0).abs() < 1e-6);
+        assert_eq!(record(&mut Correlation::default(), &[]), 0.0);
+    }
+}
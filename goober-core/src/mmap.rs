@@ -0,0 +1,261 @@
+//! Memory images of networks, which can be reinterpreted in place as
+//! the network struct without parsing or copying, so that large networks
+//! can be memory-mapped, starting instantly and sharing pages across
+//! processes, or embedded in the binary with [`include_net!`](crate::include_net).
+//! - An image is a 64 byte header, padded to the alignment of the
+//!   network, followed by the bytes of the struct with its
+//!   parameters stored little-endian and any padding zeroed.
+//! - The header records a signature of the layout of the struct, by the
+//!   name, storage type, shape and offset of every tensor, so images can
+//!   only be viewed as the same network built by a compatible compiler.
+
+use crate::{crc32, FeedForwardNetwork, Float, LoadError, Param, ParamVisitor};
+
+const MAGIC: &[u8; 4] = b"GBMM";
+
+const VERSION: u32 = 1;
+
+const HEADER: usize = 64;
+
+fn body_offset<T>() -> usize {
+    HEADER.next_multiple_of(std::mem::align_of::<T>())
+}
+
+/// Records the offset of each tensor from the start of the network,
+/// optionally writing its values into an image of the struct.
+struct Layout<'a> {
+    base: usize,
+    signature: Vec<u8>,
+    image: Option<&'a mut [u8]>,
+}
+
+impl ParamVisitor for Layout<'_> {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        assert_eq!(
+            std::mem::size_of::<F>(),
+            F::BYTES,
+            "storage type of `{}` has padding",
+            param.name
+        );
+
+        let offset = param.values.as_ptr() as usize - self.base;
+
+        self.signature.extend_from_slice(param.name.as_bytes());
+        self.signature.push(0);
+        self.signature.push(F::ID);
+        for &dim in param.shape {
+            self.signature
+                .extend_from_slice(&(dim as u64).to_le_bytes());
+        }
+        self.signature
+            .extend_from_slice(&(offset as u64).to_le_bytes());
+
+        if let Some(image) = self.image.as_deref_mut() {
+            let mut bytes = Vec::with_capacity(param.values.len() * F::BYTES);
+            for &x in param.values {
+                x.write_le(&mut bytes);
+            }
+            image[offset..offset + bytes.len()].copy_from_slice(&bytes);
+        }
+    }
+}
+
+fn signature<T: FeedForwardNetwork>(net: &T, image: Option<&mut [u8]>) -> u32 {
+    let mut layout = Layout {
+        base: net as *const T as usize,
+        signature: (std::mem::size_of::<T>() as u64).to_le_bytes().to_vec(),
+        image,
+    };
+    net.visit_params("", &mut layout);
+
+    crc32(&layout.signature)
+}
+
+/// Writes the memory image of `net`.
+pub fn to_bytes<T: FeedForwardNetwork>(net: &T) -> Vec<u8> {
+    let offset = body_offset::<T>();
+    let size = std::mem::size_of::<T>();
+
+    let mut out = vec![0; offset + size];
+    let signature = signature(net, Some(&mut out[offset..]));
+
+    out[..4].copy_from_slice(MAGIC);
+    out[4..8].copy_from_slice(&VERSION.to_le_bytes());
+    out[8..12].copy_from_slice(&signature.to_le_bytes());
+    out[12..16].copy_from_slice(&(std::mem::align_of::<T>() as u32).to_le_bytes());
+    out[16..24].copy_from_slice(&(size as u64).to_le_bytes());
+    out[24..32].copy_from_slice(&(offset as u64).to_le_bytes());
+    out
+}
+
+pub fn save<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    std::fs::write(path, to_bytes(net))
+}
+
+/// Validates the header of an image of `T`, returning
+/// its body and the signature of its layout.
+fn body<T>(bytes: &[u8]) -> Result<(&[u8], u32), LoadError> {
+    let header = bytes.get(..HEADER).ok_or(LoadError::Truncated)?;
+    let word = |at: usize| u32::from_le_bytes(header[at..at + 4].try_into().unwrap());
+    let long = |at: usize| u64::from_le_bytes(header[at..at + 8].try_into().unwrap()) as usize;
+
+    if &header[..4] != MAGIC {
+        return Err(LoadError::BadMagic);
+    }
+
+    if word(4) != VERSION {
+        return Err(LoadError::UnsupportedVersion(word(4)));
+    }
+
+    if cfg!(target_endian = "big") {
+        return Err(LoadError::Mismatch("image on big-endian host".to_string()));
+    }
+
+    let (align, size, offset) = (word(12) as usize, long(16), long(24));
+    if align != std::mem::align_of::<T>() || size != std::mem::size_of::<T>() {
+        return Err(LoadError::Mismatch(format!(
+            "image has size {size} and alignment {align}, expected {} and {}",
+            std::mem::size_of::<T>(),
+            std::mem::align_of::<T>(),
+        )));
+    }
+
+    if offset != body_offset::<T>() {
+        return Err(LoadError::Malformed(format!("image at offset {offset}")));
+    }
+
+    let body = bytes
+        .get(offset..offset + size)
+        .ok_or(LoadError::Truncated)?;
+    if bytes.len() != offset + size {
+        return Err(LoadError::Malformed(
+            "trailing bytes after image".to_string(),
+        ));
+    }
+
+    Ok((body, word(8)))
+}
+
+/// Reinterprets a memory image written by [`to_bytes`] as the network
+/// it was written from, after validating its header and layout.
+/// - `bytes` must be aligned such that the network within it is aligned,
+///   which holds for any allocation or mapping aligned to 64 bytes.
+///
+/// # Safety
+/// Every field of `T` that is not a parameter must be valid for any
+/// bit pattern, as for [`FeedForwardNetwork::boxed_and_zeroed`].
+pub unsafe fn view<T: FeedForwardNetwork>(bytes: &[u8]) -> Result<&T, LoadError> {
+    let (body, expected) = body::<T>(bytes)?;
+
+    if !(body.as_ptr() as usize).is_multiple_of(std::mem::align_of::<T>()) {
+        return Err(LoadError::Malformed("misaligned image".to_string()));
+    }
+
+    // SAFETY: the body is the size of `T` and aligned for it, every
+    // parameter is valid for any bit pattern and every other field is
+    // by the contract of this function
+    let net = unsafe { &*body.as_ptr().cast::<T>() };
+
+    if signature(net, None) != expected {
+        return Err(LoadError::Mismatch(
+            "image has a different layout".to_string(),
+        ));
+    }
+
+    Ok(net)
+}
+
+/// As [`view`], copying the image into a new allocation,
+/// so `bytes` need not be aligned.
+///
+/// # Safety
+/// As for [`view`].
+pub unsafe fn read<T: FeedForwardNetwork>(bytes: &[u8]) -> Result<Box<T>, LoadError> {
+    let (body, expected) = body::<T>(bytes)?;
+
+    let mut net = T::boxed_and_zeroed();
+
+    // SAFETY: the body is the size of `T`, and the result
+    // is valid by the contract of this function
+    unsafe {
+        let ptr = (&mut *net as *mut T).cast::<u8>();
+        std::ptr::copy_nonoverlapping(body.as_ptr(), ptr, body.len());
+    }
+
+    if signature(&*net, None) != expected {
+        return Err(LoadError::Mismatch(
+            "image has a different layout".to_string(),
+        ));
+    }
+
+    Ok(net)
+}
+
+/// Embeds a memory image written by [`save`] in the binary, evaluating
+/// to a `&'static` reference to the network viewed in place, without
+/// copying or parsing, and panicking if it is not a valid image of the
+/// network.
+///
+/// ```ignore
+/// static NET: LazyLock<&Net> = LazyLock::new(|| unsafe { include_net!(Net, "net.bin") });
+/// ```
+///
+/// # Safety
+/// As for [`view`], so must be expanded in an `unsafe` block.
+#[macro_export]
+macro_rules! include_net {
+    ($t:ty, $path:expr) => {{
+        #[repr(C)]
+        struct __Aligned<A, B: ?Sized> {
+            _align: [A; 0],
+            bytes: B,
+        }
+
+        static IMAGE: &__Aligned<$t, [u8]> = &__Aligned {
+            _align: [],
+            bytes: *include_bytes!($path),
+        };
+
+        match $crate::mmap::view::<$t>(&IMAGE.bytes) {
+            Ok(net) => net,
+            Err(err) => panic!("invalid image of network: {err}"),
+        }
+    }};
+}
+
+/// Network viewed in place within a memory-mapped image.
+#[cfg(feature = "mmap")]
+pub struct Mapped<T> {
+    mmap: memmap2::Mmap,
+    phantom: std::marker::PhantomData<T>,
+}
+
+#[cfg(feature = "mmap")]
+impl<T: FeedForwardNetwork> std::ops::Deref for Mapped<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: validated by `view` when mapped
+        unsafe { &*self.mmap[body_offset::<T>()..].as_ptr().cast::<T>() }
+    }
+}
+
+/// Memory-maps an image written by [`save`], validating it as by [`view`].
+///
+/// # Safety
+/// As for [`view`], and the file must not be modified while mapped.
+#[cfg(feature = "mmap")]
+pub unsafe fn map<T: FeedForwardNetwork>(path: &str) -> Result<Mapped<T>, LoadError> {
+    let file = std::fs::File::open(path)?;
+
+    // SAFETY: the caller guarantees the file is not modified while mapped
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+    // SAFETY: forwarded to the caller
+    unsafe { view::<T>(&mmap)? };
+
+    Ok(Mapped {
+        mmap,
+        phantom: std::marker::PhantomData,
+    })
+}
@@ -0,0 +1,321 @@
+//! Reading and writing tensors of parameters as NumPy `.npy`
+//! files, either one file per tensor in a directory, or all
+//! together in an `.npz` archive, and reading datasets saved
+//! as a pair of arrays of inputs and targets.
+
+use crate::{
+    crc32, params::load_named, params::Tensors, FeedForwardNetwork, Float, LoadError, Param,
+    ParamVisitor, Vector,
+};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+fn descr<F: Float>() -> &'static str {
+    match F::BYTES {
+        8 => "<f8",
+        2 if F::ID == 2 => "<f2",
+        _ => "<f4",
+    }
+}
+
+/// `shape` as a Python tuple.
+fn shape(shape: &[usize]) -> String {
+    match shape {
+        [dim] => format!("({dim},)"),
+        dims => format!(
+            "({})",
+            dims.iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn encode<F: Float>(param: &Param<'_, F>) -> Vec<u8> {
+    let shape = shape(param.shape);
+
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}",
+        descr::<F>()
+    );
+    while !(MAGIC.len() + 4 + header.len() + 1).is_multiple_of(64) {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut out = MAGIC.to_vec();
+    out.extend_from_slice(&[1, 0]);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+
+    for &x in param.values {
+        match descr::<F>() {
+            "<f4" if F::BYTES != 4 => out.extend_from_slice(&x.to_f32().to_le_bytes()),
+            _ => x.write_le(&mut out),
+        }
+    }
+
+    out
+}
+
+fn header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, LoadError> {
+    let malformed = || LoadError::Malformed(format!("missing `{key}` in header"));
+    let start = header.find(&format!("'{key}':")).ok_or_else(malformed)? + key.len() + 3;
+    Ok(header[start..].trim_start())
+}
+
+fn decode(bytes: &[u8]) -> Result<(Vec<usize>, Vec<f64>), LoadError> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(LoadError::BadMagic);
+    }
+
+    let (len_bytes, offset) = match bytes.get(6) {
+        Some(1) => (2, 8),
+        Some(2 | 3) => (4, 8),
+        _ => return Err(LoadError::Malformed("unsupported version".to_string())),
+    };
+
+    let len = bytes
+        .get(offset..offset + len_bytes)
+        .ok_or(LoadError::Truncated)?
+        .iter()
+        .rev()
+        .fold(0, |acc, &b| acc << 8 | b as usize);
+
+    let start = offset + len_bytes;
+    let header = bytes.get(start..start + len).ok_or(LoadError::Truncated)?;
+    let header =
+        std::str::from_utf8(header).map_err(|err| LoadError::Malformed(err.to_string()))?;
+
+    if !header_value(header, "fortran_order")?.starts_with("False") {
+        return Err(LoadError::Mismatch("fortran order".to_string()));
+    }
+
+    let descr = header_value(header, "descr")?;
+    let (size, read): (usize, fn(&[u8]) -> f64) = match descr.get(..5) {
+        Some("'<f4'") => (4, |b| f64::from(f32::read_le(b))),
+        Some("'<f8'") => (8, f64::read_le),
+        Some("'<f2'") => (2, |b| crate::f16::read_le(b).to_f64()),
+        Some("'<i8'") => (8, |b| i64::from_le_bytes(b.try_into().unwrap()) as f64),
+        Some("'<i4'") => (4, |b| f64::from(i32::from_le_bytes(b.try_into().unwrap()))),
+        Some("'|u1'" | "'|b1'") => (1, |b| f64::from(b[0])),
+        _ => return Err(LoadError::Mismatch(format!("dtype {descr}"))),
+    };
+
+    let shape = header_value(header, "shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|s| s.split(')').next())
+        .ok_or_else(|| LoadError::Malformed("shape".to_string()))?
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse())
+        .collect::<Result<Vec<usize>, _>>()
+        .map_err(|err| LoadError::Malformed(err.to_string()))?;
+
+    let data = &bytes[start + len..];
+    let count = shape.iter().product::<usize>();
+    if data.len() < count * size {
+        return Err(LoadError::Truncated);
+    }
+
+    let values = data.chunks_exact(size).take(count).map(read).collect();
+    Ok((shape, values))
+}
+
+struct Files(Vec<(String, Vec<u8>)>);
+
+impl ParamVisitor for Files {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.0.push((format!("{}.npy", param.name), encode(&param)));
+    }
+}
+
+fn files<T: FeedForwardNetwork>(net: &T) -> Vec<(String, Vec<u8>)> {
+    let mut files = Files(Vec::new());
+    net.visit_params("", &mut files);
+    files.0
+}
+
+/// Writes each tensor of parameters to `<dir>/<name>.npy`.
+pub fn save_dir<T: FeedForwardNetwork>(net: &T, dir: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (name, bytes) in files(net) {
+        std::fs::write(std::path::Path::new(dir).join(name), bytes)?;
+    }
+    Ok(())
+}
+
+/// Reads each tensor of parameters from `<dir>/<name>.npy`.
+pub fn load_dir<T: FeedForwardNetwork>(net: &mut T, dir: &str) -> Result<(), LoadError> {
+    let mut tensors = Tensors::new();
+    for (name, _) in files(net) {
+        let bytes = std::fs::read(std::path::Path::new(dir).join(&name))?;
+        tensors.insert(name.trim_end_matches(".npy").to_string(), decode(&bytes)?);
+    }
+    load_named(net, &tensors)
+}
+
+/// Writes an uncompressed `.npz` archive, as by `numpy.savez`.
+pub fn to_npz<T: FeedForwardNetwork>(net: &T) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+    let files = files(net);
+
+    for (name, data) in &files {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+
+        // local file header, stored without compression
+        let mut header = Vec::new();
+        header.extend_from_slice(&20u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&0x21u16.to_le_bytes());
+        header.extend_from_slice(&crc.to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        out.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(data);
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&header);
+        central.extend_from_slice(&[0; 6]);
+        central.extend_from_slice(&0u32.to_le_bytes());
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+
+    out.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+pub fn save_npz<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    std::fs::write(path, to_npz(net))
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> Result<usize, LoadError> {
+    let b = bytes.get(pos..pos + 2).ok_or(LoadError::Truncated)?;
+    Ok(u16::from_le_bytes([b[0], b[1]]) as usize)
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> Result<usize, LoadError> {
+    let b = bytes.get(pos..pos + 4).ok_or(LoadError::Truncated)?;
+    Ok(u32::from_le_bytes(b.try_into().unwrap()) as usize)
+}
+
+/// Reads an uncompressed `.npz` archive, as written by `numpy.savez`.
+pub fn from_npz<T: FeedForwardNetwork>(net: &mut T, bytes: &[u8]) -> Result<(), LoadError> {
+    let end = (0..bytes.len().saturating_sub(21))
+        .rev()
+        .find(|&i| bytes[i..].starts_with(&0x06054b50u32.to_le_bytes()))
+        .ok_or(LoadError::BadMagic)?;
+
+    let entries = u16_at(bytes, end + 10)?;
+    let mut pos = u32_at(bytes, end + 16)?;
+    let mut tensors = Tensors::new();
+
+    for _ in 0..entries {
+        if u32_at(bytes, pos)? != 0x02014b50 {
+            return Err(LoadError::Malformed("central directory".to_string()));
+        }
+
+        if u16_at(bytes, pos + 10)? != 0 {
+            return Err(LoadError::Mismatch("compressed archive".to_string()));
+        }
+
+        let size = u32_at(bytes, pos + 20)?;
+        let name_len = u16_at(bytes, pos + 28)?;
+        let skip = u16_at(bytes, pos + 30)? + u16_at(bytes, pos + 32)?;
+        let offset = u32_at(bytes, pos + 42)?;
+        let name = bytes
+            .get(pos + 46..pos + 46 + name_len)
+            .ok_or(LoadError::Truncated)?;
+        let name = String::from_utf8_lossy(name);
+        pos += 46 + name_len + skip;
+
+        let start = offset + 30 + u16_at(bytes, offset + 26)? + u16_at(bytes, offset + 28)?;
+        let data = bytes.get(start..start + size).ok_or(LoadError::Truncated)?;
+
+        let name = name.trim_end_matches(".npy").to_string();
+        tensors.insert(name, decode(data)?);
+    }
+
+    load_named(net, &tensors)
+}
+
+pub fn load_npz<T: FeedForwardNetwork>(net: &mut T, path: &str) -> Result<(), LoadError> {
+    from_npz(net, &std::fs::read(path)?)
+}
+
+/// Reads samples of `M` inputs and `N` targets from the `.npy` arrays `x`,
+/// of shape `(samples, M)`, and `y`, of shape `(samples, N)`, or
+/// `(samples,)` if `N` is 1, as saved by `numpy.save`.
+/// - Arrays of floats, of `int32` or `int64`, or of `uint8` or `bool`
+///   are read, converted to `f32`.
+pub fn dataset_from_npy<const M: usize, const N: usize>(
+    x: &[u8],
+    y: &[u8],
+) -> Result<Vec<(Vector<M>, Vector<N>)>, LoadError> {
+    let (x_shape, x) = decode(x)?;
+    let (y_shape, y) = decode(y)?;
+
+    let samples = match *x_shape {
+        [samples, m] if m == M => samples,
+        _ => {
+            let message = format!("inputs of shape {}, expected (_, {M})", shape(&x_shape));
+            return Err(LoadError::Mismatch(message));
+        }
+    };
+    match *y_shape {
+        [n] if n == samples && N == 1 => {}
+        [n, columns] if n == samples && columns == N => {}
+        _ => {
+            let expected = match N {
+                1 => format!("({samples},) or ({samples}, 1)"),
+                _ => format!("({samples}, {N})"),
+            };
+            let message = format!("targets of shape {}, expected {expected}", shape(&y_shape));
+            return Err(LoadError::Mismatch(message));
+        }
+    }
+
+    let data = x
+        .chunks_exact(M)
+        .zip(y.chunks_exact(N))
+        .map(|(x, y)| {
+            (
+                Vector::from_fn(|i| x[i] as f32),
+                Vector::from_fn(|i| y[i] as f32),
+            )
+        })
+        .collect();
+    Ok(data)
+}
+
+/// Reads a dataset from the `.npy` files at `x` and `y`,
+/// as [`dataset_from_npy`].
+pub fn load_dataset<const M: usize, const N: usize>(
+    x: &str,
+    y: &str,
+) -> Result<Vec<(Vector<M>, Vector<N>)>, LoadError> {
+    dataset_from_npy(&std::fs::read(x)?, &std::fs::read(y)?)
+}
@@ -0,0 +1,620 @@
+//! Exporting networks as [ONNX](https://onnx.ai) models, from
+//! the [`Graph`] recorded by [`FeedForwardNetwork::trace`], and
+//! importing the weights of ONNX models with matching graphs.
+//!
+//! Dense inputs are a float tensor of shape `[M]`, and sparse
+//! inputs an int64 tensor of active feature indices, of
+//! dynamic length. The output is a float tensor of shape `[N]`.
+
+use std::collections::HashMap;
+
+use crate::{
+    FeedForwardNetwork, Float, Graph, LoadError, Node, Op, Param, ParamMut, ParamVisitor,
+    ParamVisitorMut, Unsupported,
+};
+
+const OPSET: u64 = 13;
+const IR_VERSION: u64 = 7;
+
+const FLOAT: u64 = 1;
+const INT64: u64 = 7;
+
+const ATTR_INT: u64 = 2;
+const ATTR_INTS: u64 = 7;
+
+/// Minimal protobuf message encoder.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut x: u64) {
+        while x >= 0x80 {
+            self.0.push(x as u8 | 0x80);
+            x >>= 7;
+        }
+        self.0.push(x as u8);
+    }
+
+    fn int(&mut self, field: u64, x: u64) -> &mut Self {
+        self.varint(field << 3);
+        self.varint(x);
+        self
+    }
+
+    fn bytes(&mut self, field: u64, bytes: &[u8]) -> &mut Self {
+        self.varint(field << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn string(&mut self, field: u64, s: &str) -> &mut Self {
+        self.bytes(field, s.as_bytes())
+    }
+
+    fn message(&mut self, field: u64, msg: &Message) -> &mut Self {
+        self.bytes(field, &msg.0)
+    }
+}
+
+fn tensor(name: &str, dims: &[usize], data_type: u64, raw: &[u8]) -> Message {
+    let mut msg = Message::default();
+    for &dim in dims {
+        msg.int(1, dim as u64);
+    }
+    msg.int(2, data_type).string(8, name).bytes(9, raw);
+    msg
+}
+
+fn float_tensor(name: &str, dims: &[usize], values: &[f32]) -> Message {
+    let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    tensor(name, dims, FLOAT, &raw)
+}
+
+fn int64_tensor(name: &str, values: &[i64]) -> Message {
+    let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    tensor(name, &[values.len()], INT64, &raw)
+}
+
+fn value_info(name: &str, elem_type: u64, dim: Result<usize, &str>) -> Message {
+    let mut dimension = Message::default();
+    match dim {
+        Ok(size) => dimension.int(1, size as u64),
+        Err(param) => dimension.string(2, param),
+    };
+
+    let mut shape = Message::default();
+    shape.message(1, &dimension);
+
+    let mut tensor_type = Message::default();
+    tensor_type.int(1, elem_type).message(2, &shape);
+
+    let mut ty = Message::default();
+    ty.message(1, &tensor_type);
+
+    let mut msg = Message::default();
+    msg.string(1, name).message(2, &ty);
+    msg
+}
+
+fn int_attr(name: &str, x: i64) -> Message {
+    let mut msg = Message::default();
+    msg.string(1, name).int(3, x as u64).int(20, ATTR_INT);
+    msg
+}
+
+fn ints_attr(name: &str, xs: &[i64]) -> Message {
+    let mut msg = Message::default();
+    msg.string(1, name);
+    for &x in xs {
+        msg.int(8, x as u64);
+    }
+    msg.int(20, ATTR_INTS);
+    msg
+}
+
+struct Tensors(HashMap<String, (Vec<usize>, Vec<f32>)>);
+
+impl ParamVisitor for Tensors {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let values = param.values.iter().map(|x| x.to_f32()).collect();
+        self.0
+            .insert(param.name.to_string(), (param.shape.to_vec(), values));
+    }
+}
+
+struct Builder {
+    tensors: Tensors,
+    nodes: Vec<Message>,
+    initializers: Vec<Message>,
+    count: usize,
+}
+
+impl Builder {
+    fn fresh(&mut self, hint: &str) -> String {
+        self.count += 1;
+        format!("{hint}_{}", self.count)
+    }
+
+    fn node(&mut self, op_type: &str, inputs: &[&str], output: &str, attrs: &[Message]) {
+        let mut msg = Message::default();
+        for input in inputs {
+            msg.string(1, input);
+        }
+        msg.string(2, output)
+            .string(3, &format!("{op_type}_{}", self.nodes.len()))
+            .string(4, op_type);
+        for attr in attrs {
+            msg.message(5, attr);
+        }
+        self.nodes.push(msg);
+    }
+
+    fn param(&mut self, name: &str) -> Result<&(Vec<usize>, Vec<f32>), Unsupported> {
+        self.tensors
+            .0
+            .get(name)
+            .ok_or_else(|| Unsupported(format!("unknown tensor `{name}`")))
+    }
+
+    fn initializer(&mut self, name: &str) -> Result<(), Unsupported> {
+        let (shape, values) = self.param(name)?;
+        let tensor = float_tensor(name, shape, values);
+        self.initializers.push(tensor);
+        Ok(())
+    }
+
+    fn constant(&mut self, hint: &str, values: &[i64]) -> String {
+        let name = self.fresh(hint);
+        self.initializers.push(int64_tensor(&name, values));
+        name
+    }
+
+    fn float_constant(&mut self, hint: &str, value: f32) -> String {
+        let name = self.fresh(hint);
+        self.initializers.push(float_tensor(&name, &[], &[value]));
+        name
+    }
+
+    fn add(&mut self, node: &Node) -> Result<(), Unsupported> {
+        let input = node.inputs[0].as_str();
+        let output = node.output.as_str();
+
+        match &node.op {
+            Op::Linear { weight, bias } => {
+                self.initializer(weight)?;
+                self.initializer(bias)?;
+                let product = self.fresh("matmul");
+                self.node("MatMul", &[weight, input], &product, &[]);
+                self.node("Add", &[&product, bias], output, &[]);
+            }
+            Op::SparseLinear { weight, bias } => {
+                self.initializer(weight)?;
+                self.initializer(bias)?;
+                let axes = self.constant("axes", &[0]);
+                let rows = self.fresh("gather");
+                let sum = self.fresh("sum");
+                self.node("Gather", &[weight, input], &rows, &[int_attr("axis", 0)]);
+                self.node(
+                    "ReduceSum",
+                    &[&rows, &axes],
+                    &sum,
+                    &[int_attr("keepdims", 0)],
+                );
+                self.node("Add", &[&sum, bias], output, &[]);
+            }
+            Op::Conv1D {
+                weight,
+                bias,
+                kernel,
+            } => {
+                let values = self.param(weight)?.1[..*kernel].to_vec();
+                let len = self.param(weight)?.0[0];
+                let kernel_name = self.fresh("kernel");
+                self.initializers
+                    .push(float_tensor(&kernel_name, &[1, 1, *kernel], &values));
+                self.initializer(bias)?;
+
+                let in_shape = self.constant("shape", &[1, 1, len as i64]);
+                let out_shape = self.constant("shape", &[node.size as i64]);
+                let reshaped = self.fresh("reshape");
+                let conv = self.fresh("conv");
+                let flat = self.fresh("flatten");
+                self.node("Reshape", &[input, &in_shape], &reshaped, &[]);
+                let kernel_shape = ints_attr("kernel_shape", &[*kernel as i64]);
+                self.node("Conv", &[&reshaped, &kernel_name], &conv, &[kernel_shape]);
+                self.node("Reshape", &[&conv, &out_shape], &flat, &[]);
+                self.node("Add", &[&flat, bias], output, &[]);
+            }
+            Op::Activation(id) => match id {
+                0 => self.node("Identity", &[input], output, &[]),
+                1 => self.node("Relu", &[input], output, &[]),
+                2 => {
+                    let zero = self.float_constant("zero", 0.0);
+                    let one = self.float_constant("one", 1.0);
+                    let clipped = self.fresh("clip");
+                    self.node("Clip", &[input, &zero, &one], &clipped, &[]);
+                    self.node("Mul", &[&clipped, &clipped], output, &[]);
+                }
+                3 => self.node("Tanh", &[input], output, &[]),
+                _ => return Err(Unsupported(format!("activation with id {id}"))),
+            },
+            Op::Add => self.node("Add", &[input, &node.inputs[1]], output, &[]),
+        }
+
+        Ok(())
+    }
+
+    /// Type of the network input, from the first operation consuming it.
+    fn input(&self, graph: &Graph) -> Result<Message, Unsupported> {
+        let node = graph
+            .nodes()
+            .iter()
+            .find(|node| node.inputs.iter().any(|x| x == Graph::INPUT))
+            .ok_or_else(|| Unsupported("network without operations".to_string()))?;
+
+        let info = match &node.op {
+            Op::SparseLinear { .. } => value_info(Graph::INPUT, INT64, Err("features")),
+            Op::Linear { weight, .. } => {
+                let size = self.tensors.0[weight].0[1];
+                value_info(Graph::INPUT, FLOAT, Ok(size))
+            }
+            Op::Conv1D { weight, .. } => {
+                let size = self.tensors.0[weight].0[0];
+                value_info(Graph::INPUT, FLOAT, Ok(size))
+            }
+            _ => return Err(Unsupported("input to non-parametric layer".to_string())),
+        };
+
+        Ok(info)
+    }
+}
+
+/// Converts a network to a serialized ONNX `ModelProto`.
+pub fn to_bytes<T: FeedForwardNetwork>(net: &T) -> Result<Vec<u8>, Unsupported> {
+    let mut graph = Graph::default();
+    net.trace("", Graph::INPUT, &mut graph)?;
+
+    let mut builder = Builder {
+        tensors: Tensors(HashMap::new()),
+        nodes: Vec::new(),
+        initializers: Vec::new(),
+        count: 0,
+    };
+    net.visit_params("", &mut builder.tensors);
+
+    for node in graph.nodes() {
+        builder.add(node)?;
+    }
+
+    let input = builder.input(&graph)?;
+    let size = graph.nodes().last().unwrap().size;
+    let output = value_info(graph.output(), FLOAT, Ok(size));
+
+    let mut proto = Message::default();
+    for node in &builder.nodes {
+        proto.message(1, node);
+    }
+    proto.string(2, "goober");
+    for init in &builder.initializers {
+        proto.message(5, init);
+    }
+    proto.message(11, &input).message(12, &output);
+
+    let mut opset = Message::default();
+    opset.string(1, "").int(2, OPSET);
+
+    let mut model = Message::default();
+    model
+        .int(1, IR_VERSION)
+        .string(2, "goober")
+        .message(7, &proto)
+        .message(8, &opset);
+
+    Ok(model.0)
+}
+
+pub fn save<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    let bytes = to_bytes(net).map_err(std::io::Error::other)?;
+    std::fs::write(path, bytes)
+}
+
+/// Protobuf field number, and either its integer
+/// value or its length-delimited bytes.
+type Field<'a> = (u64, Result<u64, &'a [u8]>);
+
+/// Minimal protobuf message decoder.
+fn decode(mut bytes: &[u8]) -> Result<Vec<Field<'_>>, LoadError> {
+    fn varint(bytes: &mut &[u8]) -> Result<u64, LoadError> {
+        let mut res = 0;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = bytes.split_first().ok_or(LoadError::Truncated)?;
+            *bytes = rest;
+            res |= u64::from(byte & 0x7F) << shift;
+            if byte < 0x80 {
+                return Ok(res);
+            }
+        }
+        Err(LoadError::Malformed("invalid varint".to_string()))
+    }
+
+    let mut res = Vec::new();
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes)?;
+        let value = match key & 7 {
+            0 => Ok(varint(&mut bytes)?),
+            1 | 5 => {
+                let len = if key & 7 == 1 { 8 } else { 4 };
+                let fixed = bytes.get(..len).ok_or(LoadError::Truncated)?;
+                bytes = &bytes[len..];
+                Ok(fixed
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, &b| acc << 8 | u64::from(b)))
+            }
+            2 => {
+                let len = varint(&mut bytes)? as usize;
+                let value = bytes.get(..len).ok_or(LoadError::Truncated)?;
+                bytes = &bytes[len..];
+                Err(value)
+            }
+            wire => return Err(LoadError::Malformed(format!("wire type {wire}"))),
+        };
+        res.push((key >> 3, value));
+    }
+
+    Ok(res)
+}
+
+fn decode_bytes(bytes: &[u8], field: u64) -> Result<Vec<&[u8]>, LoadError> {
+    Ok(decode(bytes)?
+        .into_iter()
+        .filter(|&(f, _)| f == field)
+        .filter_map(|(_, v)| v.err())
+        .collect())
+}
+
+fn decode_strings(bytes: &[u8], field: u64) -> Result<Vec<String>, LoadError> {
+    decode_bytes(bytes, field)?
+        .into_iter()
+        .map(|s| String::from_utf8(s.to_vec()).map_err(|e| LoadError::Malformed(e.to_string())))
+        .collect()
+}
+
+/// Decodes a float `TensorProto` into its dims and values.
+fn decode_tensor(bytes: &[u8]) -> Result<(Vec<usize>, Vec<f32>), LoadError> {
+    let mut dims = Vec::new();
+    let mut values = Vec::new();
+    let mut data_type = FLOAT;
+
+    for (field, value) in decode(bytes)? {
+        match (field, value) {
+            (1, Ok(dim)) => dims.push(dim as usize),
+            (2, Ok(ty)) => data_type = ty,
+            (4, Ok(bits)) => values.push(f32::from_bits(bits as u32)),
+            (4, Err(packed)) | (9, Err(packed)) => values.extend(
+                packed
+                    .chunks_exact(4)
+                    .map(|x| f32::from_le_bytes(x.try_into().unwrap())),
+            ),
+            _ => {}
+        }
+    }
+
+    if data_type != FLOAT {
+        return Err(LoadError::Mismatch(format!("tensor of type {data_type}")));
+    }
+
+    if values.len() != dims.iter().product::<usize>() {
+        return Err(LoadError::Malformed("tensor size".to_string()));
+    }
+
+    Ok((dims, values))
+}
+
+/// Operation recovered from an ONNX graph, with its parameters.
+struct Imported {
+    op: Op,
+    weight: Option<(Vec<usize>, Vec<f32>)>,
+    bias: Option<Vec<f32>>,
+}
+
+fn import_ops(bytes: &[u8]) -> Result<Vec<Imported>, LoadError> {
+    let graph = decode_bytes(bytes, 7)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| LoadError::Malformed("missing graph".to_string()))?;
+
+    let mut inits = HashMap::new();
+    for tensor in decode_bytes(graph, 5)? {
+        let name = decode_strings(tensor, 8)?.pop().unwrap_or_default();
+        if decode(tensor)?
+            .iter()
+            .any(|&(f, v)| f == 2 && v == Ok(INT64))
+        {
+            continue;
+        }
+        inits.insert(name, decode_tensor(tensor)?);
+    }
+
+    let mut ops: Vec<Imported> = Vec::new();
+    let mut clipped = false;
+
+    let op = |op, weight| Imported {
+        op,
+        weight,
+        bias: None,
+    };
+    let linear = || Op::Linear {
+        weight: String::new(),
+        bias: String::new(),
+    };
+
+    for node in decode_bytes(graph, 1)? {
+        let op_type = decode_strings(node, 4)?.pop().unwrap_or_default();
+        let inputs = decode_strings(node, 1)?;
+        let init = |i: usize| inputs.get(i).and_then(|x| inits.get(x)).cloned();
+
+        match op_type.as_str() {
+            "MatMul" => match (init(0), init(1)) {
+                (Some(weight), None) => ops.push(op(linear(), Some(weight))),
+                (None, Some((dims, values))) if dims.len() == 2 => {
+                    let (m, n) = (dims[0], dims[1]);
+                    let transposed = (0..n * m).map(|i| values[(i % m) * n + i / m]).collect();
+                    ops.push(op(linear(), Some((vec![n, m], transposed))));
+                }
+                _ => return Err(LoadError::Mismatch("MatMul without weights".to_string())),
+            },
+            "Gather" => ops.push(Imported {
+                op: Op::SparseLinear {
+                    weight: String::new(),
+                    bias: String::new(),
+                },
+                weight: init(0),
+                bias: None,
+            }),
+            "Conv" => {
+                let weight = init(1).map(|(_, values)| (vec![values.len()], values));
+                let kernel = weight.as_ref().map_or(0, |w| w.1.len());
+                ops.push(Imported {
+                    op: Op::Conv1D {
+                        weight: String::new(),
+                        bias: String::new(),
+                        kernel,
+                    },
+                    weight,
+                    bias: init(2).map(|(_, values)| values),
+                });
+            }
+            "Add" => match (init(0), init(1)) {
+                (None, None) => ops.push(op(Op::Add, None)),
+                (Some((_, bias)), None) | (None, Some((_, bias))) => {
+                    let last = ops.last_mut().filter(|last| last.weight.is_some());
+                    let last = last.ok_or_else(|| {
+                        LoadError::Mismatch("bias without preceding layer".to_string())
+                    })?;
+                    last.bias = Some(match last.bias.take() {
+                        Some(prev) => bias.iter().map(|&b| b + prev[0]).collect(),
+                        None => bias,
+                    });
+                }
+                _ => return Err(LoadError::Mismatch("Add of two constants".to_string())),
+            },
+            "Identity" => ops.push(op(Op::Activation(0), None)),
+            "Relu" => ops.push(op(Op::Activation(1), None)),
+            "Tanh" => ops.push(op(Op::Activation(3), None)),
+            "Clip" => clipped = true,
+            "Mul" if clipped && inputs.len() == 2 && inputs[0] == inputs[1] => {
+                clipped = false;
+                ops.push(op(Op::Activation(2), None));
+            }
+            "ReduceSum" | "Reshape" | "Flatten" | "Squeeze" | "Unsqueeze" | "Constant" => {}
+            other => return Err(LoadError::Mismatch(format!("unsupported op `{other}`"))),
+        }
+    }
+
+    Ok(ops
+        .into_iter()
+        .filter(|imported| imported.op != Op::Activation(0))
+        .collect())
+}
+
+struct Assign(HashMap<String, Vec<f32>>);
+
+impl ParamVisitorMut for Assign {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        if let Some(values) = self.0.get(param.name) {
+            for (x, &y) in param.values.iter_mut().zip(values) {
+                *x = F::from_f32(y);
+            }
+        }
+    }
+}
+
+struct Shapes(HashMap<String, Vec<usize>>);
+
+impl ParamVisitor for Shapes {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.0.insert(param.name.to_string(), param.shape.to_vec());
+    }
+}
+
+/// Loads the weights of an ONNX model into a network of the same
+/// architecture, failing if any operation or shape does not match.
+///
+/// Supports `MatMul`, `Add`, `Relu`, `Tanh`, `Conv` (1D) and the
+/// `Gather`-based sparse layers written by [`to_bytes`].
+pub fn from_bytes<T: FeedForwardNetwork>(net: &mut T, bytes: &[u8]) -> Result<(), LoadError> {
+    let imported = import_ops(bytes)?;
+
+    let mut graph = Graph::default();
+    net.trace("", Graph::INPUT, &mut graph)
+        .map_err(|err| LoadError::Mismatch(err.to_string()))?;
+
+    let expected: Vec<&Node> = graph
+        .nodes()
+        .iter()
+        .filter(|node| node.op != Op::Activation(0))
+        .collect();
+
+    if expected.len() != imported.len() {
+        return Err(LoadError::Mismatch(format!(
+            "expected {} operations, found {}",
+            expected.len(),
+            imported.len()
+        )));
+    }
+
+    let mut shapes = Shapes(HashMap::new());
+    net.visit_params("", &mut shapes);
+
+    let mut values = HashMap::new();
+    for (node, imported) in expected.iter().zip(imported) {
+        let mismatch = || LoadError::Mismatch(format!("operation `{}`", node.output));
+
+        let (weight, bias) = match (&node.op, &imported.op) {
+            (Op::Linear { weight, bias }, Op::Linear { .. })
+            | (Op::SparseLinear { weight, bias }, Op::SparseLinear { .. }) => (weight, bias),
+            (
+                Op::Conv1D {
+                    weight,
+                    bias,
+                    kernel,
+                },
+                Op::Conv1D { kernel: found, .. },
+            ) if kernel == found => (weight, bias),
+            (expected, found) if expected == found => continue,
+            _ => return Err(mismatch()),
+        };
+
+        let (dims, mut weights) = imported.weight.ok_or_else(mismatch)?;
+        let weight_shape = &shapes.0[weight];
+
+        if let Op::Conv1D { .. } = node.op {
+            weights.resize(weight_shape[0], 0.0);
+        } else if &dims != weight_shape {
+            return Err(LoadError::Mismatch(format!(
+                "`{weight}` has shape {dims:?}, expected {weight_shape:?}"
+            )));
+        }
+
+        let size = shapes.0[bias][0];
+        let biases = match imported.bias {
+            Some(b) if b.len() == size => b,
+            Some(b) if b.len() == 1 => vec![b[0]; size],
+            Some(_) => return Err(LoadError::Mismatch(format!("shape of `{bias}`"))),
+            None => vec![0.0; size],
+        };
+
+        values.insert(weight.clone(), weights);
+        values.insert(bias.clone(), biases);
+    }
+
+    let mut assign = Assign(values);
+    net.visit_params_mut("", &mut assign);
+    Ok(())
+}
+
+pub fn load<T: FeedForwardNetwork>(net: &mut T, path: &str) -> Result<(), LoadError> {
+    from_bytes(net, &std::fs::read(path)?)
+}
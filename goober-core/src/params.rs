@@ -0,0 +1,121 @@
+use alloc::{
+    borrow::Cow,
+    format,
+    string::{String, ToString},
+};
+
+use crate::Float;
+#[cfg(feature = "std")]
+use crate::{FeedForwardNetwork, LoadError, Real};
+
+/// Named tensor of parameters within a network.
+/// - `shape` lists the size of each dimension, with
+///   `values` stored in row-major order.
+/// - `activation` is the [`ID`](crate::activation::Activation::ID)
+///   of the activation of the layer the tensor belongs to.
+pub struct Param<'a, F: Float> {
+    pub name: &'a str,
+    pub shape: &'a [usize],
+    pub activation: u8,
+    pub values: &'a [F],
+}
+
+/// Mutable counterpart to [`Param`].
+pub struct ParamMut<'a, F: Float> {
+    pub name: &'a str,
+    pub shape: &'a [usize],
+    pub activation: u8,
+    pub values: &'a mut [F],
+}
+
+/// Visits each tensor of parameters in a network, in a fixed order.
+pub trait ParamVisitor {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>);
+}
+
+/// Visits each tensor of parameters in a network mutably,
+/// in the same order as [`ParamVisitor`].
+pub trait ParamVisitorMut {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>);
+}
+
+/// Visits the output of each layer computed in a forward pass, named
+/// as the parameters of the layer are, in the order they are computed.
+pub trait ActivationVisitor {
+    fn visit<F: Float>(&mut self, name: &str, values: &[F]);
+}
+
+/// Joins a parameter name onto the name of its parent.
+pub fn param_name(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+/// As [`param_name`], borrowing `name` when there is no prefix, so that
+/// naming the layers of the outermost network does not allocate.
+pub fn param_name_cow<'a>(prefix: &str, name: &'a str) -> Cow<'a, str> {
+    if prefix.is_empty() {
+        Cow::Borrowed(name)
+    } else {
+        Cow::Owned(format!("{prefix}.{name}"))
+    }
+}
+
+#[cfg(feature = "std")]
+/// Values of a tensor of parameters, by name, as read from a file.
+pub(crate) type Tensors = std::collections::HashMap<String, (Vec<usize>, Vec<f64>)>;
+
+#[cfg(feature = "std")]
+struct Check<'a>(&'a Tensors, Option<LoadError>);
+
+#[cfg(feature = "std")]
+impl ParamVisitor for Check<'_> {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        if self.1.is_some() {
+            return;
+        }
+
+        self.1 = match self.0.get(param.name) {
+            None => Some(LoadError::Mismatch(format!("`{}` is missing", param.name))),
+            Some((shape, _)) if shape != param.shape => Some(LoadError::Mismatch(format!(
+                "`{}` has shape {shape:?}, expected {:?}",
+                param.name, param.shape
+            ))),
+            Some(_) => None,
+        };
+    }
+}
+
+#[cfg(feature = "std")]
+struct Assign<'a>(&'a Tensors);
+
+#[cfg(feature = "std")]
+impl ParamVisitorMut for Assign<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        let (_, values) = &self.0[param.name];
+        for (x, &y) in param.values.iter_mut().zip(values) {
+            *x = F::from_compute(F::Compute::from_f64(y));
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Assigns every parameter of `net` from `tensors` by name, after
+/// checking that all are present with the expected shapes.
+pub(crate) fn load_named<T: FeedForwardNetwork>(
+    net: &mut T,
+    tensors: &Tensors,
+) -> Result<(), LoadError> {
+    let mut check = Check(tensors, None);
+    net.visit_params("", &mut check);
+
+    if let Some(err) = check.1 {
+        return Err(err);
+    }
+
+    net.visit_params_mut("", &mut Assign(tensors));
+    Ok(())
+}
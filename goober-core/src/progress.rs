@@ -0,0 +1,94 @@
+//! Progress bar for training, drawn with [`indicatif`].
+
+use std::time::Instant;
+
+use indicatif::{ProgressBar as Bar, ProgressStyle};
+
+use crate::callback::{Callback, Control, Progress};
+
+const TEMPLATE: &str =
+    "{prefix} [{elapsed_precise}] {wide_bar} {pos}/{len} batches, ETA {eta} | {msg}";
+
+/// Shows the progress through each epoch, with the rate of steps and
+/// samples per second, the current loss and the estimated time left.
+/// - Nothing is drawn when stderr is not a terminal.
+pub struct ProgressBar {
+    bar: Option<(u64, Bar)>,
+    start: Instant,
+    start_step: u64,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressBar {
+    pub fn new() -> Self {
+        Self {
+            bar: None,
+            start: Instant::now(),
+            start_step: 0,
+        }
+    }
+
+    fn bar<T>(&mut self, progress: &Progress<'_, T>) -> &Bar {
+        let batches = progress.batches_per_epoch.max(1);
+        // a batch ending an epoch has already advanced the epoch
+        let epoch = (progress.step - 1) / batches;
+
+        if self
+            .bar
+            .as_ref()
+            .is_none_or(|(current, _)| *current != epoch)
+        {
+            let bar = Bar::new(batches);
+            bar.set_style(ProgressStyle::with_template(TEMPLATE).unwrap());
+            bar.set_prefix(format!("epoch {}", epoch + 1));
+
+            self.bar = Some((epoch, bar));
+            self.start = Instant::now();
+            self.start_step = progress.step - 1;
+        }
+
+        let (_, bar) = self.bar.as_ref().unwrap();
+        bar.set_position(progress.step - epoch * batches);
+        bar
+    }
+}
+
+impl<T> Callback<T> for ProgressBar {
+    fn on_batch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        let bar = self.bar(progress).clone();
+
+        let steps = (progress.step - self.start_step) as f64;
+        let per_sec = steps / self.start.elapsed().as_secs_f64().max(1e-9);
+
+        bar.set_message(format!(
+            "{per_sec:.1} steps/s, {:.0} samples/s, loss {:.6}",
+            per_sec * progress.batch_size as f64,
+            progress.loss
+        ));
+
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        if let Some((_, bar)) = self.bar.take() {
+            let mut msg = format!("loss {:.6}", progress.loss);
+            if let Some(validation) = progress.validation {
+                msg += &format!(", val_loss {:.6}", validation.loss);
+            }
+            bar.finish_with_message(msg);
+        }
+
+        Control::Continue
+    }
+
+    fn on_train_end(&mut self, _: &mut T) {
+        if let Some((_, bar)) = self.bar.take() {
+            bar.abandon();
+        }
+    }
+}
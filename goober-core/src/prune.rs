@@ -0,0 +1,164 @@
+//! Magnitude pruning, zeroing the weights of smallest magnitude in each
+//! layer, to shrink networks before quantizing them for deployment.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, layer::{DenseConnected, SparseConnected}, prune::Prune,
+//! #     trainer::Trainer, FeedForwardNetwork, SparseVector, Vector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let mut net = Net::boxed_and_zeroed();
+//! # let data: Vec<(SparseVector, Vector<1>)> = Vec::new();
+//! let mask = Prune::new(0.5).with_layer("l1", 0.9).apply(&mut *net);
+//! // keep the pruned weights at zero while fine-tuning
+//! let loss = Trainer::new(net, data).with_mask(mask).run(2)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use crate::{FeedForwardNetwork, Float, ParamMut, ParamVisitorMut};
+
+/// Whether the tensor `name` belongs to the layer `layer`, or to a
+/// layer nested within it.
+fn in_layer(name: &str, layer: &str) -> bool {
+    name.strip_prefix(layer)
+        .is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// Target sparsity for each layer of a network.
+/// - Only tensors of weights, with more than one dimension, are pruned,
+///   leaving biases as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prune {
+    sparsity: f32,
+    layers: Vec<(String, f32)>,
+}
+
+impl Prune {
+    /// Prunes the fraction `sparsity` of the weights of every layer.
+    pub fn new(sparsity: f32) -> Self {
+        Self {
+            sparsity,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Prunes the fraction `sparsity` of the weights of the layer `name`,
+    /// and any layers within it, instead.
+    /// - Later calls take precedence for the same layers.
+    pub fn with_layer(mut self, name: &str, sparsity: f32) -> Self {
+        self.layers.push((name.into(), sparsity));
+        self
+    }
+
+    /// Fraction of the tensor `name` to prune.
+    pub fn sparsity(&self, name: &str) -> f32 {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(layer, _)| in_layer(name, layer))
+            .map_or(self.sparsity, |&(_, sparsity)| sparsity)
+    }
+
+    /// Zeroes the weights of smallest magnitude of `net`,
+    /// returning which were pruned.
+    pub fn apply<T: FeedForwardNetwork>(&self, net: &mut T) -> Mask {
+        let mut visitor = Select {
+            prune: self,
+            mask: Mask::default(),
+        };
+        net.visit_params_mut("", &mut visitor);
+        visitor.mask
+    }
+}
+
+struct Select<'a> {
+    prune: &'a Prune,
+    mask: Mask,
+}
+
+impl ParamVisitorMut for Select<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        if param.shape.len() < 2 {
+            return;
+        }
+
+        let len = param.values.len();
+        let sparsity = self.prune.sparsity(param.name).clamp(0.0, 1.0);
+        let count = libm::roundf(sparsity * len as f32) as usize;
+
+        let mut order: Vec<usize> = (0..len).collect();
+        order.sort_by(|&i, &j| {
+            let (x, y) = (param.values[i].to_f32(), param.values[j].to_f32());
+            libm::fabsf(x).total_cmp(&libm::fabsf(y)).then(i.cmp(&j))
+        });
+
+        let mut keep = alloc::vec![true; len];
+        for &i in &order[..count] {
+            keep[i] = false;
+            param.values[i] = F::ZERO;
+        }
+
+        self.mask.tensors.push((param.name.into(), keep));
+    }
+}
+
+/// Weights removed by [`Prune::apply`], to be kept at zero afterwards.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mask {
+    /// Name of each pruned tensor, and whether each weight is kept.
+    tensors: Vec<(String, Vec<bool>)>,
+}
+
+impl Mask {
+    /// Zeroes the pruned weights of `net`.
+    pub fn apply<T: FeedForwardNetwork>(&self, net: &mut T) {
+        net.visit_params_mut("", &mut Reapply(self));
+    }
+
+    /// Fraction of the weights of the tensor `name` which were pruned.
+    pub fn tensor_sparsity(&self, name: &str) -> Option<f32> {
+        let (_, keep) = self.tensors.iter().find(|(tensor, _)| tensor == name)?;
+        Some(fraction(pruned(keep), keep.len()))
+    }
+
+    /// Fraction of the weights of all pruned tensors which were pruned.
+    pub fn sparsity(&self) -> f32 {
+        let total = self.tensors.iter().map(|(_, keep)| keep.len()).sum();
+        let removed = self.tensors.iter().map(|(_, keep)| pruned(keep)).sum();
+        fraction(removed, total)
+    }
+}
+
+fn pruned(keep: &[bool]) -> usize {
+    keep.iter().filter(|&&keep| !keep).count()
+}
+
+fn fraction(pruned: usize, total: usize) -> f32 {
+    if total == 0 {
+        0.0
+    } else {
+        pruned as f32 / total as f32
+    }
+}
+
+struct Reapply<'a>(&'a Mask);
+
+impl ParamVisitorMut for Reapply<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        if let Some((_, keep)) = self.0.tensors.iter().find(|(name, _)| name == param.name) {
+            for (x, &keep) in param.values.iter_mut().zip(keep) {
+                if !keep {
+                    *x = F::ZERO;
+                }
+            }
+        }
+    }
+}
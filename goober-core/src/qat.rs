@@ -0,0 +1,86 @@
+//! Quantization-aware training, simulating the quantization of layers
+//! for inference in the forward pass while training, so that the network
+//! learns weights which lose little accuracy once quantized.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, layer::{DenseConnected, SparseConnected}, qat::{Qat, QatScale},
+//! #     FeedForwardNetwork,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let mut trainer = goober::trainer::Trainer::new(Net::boxed_and_zeroed(), Vec::new());
+//! let qat = Qat::new()
+//!     .with_layer("l1", QatScale::sparse(255))
+//!     .with_layer("l2", QatScale::dense(255, 64));
+//! let loss = trainer.with_qat(qat).run(10)?;
+//! # Ok(())
+//! # }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+/// Scales a layer is quantized at, as passed to its `quantize`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QatScale {
+    /// Scale of the inputs, and so of the outputs, of a dense layer.
+    pub input: i16,
+    /// Scale of the weights, and of the outputs of a sparse layer.
+    pub weight: i16,
+}
+
+impl QatScale {
+    /// Scales of a sparse layer quantized at `scale`, whose
+    /// weights and outputs are both at `scale`.
+    pub const fn sparse(scale: i16) -> Self {
+        Self {
+            input: 1,
+            weight: scale,
+        }
+    }
+
+    /// Scales of a dense layer with inputs at `input`
+    /// and weights at `weight`.
+    pub const fn dense(input: i16, weight: i16) -> Self {
+        Self { input, weight }
+    }
+}
+
+/// Scales of each layer of a network to simulate quantizing, by name.
+/// - In the forward pass, weights and biases are rounded to values
+///   representable once quantized, and so are outputs, with gradients
+///   passed straight through the rounding to the full precision weights.
+/// - After each step, weights are clamped to the range representable
+///   once quantized.
+/// - Layers not named, and layers which cannot be quantized, are
+///   trained as usual.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Qat {
+    layers: Vec<(String, QatScale)>,
+}
+
+impl Qat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Simulates quantizing the layer `name` at `scale`.
+    /// - Later calls take precedence for the same layer.
+    pub fn with_layer(mut self, name: &str, scale: QatScale) -> Self {
+        self.layers.push((name.into(), scale));
+        self
+    }
+
+    /// Scales of the layer `name`, if it is quantized.
+    pub fn scale(&self, name: &str) -> Option<QatScale> {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(layer, _)| layer == name)
+            .map(|&(_, scale)| scale)
+    }
+}
@@ -0,0 +1,42 @@
+/// Small, fast xorshift pseudo-random number generator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rand(u64);
+
+impl Default for Rand {
+    fn default() -> Self {
+        Self::with_seed(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+impl Rand {
+    pub const fn with_seed(seed: u64) -> Self {
+        // state must be non-zero
+        Self(if seed == 0 { 1 } else { seed })
+    }
+
+    /// Current state, from which [`with_seed`](Self::with_seed)
+    /// continues the same sequence.
+    pub const fn state(&self) -> u64 {
+        self.0
+    }
+
+    pub fn rand_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniformly distributed in `[0, 1)`.
+    pub fn rand_f32(&mut self) -> f32 {
+        (self.rand_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Normally distributed with mean 0 and standard deviation 1,
+    /// by the Box-Muller transform.
+    pub fn rand_normal(&mut self) -> f32 {
+        let u = 1.0 - self.rand_f32();
+        let v = self.rand_f32();
+        libm::sqrtf(-2.0 * libm::logf(u)) * libm::cosf(core::f32::consts::TAU * v)
+    }
+}
@@ -0,0 +1,312 @@
+//! Reading and writing networks in the
+//! [safetensors](https://github.com/huggingface/safetensors) format,
+//! with one tensor per [`Param`], named as visited.
+//! - The `torch` variants instead name and lay out tensors as the
+//!   `state_dict` of an equivalent `torch.nn.Sequential`, so that
+//!   results can be cross-checked against PyTorch.
+
+use std::collections::HashMap;
+
+use crate::{
+    json::Json, FeedForwardNetwork, Float, Graph, LoadError, Op, Param, ParamMut, ParamVisitor,
+    ParamVisitorMut, Real, Unsupported,
+};
+
+/// Name of each tensor in the file by the name it is visited
+/// with, and whether it is stored transposed.
+type Names = HashMap<String, (String, bool)>;
+
+/// Names tensors as in the `state_dict` of a `torch.nn.Sequential`
+/// whose modules correspond to the operations traced from `net`, with
+/// activations counted as modules unless they are the identity.
+/// - Sparse layers correspond to an `nn.Linear` applied to the
+///   one-hot encoding of the input, so their weights are transposed.
+fn torch_names<T: FeedForwardNetwork>(net: &T) -> Result<Names, Unsupported> {
+    let mut graph = Graph::default();
+    net.trace("", Graph::INPUT, &mut graph)?;
+
+    let mut names = Names::new();
+    let mut prev = Graph::INPUT;
+    let mut idx = 0;
+
+    for node in graph.nodes() {
+        if node.inputs != [prev] {
+            return Err(Unsupported(
+                "non-sequential networks in PyTorch naming".to_string(),
+            ));
+        }
+        prev = &node.output;
+
+        let (weight, bias, transpose) = match &node.op {
+            Op::Linear { weight, bias } => (weight, bias, false),
+            Op::SparseLinear { weight, bias } => (weight, bias, true),
+            Op::Activation(0) => continue,
+            Op::Activation(_) => {
+                idx += 1;
+                continue;
+            }
+            op => return Err(Unsupported(format!("{op:?} in PyTorch naming"))),
+        };
+
+        names.insert(weight.clone(), (format!("{idx}.weight"), transpose));
+        names.insert(bias.clone(), (format!("{idx}.bias"), false));
+        idx += 1;
+    }
+
+    Ok(names)
+}
+
+/// Index in row-major order of element `k` of the transpose
+/// of a matrix with `rows` rows and `cols` columns.
+fn transposed(k: usize, rows: usize, cols: usize) -> usize {
+    (k % rows) * cols + k / rows
+}
+
+/// Name, shape and transposition of a tensor in the file.
+fn file_entry(names: Option<&Names>, name: &str, shape: &[usize]) -> (String, Vec<usize>, bool) {
+    match names.and_then(|names| names.get(name)) {
+        Some((renamed, true)) => (renamed.clone(), shape.iter().rev().copied().collect(), true),
+        Some((renamed, false)) => (renamed.clone(), shape.to_vec(), false),
+        None => (name.to_string(), shape.to_vec(), false),
+    }
+}
+
+fn dtype<F: Float>() -> &'static str {
+    match F::ID {
+        0 => "F32",
+        1 => "F64",
+        2 => "F16",
+        3 => "BF16",
+        _ => unreachable!(),
+    }
+}
+
+fn read_dtype(dtype: &str, bytes: &[u8]) -> f64 {
+    match dtype {
+        "F32" => f64::from(f32::read_le(bytes)),
+        "F64" => f64::read_le(bytes),
+        "F16" => crate::f16::read_le(bytes).to_f64(),
+        "BF16" => crate::bf16::read_le(bytes).to_f64(),
+        _ => unreachable!(),
+    }
+}
+
+fn dtype_bytes(dtype: &str) -> Option<usize> {
+    match dtype {
+        "F32" => Some(4),
+        "F64" => Some(8),
+        "F16" | "BF16" => Some(2),
+        _ => None,
+    }
+}
+
+struct Writer<'a> {
+    names: Option<&'a Names>,
+    header: Vec<(String, Json)>,
+    data: Vec<u8>,
+}
+
+impl ParamVisitor for Writer<'_> {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let (name, shape, transpose) = file_entry(self.names, param.name, param.shape);
+
+        let start = self.data.len();
+        for k in 0..param.values.len() {
+            let idx = if transpose {
+                transposed(k, param.shape[0], param.shape[1])
+            } else {
+                k
+            };
+            param.values[idx].write_le(&mut self.data);
+        }
+
+        let shape = shape.iter().map(|&d| Json::Number(d as f64));
+        let offsets = [start, self.data.len()].map(|x| Json::Number(x as f64));
+
+        let info = Json::Object(vec![
+            ("dtype".to_string(), Json::String(dtype::<F>().to_string())),
+            ("shape".to_string(), Json::Array(shape.collect())),
+            ("data_offsets".to_string(), Json::Array(offsets.to_vec())),
+        ]);
+
+        self.header.push((name, info));
+    }
+}
+
+pub fn to_bytes<T: FeedForwardNetwork>(net: &T) -> Vec<u8> {
+    write(net, None)
+}
+
+fn write<T: FeedForwardNetwork>(net: &T, names: Option<&Names>) -> Vec<u8> {
+    let mut writer = Writer {
+        names,
+        header: Vec::new(),
+        data: Vec::new(),
+    };
+    net.visit_params("", &mut writer);
+
+    let mut header = Json::Object(writer.header).to_string();
+    while !header.len().is_multiple_of(8) {
+        header.push(' ');
+    }
+
+    let mut out = Vec::with_capacity(8 + header.len() + writer.data.len());
+    out.extend_from_slice(&(header.len() as u64).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.append(&mut writer.data);
+    out
+}
+
+pub fn save<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    std::fs::write(path, to_bytes(net))
+}
+
+/// As [`to_bytes`], with tensors named and laid out to load into the
+/// `state_dict` of the equivalent `torch.nn.Sequential`, failing if
+/// the network is not a chain of dense and sparse layers.
+pub fn to_torch_bytes<T: FeedForwardNetwork>(net: &T) -> Result<Vec<u8>, Unsupported> {
+    Ok(write(net, Some(&torch_names(net)?)))
+}
+
+pub fn save_torch<T: FeedForwardNetwork>(net: &T, path: &str) -> std::io::Result<()> {
+    let bytes = to_torch_bytes(net).map_err(std::io::Error::other)?;
+    std::fs::write(path, bytes)
+}
+
+struct Reader<'a> {
+    names: Option<&'a Names>,
+    header: &'a Json,
+    data: &'a [u8],
+    write: bool,
+    err: Option<LoadError>,
+}
+
+impl Reader<'_> {
+    fn read<F: Float>(&mut self, param: ParamMut<'_, F>) -> Result<(), LoadError> {
+        let (name, expected, transpose) = file_entry(self.names, param.name, param.shape);
+
+        let mismatch = |reason: &str| LoadError::Mismatch(format!("`{name}` {reason}"));
+        let malformed = || LoadError::Malformed(format!("invalid entry for `{name}`"));
+
+        let info = self
+            .header
+            .get(&name)
+            .ok_or_else(|| mismatch("is missing"))?;
+
+        let dtype = info
+            .get("dtype")
+            .and_then(Json::as_str)
+            .ok_or_else(malformed)?;
+        let size = dtype_bytes(dtype).ok_or_else(|| mismatch("has unsupported dtype"))?;
+
+        let shape = info
+            .get("shape")
+            .and_then(Json::as_array)
+            .ok_or_else(malformed)?
+            .iter()
+            .map(Json::as_usize)
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(malformed)?;
+
+        if shape != expected {
+            return Err(mismatch(&format!(
+                "has shape {shape:?}, expected {expected:?}"
+            )));
+        }
+
+        let offsets = info
+            .get("data_offsets")
+            .and_then(Json::as_array)
+            .ok_or_else(malformed)?;
+
+        let (start, end) = match offsets {
+            [start, end] => (
+                start.as_usize().ok_or_else(malformed)?,
+                end.as_usize().ok_or_else(malformed)?,
+            ),
+            _ => return Err(malformed()),
+        };
+
+        let bytes = self.data.get(start..end).ok_or(LoadError::Truncated)?;
+        if bytes.len() != param.values.len() * size {
+            return Err(malformed());
+        }
+
+        if self.write {
+            for (k, bytes) in bytes.chunks_exact(size).enumerate() {
+                let idx = if transpose {
+                    transposed(k, param.shape[0], param.shape[1])
+                } else {
+                    k
+                };
+                param.values[idx] = F::from_compute(F::Compute::from_f64(read_dtype(dtype, bytes)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ParamVisitorMut for Reader<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        if self.err.is_none() {
+            self.err = self.read(param).err();
+        }
+    }
+}
+
+/// Loads a network from safetensors, converting from the stored dtype
+/// to the storage type of each tensor. Tensors in the file which are
+/// not part of the network are ignored.
+pub fn from_bytes<T: FeedForwardNetwork>(net: &mut T, bytes: &[u8]) -> Result<(), LoadError> {
+    read(net, bytes, None)
+}
+
+fn read<T: FeedForwardNetwork>(
+    net: &mut T,
+    bytes: &[u8],
+    names: Option<&Names>,
+) -> Result<(), LoadError> {
+    let len = bytes.get(..8).ok_or(LoadError::Truncated)?;
+    let len = u64::from_le_bytes(len.try_into().unwrap()) as usize;
+
+    let header = bytes
+        .get(8..8usize.saturating_add(len))
+        .ok_or(LoadError::Truncated)?;
+    let header =
+        std::str::from_utf8(header).map_err(|err| LoadError::Malformed(err.to_string()))?;
+    let header = Json::parse(header).map_err(LoadError::Malformed)?;
+
+    // validate everything before overwriting any parameters
+    for write in [false, true] {
+        let mut reader = Reader {
+            names,
+            header: &header,
+            data: &bytes[8 + len..],
+            write,
+            err: None,
+        };
+        net.visit_params_mut("", &mut reader);
+
+        if let Some(err) = reader.err {
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn load<T: FeedForwardNetwork>(net: &mut T, path: &str) -> Result<(), LoadError> {
+    from_bytes(net, &std::fs::read(path)?)
+}
+
+/// As [`from_bytes`], reading tensors named and laid out
+/// as by [`to_torch_bytes`].
+pub fn from_torch_bytes<T: FeedForwardNetwork>(net: &mut T, bytes: &[u8]) -> Result<(), LoadError> {
+    let names = torch_names(net).map_err(|err| LoadError::Mismatch(err.to_string()))?;
+    read(net, bytes, Some(&names))
+}
+
+pub fn load_torch<T: FeedForwardNetwork>(net: &mut T, path: &str) -> Result<(), LoadError> {
+    from_torch_bytes(net, &std::fs::read(path)?)
+}
@@ -0,0 +1,289 @@
+use crate::{FeedForwardNetwork, Float, Param, ParamMut, ParamVisitor, ParamVisitorMut};
+
+const MAGIC: [u8; 4] = *b"GBNN";
+
+const VERSION: u32 = 1;
+
+/// Error encountered when loading a saved network.
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    BadMagic,
+    UnsupportedVersion(u32),
+    BadChecksum,
+    Truncated,
+    Malformed(String),
+    /// The saved network does not match the architecture
+    /// of the network being loaded into.
+    Mismatch(String),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::BadMagic => write!(f, "not a saved network"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported version {version}"),
+            Self::BadChecksum => write!(f, "checksum mismatch"),
+            Self::Truncated => write!(f, "unexpected end of file"),
+            Self::Malformed(reason) => write!(f, "malformed file: {reason}"),
+            Self::Mismatch(reason) => write!(f, "architecture mismatch: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// CRC-32 (IEEE) checksum.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+
+    !crc
+}
+
+struct Writer {
+    out: Vec<u8>,
+    count: u32,
+}
+
+impl ParamVisitor for Writer {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.count += 1;
+        write_header(
+            &mut self.out,
+            param.name,
+            F::ID,
+            param.activation,
+            param.shape,
+        );
+        for &x in param.values {
+            x.write_le(&mut self.out);
+        }
+    }
+}
+
+/// # Panics
+/// If the name, rank or any dimension is too large to be recorded,
+/// rather than writing a file which cannot be loaded.
+fn write_header(out: &mut Vec<u8>, name: &str, float: u8, activation: u8, shape: &[usize]) {
+    let len = u16::try_from(name.len())
+        .unwrap_or_else(|_| panic!("parameter name {name:?} is longer than {} bytes", u16::MAX));
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(name.as_bytes());
+    out.push(float);
+    out.push(activation);
+    let rank = u8::try_from(shape.len())
+        .unwrap_or_else(|_| panic!("parameter {name} has more than {} dimensions", u8::MAX));
+    out.push(rank);
+    for &dim in shape {
+        let dim = u32::try_from(dim)
+            .unwrap_or_else(|_| panic!("parameter {name} has a dimension of size {dim}"));
+        out.extend_from_slice(&dim.to_le_bytes());
+    }
+}
+
+pub(crate) fn to_bytes<T: FeedForwardNetwork>(net: &T) -> Vec<u8> {
+    let mut writer = Writer {
+        out: Vec::new(),
+        count: 0,
+    };
+    net.visit_params("", &mut writer);
+
+    let mut out = Vec::with_capacity(writer.out.len() + 16);
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&writer.count.to_le_bytes());
+    out.append(&mut writer.out);
+    out.extend_from_slice(&crc32(&out).to_le_bytes());
+    out
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    remaining: u32,
+    write: bool,
+    err: Option<LoadError>,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], LoadError> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + len)
+            .ok_or(LoadError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn read<F: Float>(&mut self, param: ParamMut<'_, F>) -> Result<(), LoadError> {
+        if self.remaining == 0 {
+            return Err(LoadError::Mismatch(format!("missing `{}`", param.name)));
+        }
+        self.remaining -= 1;
+
+        let mut expected = Vec::new();
+        write_header(
+            &mut expected,
+            param.name,
+            F::ID,
+            param.activation,
+            param.shape,
+        );
+
+        let found = self.take(expected.len())?;
+        if found != expected.as_slice() {
+            return Err(LoadError::Mismatch(format!(
+                "`{}` differs in name, type, activation or shape",
+                param.name
+            )));
+        }
+
+        let values = self.take(param.values.len() * F::BYTES)?;
+        if !self.write {
+            return Ok(());
+        }
+
+        for (x, bytes) in param.values.iter_mut().zip(values.chunks_exact(F::BYTES)) {
+            *x = F::read_le(bytes);
+        }
+
+        Ok(())
+    }
+}
+
+impl ParamVisitorMut for Reader<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        if self.err.is_none() {
+            self.err = self.read(param).err();
+        }
+    }
+}
+
+pub(crate) fn from_bytes<T: FeedForwardNetwork>(
+    net: &mut T,
+    bytes: &[u8],
+) -> Result<(), LoadError> {
+    if bytes.len() < 16 {
+        return Err(LoadError::Truncated);
+    }
+
+    let (body, crc) = bytes.split_at(bytes.len() - 4);
+
+    if body[..4] != MAGIC {
+        return Err(LoadError::BadMagic);
+    }
+
+    let version = u32::from_le_bytes(body[4..8].try_into().unwrap());
+    if version != VERSION {
+        return Err(LoadError::UnsupportedVersion(version));
+    }
+
+    if crc32(body).to_le_bytes() != crc {
+        return Err(LoadError::BadChecksum);
+    }
+
+    // validate everything before overwriting any parameters
+    for write in [false, true] {
+        let mut reader = Reader {
+            bytes: body,
+            pos: 12,
+            remaining: u32::from_le_bytes(body[8..12].try_into().unwrap()),
+            write,
+            err: None,
+        };
+        net.visit_params_mut("", &mut reader);
+
+        if let Some(err) = reader.err {
+            return Err(err);
+        }
+
+        if reader.remaining != 0 || reader.pos != body.len() {
+            return Err(LoadError::Mismatch(
+                "unexpected extra parameters".to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+struct RawWriter(Vec<u8>);
+
+impl ParamVisitor for RawWriter {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        for &x in param.values {
+            x.write_le(&mut self.0);
+        }
+    }
+}
+
+pub(crate) fn to_raw_bytes<T: FeedForwardNetwork>(net: &T) -> Vec<u8> {
+    let mut writer = RawWriter(Vec::new());
+    net.visit_params("", &mut writer);
+    writer.0
+}
+
+struct RawReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl ParamVisitorMut for RawReader<'_> {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        let len = param.values.len() * F::BYTES;
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        for (x, bytes) in param.values.iter_mut().zip(bytes.chunks_exact(F::BYTES)) {
+            *x = F::read_le(bytes);
+        }
+        self.pos += len;
+    }
+}
+
+pub(crate) fn from_raw_bytes<T: FeedForwardNetwork>(
+    net: &mut T,
+    bytes: &[u8],
+) -> Result<(), LoadError> {
+    let expected = to_raw_bytes(net).len();
+    if bytes.len() != expected {
+        return Err(LoadError::Mismatch(format!(
+            "expected {expected} bytes of parameters, found {}",
+            bytes.len()
+        )));
+    }
+
+    let mut reader = RawReader { bytes, pos: 0 };
+    net.visit_params_mut("", &mut reader);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    #[should_panic(expected = "longer than 65535 bytes")]
+    fn long_name() {
+        let name = "a".repeat(usize::from(u16::MAX) + 1);
+        write_header(&mut Vec::new(), &name, 0, 0, &[1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "more than 255 dimensions")]
+    fn high_rank() {
+        write_header(&mut Vec::new(), "weights", 0, 0, &[1; 256]);
+    }
+}
@@ -0,0 +1,199 @@
+//! Learning rate schedules, giving the learning rate to use at each
+//! step of the optimiser.
+
+use alloc::{string::String, vec::Vec};
+
+/// Learning rate as a function of the number of steps taken so far,
+/// and of any validation metrics reported through [`observe`](Self::observe).
+pub trait Scheduler {
+    /// Learning rate for the step after `step` steps have been taken.
+    fn lr(&self, step: u64) -> f32;
+
+    /// Reports a validation metric, where lower is better,
+    /// for schedules which adapt to it.
+    fn observe(&mut self, metric: f32) {
+        let _ = metric;
+    }
+
+    /// State which is not determined by the configuration of the
+    /// schedule and the step, as stored in checkpoints.
+    fn state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Restores state returned by [`state`](Self::state).
+    fn set_state(&mut self, state: &[u8]) -> Result<(), String> {
+        if state.is_empty() {
+            Ok(())
+        } else {
+            Err(alloc::format!(
+                "expected no scheduler state, found {} bytes",
+                state.len()
+            ))
+        }
+    }
+}
+
+/// Fixed learning rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Constant(pub f32);
+
+impl Scheduler for Constant {
+    fn lr(&self, _: u64) -> f32 {
+        self.0
+    }
+}
+
+/// Multiplies the learning rate by `gamma` every `every` steps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StepDecay {
+    pub lr: f32,
+    pub gamma: f32,
+    pub every: u64,
+}
+
+impl Scheduler for StepDecay {
+    fn lr(&self, step: u64) -> f32 {
+        self.lr * libm::powf(self.gamma, (step / self.every.max(1)) as f32)
+    }
+}
+
+/// Linear warmup from zero over `warmup` steps, followed by cosine
+/// decay down to `min_lr` at `steps`, after which it stays at `min_lr`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cosine {
+    pub lr: f32,
+    pub min_lr: f32,
+    pub warmup: u64,
+    pub steps: u64,
+}
+
+impl Scheduler for Cosine {
+    fn lr(&self, step: u64) -> f32 {
+        if step < self.warmup {
+            return self.lr * (step + 1) as f32 / self.warmup as f32;
+        }
+
+        let decay = self.steps.saturating_sub(self.warmup).max(1);
+        let progress = ((step - self.warmup) as f32 / decay as f32).min(1.0);
+        let cos = libm::cosf(core::f32::consts::PI * progress);
+        self.min_lr + 0.5 * (self.lr - self.min_lr) * (1.0 + cos)
+    }
+}
+
+/// Multiplies the learning rate by `factor`, down to at most `min_lr`,
+/// whenever the observed metric has not improved on the best so far by
+/// more than `min_delta` for more than `patience` observations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Plateau {
+    pub factor: f32,
+    pub patience: u32,
+    pub min_delta: f32,
+    pub min_lr: f32,
+    lr: f32,
+    best: f32,
+    bad: u32,
+}
+
+impl Plateau {
+    pub fn new(lr: f32, factor: f32, patience: u32) -> Self {
+        Self {
+            factor,
+            patience,
+            min_delta: 0.0,
+            min_lr: 0.0,
+            lr,
+            best: f32::INFINITY,
+            bad: 0,
+        }
+    }
+}
+
+impl Scheduler for Plateau {
+    fn lr(&self, _: u64) -> f32 {
+        self.lr
+    }
+
+    fn observe(&mut self, metric: f32) {
+        if metric < self.best - self.min_delta {
+            self.best = metric;
+            self.bad = 0;
+            return;
+        }
+
+        self.bad += 1;
+        if self.bad > self.patience {
+            self.lr = (self.lr * self.factor).max(self.min_lr);
+            self.bad = 0;
+        }
+    }
+
+    fn state(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(12);
+        out.extend_from_slice(&self.lr.to_le_bytes());
+        out.extend_from_slice(&self.best.to_le_bytes());
+        out.extend_from_slice(&self.bad.to_le_bytes());
+        out
+    }
+
+    fn set_state(&mut self, state: &[u8]) -> Result<(), String> {
+        if state.len() != 12 {
+            return Err(alloc::format!(
+                "expected 12 bytes of scheduler state, found {}",
+                state.len()
+            ));
+        }
+
+        let word = |i: usize| <[u8; 4]>::try_from(&state[4 * i..4 * i + 4]).unwrap();
+        self.lr = f32::from_le_bytes(word(0));
+        self.best = f32::from_le_bytes(word(1));
+        self.bad = u32::from_le_bytes(word(2));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn schedules() {
+        assert_eq!(Constant(0.1).lr(1000), 0.1);
+
+        let decay = StepDecay {
+            lr: 1.0,
+            gamma: 0.5,
+            every: 10,
+        };
+        assert_eq!(
+            [0, 9, 10, 25].map(|step| decay.lr(step)),
+            [1.0, 1.0, 0.5, 0.25]
+        );
+
+        let cosine = Cosine {
+            lr: 1.0,
+            min_lr: 0.0,
+            warmup: 4,
+            steps: 14,
+        };
+        assert_eq!(cosine.lr(0), 0.25);
+        assert_eq!(cosine.lr(3), 1.0);
+        assert!((cosine.lr(9) - 0.5).abs() < 1e-6);
+        assert!(cosine.lr(14).abs() < 1e-6);
+        assert_eq!(cosine.lr(100), cosine.lr(14));
+    }
+
+    #[test]
+    fn plateau() {
+        let mut plateau = Plateau::new(1.0, 0.5, 1);
+        for metric in [3.0, 2.0, 2.0, 2.0] {
+            plateau.observe(metric);
+        }
+        assert_eq!(plateau.lr(0), 0.5);
+
+        let mut restored = Plateau::new(1.0, 0.5, 1);
+        restored.set_state(&plateau.state()).unwrap();
+        assert_eq!(restored, plateau);
+        assert!(restored.set_state(&[]).is_err());
+    }
+}
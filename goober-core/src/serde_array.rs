@@ -0,0 +1,51 @@
+//! Serializes `[T; N]` as a tuple, for any `N`, for use with
+//! `#[serde(with = "goober_core::serde_array")]`.
+
+use std::marker::PhantomData;
+
+use serde::{
+    de::{Error, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+pub fn serialize<S: Serializer, T: Serialize, const N: usize>(
+    arr: &[T; N],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut tup = serializer.serialize_tuple(N)?;
+    for x in arr {
+        tup.serialize_element(x)?;
+    }
+    tup.end()
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>, const N: usize>(
+    deserializer: D,
+) -> Result<[T; N], D::Error> {
+    struct ArrayVisitor<T, const N: usize>(PhantomData<T>);
+
+    impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for ArrayVisitor<T, N> {
+        type Value = [T; N];
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "an array of length {N}")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut res = Vec::with_capacity(N);
+            while let Some(x) = seq.next_element()? {
+                if res.len() == N {
+                    return Err(A::Error::invalid_length(N + 1, &self));
+                }
+                res.push(x);
+            }
+
+            let len = res.len();
+            res.try_into()
+                .map_err(|_| A::Error::invalid_length(len, &self))
+        }
+    }
+
+    deserializer.deserialize_tuple(N, ArrayVisitor(PhantomData))
+}
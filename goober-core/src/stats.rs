@@ -0,0 +1,154 @@
+//! Summary statistics of every tensor of parameters in a network, or of
+//! its [`Gradient`](crate::Gradient), for spotting exploding or dead layers.
+
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{Float, Gradient, Param, ParamVisitor};
+
+/// Statistics of the values of a single tensor of parameters.
+/// - Non-finite values are counted in `non_finite` and
+///   otherwise excluded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TensorStats {
+    pub name: String,
+    pub shape: Vec<usize>,
+    pub count: usize,
+    pub mean: f32,
+    pub std: f32,
+    pub min: f32,
+    pub max: f32,
+    /// Number of values which are exactly zero.
+    pub zeros: usize,
+    pub non_finite: usize,
+    /// Counts of the finite values in equal width bins from `min` to `max`.
+    pub histogram: Vec<usize>,
+}
+
+impl TensorStats {
+    fn new(name: &str, shape: &[usize], values: &[f32], bins: usize) -> Self {
+        let finite: Vec<f32> = values.iter().copied().filter(|x| x.is_finite()).collect();
+        let n = finite.len().max(1) as f64;
+
+        let min = finite.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = finite.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let mean = finite.iter().map(|&x| f64::from(x)).sum::<f64>() / n;
+        let var = finite
+            .iter()
+            .map(|&x| (f64::from(x) - mean) * (f64::from(x) - mean))
+            .sum::<f64>()
+            / n;
+
+        let mut histogram = vec![0; bins.max(1)];
+        let width = (max - min) / histogram.len() as f32;
+        for &x in &finite {
+            let bin = if width > 0.0 {
+                (((x - min) / width) as usize).min(histogram.len() - 1)
+            } else {
+                0
+            };
+            histogram[bin] += 1;
+        }
+
+        let (min, max) = if finite.is_empty() {
+            (0.0, 0.0)
+        } else {
+            (min, max)
+        };
+
+        Self {
+            name: name.into(),
+            shape: shape.to_vec(),
+            count: values.len(),
+            mean: mean as f32,
+            std: libm::sqrt(var) as f32,
+            min,
+            max,
+            zeros: values.iter().filter(|&&x| x == 0.0).count(),
+            non_finite: values.len() - finite.len(),
+            histogram,
+        }
+    }
+
+    /// Fraction of the values which are exactly zero.
+    pub fn zero_fraction(&self) -> f32 {
+        self.zeros as f32 / self.count.max(1) as f32
+    }
+}
+
+struct Collect {
+    bins: usize,
+    tensors: Vec<TensorStats>,
+}
+
+impl ParamVisitor for Collect {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let values: Vec<f32> = param.values.iter().map(|x| x.to_f32()).collect();
+        let stats = TensorStats::new(param.name, param.shape, &values, self.bins);
+        self.tensors.push(stats);
+    }
+}
+
+/// Statistics of every tensor of parameters in a network, in the
+/// order they are visited.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stats {
+    tensors: Vec<TensorStats>,
+}
+
+impl Stats {
+    /// Collects statistics of `net`, or of gradients, with histograms
+    /// of `bins` bins.
+    pub fn of<T: Gradient>(net: &T, bins: usize) -> Self {
+        let mut collect = Collect {
+            bins,
+            tensors: Vec::new(),
+        };
+        net.visit_grads("", &mut collect);
+
+        Self {
+            tensors: collect.tensors,
+        }
+    }
+
+    pub fn tensors(&self) -> &[TensorStats] {
+        &self.tensors
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TensorStats> {
+        self.tensors.iter().find(|stats| stats.name == name)
+    }
+}
+
+impl core::fmt::Display for Stats {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let width = self
+            .tensors
+            .iter()
+            .map(|stats| stats.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+
+        writeln!(
+            f,
+            "{:width$}  {:>11} {:>11} {:>11} {:>11} {:>7} {:>7}",
+            "name", "mean", "std", "min", "max", "zeros", "nonfin"
+        )?;
+
+        for stats in &self.tensors {
+            writeln!(
+                f,
+                "{:width$}  {:>11.4e} {:>11.4e} {:>11.4e} {:>11.4e} {:>6.1}% {:>7}",
+                stats.name,
+                stats.mean,
+                stats.std,
+                stats.min,
+                stats.max,
+                100.0 * stats.zero_fraction(),
+                stats.non_finite
+            )?;
+        }
+
+        Ok(())
+    }
+}
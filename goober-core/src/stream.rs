@@ -0,0 +1,604 @@
+//! Samples read and decoded on a background thread, kept a bounded number
+//! of chunks ahead of training, so that reading from disk overlaps with
+//! computing gradients, for datasets read once per epoch from start to end,
+//! and [`Batches`] of them built ahead of training.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, dataset::Record, layer::{DenseConnected, SparseConnected},
+//! #     stream::Stream, FeedForwardNetwork, SparseVector, Vector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # struct Position;
+//! # impl Record for Position {
+//! #     type Input = SparseVector;
+//! #     type Output = Vector<1>;
+//! #     const SIZE: usize = 32;
+//! #     fn decode(_: &[u8]) -> (SparseVector, Vector<1>) { unimplemented!() }
+//! # }
+//! # fn main() -> Result<(), std::io::Error> {
+//! # let mut trainer = goober::trainer::Trainer::new(Net::boxed_and_zeroed(), Vec::new());
+//! # let epochs = 10;
+//! for epoch in 0..epochs {
+//!     let stream = Stream::open::<Position>("positions.bin.zst", 16)?;
+//!     // positions of the same game are stored together
+//!     trainer.run_stream(stream.shuffled(1 << 20, epoch))?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, Cursor, Read},
+    iter::Fuse,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    vec,
+};
+
+#[cfg(feature = "zstd")]
+use crate::zstd;
+use crate::{
+    dataset::{FeatureEncoder, Record},
+    Rand,
+};
+
+/// Number of records read and decoded at a time.
+const CHUNK: usize = 1024;
+
+/// Samples of inputs of type `I` and targets of type `O`, in the order
+/// they are read, failing with the first error reading them.
+pub struct Stream<I, O> {
+    chunks: Receiver<io::Result<Vec<(I, O)>>>,
+    chunk: vec::IntoIter<(I, O)>,
+    reader: Option<JoinHandle<()>>,
+    skipped: Arc<AtomicU64>,
+}
+
+impl<I: Send + 'static, O: Send + 'static> Stream<I, O> {
+    /// Calls `read` for each chunk of samples on a background thread,
+    /// until it returns `None` or fails, keeping up to `capacity` chunks
+    /// ready, and blocking the thread once they are.
+    /// - The thread stops once the stream is dropped.
+    pub fn spawn<F>(capacity: usize, mut read: F) -> Self
+    where
+        F: FnMut() -> io::Result<Option<Vec<(I, O)>>> + Send + 'static,
+    {
+        let (sender, chunks) = mpsc::sync_channel(capacity);
+        let reader = thread::spawn(move || {
+            while let Some(chunk) = read().transpose() {
+                let failed = chunk.is_err();
+                if sender.send(chunk).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            chunks,
+            chunk: Vec::new().into_iter(),
+            reader: Some(reader),
+            skipped: Arc::default(),
+        }
+    }
+
+    /// Reads records in the format `R` from `reader`.
+    /// - Fails if the last record is incomplete.
+    pub fn records<R>(reader: impl Read + Send + 'static, capacity: usize) -> Self
+    where
+        R: Record<Input = I, Output = O> + 'static,
+    {
+        Loader::<R>::new(capacity).read(reader)
+    }
+
+    /// Reads records in the format `R` from `reader`, with their inputs
+    /// encoded by `encoder` on the background thread.
+    /// - Fails if the last record is incomplete.
+    pub fn encoded<R, E>(reader: impl Read + Send + 'static, capacity: usize, encoder: E) -> Self
+    where
+        R: Record<Output = O> + 'static,
+        E: FeatureEncoder<R::Input, Input = I> + Send + Sync + 'static,
+    {
+        Loader::<R>::new(capacity)
+            .with_encoder(encoder)
+            .read(reader)
+    }
+
+    /// Reads records in the format `R` from the file at `path`.
+    /// - Files compressed with zstd are decompressed as they are read,
+    ///   on the background thread, with the `zstd` feature.
+    pub fn open<R>(path: impl AsRef<Path>, capacity: usize) -> io::Result<Self>
+    where
+        R: Record<Input = I, Output = O> + 'static,
+    {
+        Loader::<R>::new(capacity).open(path)
+    }
+
+    /// Reads records in the format `R` from the file at `path`, as
+    /// [`open`](Self::open), with their inputs encoded by `encoder`.
+    pub fn open_encoded<R, E>(
+        path: impl AsRef<Path>,
+        capacity: usize,
+        encoder: E,
+    ) -> io::Result<Self>
+    where
+        R: Record<Output = O> + 'static,
+        E: FeatureEncoder<R::Input, Input = I> + Send + Sync + 'static,
+    {
+        Loader::<R>::new(capacity).with_encoder(encoder).open(path)
+    }
+
+    /// Number of records skipped by the filter of the [`Loader`] reading
+    /// them so far, which runs ahead of the samples taken from the stream.
+    pub fn skipped(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+
+    /// Shuffles the stream, as [`Shuffle::new`].
+    pub fn shuffled(self, capacity: usize, seed: u64) -> Shuffle<Self, (I, O)> {
+        Shuffle::new(self, capacity, seed)
+    }
+}
+
+/// Decodes records in the format `R`, read on the background thread of a
+/// [`Stream`], optionally skipping some, and encoding the inputs of the
+/// rest into inputs of type `I`, on that thread, to keep the work of
+/// preparing samples off the thread training on them.
+///
+/// ```no_run
+/// # use goober::{dataset::{FeatureEncoder, Record}, stream::Loader, SparseVector, Vector};
+/// # struct Board;
+/// # impl Board {
+/// #     fn in_check(&self) -> bool { false }
+/// #     fn ply(&self) -> u32 { 0 }
+/// #     fn tactics(&self) -> f32 { 0.0 }
+/// #     fn game_id(&self) -> u64 { 0 }
+/// # }
+/// # struct PackedBoard;
+/// # impl Record for PackedBoard {
+/// #     type Input = Board;
+/// #     type Output = Vector<1>;
+/// #     const SIZE: usize = 32;
+/// #     fn decode(_: &[u8]) -> (Board, Vector<1>) { (Board, Vector::from_raw([0.0])) }
+/// # }
+/// # struct HalfKp;
+/// # impl FeatureEncoder<Board> for HalfKp {
+/// #     type Input = SparseVector;
+/// #     fn encode(&self, _: &Board) -> SparseVector { SparseVector::with_capacity(0) }
+/// # }
+/// # fn main() -> Result<(), std::io::Error> {
+/// let stream = Loader::<PackedBoard>::new(16)
+///     .with_filter(|board, _| !board.in_check() && board.ply() >= 16)
+///     .with_encoder(HalfKp)
+///     .open("positions.bin")?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Loader<R: Record, I = <R as Record>::Input> {
+    capacity: usize,
+    threads: usize,
+    prepare: Prepare<R, I>,
+}
+
+/// Decoding, filtering and encoding of records in the format `R`.
+struct Prepare<R: Record, I> {
+    keep: Option<Keep<R>>,
+    encode: Box<dyn Fn(R::Input) -> I + Send + Sync>,
+    skipped: Arc<AtomicU64>,
+}
+
+/// Whether to keep a sample of a record in the format `R`.
+type Keep<R> = Box<dyn Fn(&<R as Record>::Input, &<R as Record>::Output) -> bool + Send + Sync>;
+
+impl<R: Record, I> Prepare<R, I> {
+    /// Samples of the records of `bytes` which are kept.
+    fn chunk(&self, bytes: &[u8]) -> Vec<(I, R::Output)> {
+        let chunk: Vec<_> = bytes
+            .chunks_exact(R::SIZE)
+            .map(R::decode)
+            .filter(|(raw, target)| self.keep.as_ref().is_none_or(|keep| keep(raw, target)))
+            .map(|(raw, target)| ((self.encode)(raw), target))
+            .collect();
+
+        let skipped = bytes.len() / R::SIZE - chunk.len();
+        self.skipped.fetch_add(skipped as u64, Ordering::Relaxed);
+        chunk
+    }
+
+    /// Reports the end of the records, once every chunk is prepared.
+    fn end(&self) {
+        event!(
+            INFO,
+            skipped = self.skipped.load(Ordering::Relaxed),
+            "stream read"
+        );
+    }
+}
+
+impl<R: Record + 'static> Loader<R> {
+    /// Keeps up to `capacity` chunks of samples ready, as [`Stream::spawn`].
+    pub fn new(capacity: usize) -> Self {
+        let identity: fn(R::Input) -> R::Input = |raw| raw;
+        Self {
+            capacity,
+            threads: 1,
+            prepare: Prepare {
+                keep: None,
+                encode: Box::new(identity),
+                skipped: Arc::default(),
+            },
+        }
+    }
+
+    /// Encodes the inputs of the records with `encoder`.
+    pub fn with_encoder<E>(self, encoder: E) -> Loader<R, E::Input>
+    where
+        E: FeatureEncoder<R::Input> + Send + Sync + 'static,
+    {
+        Loader {
+            capacity: self.capacity,
+            threads: self.threads,
+            prepare: Prepare {
+                keep: self.prepare.keep,
+                encode: Box::new(move |raw| encoder.encode(&raw)),
+                skipped: self.prepare.skipped,
+            },
+        }
+    }
+}
+
+impl<R: Record + 'static, I: Send + 'static> Loader<R, I>
+where
+    R::Output: Send + 'static,
+{
+    /// Skips records for which `keep` is false, before encoding them,
+    /// counting them in [`Stream::skipped`].
+    pub fn with_filter<P>(mut self, keep: P) -> Self
+    where
+        P: Fn(&R::Input, &R::Output) -> bool + Send + Sync + 'static,
+    {
+        self.prepare.keep = Some(Box::new(keep));
+        self
+    }
+
+    /// Decodes, filters and encodes chunks of records on a pool of
+    /// `threads` threads, rather than the thread reading them, for
+    /// when that work is slower than reading.
+    /// - Samples are still taken from the stream in the order read.
+    /// - Up to `threads` chunks are prepared at once, on top of the
+    ///   `capacity` chunks ready.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Reads records from `reader`.
+    /// - Fails if the last record is incomplete.
+    pub fn read(self, mut reader: impl Read + Send + 'static) -> Stream<I, R::Output> {
+        let skipped = Arc::clone(&self.prepare.skipped);
+        let prepare = Arc::new(self.prepare);
+        let threads = self.threads;
+
+        let mut stream = if threads == 1 {
+            let mut buf = vec![0; R::SIZE * CHUNK];
+            Stream::spawn(self.capacity, move || {
+                match read_records::<R>(&mut reader, &mut buf)? {
+                    0 => {
+                        prepare.end();
+                        Ok(None)
+                    }
+                    len => Ok(Some(prepare.chunk(&buf[..len]))),
+                }
+            })
+        } else {
+            let jobs = workers(threads, Arc::clone(&prepare));
+            let mut pending = VecDeque::new();
+            let (mut done, mut failed) = (false, None);
+
+            Stream::spawn(self.capacity, move || {
+                while pending.len() < threads && !done {
+                    let mut buf = vec![0; R::SIZE * CHUNK];
+                    match read_records::<R>(&mut reader, &mut buf) {
+                        Ok(0) => done = true,
+                        Ok(len) => {
+                            buf.truncate(len);
+                            let (sender, chunk) = mpsc::sync_channel(1);
+                            // a failure leaves `chunk` disconnected, and reported below
+                            let _ = jobs.send((buf, sender));
+                            pending.push_back(chunk);
+                        }
+                        Err(err) => {
+                            done = true;
+                            failed = Some(err);
+                        }
+                    }
+                }
+
+                match pending.pop_front() {
+                    Some(chunk) => match chunk.recv() {
+                        Ok(chunk) => Ok(Some(chunk)),
+                        Err(_) => Err(io::Error::other("worker thread panicked")),
+                    },
+                    None => match failed.take() {
+                        Some(err) => Err(err),
+                        None => {
+                            prepare.end();
+                            Ok(None)
+                        }
+                    },
+                }
+            })
+        };
+
+        stream.skipped = skipped;
+        stream
+    }
+
+    /// Reads records from the file at `path`, decompressing
+    /// it if compressed with zstd, as [`Stream::open`].
+    pub fn open(self, path: impl AsRef<Path>) -> io::Result<Stream<I, R::Output>> {
+        Ok(self.read(open(path.as_ref())?))
+    }
+}
+
+/// A chunk of records to prepare, and where to send its samples.
+type Job<I, O> = (Vec<u8>, SyncSender<Vec<(I, O)>>);
+
+/// Starts `threads` threads preparing the chunks of records sent to them,
+/// which stop once the sender returned is dropped.
+fn workers<R, I>(threads: usize, prepare: Arc<Prepare<R, I>>) -> Sender<Job<I, R::Output>>
+where
+    R: Record + 'static,
+    I: Send + 'static,
+    R::Output: Send + 'static,
+{
+    let (sender, jobs) = mpsc::channel::<Job<I, R::Output>>();
+    let jobs = Arc::new(Mutex::new(jobs));
+
+    for _ in 0..threads {
+        let jobs = Arc::clone(&jobs);
+        let prepare = Arc::clone(&prepare);
+        thread::spawn(move || loop {
+            let job = jobs.lock().unwrap().recv();
+            let Ok((bytes, chunk)) = job else {
+                break;
+            };
+            let _ = chunk.send(prepare.chunk(&bytes));
+        });
+    }
+
+    sender
+}
+
+/// Reads a chunk of whole records in the format `R` into `buf`,
+/// returning the number of bytes read.
+fn read_records<R: Record>(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let len = read_full(reader, buf)?;
+    if !len.is_multiple_of(R::SIZE) {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stream ends within a record",
+        ));
+    }
+    Ok(len)
+}
+
+/// Reads into `buf` until it is full or the end of `reader`,
+/// returning the number of bytes read.
+pub(crate) fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(len)
+}
+
+/// The file at `path`, decompressed if compressed with zstd.
+/// - Without the `zstd` feature, compressed files are an error.
+fn open(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 4];
+    let len = read_full(&mut file, &mut magic)?;
+    let file = Cursor::new(magic[..len].to_vec()).chain(file);
+
+    #[cfg(feature = "zstd")]
+    if zstd::is_compressed(&magic[..len]) {
+        return Ok(Box::new(zstd::Decoder::new(io::BufReader::new(file))));
+    }
+    #[cfg(not(feature = "zstd"))]
+    if magic == ZSTD_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{} is compressed with zstd, which requires the `zstd` feature",
+                path.display()
+            ),
+        ));
+    }
+
+    Ok(Box::new(file))
+}
+
+/// Magic number starting each zstd frame.
+#[cfg(not(feature = "zstd"))]
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+impl<I, O> Iterator for Stream<I, O> {
+    type Item = io::Result<(I, O)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sample) = self.chunk.next() {
+                return Some(Ok(sample));
+            }
+
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => self.chunk = chunk.into_iter(),
+                Ok(Err(err)) => return Some(Err(err)),
+                // the reader has finished, so report whether it panicked
+                Err(_) => {
+                    let panicked = self.reader.take()?.join().is_err();
+                    return panicked.then(|| Err(io::Error::other("reader thread panicked")));
+                }
+            }
+        }
+    }
+}
+
+/// Shuffles a stream of samples through a buffer of a fixed number of
+/// samples, breaking up runs of similar samples stored together, such
+/// as positions from the same game, which slow convergence.
+/// - Each sample is drawn at random from the buffer, then replaced by
+///   the next read, so a sample moves fewer than `capacity` places earlier,
+///   though it may be held back arbitrarily long.
+/// - Errors are passed on as soon as they are read.
+pub struct Shuffle<S, T> {
+    samples: Fuse<S>,
+    buffer: Vec<T>,
+    capacity: usize,
+    rng: Rand,
+}
+
+impl<S, T> Shuffle<S, T>
+where
+    S: Iterator<Item = io::Result<T>>,
+{
+    /// Shuffles `samples` through a buffer of `capacity` samples,
+    /// drawing from a generator seeded with `seed`.
+    pub fn new<I>(samples: I, capacity: usize, seed: u64) -> Self
+    where
+        I: IntoIterator<IntoIter = S>,
+    {
+        Self {
+            samples: samples.into_iter().fuse(),
+            buffer: Vec::with_capacity(capacity),
+            capacity: capacity.max(1),
+            rng: Rand::with_seed(seed),
+        }
+    }
+}
+
+impl<S, T> Iterator for Shuffle<S, T>
+where
+    S: Iterator<Item = io::Result<T>>,
+{
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.len() < self.capacity {
+            match self.samples.next() {
+                Some(Ok(sample)) => self.buffer.push(sample),
+                Some(Err(err)) => return Some(Err(err)),
+                None => break,
+            }
+        }
+
+        if self.buffer.is_empty() {
+            return None;
+        }
+        let i = (self.rng.rand_u64() % self.buffer.len() as u64) as usize;
+        Some(Ok(self.buffer.swap_remove(i)))
+    }
+}
+
+/// Batches of samples built on a thread of their own, one batch ahead of
+/// training, so that taking samples from a stream, shuffling them, and
+/// moving them into a batch overlaps with training on the batch before.
+/// - Building stops while a built batch waits to be taken, so at most
+///   two batches are held at once.
+/// - The thread stops once the batches are dropped, or after the first
+///   error, which is passed on in turn.
+///
+/// ```no_run
+/// # use goober::{
+/// #     activation::{ReLU, Tanh}, dataset::Record, layer::{DenseConnected, SparseConnected},
+/// #     stream::{Batches, Stream}, FeedForwardNetwork, SparseVector, Vector,
+/// # };
+/// # #[derive(FeedForwardNetwork)]
+/// # pub struct Net {
+/// #     l1: SparseConnected<ReLU, 768, 32>,
+/// #     l2: DenseConnected<Tanh, 32, 1>,
+/// # }
+/// # struct Position;
+/// # impl Record for Position {
+/// #     type Input = SparseVector;
+/// #     type Output = Vector<1>;
+/// #     const SIZE: usize = 32;
+/// #     fn decode(_: &[u8]) -> (SparseVector, Vector<1>) { unimplemented!() }
+/// # }
+/// # fn main() -> Result<(), std::io::Error> {
+/// # let mut trainer = goober::trainer::Trainer::new(Net::boxed_and_zeroed(), Vec::new());
+/// # let epoch = 0;
+/// let stream = Stream::open::<Position>("positions.bin", 16)?;
+/// let batches = Batches::new(stream.shuffled(1 << 20, epoch), 16384);
+/// trainer.run_batches(batches)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Batches<T> {
+    batches: Receiver<io::Result<Vec<T>>>,
+    builder: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + 'static> Batches<T> {
+    /// Builds batches of `batch_size` samples taken from `samples`, the last
+    /// of which may be smaller.
+    pub fn new<I>(samples: I, batch_size: usize) -> Self
+    where
+        I: IntoIterator<Item = io::Result<T>>,
+        I::IntoIter: Send + 'static,
+    {
+        let mut samples = samples.into_iter();
+        let batch_size = batch_size.max(1);
+
+        // a rendezvous channel, so the next batch is handed over only once taken
+        let (sender, batches) = mpsc::sync_channel(0);
+        let builder = thread::spawn(move || loop {
+            let batch = samples
+                .by_ref()
+                .take(batch_size)
+                .collect::<io::Result<Vec<_>>>();
+            let full = matches!(&batch, Ok(batch) if batch.len() == batch_size);
+            let empty = matches!(&batch, Ok(batch) if batch.is_empty());
+            if empty || sender.send(batch).is_err() || !full {
+                break;
+            }
+        });
+
+        Self {
+            batches,
+            builder: Some(builder),
+        }
+    }
+}
+
+impl<T> Iterator for Batches<T> {
+    type Item = io::Result<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.batches.recv() {
+            Ok(batch) => Some(batch),
+            // the builder has finished, so report whether it panicked
+            Err(_) => {
+                let panicked = self.builder.take()?.join().is_err();
+                panicked.then(|| Err(io::Error::other("batch thread panicked")))
+            }
+        }
+    }
+}
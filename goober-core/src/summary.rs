@@ -0,0 +1,209 @@
+//! Table of the layers of a network, with their shapes and number of
+//! parameters, as returned by [`FeedForwardNetwork::summary`].
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{activation::Activation, FeedForwardNetwork, Float, Param, ParamVisitor};
+
+/// Name of the type `T`, without the paths of it and its parameters.
+pub fn short_type_name<T: ?Sized>() -> String {
+    let full = core::any::type_name::<T>();
+    let mut out = String::new();
+    let mut ident = String::new();
+    let mut chars = full.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            ident.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            ident.push(c);
+        } else {
+            out.push_str(&ident);
+            ident.clear();
+            out.push(c);
+        }
+    }
+
+    out.push_str(&ident);
+    out
+}
+
+#[derive(Default)]
+struct Count {
+    params: usize,
+    bytes: usize,
+}
+
+impl ParamVisitor for Count {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.params += param.values.len();
+        self.bytes += core::mem::size_of_val(param.values);
+    }
+}
+
+/// A row of a [`Summary`].
+/// - `input`, `output` and `activation` are empty for layers
+///   which do not describe them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerSummary {
+    pub name: String,
+    /// Type of the layer, without paths.
+    pub kind: String,
+    pub input: String,
+    pub output: String,
+    pub activation: String,
+    pub params: usize,
+    /// Memory taken by the parameters.
+    pub bytes: usize,
+}
+
+impl LayerSummary {
+    /// Describes `layer`, named `name`, by its type and parameters.
+    pub fn new<T: FeedForwardNetwork>(layer: &T, name: &str) -> Self {
+        let mut count = Count::default();
+        layer.visit_params("", &mut count);
+
+        Self {
+            name: name.to_string(),
+            kind: short_type_name::<T>(),
+            input: String::new(),
+            output: String::new(),
+            activation: String::new(),
+            params: count.params,
+            bytes: count.bytes,
+        }
+    }
+
+    pub fn with_shapes(mut self, input: impl Into<String>, output: impl Into<String>) -> Self {
+        self.input = input.into();
+        self.output = output.into();
+        self
+    }
+
+    pub fn with_activation<A: Activation>(mut self) -> Self {
+        self.activation = short_type_name::<A>();
+        self
+    }
+}
+
+/// Layers of a network, in the order they are visited.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Summary {
+    layers: Vec<LayerSummary>,
+}
+
+impl Summary {
+    pub fn of<T: FeedForwardNetwork>(net: &T) -> Self {
+        let mut summary = Self::default();
+        net.summarize("", &mut summary);
+        summary
+    }
+
+    pub fn push(&mut self, layer: LayerSummary) {
+        self.layers.push(layer);
+    }
+
+    pub fn layers(&self) -> &[LayerSummary] {
+        &self.layers
+    }
+
+    /// Total number of parameters.
+    pub fn params(&self) -> usize {
+        self.layers.iter().map(|layer| layer.params).sum()
+    }
+
+    /// Total memory taken by the parameters.
+    pub fn bytes(&self) -> usize {
+        self.layers.iter().map(|layer| layer.bytes).sum()
+    }
+}
+
+fn human_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+impl core::fmt::Display for Summary {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let width = |header: &str, field: fn(&LayerSummary) -> &str| {
+            self.layers
+                .iter()
+                .map(|layer| field(layer).len())
+                .max()
+                .unwrap_or(0)
+                .max(header.len())
+        };
+
+        let name = width("layer", |layer| &layer.name);
+        let kind = width("type", |layer| &layer.kind);
+        let input = width("input", |layer| &layer.input);
+        let output = width("output", |layer| &layer.output);
+        let activation = width("activation", |layer| &layer.activation);
+
+        writeln!(
+            f,
+            "{:name$}  {:kind$}  {:input$}  {:output$}  {:activation$}  {:>10}  {:>10}",
+            "layer", "type", "input", "output", "activation", "params", "memory"
+        )?;
+
+        for layer in &self.layers {
+            writeln!(
+                f,
+                "{:name$}  {:kind$}  {:input$}  {:output$}  {:activation$}  {:>10}  {:>10}",
+                layer.name,
+                layer.kind,
+                layer.input,
+                layer.output,
+                layer.activation,
+                layer.params,
+                human_bytes(layer.bytes)
+            )?;
+        }
+
+        writeln!(f, "total params: {}", self.params())?;
+        writeln!(
+            f,
+            "memory: {} of parameters, {} while training with Adam",
+            human_bytes(self.bytes()),
+            human_bytes(4 * self.bytes())
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn short_names() {
+        assert_eq!(short_type_name::<crate::activation::ReLU>(), "ReLU");
+        assert_eq!(
+            short_type_name::<crate::Vector<4, crate::Stochastic<crate::bf16>>>(),
+            "Vector<4, Stochastic<bf16>>"
+        );
+    }
+
+    #[test]
+    fn bytes() {
+        assert_eq!(human_bytes(1000), "1000 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(3 << 20), "3.0 MiB");
+    }
+}
@@ -0,0 +1,427 @@
+//! Hyperparameter sweeps, expanding a [`Config`] into trials over a grid
+//! or a random sample of values, running a short training for each, and
+//! summarising their final losses.
+//!
+//! A sweep is a configuration file with a `[sweep]` table, and a
+//! `[sweep.params]` table giving the values to try for each key:
+//!
+//! ```toml
+//! epochs = 2
+//!
+//! [schedule]
+//! kind = "constant"
+//! lr = 0.001
+//!
+//! [sweep]
+//! mode = "random"
+//! trials = 8
+//! seed = 1
+//!
+//! [sweep.params]
+//! schedule.lr = [0.0003, 0.001, 0.003]
+//! optimizer.ema = [0.99, 0.999]
+//! args.hidden = [64, 128, 256]
+//! ```
+//!
+//! ```no_run
+//! # use goober::{config::Config, sweep::Sweep};
+//! # fn train(
+//! #     config: &Config,
+//! #     args: Vec<(String, String)>,
+//! # ) -> Result<f32, Box<dyn std::error::Error>> {
+//! #     Ok(0.0)
+//! # }
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let sweep = Sweep::load("sweep.toml")?;
+//! let report = sweep.run(4, |trial| train(&trial.config()?, trial.args()));
+//! println!("{report}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use crate::{
+    config::{Config, ConfigError},
+    toml::{self, Entry, Toml},
+    Rand,
+};
+
+/// Prefix of parameters which are not part of the [`Config`], such
+/// as the architecture, passed to the trial through [`Trial::args`].
+const ARGS: &str = "args.";
+
+/// Whether to try every combination of values, or a random sample.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// `"grid"`, the default.
+    Grid,
+    /// `"random"`, with `trials` trials, each taking every parameter
+    /// independently at random from its values, from `seed`.
+    Random { trials: usize, seed: u64 },
+}
+
+/// Base configuration along with the values to sweep over.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    base: Vec<Entry>,
+    params: Vec<Param>,
+    mode: Mode,
+}
+
+/// Values to try for a key, declared on `line`.
+#[derive(Clone, Debug, PartialEq)]
+struct Param {
+    key: String,
+    values: Vec<Toml>,
+    line: usize,
+}
+
+fn invalid(key: &str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        key: key.into(),
+        message: message.into(),
+    }
+}
+
+impl Sweep {
+    /// Reads a sweep from the file at `path`, with relative paths
+    /// to data taken relative to the directory of the file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let mut sweep = Self::parse(&std::fs::read_to_string(path)?)?;
+
+        if let Some(dir) = path.parent() {
+            let resolve = |value: &mut Toml| {
+                if let Toml::String(s) = value {
+                    *s = dir.join(&*s).to_string_lossy().into_owned();
+                }
+            };
+
+            for (key, value, _) in &mut sweep.base {
+                match key.as_str() {
+                    "data.train" | "data.validation" => {
+                        resolve(value);
+                        if let Toml::Array(values) = value {
+                            values.iter_mut().for_each(resolve);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(sweep)
+    }
+
+    /// Reads a sweep from TOML, leaving relative paths as they are.
+    /// - Fails if the configuration of any trial is invalid.
+    /// - Checkpoints are left out of the configuration of trials,
+    ///   which may run at the same time.
+    pub fn parse(text: &str) -> Result<Self, ConfigError> {
+        let entries =
+            toml::parse(text).map_err(|(line, message)| ConfigError::Syntax { line, message })?;
+
+        let mut base = Vec::new();
+        let mut params = Vec::new();
+        let (mut mode, mut trials, mut seed) = (None, None, 0);
+        let count = |key: &str, value: &Toml| {
+            let count = value.as_u64();
+            count.ok_or_else(|| invalid(key, "should be a non-negative integer"))
+        };
+
+        for (key, value, line) in entries {
+            if let Some(param) = key.strip_prefix("sweep.params.") {
+                let values = match value {
+                    Toml::Array(values) if values.is_empty() => {
+                        return Err(invalid(&key, "has no values"));
+                    }
+                    Toml::Array(values) => values,
+                    value => vec![value],
+                };
+                params.push(Param {
+                    key: param.to_string(),
+                    values,
+                    line,
+                });
+                continue;
+            }
+
+            match key.as_str() {
+                "sweep.mode" => match value {
+                    Toml::String(s) => mode = Some(s),
+                    _ => return Err(invalid(&key, "should be a string")),
+                },
+                "sweep.trials" => trials = Some(count(&key, &value)? as usize),
+                "sweep.seed" => seed = count(&key, &value)?,
+                _ if key.starts_with("sweep.") => {
+                    return Err(invalid(&key, format!("on line {line} is unknown")));
+                }
+                _ if key.starts_with("checkpoint.") => {}
+                _ => base.push((key, value, line)),
+            }
+        }
+
+        let mode = match (mode.as_deref(), trials) {
+            (None | Some("grid"), None) => Mode::Grid,
+            (None | Some("grid"), Some(_)) => {
+                return Err(invalid("sweep.trials", "only applies to random sweeps"))
+            }
+            (Some("random"), Some(trials)) => Mode::Random { trials, seed },
+            (Some("random"), None) => return Err(invalid("sweep.trials", "is missing")),
+            (Some(mode), _) => {
+                let message = format!("is `{mode}`, expected one of grid, random");
+                return Err(invalid("sweep.mode", message));
+            }
+        };
+
+        let sweep = Self { base, params, mode };
+        for trial in sweep.trials() {
+            trial.config()?;
+        }
+        Ok(sweep)
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Every trial of the sweep, in order.
+    pub fn trials(&self) -> Vec<Trial> {
+        let choices: Vec<Vec<usize>> = match self.mode {
+            Mode::Grid => {
+                let mut choices = vec![Vec::new()];
+                for param in &self.params {
+                    choices = choices
+                        .into_iter()
+                        .flat_map(|prefix| {
+                            (0..param.values.len()).map(move |i| {
+                                let mut choice = prefix.clone();
+                                choice.push(i);
+                                choice
+                            })
+                        })
+                        .collect();
+                }
+                choices
+            }
+            Mode::Random { trials, seed } => {
+                let mut rng = Rand::with_seed(seed);
+                (0..trials)
+                    .map(|_| {
+                        self.params
+                            .iter()
+                            .map(|param| (rng.rand_u64() % param.values.len() as u64) as usize)
+                            .collect()
+                    })
+                    .collect()
+            }
+        };
+
+        choices
+            .into_iter()
+            .enumerate()
+            .map(|(index, choice)| Trial {
+                index,
+                params: self
+                    .params
+                    .iter()
+                    .zip(choice)
+                    .map(|(param, i)| (param.key.clone(), param.values[i].clone()))
+                    .collect(),
+                lines: self.params.iter().map(|param| param.line).collect(),
+                base: self.base.clone(),
+            })
+            .collect()
+    }
+
+    /// Runs `train` on every trial, on up to `jobs` threads at a time,
+    /// each returning the final loss of its trial.
+    /// - `train` may itself run the trial in another process,
+    ///   writing its configuration with [`Trial::to_toml`].
+    pub fn run<E, F>(&self, jobs: usize, train: F) -> Report
+    where
+        E: fmt::Display,
+        F: Fn(&Trial) -> Result<f32, E> + Sync,
+    {
+        let trials = self.trials();
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(trials.len()));
+
+        std::thread::scope(|s| {
+            for _ in 0..jobs.clamp(1, trials.len().max(1)) {
+                s.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(trial) = trials.get(i) else {
+                        break;
+                    };
+
+                    let loss = train(trial).map_err(|err| err.to_string());
+                    results.lock().unwrap().push((trial.clone(), loss));
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_by_key(|(trial, _)| trial.index);
+        Report { results }
+    }
+}
+
+/// A single combination of values of the parameters of a [`Sweep`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trial {
+    /// Position of the trial in the sweep, from zero.
+    pub index: usize,
+    params: Vec<(String, Toml)>,
+    /// Line declaring each parameter.
+    lines: Vec<usize>,
+    base: Vec<Entry>,
+}
+
+impl Trial {
+    /// Key and value, as TOML, of each parameter,
+    /// including `args.` parameters.
+    pub fn params(&self) -> Vec<(String, String)> {
+        self.params
+            .iter()
+            .map(|(key, value)| (key.clone(), value.to_string()))
+            .collect()
+    }
+
+    /// Entries of the base configuration, with those of the
+    /// parameters replaced or added.
+    fn entries(&self) -> Vec<Entry> {
+        let mut entries = self.base.clone();
+        for ((key, value), &line) in self.params.iter().zip(&self.lines) {
+            if key.starts_with(ARGS) {
+                continue;
+            }
+            match entries.iter_mut().find(|(k, _, _)| k == key) {
+                Some(entry) => entry.1 = value.clone(),
+                None => entries.push((key.clone(), value.clone(), line)),
+            }
+        }
+        entries
+    }
+
+    /// Configuration of the trial.
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        Config::from_entries(self.entries()).map_err(|err| match err {
+            ConfigError::Invalid { key, message } => ConfigError::Invalid {
+                key,
+                message: format!("{message}, in trial {}", self.index),
+            },
+            err => err,
+        })
+    }
+
+    /// Configuration of the trial as TOML, to pass to another process.
+    pub fn to_toml(&self) -> String {
+        toml::write(&self.entries())
+    }
+
+    /// Values of the `args.` parameters, without the prefix, as text,
+    /// such as to pass as command line options.
+    pub fn args(&self) -> Vec<(String, String)> {
+        self.params
+            .iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix(ARGS)?;
+                let value = match value {
+                    Toml::String(s) => s.clone(),
+                    value => value.to_string(),
+                };
+                Some((name.to_string(), value))
+            })
+            .collect()
+    }
+
+    /// Where to write files for the trial within `dir`,
+    /// named `trial-<index>` with the extension `ext`.
+    pub fn path(&self, dir: &Path, ext: &str) -> PathBuf {
+        dir.join(format!("trial-{}.{ext}", self.index))
+    }
+}
+
+/// Final loss of each trial of a sweep, or why it failed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    results: Vec<(Trial, Result<f32, String>)>,
+}
+
+impl Report {
+    /// Results of every trial, in the order of the sweep.
+    pub fn results(&self) -> &[(Trial, Result<f32, String>)] {
+        &self.results
+    }
+
+    /// Trial with the lowest loss, and its loss.
+    pub fn best(&self) -> Option<(&Trial, f32)> {
+        self.ranked().next()
+    }
+
+    /// Successful trials, from lowest loss to highest.
+    fn ranked(&self) -> impl Iterator<Item = (&Trial, f32)> {
+        let mut ranked: Vec<_> = self
+            .results
+            .iter()
+            .filter_map(|(trial, loss)| Some((trial, *loss.as_ref().ok()?)))
+            .collect();
+        ranked.sort_by(|x, y| x.1.total_cmp(&y.1).then(x.0.index.cmp(&y.0.index)));
+        ranked.into_iter()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Some((first, _)) = self.results.first() else {
+            return write!(f, "no trials");
+        };
+
+        let cells = |trial: &Trial| -> Vec<String> {
+            trial
+                .params
+                .iter()
+                .map(|(_, value)| value.to_string())
+                .collect()
+        };
+        let names: Vec<&str> = first.params.iter().map(|(key, _)| key.as_str()).collect();
+        let mut widths: Vec<usize> = names.iter().map(|name| name.len()).collect();
+        for (trial, _) in &self.results {
+            for (width, cell) in widths.iter_mut().zip(cells(trial)) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        write!(f, "{:>5}  {:>12}", "trial", "loss")?;
+        for (name, width) in names.iter().zip(&widths) {
+            write!(f, "  {name:width$}")?;
+        }
+        writeln!(f)?;
+
+        for (trial, loss) in self.ranked() {
+            write!(f, "{:>5}  {loss:>12.6}", trial.index)?;
+            for (cell, width) in cells(trial).iter().zip(&widths) {
+                write!(f, "  {cell:width$}")?;
+            }
+            writeln!(f)?;
+        }
+
+        for (trial, loss) in &self.results {
+            let Err(err) = loss else {
+                continue;
+            };
+            writeln!(f, "{:>5}  {:>12}  {err}", trial.index, "failed")?;
+        }
+
+        Ok(())
+    }
+}
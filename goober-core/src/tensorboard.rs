@@ -0,0 +1,349 @@
+//! Writing [TensorBoard](https://www.tensorflow.org/tensorboard) event
+//! files, so that runs can be monitored alongside other experiments.
+//!
+//! Event files are a sequence of TFRecords, each holding an `Event`
+//! protobuf message, which are encoded here directly.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    callback::{Callback, Control, Progress},
+    FeedForwardNetwork, Float, Param, ParamVisitor,
+};
+
+const BUCKETS: usize = 30;
+
+/// Minimal protobuf message encoder.
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, mut x: u64) {
+        while x >= 0x80 {
+            self.0.push(x as u8 | 0x80);
+            x >>= 7;
+        }
+        self.0.push(x as u8);
+    }
+
+    fn int(&mut self, field: u64, x: u64) -> &mut Self {
+        self.varint(field << 3);
+        self.varint(x);
+        self
+    }
+
+    fn double(&mut self, field: u64, x: f64) -> &mut Self {
+        self.varint(field << 3 | 1);
+        self.0.extend_from_slice(&x.to_le_bytes());
+        self
+    }
+
+    fn float(&mut self, field: u64, x: f32) -> &mut Self {
+        self.varint(field << 3 | 5);
+        self.0.extend_from_slice(&x.to_le_bytes());
+        self
+    }
+
+    fn doubles(&mut self, field: u64, xs: &[f64]) -> &mut Self {
+        let bytes: Vec<u8> = xs.iter().flat_map(|x| x.to_le_bytes()).collect();
+        self.bytes(field, &bytes)
+    }
+
+    fn bytes(&mut self, field: u64, bytes: &[u8]) -> &mut Self {
+        self.varint(field << 3 | 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+        self
+    }
+
+    fn string(&mut self, field: u64, s: &str) -> &mut Self {
+        self.bytes(field, s.as_bytes())
+    }
+
+    fn message(&mut self, field: u64, msg: &Message) -> &mut Self {
+        self.bytes(field, &msg.0)
+    }
+}
+
+/// CRC-32C (Castagnoli) checksum, masked as in TFRecords.
+fn masked_crc32c(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0x82F63B78 & (crc & 1).wrapping_neg());
+        }
+    }
+
+    let crc = !crc;
+    crc.rotate_right(15).wrapping_add(0xa282ead8)
+}
+
+/// Distribution of a set of values, as summarised in a histogram.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub min: f64,
+    pub max: f64,
+    pub num: f64,
+    pub sum: f64,
+    pub sum_squares: f64,
+    /// Upper edge of each bucket, increasing.
+    pub bucket_limits: Vec<f64>,
+    /// Number of values in each bucket.
+    pub buckets: Vec<f64>,
+}
+
+impl Histogram {
+    /// Summarises `values` into equal width buckets spanning their range.
+    pub fn new(values: impl IntoIterator<Item = f32>, buckets: usize) -> Self {
+        let values: Vec<f64> = values.into_iter().map(f64::from).collect();
+        let buckets = buckets.max(1);
+
+        if values.is_empty() {
+            return Self {
+                min: 0.0,
+                max: 0.0,
+                num: 0.0,
+                sum: 0.0,
+                sum_squares: 0.0,
+                bucket_limits: Vec::new(),
+                buckets: Vec::new(),
+            };
+        }
+
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        let width = (max - min) / buckets as f64;
+        let bucket_limits = if width > 0.0 {
+            (1..=buckets).map(|i| min + width * i as f64).collect()
+        } else {
+            vec![max]
+        };
+
+        let mut counts = vec![0.0; bucket_limits.len()];
+        for &x in &values {
+            let idx = if width > 0.0 {
+                (((x - min) / width) as usize).min(counts.len() - 1)
+            } else {
+                0
+            };
+            counts[idx] += 1.0;
+        }
+
+        Self {
+            min,
+            max,
+            num: values.len() as f64,
+            sum: values.iter().sum(),
+            sum_squares: values.iter().map(|x| x * x).sum(),
+            bucket_limits,
+            buckets: counts,
+        }
+    }
+
+    fn encode(&self) -> Message {
+        let mut msg = Message::default();
+        msg.double(1, self.min)
+            .double(2, self.max)
+            .double(3, self.num)
+            .double(4, self.sum)
+            .double(5, self.sum_squares)
+            .doubles(6, &self.bucket_limits)
+            .doubles(7, &self.buckets);
+        msg
+    }
+}
+
+/// Writes scalars and histograms to a new event file in a log directory.
+pub struct SummaryWriter {
+    out: BufWriter<File>,
+}
+
+impl SummaryWriter {
+    /// Creates a new event file in `dir`, creating `dir` if needed.
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let name = format!(
+            "events.out.tfevents.{}.goober.{}.{}",
+            now.as_secs(),
+            std::process::id(),
+            now.subsec_nanos()
+        );
+
+        let mut writer = Self {
+            out: BufWriter::new(File::create(dir.join(name))?),
+        };
+
+        let mut event = Self::event(0);
+        event.string(3, "brain.Event:2");
+        writer.write_record(&event.0)?;
+        writer.flush()?;
+
+        Ok(writer)
+    }
+
+    fn event(step: u64) -> Message {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut event = Message::default();
+        event.double(1, now.as_secs_f64()).int(2, step);
+        event
+    }
+
+    fn write_record(&mut self, data: &[u8]) -> io::Result<()> {
+        let len = (data.len() as u64).to_le_bytes();
+        self.out.write_all(&len)?;
+        self.out.write_all(&masked_crc32c(&len).to_le_bytes())?;
+        self.out.write_all(data)?;
+        self.out.write_all(&masked_crc32c(data).to_le_bytes())
+    }
+
+    fn write_value(&mut self, step: u64, value: &Message) -> io::Result<()> {
+        let mut summary = Message::default();
+        summary.message(1, value);
+
+        let mut event = Self::event(step);
+        event.message(5, &summary);
+        self.write_record(&event.0)
+    }
+
+    pub fn add_scalar(&mut self, tag: &str, value: f32, step: u64) -> io::Result<()> {
+        let mut msg = Message::default();
+        msg.string(1, tag).float(2, value);
+        self.write_value(step, &msg)
+    }
+
+    pub fn add_histogram(&mut self, tag: &str, histogram: &Histogram, step: u64) -> io::Result<()> {
+        let mut msg = Message::default();
+        msg.string(1, tag).message(5, &histogram.encode());
+        self.write_value(step, &msg)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+struct Histograms(Vec<(String, Histogram)>);
+
+impl ParamVisitor for Histograms {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let histogram = Histogram::new(param.values.iter().map(|x| x.to_f32()), BUCKETS);
+        self.0.push((param.name.to_string(), histogram));
+    }
+}
+
+/// Logs `loss`, `lr` and, with a validation set, `val_loss` at the end of
+/// each epoch, and optionally every so many steps, along with histograms
+/// of every tensor of parameters at the end of each epoch.
+/// - Failing to write panics.
+pub struct TensorBoard {
+    writer: SummaryWriter,
+    every: Option<u64>,
+    histograms: bool,
+}
+
+impl TensorBoard {
+    pub fn new(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self {
+            writer: SummaryWriter::new(dir)?,
+            every: None,
+            histograms: true,
+        })
+    }
+
+    /// Also logs scalars after every `every` steps.
+    pub fn with_every(mut self, every: u64) -> Self {
+        self.every = Some(every.max(1));
+        self
+    }
+
+    /// Whether to log histograms of the parameters.
+    pub fn with_histograms(mut self, histograms: bool) -> Self {
+        self.histograms = histograms;
+        self
+    }
+
+    fn write<T: FeedForwardNetwork>(
+        &mut self,
+        progress: &Progress<'_, T>,
+        epoch_end: bool,
+    ) -> io::Result<()> {
+        let step = progress.step;
+        self.writer.add_scalar("loss", progress.loss, step)?;
+        self.writer.add_scalar("lr", progress.lr, step)?;
+
+        if let Some(validation) = progress.validation {
+            self.writer.add_scalar("val_loss", validation.loss, step)?;
+        }
+
+        if epoch_end && self.histograms {
+            let mut histograms = Histograms(Vec::new());
+            progress.net.visit_params("", &mut histograms);
+            for (name, histogram) in &histograms.0 {
+                self.writer.add_histogram(name, histogram, step)?;
+            }
+        }
+
+        self.writer.flush()
+    }
+
+    fn log<T: FeedForwardNetwork>(&mut self, progress: &Progress<'_, T>, epoch_end: bool) {
+        if let Err(err) = self.write(progress, epoch_end) {
+            panic!("failed to write TensorBoard events: {err}");
+        }
+    }
+}
+
+impl<T: FeedForwardNetwork> Callback<T> for TensorBoard {
+    fn on_batch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        if self
+            .every
+            .is_some_and(|every| progress.step.is_multiple_of(every))
+        {
+            self.log(progress, false);
+        }
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        self.log(progress, true);
+        Control::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn crc32c() {
+        // unmasked CRC-32C of "123456789" is 0xE3069283
+        let masked = 0xE3069283u32.rotate_right(15).wrapping_add(0xa282ead8);
+        assert_eq!(masked_crc32c(b"123456789"), masked);
+    }
+
+    #[test]
+    fn histogram() {
+        let histogram = Histogram::new([0.0, 1.0, 1.5, 4.0], 4);
+        assert_eq!(histogram.bucket_limits, [1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(histogram.buckets, [1.0, 2.0, 0.0, 1.0]);
+        assert_eq!(histogram.sum_squares, 19.25);
+
+        let constant = Histogram::new([2.0, 2.0], 4);
+        assert_eq!(constant.buckets, [2.0]);
+    }
+}
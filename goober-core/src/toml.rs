@@ -0,0 +1,419 @@
+//! Reader for the subset of TOML used by configuration files: tables
+//! with dotted names, and keys holding strings, integers, floats,
+//! booleans, or arrays of these, which may span several lines.
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Toml {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Toml>),
+}
+
+impl Toml {
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Integer(x) => Some(*x as f64),
+            Self::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Integer(x) => u64::try_from(*x).ok(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Toml]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Toml {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::String(s) => write_string(s, f),
+            Self::Integer(x) => write!(f, "{x}"),
+            Self::Float(x) if x.is_nan() => write!(f, "nan"),
+            Self::Float(x) if x.is_infinite() => {
+                write!(f, "{}inf", if *x < 0.0 { "-" } else { "" })
+            }
+            Self::Float(x) => write!(f, "{x:?}"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value}")?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+fn write_string(s: &str, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
+
+/// Writes `entries` as TOML, each as a dotted key at the top level,
+/// such that [`parse`] reads back the same keys and values.
+/// - Keys are written as they are, so should be bare.
+pub(crate) fn write(entries: &[Entry]) -> String {
+    let mut out = String::new();
+    for (key, value, _) in entries {
+        out.push_str(key);
+        out.push_str(" = ");
+        out.push_str(&value.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+/// A key, qualified by its table as `table.key`,
+/// with its value and the line it is on, from one.
+pub(crate) type Entry = (String, Toml, usize);
+
+/// Every key of `text`, in order, or the line and
+/// description of the first syntax error.
+pub(crate) fn parse(text: &str) -> Result<Vec<Entry>, (usize, String)> {
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut table = String::new();
+    let mut parser = Parser {
+        bytes: text.as_bytes(),
+        pos: 0,
+        line: 1,
+    };
+
+    loop {
+        parser.blank();
+        if parser.done() {
+            return Ok(entries);
+        }
+
+        parser
+            .statement(&mut table, &mut entries)
+            .map_err(|message| (parser.line, message))?;
+
+        parser.whitespace();
+        if !parser.done() && !parser.newline() {
+            let rest = parser.rest_of_line();
+            return Err((parser.line, format!("unexpected `{rest}`")));
+        }
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Line of `pos`, from one.
+    line: usize,
+}
+
+impl Parser<'_> {
+    /// Table header, setting `table`, or key and value, added to `entries`.
+    fn statement(&mut self, table: &mut String, entries: &mut Vec<Entry>) -> Result<(), String> {
+        if self.eat(b'[') {
+            *table = self.key()?;
+            return self.expect(b']');
+        }
+
+        let line = self.line;
+        let key = self.key()?;
+        self.expect(b'=')?;
+        let value = self.value()?;
+
+        let key = if table.is_empty() {
+            key
+        } else {
+            format!("{table}.{key}")
+        };
+
+        if entries.iter().any(|(k, _, _)| *k == key) {
+            return Err(format!("duplicate key `{key}`"));
+        }
+        entries.push((key, value, line));
+        Ok(())
+    }
+
+    /// Skips whitespace, and a comment running to the end of the line.
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r') = self.bytes.get(self.pos) {
+            self.pos += 1;
+        }
+        if self.bytes.get(self.pos) == Some(&b'#') {
+            while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                self.pos += 1;
+            }
+        }
+    }
+
+    /// Skips whitespace, comments, and line breaks.
+    fn blank(&mut self) {
+        self.whitespace();
+        while self.newline() {
+            self.whitespace();
+        }
+    }
+
+    fn newline(&mut self) -> bool {
+        let found = self.bytes.get(self.pos) == Some(&b'\n');
+        if found {
+            self.pos += 1;
+            self.line += 1;
+        }
+        found
+    }
+
+    fn rest_of_line(&self) -> String {
+        let rest = &self.bytes[self.pos..];
+        let end = rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len());
+        String::from_utf8_lossy(&rest[..end]).trim_end().to_string()
+    }
+
+    fn done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.whitespace();
+        let found = self.bytes.get(self.pos) == Some(&byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.eat(byte) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}`", byte as char))
+        }
+    }
+
+    /// Bare or quoted key, with dotted parts joined by `.`.
+    fn key(&mut self) -> Result<String, String> {
+        let mut key = String::new();
+        loop {
+            self.whitespace();
+            let part = match self.bytes.get(self.pos) {
+                Some(b'"' | b'\'') => self.string()?,
+                _ => {
+                    let start = self.pos;
+                    while let Some(b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-') =
+                        self.bytes.get(self.pos)
+                    {
+                        self.pos += 1;
+                    }
+                    if start == self.pos {
+                        return Err("expected a key".into());
+                    }
+                    String::from_utf8_lossy(&self.bytes[start..self.pos]).into_owned()
+                }
+            };
+
+            key.push_str(&part);
+            if !self.eat(b'.') {
+                return Ok(key);
+            }
+            key.push('.');
+        }
+    }
+
+    fn value(&mut self) -> Result<Toml, String> {
+        self.whitespace();
+        match self.bytes.get(self.pos) {
+            Some(b'"' | b'\'') => self.string().map(Toml::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                loop {
+                    self.blank();
+                    if self.eat(b']') {
+                        break;
+                    }
+                    values.push(self.value()?);
+                    self.blank();
+                    if !self.eat(b',') {
+                        self.blank();
+                        self.expect(b']')?;
+                        break;
+                    }
+                }
+                Ok(Toml::Array(values))
+            }
+            Some(_) => {
+                let start = self.pos;
+                while let Some(
+                    b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'-' | b'+' | b'.',
+                ) = self.bytes.get(self.pos)
+                {
+                    self.pos += 1;
+                }
+                let word = String::from_utf8_lossy(&self.bytes[start..self.pos]);
+                scalar(&word).ok_or_else(|| format!("invalid value `{word}`"))
+            }
+            None => Err("expected a value".into()),
+        }
+    }
+
+    /// Basic string in double quotes, with escapes,
+    /// or literal string in single quotes.
+    fn string(&mut self) -> Result<String, String> {
+        let quote = self.bytes[self.pos];
+        self.pos += 1;
+        let mut out = Vec::new();
+
+        loop {
+            let Some(&byte) = self.bytes.get(self.pos).filter(|&&b| b != b'\n') else {
+                return Err("unterminated string".into());
+            };
+            self.pos += 1;
+
+            match byte {
+                b if b == quote => break,
+                b'\\' if quote == b'"' => {
+                    let escaped = match self.bytes.get(self.pos) {
+                        Some(b'"') => b'"',
+                        Some(b'\\') => b'\\',
+                        Some(b'n') => b'\n',
+                        Some(b't') => b'\t',
+                        Some(b'r') => b'\r',
+                        _ => return Err("unsupported escape".into()),
+                    };
+                    self.pos += 1;
+                    out.push(escaped);
+                }
+                b => out.push(b),
+            }
+        }
+
+        String::from_utf8(out).map_err(|_| "invalid UTF-8 in string".into())
+    }
+}
+
+fn scalar(word: &str) -> Option<Toml> {
+    match word {
+        "true" => return Some(Toml::Bool(true)),
+        "false" => return Some(Toml::Bool(false)),
+        "inf" | "+inf" => return Some(Toml::Float(f64::INFINITY)),
+        "-inf" => return Some(Toml::Float(f64::NEG_INFINITY)),
+        "nan" | "+nan" | "-nan" => return Some(Toml::Float(f64::NAN)),
+        _ => {}
+    }
+
+    let digits = word.replace('_', "");
+    if let Ok(x) = digits.parse() {
+        return Some(Toml::Integer(x));
+    }
+
+    let numeric = digits.bytes().any(|b| b.is_ascii_digit())
+        && digits
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-'));
+    digits.parse().ok().filter(|_| numeric).map(Toml::Float)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_subset() {
+        let text = r#"
+            # comment
+            epochs = 1_000 # trailing comment
+            name = "a \"b\" # c"
+
+            [data.files]
+            paths = ['x.bin', "y.bin", ]
+            lr = 1e-3
+            on = true
+        "#;
+
+        let entries = parse(text).unwrap();
+        assert_eq!(
+            entries,
+            [
+                ("epochs".into(), Toml::Integer(1000), 3),
+                ("name".into(), Toml::String("a \"b\" # c".into()), 4),
+                (
+                    "data.files.paths".into(),
+                    Toml::Array(vec![
+                        Toml::String("x.bin".into()),
+                        Toml::String("y.bin".into())
+                    ]),
+                    7
+                ),
+                ("data.files.lr".into(), Toml::Float(1e-3), 8),
+                ("data.files.on".into(), Toml::Bool(true), 9),
+            ]
+        );
+
+        assert_eq!(parse("a = 1\na = 2"), Err((2, "duplicate key `a`".into())));
+        assert_eq!(
+            parse("a = [\n  1, # one\n\n  2,\n]\nb = 3"),
+            Ok(vec![
+                (
+                    "a".into(),
+                    Toml::Array(vec![Toml::Integer(1), Toml::Integer(2)]),
+                    1
+                ),
+                ("b".into(), Toml::Integer(3), 6),
+            ])
+        );
+        assert_eq!(parse("a = [1,\n2"), Err((2, "expected `]`".into())));
+        assert_eq!(
+            parse("a = \"x\ny\""),
+            Err((1, "unterminated string".into()))
+        );
+        assert_eq!(parse("a = \"x"), Err((1, "unterminated string".into())));
+        assert!(parse("a = 1 2").is_err());
+        assert!(parse("a = nope").is_err());
+
+        let written = write(&entries);
+        assert!(written.starts_with("epochs = 1000\nname = \"a \\\"b\\\" # c\"\n"));
+        let reread = parse(&written).unwrap();
+        let values = |entries: &[Entry]| {
+            entries
+                .iter()
+                .map(|(k, v, _)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(values(&reread), values(&entries));
+    }
+}
@@ -0,0 +1,1104 @@
+//! High-level training loop, owning a network along with its gradient,
+//! optimiser state, learning rate schedule and training data.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh}, layer::{DenseConnected, SparseConnected},
+//! #     schedule::Cosine, trainer::Trainer, FeedForwardNetwork, SparseVector, Vector,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 32>,
+//! #     l2: DenseConnected<Tanh, 32, 1>,
+//! # }
+//! # fn main() -> std::io::Result<()> {
+//! # let net = Net::boxed_and_zeroed();
+//! # let data: Vec<(SparseVector, Vector<1>)> = Vec::new();
+//! let mut trainer = Trainer::new(net, data)
+//!     .with_scheduler(Cosine { lr: 0.001, min_lr: 0.0, warmup: 100, steps: 10_000 })
+//!     .with_batch_size(256);
+//!
+//! let loss = trainer.run(10)?;
+//! trainer.net().save("net.gbnn")?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt, io,
+    ops::{Add, Mul},
+};
+
+use crate::{
+    augment::Augment,
+    callback::{Callback, Control, Progress},
+    checkpoint::{Checkpoint, CheckpointManager},
+    dataset::{Dataset, Sample},
+    loss::{Loss, Mse},
+    lr_finder::{LrFinder, LrSweep},
+    merge::{average, lerp, lerp_in_place},
+    metrics::{evaluate, Metrics},
+    prune::{Mask, Prune},
+    qat::Qat,
+    schedule::{Constant, Scheduler},
+    seed_stochastic_rounding, ActivationVisitor, FeedForwardNetwork, Float, Gradient, OutputLayer,
+    Param, ParamMut, ParamVisitor, ParamVisitorMut, Rand,
+};
+
+/// `(1 - alpha) hard + alpha soft`, kept out of [`Trainer::with_teacher`],
+/// where the bound on multiplying outputs by `f32` confuses inference.
+fn blend(hard: f32, soft: f32, alpha: f32) -> f32 {
+    (1.0 - alpha) * hard + alpha * soft
+}
+
+/// Name of the moving average of the network saved alongside checkpoints.
+const EMA: &str = "ema.gbnn";
+
+/// Stands in for the data of a trainer while it is borrowed.
+struct Empty;
+
+impl<I, O> Dataset<I, O> for Empty {
+    fn len(&self) -> usize {
+        0
+    }
+
+    fn get(&self, _: usize) -> Sample<'_, I, O> {
+        unreachable!("the data of a trainer is in use")
+    }
+}
+
+struct Zero;
+
+impl ParamVisitorMut for Zero {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        param.values.fill(F::ZERO);
+    }
+}
+
+/// Finds the name of the first tensor holding a non-finite value.
+#[derive(Default)]
+struct FindNonFinite(Option<String>);
+
+impl FindNonFinite {
+    fn check<F: Float>(&mut self, name: &str, values: &[F]) {
+        if self.0.is_none() && values.iter().any(|x| !x.to_f32().is_finite()) {
+            self.0 = Some(name.to_string());
+        }
+    }
+}
+
+impl ActivationVisitor for FindNonFinite {
+    fn visit<F: Float>(&mut self, name: &str, values: &[F]) {
+        self.check(name, values);
+    }
+}
+
+impl ParamVisitor for FindNonFinite {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.check(param.name, param.values);
+    }
+}
+
+/// Where a [`NonFinite`] value was found.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NonFiniteKind {
+    /// In the output of a layer, in the forward pass.
+    Activation,
+    /// In the loss of a sample.
+    Loss,
+    /// In a tensor of the gradient accumulated over a batch.
+    Gradient,
+}
+
+/// A NaN or infinity caught by a trainer with
+/// [`with_nan_guard`](Trainer::with_nan_guard).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NonFinite {
+    pub kind: NonFiniteKind,
+    /// Name of the layer or tensor of parameters, as passed to
+    /// [`visit_activations`](FeedForwardNetwork::visit_activations)
+    /// or [`visit_params`](FeedForwardNetwork::visit_params).
+    pub name: String,
+    /// Step of the optimiser which was being taken, counting from one.
+    pub step: u64,
+    /// Index into the training data of the sample, except for gradients,
+    /// which are only checked once accumulated over the whole batch.
+    pub sample: Option<usize>,
+}
+
+impl fmt::Display for NonFinite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            NonFiniteKind::Activation => "activation",
+            NonFiniteKind::Loss => "loss",
+            NonFiniteKind::Gradient => "gradient",
+        };
+
+        write!(f, "non-finite {kind}")?;
+        if !self.name.is_empty() {
+            write!(f, " in `{}`", self.name)?;
+        }
+        write!(f, " at step {}", self.step)?;
+        if let Some(sample) = self.sample {
+            write!(f, " on sample {sample}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for NonFinite {}
+
+/// Trains a network of type `T` on pairs of inputs and targets with Adam,
+/// under the learning rate schedule `S` and loss function `L`.
+/// - Each epoch visits every sample once, in an order drawn from the
+///   generator of the [`Checkpoint`] at its start, so a run resumed
+///   from a checkpoint saved between epochs replays the same order.
+/// - The loss of each epoch is reported to the schedule through
+///   [`Scheduler::observe`], before any callbacks are invoked, being
+///   the validation loss if there is a validation set, or otherwise
+///   the mean training loss.
+pub struct Trainer<T: FeedForwardNetwork, S = Constant, L = Mse> {
+    ckpt: Checkpoint<T, S>,
+    grad: Box<T::Grad>,
+    data: Box<dyn Dataset<T::InputType, T::OutputType>>,
+    order: Vec<usize>,
+    loss: L,
+    batch_size: usize,
+    shuffle: bool,
+    callbacks: Vec<Box<dyn Callback<T>>>,
+    checkpoints: Option<(CheckpointManager, u64)>,
+    validation: Option<Validation<T>>,
+    nan_guard: bool,
+    deterministic: Option<u64>,
+    swa: Option<Swa<T>>,
+    ema: Option<Ema<T>>,
+    teacher: Option<Teacher<T>>,
+    weigh: Weigh<T>,
+    augment: Option<Augmentation<T>>,
+    /// Outputs of each layer for the sample being trained on, reused
+    /// for every sample, see [`FeedForwardNetwork::out_into`].
+    layers: Option<Box<T::Layers>>,
+    /// Error of the input of the sample being trained on, reused for
+    /// every sample, see [`FeedForwardNetwork::backprop_into`].
+    input_err: Option<Box<T::InputType>>,
+    mask: Option<Mask>,
+    qat: Option<Qat>,
+    /// Batches in the last whole pass of [`Trainer::run_stream`].
+    stream_batches: u64,
+    /// Whole passes of [`Trainer::run_stream`], counted as the epochs
+    /// of a trainer without data of its own.
+    stream_epochs: u64,
+    stopped: bool,
+}
+
+/// Exponential moving average of the network after each step.
+struct Ema<T> {
+    decay: f32,
+    net: Option<Box<T>>,
+}
+
+/// Running average of the network at the end of each epoch.
+struct Swa<T> {
+    start: u64,
+    net: Option<Box<T>>,
+    count: u64,
+}
+
+/// Metrics of the network under the trainer's loss at the time.
+type Validation<T> =
+    Box<dyn FnMut(&T, &(dyn Loss<<T as FeedForwardNetwork>::OutputType> + Sync)) -> Metrics>;
+
+/// Loss and its gradient for an input, output and target,
+/// blended with the loss against the output of a teacher.
+type Teacher<T> = Box<
+    dyn Fn(
+        &<T as FeedForwardNetwork>::InputType,
+        &<T as FeedForwardNetwork>::OutputType,
+        &<T as FeedForwardNetwork>::OutputType,
+    ) -> (f32, <T as FeedForwardNetwork>::OutputType),
+>;
+
+/// Scales the gradient of the loss of a sample by its weight.
+type Weigh<T> =
+    fn(f32, <T as FeedForwardNetwork>::OutputType) -> <T as FeedForwardNetwork>::OutputType;
+
+type Augmentation<T> =
+    Box<dyn Augment<<T as FeedForwardNetwork>::InputType, <T as FeedForwardNetwork>::OutputType>>;
+
+/// Samples copied out of the data, with their weights.
+type WeightedSamples<T> = Vec<(
+    <T as FeedForwardNetwork>::InputType,
+    <T as FeedForwardNetwork>::OutputType,
+    f32,
+)>;
+
+impl<T: FeedForwardNetwork> Trainer<T> {
+    /// Trains `net` on `data`, by default with the mean squared error,
+    /// a constant learning rate of `0.001` and shuffled batches of 1024.
+    pub fn new(net: Box<T>, data: Vec<(T::InputType, T::OutputType)>) -> Self
+    where
+        T::InputType: 'static,
+        T::OutputType: 'static,
+        f32: Mul<T::OutputType, Output = T::OutputType>,
+    {
+        Self::from_dataset(net, data)
+    }
+
+    /// As [`new`](Self::new), reading samples from `data`.
+    /// - The loss of each sample, and so its gradient, is scaled by its
+    ///   weight in the data, given by [`Dataset::weight`], such as with
+    ///   [`Weighted`](crate::dataset::Weighted).
+    /// - Samples of a [`Stream`](crate::stream::Stream) have no weights.
+    /// - Validation losses are not weighted.
+    pub fn from_dataset<D>(net: Box<T>, data: D) -> Self
+    where
+        D: Dataset<T::InputType, T::OutputType> + 'static,
+        f32: Mul<T::OutputType, Output = T::OutputType>,
+    {
+        Self {
+            ckpt: Checkpoint::new(net, Constant(0.001)),
+            grad: T::zeroed_grad(),
+            order: (0..data.len()).collect(),
+            data: Box::new(data),
+            loss: Mse,
+            batch_size: 1024,
+            shuffle: true,
+            callbacks: Vec::new(),
+            checkpoints: None,
+            validation: None,
+            nan_guard: false,
+            deterministic: None,
+            swa: None,
+            ema: None,
+            teacher: None,
+            weigh: |weight, err| weight * err,
+            augment: None,
+            layers: None,
+            input_err: None,
+            mask: None,
+            qat: None,
+            stream_batches: 0,
+            stream_epochs: 0,
+            stopped: false,
+        }
+    }
+}
+
+impl<T: FeedForwardNetwork, S: Scheduler, L: Loss<T::OutputType> + Sync> Trainer<T, S, L> {
+    pub fn with_scheduler<S2: Scheduler>(self, scheduler: S2) -> Trainer<T, S2, L> {
+        let Checkpoint {
+            net,
+            momentum,
+            velocity,
+            step,
+            rng,
+            ..
+        } = self.ckpt;
+
+        Trainer {
+            ckpt: Checkpoint {
+                net,
+                momentum,
+                velocity,
+                step,
+                scheduler,
+                rng,
+            },
+            grad: self.grad,
+            data: self.data,
+            order: self.order,
+            loss: self.loss,
+            batch_size: self.batch_size,
+            shuffle: self.shuffle,
+            callbacks: self.callbacks,
+            checkpoints: self.checkpoints,
+            validation: self.validation,
+            nan_guard: self.nan_guard,
+            deterministic: self.deterministic,
+            swa: self.swa,
+            ema: self.ema,
+            teacher: self.teacher,
+            weigh: self.weigh,
+            augment: self.augment,
+            layers: self.layers,
+            input_err: self.input_err,
+            mask: self.mask,
+            qat: self.qat,
+            stream_batches: self.stream_batches,
+            stream_epochs: self.stream_epochs,
+            stopped: self.stopped,
+        }
+    }
+
+    pub fn with_loss<L2: Loss<T::OutputType> + Sync>(self, loss: L2) -> Trainer<T, S, L2> {
+        Trainer {
+            ckpt: self.ckpt,
+            grad: self.grad,
+            data: self.data,
+            order: self.order,
+            loss,
+            batch_size: self.batch_size,
+            shuffle: self.shuffle,
+            callbacks: self.callbacks,
+            checkpoints: self.checkpoints,
+            validation: self.validation,
+            nan_guard: self.nan_guard,
+            deterministic: self.deterministic,
+            swa: self.swa,
+            ema: self.ema,
+            teacher: self.teacher,
+            weigh: self.weigh,
+            augment: self.augment,
+            layers: self.layers,
+            input_err: self.input_err,
+            mask: self.mask,
+            qat: self.qat,
+            stream_batches: self.stream_batches,
+            stream_epochs: self.stream_epochs,
+            stopped: self.stopped,
+        }
+    }
+
+    /// Number of samples in each step of the optimiser, at least one.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Seeds the generator which shuffles the data.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.ckpt.rng = Rand::with_seed(seed);
+        self
+    }
+
+    /// Makes training reproducible from `seed`, so that two runs on the
+    /// same data with the same settings produce identical checkpoints,
+    /// including across resuming from a checkpoint.
+    /// - Seeds the generator which shuffles the data, as
+    ///   [`with_seed`](Self::with_seed).
+    /// - Reseeds [`Stochastic`](crate::Stochastic) rounding before each
+    ///   step, from `seed` and the step.
+    /// - Validation losses are always reduced in a fixed order, see
+    ///   [`evaluate_with_threads`](crate::metrics::evaluate_with_threads).
+    pub fn with_deterministic(mut self, seed: u64) -> Self {
+        self.ckpt.rng = Rand::with_seed(seed);
+        self.deterministic = Some(seed);
+        self
+    }
+
+    /// Keeps an average of the network at the end of each epoch from
+    /// the `start`th on, in the manner of Stochastic Weight Averaging,
+    /// which is usually better than the network at any single epoch,
+    /// see [`swa_net`](Self::swa_net).
+    /// - The average is not saved in checkpoints, so restarts when
+    ///   training is resumed.
+    pub fn with_swa(mut self, start: u64) -> Self {
+        self.swa = Some(Swa {
+            start,
+            net: None,
+            count: 0,
+        });
+        self
+    }
+
+    /// Keeps an exponential moving average of the network, moved towards
+    /// it by `1 - decay` after each step, see [`ema_net`](Self::ema_net).
+    /// - With [`with_checkpoints`](Self::with_checkpoints), the average
+    ///   is saved alongside each checkpoint, to `ema.gbnn`.
+    /// - The average starts from the network after the first step taken,
+    ///   and is not part of a [`Checkpoint`], so after resuming it should
+    ///   be loaded back with [`ema_net_mut`](Self::ema_net_mut).
+    pub fn with_ema(mut self, decay: f32) -> Self {
+        self.ema = Some(Ema { decay, net: None });
+        self
+    }
+
+    /// Whether to visit the data in a random order each epoch,
+    /// rather than the order it was given in.
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Adds a callback, invoked after those already added.
+    pub fn with_callback<C: Callback<T> + 'static>(mut self, callback: C) -> Self {
+        self.callbacks.push(Box::new(callback));
+        self
+    }
+
+    /// Saves a checkpoint with `manager` after every `every` epochs.
+    pub fn with_checkpoints(mut self, manager: CheckpointManager, every: u64) -> Self {
+        self.checkpoints = Some((manager, every.max(1)));
+        self
+    }
+
+    /// Evaluates the network on `data` at the end of each epoch,
+    /// see [`evaluate`].
+    /// - Validation is under the trainer's loss at the time, including
+    ///   one set by a later [`with_loss`](Self::with_loss).
+    pub fn with_validation(mut self, data: Vec<(T::InputType, T::OutputType)>) -> Self
+    where
+        T: Sync,
+        T::InputType: Sync + 'static,
+        T::OutputType: Sync + 'static,
+    {
+        self.validation = Some(Box::new(move |net, loss| evaluate(net, &data, loss)));
+        self
+    }
+
+    /// Checks the output of every layer and the loss of every sample, and
+    /// every tensor of the gradient of each batch before it is applied,
+    /// for NaNs and infinities, failing with the first one found.
+    /// - The error is of kind [`InvalidData`](io::ErrorKind::InvalidData),
+    ///   wrapping a [`NonFinite`] naming the layer or tensor and step.
+    /// - Only layers which implement
+    ///   [`visit_activations`](FeedForwardNetwork::visit_activations)
+    ///   are checked.
+    /// - The network is left as it was before the failing step.
+    pub fn with_nan_guard(mut self, nan_guard: bool) -> Self {
+        self.nan_guard = nan_guard;
+        self
+    }
+
+    /// Distils `teacher`, which may be any network with the same inputs
+    /// and outputs, such as a larger one, into the network, by training
+    /// on the loss against the output of `teacher` scaled by `alpha`,
+    /// plus the loss against the target scaled by `1 - alpha`.
+    /// - Uses the loss function set before this is called.
+    /// - Validation is still against the targets alone.
+    pub fn with_teacher<N>(mut self, teacher: Box<N>, alpha: f32) -> Self
+    where
+        N: FeedForwardNetwork<InputType = T::InputType, OutputType = T::OutputType> + 'static,
+        T::OutputType: Add<Output = T::OutputType>,
+        f32: Mul<T::OutputType, Output = T::OutputType>,
+        L: Clone + 'static,
+    {
+        let loss = self.loss.clone();
+        self.teacher = Some(Box::new(move |input, out, target| {
+            let (hard, hard_err) = loss.loss(out, target);
+            let (soft, soft_err) = loss.loss(out, &teacher.out(input));
+            let err = (1.0 - alpha) * hard_err + alpha * soft_err;
+            (blend(hard, soft, alpha), err)
+        }));
+        self
+    }
+
+    /// Augments the samples of each batch with `augment`, once they are
+    /// copied out of the data, drawing from the generator of the
+    /// [`Checkpoint`], such as with [`Mixup`](crate::augment::Mixup).
+    /// - Samples keep their weights, by their place in the batch.
+    /// - Validation samples are not augmented.
+    pub fn with_augmentation<A>(mut self, augment: A) -> Self
+    where
+        A: Augment<T::InputType, T::OutputType> + 'static,
+    {
+        self.augment = Some(Box::new(augment));
+        self
+    }
+
+    /// Keeps the weights pruned in `mask` at zero, zeroing them now and
+    /// again after each step, such as to fine-tune a pruned network.
+    pub fn with_mask(mut self, mask: Mask) -> Self {
+        mask.apply(&mut *self.ckpt.net);
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Trains with the quantization of the layers in `qat` simulated
+    /// in the forward pass, clamping their weights to the range
+    /// representable once quantized now and again after each step.
+    /// - Validation runs without simulating quantization.
+    pub fn with_qat(mut self, qat: Qat) -> Self {
+        self.ckpt.net.clamp_qat("", &qat);
+        self.qat = Some(qat);
+        self
+    }
+
+    pub fn net(&self) -> &T {
+        &self.ckpt.net
+    }
+
+    pub fn net_mut(&mut self) -> &mut T {
+        &mut self.ckpt.net
+    }
+
+    pub fn into_net(self) -> Box<T> {
+        self.ckpt.net
+    }
+
+    /// Average of the network over the epochs since SWA started,
+    /// if it is enabled and has.
+    pub fn swa_net(&self) -> Option<&T> {
+        self.swa.as_ref()?.net.as_deref()
+    }
+
+    /// Number of epochs averaged into [`swa_net`](Self::swa_net).
+    pub fn swa_count(&self) -> u64 {
+        self.swa.as_ref().map_or(0, |swa| swa.count)
+    }
+
+    /// Moving average of the network, if it is enabled
+    /// and a step has been taken.
+    pub fn ema_net(&self) -> Option<&T> {
+        self.ema.as_ref()?.net.as_deref()
+    }
+
+    /// As [`ema_net`](Self::ema_net), starting the average from
+    /// the current network if it is enabled but has not started.
+    pub fn ema_net_mut(&mut self) -> Option<&mut T> {
+        let ema = self.ema.as_mut()?;
+        let net = ema.net.get_or_insert_with(|| average(&[&*self.ckpt.net]));
+        Some(net)
+    }
+
+    /// Hands back [`swa_net`](Self::swa_net) if there is one,
+    /// or otherwise the network as with [`into_net`](Self::into_net).
+    pub fn into_swa_net(self) -> Box<T> {
+        match self.swa {
+            Some(Swa { net: Some(net), .. }) => net,
+            _ => self.ckpt.net,
+        }
+    }
+
+    /// Network along with the state of its optimiser and schedule,
+    /// which can be saved and loaded to resume training.
+    pub fn checkpoint(&self) -> &Checkpoint<T, S> {
+        &self.ckpt
+    }
+
+    pub fn checkpoint_mut(&mut self) -> &mut Checkpoint<T, S> {
+        &mut self.ckpt
+    }
+
+    pub fn data(&self) -> &dyn Dataset<T::InputType, T::OutputType> {
+        &*self.data
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Number of optimiser steps taken.
+    pub fn step(&self) -> u64 {
+        self.ckpt.step
+    }
+
+    /// Learning rate for the next step.
+    pub fn lr(&self) -> f32 {
+        self.ckpt.lr()
+    }
+
+    /// Batches in an epoch of the data, or if there is none, in the
+    /// last whole pass over a stream with [`run_stream`](Self::run_stream).
+    pub fn batches_per_epoch(&self) -> u64 {
+        match self.data.len() {
+            0 => self.stream_batches,
+            len => len.div_ceil(self.batch_size) as u64,
+        }
+    }
+
+    /// Number of complete epochs taken, or if there is no data, of whole
+    /// passes over a stream with [`run_stream`](Self::run_stream).
+    pub fn epoch(&self) -> u64 {
+        match self.data.len() {
+            0 => self.stream_epochs,
+            _ => self.ckpt.step / self.batches_per_epoch(),
+        }
+    }
+
+    /// Whether a callback stopped the last epoch, or call to
+    /// [`run`](Self::run).
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Runs `epochs` epochs, or until a callback stops training,
+    /// returning the mean loss of the last epoch.
+    pub fn run(&mut self, epochs: usize) -> io::Result<f32> {
+        span!(INFO, "run", epochs);
+        self.stopped = false;
+
+        let mut loss = 0.0;
+        for _ in 0..epochs {
+            loss = self.run_epoch()?;
+            if self.stopped {
+                break;
+            }
+        }
+
+        for callback in &mut self.callbacks {
+            callback.on_train_end(&mut self.ckpt.net);
+        }
+
+        Ok(loss)
+    }
+
+    /// Prunes the network with `prune`, then fine-tunes it for `epochs`
+    /// epochs with the pruned weights kept at zero, as
+    /// [`with_mask`](Self::with_mask), returning the mean loss of the
+    /// last epoch.
+    /// - Replaces any mask set before, though weights it pruned are
+    ///   still zero, so are pruned first again.
+    pub fn prune(&mut self, prune: &Prune, epochs: usize) -> io::Result<f32> {
+        self.mask = Some(prune.apply(&mut *self.ckpt.net));
+        self.run(epochs)
+    }
+
+    /// Runs a learning rate range test with `finder` on random batches of
+    /// the data, stopping early if the loss diverges.
+    /// - Trains a copy of the network with its own optimiser state, leaving
+    ///   the network, its optimiser and the generator as they were.
+    pub fn find_lr(&mut self, finder: &LrFinder) -> LrSweep {
+        let mut sweep = LrSweep::default();
+        if self.data.is_empty() {
+            return sweep;
+        }
+
+        span!(INFO, "find_lr", steps = finder.steps);
+        let copy = average(&[&*self.ckpt.net]);
+        let net = std::mem::replace(&mut self.ckpt.net, copy);
+        let mut momentum = T::boxed_and_zeroed();
+        let mut velocity = T::boxed_and_zeroed();
+        let mut rng = self.ckpt.rng;
+        let size = self.batch_size.min(self.data.len());
+        let (mut avg, mut best) = (0.0, f32::INFINITY);
+        let data = std::mem::replace(&mut self.data, Box::new(Empty));
+
+        for step in 0..finder.steps {
+            let batch: Vec<usize> = (0..size)
+                .map(|_| (rng.rand_u64() % data.len() as u64) as usize)
+                .collect();
+            let Ok(total) = self.accumulate(&*data, &batch) else {
+                break;
+            };
+
+            let beta = finder.smoothing;
+            avg = beta * avg + (1.0 - beta) * total / size as f32;
+            let loss = avg / (1.0 - beta.powi(step as i32 + 1));
+            if !loss.is_finite() || loss > finder.divergence * best {
+                break;
+            }
+            best = best.min(loss);
+
+            let lr = finder.lr(step);
+            sweep.push(lr, loss);
+            event!(DEBUG, lr, loss, "lr finder");
+            let adj = 1.0 / size as f32;
+            self.ckpt
+                .net
+                .adam(&self.grad, &mut momentum, &mut velocity, adj, lr);
+        }
+
+        self.ckpt.net = net;
+        self.data = data;
+        sweep
+    }
+
+    /// Runs a single epoch, or until a callback stops training,
+    /// returning its mean loss.
+    pub fn run_epoch(&mut self) -> io::Result<f32> {
+        span!(INFO, "epoch", epoch = self.epoch());
+        self.stopped = false;
+
+        if self.data.is_empty() {
+            return Ok(0.0);
+        }
+
+        // reshuffle from the identity, so that the order
+        // depends only on the state of the generator
+        for (i, idx) in self.order.iter_mut().enumerate() {
+            *idx = i;
+        }
+
+        if self.shuffle {
+            for i in (1..self.order.len()).rev() {
+                let j = (self.ckpt.rng.rand_u64() % (i as u64 + 1)) as usize;
+                self.order.swap(i, j);
+            }
+        }
+
+        let order = std::mem::take(&mut self.order);
+        let mut total = 0.0;
+        let mut seen = 0;
+
+        for batch in order.chunks(self.batch_size) {
+            let loss = match self.train_batch(batch) {
+                Ok(loss) => loss,
+                Err(err) => {
+                    event!(ERROR, %err, "non-finite value");
+                    self.order = order;
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+                }
+            };
+            total += loss * batch.len() as f32;
+            seen += batch.len();
+            event!(DEBUG, step = self.ckpt.step, loss, "batch");
+
+            if self.notify(loss, |cb, progress| cb.on_batch_end(progress)) == Control::Stop {
+                self.stopped = true;
+                break;
+            }
+        }
+
+        self.order = order;
+
+        let loss = total / seen as f32;
+        if self.stopped {
+            return Ok(loss);
+        }
+
+        self.end_epoch(loss)
+    }
+
+    /// Trains on every sample of `samples` in turn, in batches, as an
+    /// epoch, or until a callback stops training, returning their mean
+    /// loss, such as on a [`Stream`](crate::stream::Stream) too large
+    /// to hold as a [`Dataset`].
+    /// - Epochs are counted by whole passes over the stream, if the
+    ///   trainer has no data of its own.
+    /// - Samples of a [`NonFinite`] value are numbered by their
+    ///   position in the stream.
+    pub fn run_stream<I>(&mut self, samples: I) -> io::Result<f32>
+    where
+        I: IntoIterator<Item = io::Result<(T::InputType, T::OutputType)>>,
+    {
+        let mut samples = samples.into_iter();
+        let batch_size = self.batch_size;
+        let batches = std::iter::from_fn(|| {
+            let batch = samples
+                .by_ref()
+                .take(batch_size)
+                .collect::<io::Result<Vec<_>>>();
+            match batch {
+                Ok(batch) if batch.is_empty() => None,
+                batch => Some(batch),
+            }
+        });
+
+        self.run_batches(batches)
+    }
+
+    /// Takes a step on each batch of `batches` in turn, as an epoch, or
+    /// until a callback stops training, returning the mean loss of their
+    /// samples, as [`run_stream`](Self::run_stream), for batches built
+    /// ahead of training, such as by [`Batches`](crate::stream::Batches).
+    pub fn run_batches<B>(&mut self, batches: B) -> io::Result<f32>
+    where
+        B: IntoIterator<Item = io::Result<Vec<(T::InputType, T::OutputType)>>>,
+    {
+        span!(INFO, "stream", epoch = self.epoch());
+        self.stopped = false;
+
+        let mut indices: Vec<usize> = (0..self.batch_size).collect();
+        let (mut total, mut seen, mut batches_seen) = (0.0, 0, 0);
+
+        for batch in batches {
+            let batch = batch?;
+            if batch.is_empty() {
+                continue;
+            }
+            indices.extend(indices.len()..batch.len());
+
+            let loss = match self.train_on(&batch, &indices[..batch.len()]) {
+                Ok(loss) => loss,
+                Err(mut err) => {
+                    err.sample = err.sample.map(|i| seen + i);
+                    event!(ERROR, %err, "non-finite value");
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+                }
+            };
+            total += loss * batch.len() as f32;
+            seen += batch.len();
+            batches_seen += 1;
+            event!(DEBUG, step = self.ckpt.step, loss, "batch");
+
+            if self.notify(loss, |cb, progress| cb.on_batch_end(progress)) == Control::Stop {
+                self.stopped = true;
+                break;
+            }
+        }
+
+        if seen == 0 {
+            return Ok(0.0);
+        }
+
+        let loss = total / seen as f32;
+        if self.stopped {
+            return Ok(loss);
+        }
+
+        self.stream_batches = batches_seen;
+        self.end_epoch(loss)
+    }
+
+    /// Validates, updates the schedule and averages, notifies callbacks
+    /// and saves a checkpoint at the end of an epoch of mean loss `loss`.
+    fn end_epoch(&mut self, loss: f32) -> io::Result<f32> {
+        if self.data.is_empty() {
+            self.stream_epochs += 1;
+        }
+
+        let validation = self.validation.as_mut().map(|eval| {
+            span!(INFO, "validation");
+            eval(&self.ckpt.net, &self.loss)
+        });
+        event!(
+            INFO,
+            epoch = self.epoch(),
+            step = self.ckpt.step,
+            lr = self.ckpt.lr(),
+            loss,
+            val_loss = validation.map(|metrics| metrics.loss),
+            "epoch"
+        );
+
+        self.ckpt
+            .scheduler
+            .observe(validation.map_or(loss, |metrics| metrics.loss));
+
+        let epoch = self.epoch();
+        if let Some(swa) = self.swa.as_mut().filter(|swa| epoch >= swa.start) {
+            swa.count += 1;
+            let net = &*self.ckpt.net;
+            swa.net = Some(match &swa.net {
+                Some(avg) => {
+                    let t = 1.0 / swa.count as f32;
+                    lerp(&**avg, net, t)
+                }
+                None => average(&[net]),
+            });
+            event!(DEBUG, count = swa.count, "swa update");
+        }
+
+        let control = self.notify_with(loss, validation, |cb, progress| cb.on_epoch_end(progress));
+        if control == Control::Stop {
+            self.stopped = true;
+        }
+
+        let path = match &self.checkpoints {
+            Some((manager, every)) if self.epoch().is_multiple_of(*every) => {
+                span!(INFO, "checkpoint");
+                let path = manager.save(&self.ckpt)?;
+                if let Some(net) = self.ema.as_ref().and_then(|ema| ema.net.as_deref()) {
+                    manager.save_net(EMA, net)?;
+                }
+                event!(INFO, path = %path.display(), "saved checkpoint");
+                Some(path)
+            }
+            _ => None,
+        };
+
+        if let Some(path) = path {
+            let _ = self.notify(loss, |cb, progress| {
+                cb.on_checkpoint(progress, &path);
+                Control::Continue
+            });
+        }
+
+        Ok(loss)
+    }
+
+    fn notify<F>(&mut self, loss: f32, f: F) -> Control
+    where
+        F: FnMut(&mut dyn Callback<T>, &Progress<'_, T>) -> Control,
+    {
+        self.notify_with(loss, None, f)
+    }
+
+    /// Invokes `f` on every callback, returning whether any asked to stop.
+    fn notify_with<F>(&mut self, loss: f32, validation: Option<Metrics>, mut f: F) -> Control
+    where
+        F: FnMut(&mut dyn Callback<T>, &Progress<'_, T>) -> Control,
+    {
+        let progress = Progress {
+            step: self.ckpt.step,
+            epoch: self.epoch(),
+            batches_per_epoch: self.batches_per_epoch(),
+            batch_size: self.batch_size,
+            lr: self.ckpt.lr(),
+            loss,
+            validation,
+            net: &*self.ckpt.net,
+        };
+
+        let mut control = Control::Continue;
+        for callback in &mut self.callbacks {
+            if f(callback.as_mut(), &progress) == Control::Stop {
+                control = Control::Stop;
+            }
+        }
+
+        control
+    }
+
+    /// Takes one step of the optimiser on the samples at
+    /// indices `batch` of the data, returning their mean loss.
+    fn train_batch(&mut self, batch: &[usize]) -> Result<f32, NonFinite> {
+        // the data is taken out while training on it, leaving no allocation
+        let data = std::mem::replace(&mut self.data, Box::new(Empty));
+        let loss = self.train_on(&*data, batch);
+        self.data = data;
+        loss
+    }
+
+    /// As [`train_batch`](Self::train_batch), on the samples of `data`.
+    fn train_on(
+        &mut self,
+        data: &dyn Dataset<T::InputType, T::OutputType>,
+        batch: &[usize],
+    ) -> Result<f32, NonFinite> {
+        span!(DEBUG, "batch", size = batch.len());
+        let total = match self.augmented(data, batch) {
+            Some(samples) => {
+                let indices: Vec<usize> = (0..batch.len()).collect();
+                self.accumulate(&samples, &indices).map_err(|mut err| {
+                    err.sample = err.sample.map(|i| batch[i]);
+                    err
+                })?
+            }
+            None => self.accumulate(data, batch)?,
+        };
+
+        span!(DEBUG, "optimiser");
+        if let Some(seed) = self.deterministic {
+            let step = self.ckpt.step + 1;
+            seed_stochastic_rounding(seed ^ step.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        }
+
+        let adj = 1.0 / batch.len() as f32;
+        self.ckpt.adam(&self.grad, adj);
+
+        if let Some(mask) = &self.mask {
+            mask.apply(&mut *self.ckpt.net);
+        }
+
+        if let Some(qat) = &self.qat {
+            self.ckpt.net.clamp_qat("", qat);
+        }
+
+        if let Some(ema) = &mut self.ema {
+            match &mut ema.net {
+                Some(avg) => lerp_in_place(&mut **avg, &self.ckpt.net, 1.0 - ema.decay),
+                None => ema.net = Some(average(&[&*self.ckpt.net])),
+            }
+        }
+        Ok(adj * total)
+    }
+
+    /// Copies of the samples at indices `batch` of `data`, with their
+    /// weights, augmented, if the trainer has an augmentation.
+    fn augmented(
+        &mut self,
+        data: &dyn Dataset<T::InputType, T::OutputType>,
+        batch: &[usize],
+    ) -> Option<WeightedSamples<T>> {
+        let augment = self.augment.as_mut()?;
+        span!(DEBUG, "augment");
+
+        let mut samples: Vec<_> = batch
+            .iter()
+            .map(|&idx| data.get(idx).into_owned())
+            .collect();
+        augment.augment(&mut samples, &mut self.ckpt.rng);
+
+        let samples = samples
+            .into_iter()
+            .zip(batch)
+            .map(|((input, target), &idx)| (input, target, data.weight(idx)))
+            .collect();
+        Some(samples)
+    }
+
+    /// Accumulates the gradient of the samples at indices `batch`
+    /// of `data`, returning the sum of their losses.
+    fn accumulate(
+        &mut self,
+        data: &dyn Dataset<T::InputType, T::OutputType>,
+        batch: &[usize],
+    ) -> Result<f32, NonFinite> {
+        self.grad.visit_grads_mut("", &mut Zero);
+        let mut total = 0.0;
+
+        {
+            span!(DEBUG, "forward_backward");
+            for &idx in batch {
+                let sample = data.get(idx);
+                let (input, target) = sample.parts();
+                let net = &self.ckpt.net;
+                match (&mut self.layers, &self.qat) {
+                    (Some(layers), Some(qat)) => net.out_into_qat("", input, layers, qat),
+                    (Some(layers), None) => net.out_into(input, layers),
+                    (None, qat) => {
+                        let mut layers = Box::new(net.out_with_layers(input));
+                        if let Some(qat) = qat {
+                            net.out_into_qat("", input, &mut layers, qat);
+                        }
+                        self.layers = Some(layers);
+                    }
+                }
+                let layers = self.layers.as_deref().expect("layers were just computed");
+                let out = layers.output_layer_ref();
+                let (loss, err) = match &self.teacher {
+                    Some(teacher) => teacher(input, out, target),
+                    None => self.loss.loss(out, target),
+                };
+                let (loss, err) = match data.weight(idx) {
+                    1.0 => (loss, err),
+                    weight => (weight * loss, (self.weigh)(weight, err)),
+                };
+
+                if self.nan_guard {
+                    self.check_sample(idx, layers, loss)?;
+                }
+
+                total += loss;
+                let layers = self
+                    .layers
+                    .as_deref_mut()
+                    .expect("layers were just computed");
+                match &mut self.input_err {
+                    Some(input_err) => {
+                        net.backprop_into(input, &mut self.grad, &err, layers, input_err)
+                    }
+                    None => {
+                        let input_err = net.backprop(input, &mut self.grad, err, layers);
+                        self.input_err = Some(Box::new(input_err));
+                    }
+                }
+            }
+        }
+
+        if self.nan_guard {
+            let mut find = FindNonFinite::default();
+            self.grad.visit_grads("", &mut find);
+            if let Some(name) = find.0 {
+                return Err(self.non_finite(NonFiniteKind::Gradient, name, None));
+            }
+        }
+
+        Ok(total)
+    }
+
+    fn check_sample(&self, idx: usize, layers: &T::Layers, loss: f32) -> Result<(), NonFinite> {
+        let mut find = FindNonFinite::default();
+        self.ckpt.net.visit_activations("", layers, &mut find);
+        if let Some(name) = find.0 {
+            return Err(self.non_finite(NonFiniteKind::Activation, name, Some(idx)));
+        }
+
+        if !loss.is_finite() {
+            return Err(self.non_finite(NonFiniteKind::Loss, String::new(), Some(idx)));
+        }
+
+        Ok(())
+    }
+
+    fn non_finite(&self, kind: NonFiniteKind, name: String, sample: Option<usize>) -> NonFinite {
+        NonFinite {
+            kind,
+            name,
+            step: self.ckpt.step + 1,
+            sample,
+        }
+    }
+}
@@ -1,133 +1,438 @@
-use crate::activation::Activation;
+use alloc::vec::Vec;
+
+use crate::{activation::Activation, init::Distribution, Float, Rand, Real};
 
 /// Sparse representation of a vector, storing active
 /// indices instead of a value for each index in the vector.
+///
+/// There is no limit on the number of active indices, the
+/// capacity passed to [`with_capacity`](Self::with_capacity)
+/// only reserves space up front.
+///
+/// A vector created with [`sorted`](Self::sorted), or converted by
+/// [`sort_dedup`](Self::sort_dedup), keeps its indices sorted and
+/// deduplicated, so that [`contains`](Self::contains) and
+/// [`remove`](Self::remove) can binary search.
 #[repr(C)]
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, Eq)]
 pub struct SparseVector {
     inner: Vec<usize>,
+    sorted: bool,
+}
+
+impl PartialEq for SparseVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+/// Error returned when a sparse feature index is not
+/// less than the size of the input it indexes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeatureOutOfBounds {
+    pub index: usize,
+    pub size: usize,
+}
+
+impl core::fmt::Display for FeatureOutOfBounds {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "feature index {} out of bounds for input of size {}",
+            self.index, self.size
+        )
+    }
+}
+
+impl core::error::Error for FeatureOutOfBounds {}
+
+/// Error returned when converting a slice of the wrong
+/// length into a [`Vector`] or [`Matrix`](crate::Matrix).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthMismatch {
+    pub expected: usize,
+    pub found: usize,
 }
 
-impl std::ops::Add<SparseVector> for SparseVector {
+impl core::fmt::Display for LengthMismatch {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "expected {} elements, found {}",
+            self.expected, self.found
+        )
+    }
+}
+
+impl core::error::Error for LengthMismatch {}
+
+impl core::ops::Add<SparseVector> for SparseVector {
     type Output = SparseVector;
     fn add(mut self, mut rhs: SparseVector) -> Self::Output {
         self.inner.append(&mut rhs.inner);
+        if self.sorted {
+            self.sort_dedup();
+        }
         self
     }
 }
 
-impl std::ops::Deref for SparseVector {
+impl core::ops::Deref for SparseVector {
     type Target = [usize];
     fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
 
+impl FromIterator<usize> for SparseVector {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        Self {
+            inner: iter.into_iter().collect(),
+            sorted: false,
+        }
+    }
+}
+
+impl Extend<usize> for SparseVector {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        self.inner.extend(iter);
+        if self.sorted {
+            self.sort_dedup();
+        }
+    }
+}
+
+impl IntoIterator for SparseVector {
+    type Item = usize;
+    type IntoIter = alloc::vec::IntoIter<usize>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a SparseVector {
+    type Item = &'a usize;
+    type IntoIter = core::slice::Iter<'a, usize>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
 impl SparseVector {
+    pub fn from_slice(feats: &[usize]) -> Self {
+        Self {
+            inner: feats.to_vec(),
+            sorted: false,
+        }
+    }
+
     pub fn with_capacity(cap: usize) -> Self {
         Self {
             inner: Vec::with_capacity(cap),
+            sorted: false,
+        }
+    }
+
+    /// Empty vector that keeps its indices sorted and deduplicated.
+    pub fn sorted(cap: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(cap),
+            sorted: true,
+        }
+    }
+
+    pub fn is_sorted(&self) -> bool {
+        self.sorted
+    }
+
+    /// Sorts and deduplicates the indices, keeping
+    /// them sorted and deduplicated from then on.
+    pub fn sort_dedup(&mut self) {
+        self.inner.sort_unstable();
+        self.inner.dedup();
+        self.sorted = true;
+    }
+
+    /// Checks that every index is less than `size`.
+    pub fn check_bounds(&self, size: usize) -> Result<(), FeatureOutOfBounds> {
+        match self.inner.iter().find(|&&idx| idx >= size) {
+            Some(&index) => Err(FeatureOutOfBounds { index, size }),
+            None => Ok(()),
         }
     }
 
+    /// Adds an index, which is ignored if already
+    /// present when the vector is sorted.
     pub fn push(&mut self, val: usize) {
-        self.inner.push(val);
+        if self.sorted {
+            if let Err(pos) = self.inner.binary_search(&val) {
+                self.inner.insert(pos, val);
+            }
+        } else {
+            self.inner.push(val);
+        }
+    }
+
+    pub fn contains(&self, val: usize) -> bool {
+        if self.sorted {
+            self.inner.binary_search(&val).is_ok()
+        } else {
+            self.inner.contains(&val)
+        }
+    }
+
+    /// Removes the first occurrence of an index,
+    /// returning whether it was present.
+    pub fn remove(&mut self, val: usize) -> bool {
+        let pos = if self.sorted {
+            self.inner.binary_search(&val).ok()
+        } else {
+            self.inner.iter().position(|&x| x == val)
+        };
+
+        if let Some(pos) = pos {
+            self.inner.remove(pos);
+        }
+
+        pos.is_some()
     }
 }
 
-/// `N`-Dimensional Vector Type.
+/// Sparse representation of a vector, storing active
+/// indices along with their value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightedSparseVector {
+    inner: Vec<(usize, f32)>,
+}
+
+impl core::ops::Add<WeightedSparseVector> for WeightedSparseVector {
+    type Output = WeightedSparseVector;
+    fn add(mut self, mut rhs: WeightedSparseVector) -> Self::Output {
+        self.inner.append(&mut rhs.inner);
+        self
+    }
+}
+
+impl core::ops::Deref for WeightedSparseVector {
+    type Target = [(usize, f32)];
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl From<SparseVector> for WeightedSparseVector {
+    fn from(vec: SparseVector) -> Self {
+        Self {
+            inner: vec.iter().map(|&idx| (idx, 1.0)).collect(),
+        }
+    }
+}
+
+impl WeightedSparseVector {
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            inner: Vec::with_capacity(cap),
+        }
+    }
+
+    pub fn push(&mut self, idx: usize, val: f32) {
+        self.inner.push((idx, val));
+    }
+
+    /// Checks that every index is less than `size`.
+    pub fn check_bounds(&self, size: usize) -> Result<(), FeatureOutOfBounds> {
+        match self.inner.iter().find(|&&(idx, _)| idx >= size) {
+            Some(&(index, _)) => Err(FeatureOutOfBounds { index, size }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// `N`-Dimensional Vector Type, with elements stored as `T`.
+/// - Formatted with only the first and last few elements if long,
+///   with `{:.2}` setting the number of decimals shown, 4 by default.
 #[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Vector<const N: usize> {
-    inner: [f32; N],
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "T: serde::Serialize",
+        deserialize = "T: serde::Deserialize<'de>"
+    ))
+)]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Vector<const N: usize, T: Float = f32> {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_array"))]
+    inner: [T; N],
 }
 
-impl<const N: usize> std::ops::Index<usize> for Vector<N> {
-    type Output = f32;
+impl<const N: usize, T: Float> core::fmt::Debug for Vector<N, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "Vector<{N}> ")?;
+        self.fmt_elements(f, |f, x| write!(f, "{x:?}"))
+    }
+}
+
+impl<const N: usize, T: Float> core::fmt::Display for Vector<N, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let precision = f.precision().unwrap_or(4);
+        let width = self.shown_width(precision);
+        self.fmt_elements(f, |f, x| write!(f, "{x:>width$.precision$}"))
+    }
+}
+
+impl<const N: usize, T: Float> AsRef<[T]> for Vector<N, T> {
+    fn as_ref(&self) -> &[T] {
+        &self.inner
+    }
+}
+
+impl<const N: usize, T: Float> AsMut<[T]> for Vector<N, T> {
+    fn as_mut(&mut self) -> &mut [T] {
+        &mut self.inner
+    }
+}
+
+// SAFETY: `#[repr(C)]` arrays of `T`s, without padding, so any
+// bytes which are valid `T`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<const N: usize, T: Float + bytemuck::Zeroable> bytemuck::Zeroable for Vector<N, T> {}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<const N: usize, T: Float + bytemuck::Pod> bytemuck::Pod for Vector<N, T> where
+    Self: Copy + 'static
+{
+}
+
+impl<const N: usize, T: Float> TryFrom<&[T]> for Vector<N, T> {
+    type Error = LengthMismatch;
+
+    fn try_from(slice: &[T]) -> Result<Self, Self::Error> {
+        let inner = slice.try_into().map_err(|_| LengthMismatch {
+            expected: N,
+            found: slice.len(),
+        })?;
+        Ok(Self::from_raw(inner))
+    }
+}
+
+impl<const N: usize, T: Float> TryFrom<Vec<T>> for Vector<N, T> {
+    type Error = LengthMismatch;
+
+    fn try_from(vec: Vec<T>) -> Result<Self, Self::Error> {
+        Self::try_from(vec.as_slice())
+    }
+}
+
+impl<const N: usize, T: Float> core::ops::Index<usize> for Vector<N, T> {
+    type Output = T;
     fn index(&self, index: usize) -> &Self::Output {
         &self.inner[index]
     }
 }
 
-impl<const N: usize> std::ops::IndexMut<usize> for Vector<N> {
+impl<const N: usize, T: Float> core::ops::IndexMut<usize> for Vector<N, T> {
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
         &mut self.inner[index]
     }
 }
 
-impl<const N: usize> std::ops::Add<Vector<N>> for Vector<N> {
-    type Output = Vector<N>;
-    fn add(mut self, rhs: Vector<N>) -> Self::Output {
+impl<const N: usize, T: Float> core::ops::Add<Vector<N, T>> for Vector<N, T> {
+    type Output = Vector<N, T>;
+    fn add(mut self, rhs: Vector<N, T>) -> Self::Output {
         for (i, j) in self.inner.iter_mut().zip(rhs.inner.iter()) {
-            *i += *j;
+            *i = T::from_compute(i.to_compute() + j.to_compute());
         }
 
         self
     }
 }
 
-impl<const N: usize> std::ops::Add<f32> for Vector<N> {
-    type Output = Vector<N>;
+impl<const N: usize, T: Float> core::ops::Add<f32> for Vector<N, T> {
+    type Output = Vector<N, T>;
     fn add(mut self, rhs: f32) -> Self::Output {
+        let rhs = T::Compute::from_f32(rhs);
         for i in self.inner.iter_mut() {
-            *i += rhs;
+            *i = T::from_compute(i.to_compute() + rhs);
         }
 
         self
     }
 }
 
-impl<const N: usize> std::ops::AddAssign<Vector<N>> for Vector<N> {
-    fn add_assign(&mut self, rhs: Vector<N>) {
+impl<const N: usize, T: Float> core::ops::AddAssign<Vector<N, T>> for Vector<N, T> {
+    fn add_assign(&mut self, rhs: Vector<N, T>) {
         for (i, j) in self.inner.iter_mut().zip(rhs.inner.iter()) {
-            *i += *j;
+            *i = T::from_compute(i.to_compute() + j.to_compute());
         }
     }
 }
 
-impl<const N: usize> std::ops::Div<Vector<N>> for Vector<N> {
-    type Output = Vector<N>;
-    fn div(mut self, rhs: Vector<N>) -> Self::Output {
+impl<const N: usize, T: Float> core::ops::Div<Vector<N, T>> for Vector<N, T> {
+    type Output = Vector<N, T>;
+    fn div(mut self, rhs: Vector<N, T>) -> Self::Output {
         for (i, j) in self.inner.iter_mut().zip(rhs.inner.iter()) {
-            *i /= *j;
+            *i = T::from_compute(i.to_compute() / j.to_compute());
         }
 
         self
     }
 }
 
-impl<const N: usize> std::ops::Mul<Vector<N>> for Vector<N> {
-    type Output = Vector<N>;
-    fn mul(mut self, rhs: Vector<N>) -> Self::Output {
+impl<const N: usize, T: Float> core::ops::Mul<Vector<N, T>> for Vector<N, T> {
+    type Output = Vector<N, T>;
+    fn mul(mut self, rhs: Vector<N, T>) -> Self::Output {
         for (i, j) in self.inner.iter_mut().zip(rhs.inner.iter()) {
-            *i *= *j;
+            *i = T::from_compute(i.to_compute() * j.to_compute());
         }
 
         self
     }
 }
 
-impl<const N: usize> std::ops::Mul<Vector<N>> for f32 {
-    type Output = Vector<N>;
-    fn mul(self, mut rhs: Vector<N>) -> Self::Output {
+impl<const N: usize, T: Float> core::ops::Mul<Vector<N, T>> for f32 {
+    type Output = Vector<N, T>;
+    fn mul(self, mut rhs: Vector<N, T>) -> Self::Output {
+        let lhs = T::Compute::from_f32(self);
         for i in rhs.inner.iter_mut() {
-            *i *= self;
+            *i = T::from_compute(lhs * i.to_compute());
         }
 
         rhs
     }
 }
 
-impl<const N: usize> std::ops::SubAssign<Vector<N>> for Vector<N> {
-    fn sub_assign(&mut self, rhs: Vector<N>) {
+impl<const N: usize, T: Float> core::ops::Mul<T> for Vector<N, T> {
+    type Output = Vector<N, T>;
+    fn mul(mut self, rhs: T) -> Self::Output {
+        let rhs = rhs.to_compute();
+        for i in self.inner.iter_mut() {
+            *i = T::from_compute(i.to_compute() * rhs);
+        }
+
+        self
+    }
+}
+
+impl<const N: usize, T: Float> core::ops::SubAssign<Vector<N, T>> for Vector<N, T> {
+    fn sub_assign(&mut self, rhs: Vector<N, T>) {
         for (i, j) in self.inner.iter_mut().zip(rhs.inner.iter()) {
-            *i -= *j;
+            *i = T::from_compute(i.to_compute() - j.to_compute());
         }
     }
 }
 
-impl<const N: usize> Vector<N> {
-    pub fn from_fn<F: FnMut(usize) -> f32>(mut f: F) -> Self {
+impl<const N: usize, T: Float> Vector<N, T> {
+    pub fn as_slice(&self) -> &[T] {
+        &self.inner
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        &mut self.inner
+    }
+
+    pub fn from_fn<F: FnMut(usize) -> T>(mut f: F) -> Self {
         let mut res = Self::zeroed();
 
         for i in 0..N {
@@ -137,19 +442,90 @@ impl<const N: usize> Vector<N> {
         res
     }
 
-    pub fn dot(&self, other: &Vector<N>) -> f32 {
-        let mut score = 0.0;
+    /// Elements drawn from `dist`, such as [`Normal`](crate::init::Normal).
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_fn(|_| T::from_f32(dist.sample(rng)))
+    }
+
+    pub fn dot(&self, other: &Vector<N, T>) -> T::Compute {
+        let mut score = T::Compute::ZERO;
         for (&i, &j) in self.inner.iter().zip(other.inner.iter()) {
-            score += i * j;
+            score += i.to_compute() * j.to_compute();
         }
 
         score
     }
 
-    pub fn out<T: Activation>(&self, other: &Vector<N>) -> f32 {
-        let mut score = 0.0;
+    /// Sum of the absolute values of the elements.
+    pub fn l1_norm(&self) -> T::Compute {
+        let mut norm = T::Compute::ZERO;
+        for &i in self.inner.iter() {
+            norm += abs(i.to_compute());
+        }
+
+        norm
+    }
+
+    /// Euclidean length.
+    pub fn l2_norm(&self) -> T::Compute {
+        self.dot(self).sqrt()
+    }
+
+    /// Absolute value of each element.
+    pub fn abs(mut self) -> Self {
+        for i in self.inner.iter_mut() {
+            *i = T::from_compute(abs(i.to_compute()));
+        }
+
+        self
+    }
+
+    /// Each element clamped to `[min, max]`.
+    pub fn clamp(mut self, min: f32, max: f32) -> Self {
+        let min = T::Compute::from_f32(min);
+        let max = T::Compute::from_f32(max);
+        for i in self.inner.iter_mut() {
+            *i = T::from_compute(i.to_compute().clamp(min, max));
+        }
+
+        self
+    }
+
+    /// Lesser of each pair of elements.
+    pub fn min(mut self, other: Vector<N, T>) -> Self {
+        for (i, j) in self.inner.iter_mut().zip(other.inner.iter()) {
+            if j.to_compute() < i.to_compute() {
+                *i = *j;
+            }
+        }
+
+        self
+    }
+
+    /// Greater of each pair of elements.
+    pub fn max(mut self, other: Vector<N, T>) -> Self {
+        for (i, j) in self.inner.iter_mut().zip(other.inner.iter()) {
+            *i = T::from_compute(i.to_compute().max(j.to_compute()));
+        }
+
+        self
+    }
+
+    /// Linear interpolation, `self` at `t = 0` and `other` at `t = 1`.
+    pub fn lerp(mut self, other: Vector<N, T>, t: f32) -> Self {
+        let t = T::Compute::from_f32(t);
+        for (i, j) in self.inner.iter_mut().zip(other.inner.iter()) {
+            let (a, b) = (i.to_compute(), j.to_compute());
+            *i = T::from_compute(a + t * (b - a));
+        }
+
+        self
+    }
+
+    pub fn out<A: Activation>(&self, other: &Vector<N, T>) -> T::Compute {
+        let mut score = T::Compute::ZERO;
         for (i, j) in self.inner.iter().zip(other.inner.iter()) {
-            score += T::activate(*i) * T::activate(*j);
+            score += A::activate(i.to_compute()) * A::activate(j.to_compute());
         }
 
         score
@@ -157,43 +533,144 @@ impl<const N: usize> Vector<N> {
 
     pub fn sqrt(mut self) -> Self {
         for i in self.inner.iter_mut() {
-            *i = i.sqrt();
+            *i = T::from_compute(i.to_compute().sqrt());
         }
 
         self
     }
 
-    pub const fn from_raw(inner: [f32; N]) -> Self {
+    pub const fn from_raw(inner: [T; N]) -> Self {
         Self { inner }
     }
 
     pub const fn zeroed() -> Self {
-        Self::from_raw([0.0; N])
+        Self::from_raw([T::ZERO; N])
     }
 
-    pub fn activate<T: Activation>(mut self) -> Self {
+    /// Converts each element to `T::Compute`, in which sums over
+    /// many vectors are accumulated without rounding after each.
+    pub fn to_compute(&self) -> Vector<N, T::Compute> {
+        Vector::from_fn(|i| self.inner[i].to_compute())
+    }
+
+    /// Rounds each element of `v` to `T`.
+    pub fn from_compute(v: &Vector<N, T::Compute>) -> Self {
+        Self::from_fn(|i| T::from_compute(v.inner[i]))
+    }
+
+    pub fn activate<A: Activation>(mut self) -> Self {
         for i in self.inner.iter_mut() {
-            *i = T::activate(*i);
+            *i = T::from_compute(A::activate(i.to_compute()));
         }
 
         self
     }
 
-    pub fn derivative<T: Activation>(mut self) -> Self {
+    pub fn derivative<A: Activation>(mut self) -> Self {
         for i in self.inner.iter_mut() {
-            *i = T::derivative(*i);
+            *i = T::from_compute(A::derivative(i.to_compute()));
         }
 
         self
     }
 
-    pub fn adam(&mut self, mut g: Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
-        const B1: f32 = 0.9;
-        const B2: f32 = 0.999;
+    /// Writes the elements shown, as `[a, b, ..., z]`, each by `element`.
+    pub(crate) fn fmt_elements<F>(
+        &self,
+        f: &mut core::fmt::Formatter,
+        mut element: F,
+    ) -> core::fmt::Result
+    where
+        F: FnMut(&mut core::fmt::Formatter, f32) -> core::fmt::Result,
+    {
+        f.write_str("[")?;
+        for (k, i) in shown(N).enumerate() {
+            if k > 0 {
+                f.write_str(", ")?;
+            }
+            match i {
+                Some(i) => element(f, self.inner[i].to_f32())?,
+                None => f.write_str("...")?,
+            }
+        }
+        f.write_str("]")
+    }
+
+    /// Widest of the elements shown, with `precision` decimals.
+    pub(crate) fn shown_width(&self, precision: usize) -> usize {
+        shown(N)
+            .flatten()
+            .map(|i| {
+                let mut width = Width(0);
+                let _ = core::fmt::Write::write_fmt(
+                    &mut width,
+                    format_args!("{:.precision$}", self.inner[i].to_f32()),
+                );
+                width.0
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn adam(
+        &mut self,
+        g: Vector<N, T::Compute>,
+        m: &mut Self,
+        v: &mut Self,
+        adj: f32,
+        lr: f32,
+    ) {
+        let b1 = T::Compute::from_f64(0.9);
+        let b2 = T::Compute::from_f64(0.999);
+        let eps = T::Compute::from_f64(0.000_000_01);
+        let one = T::Compute::ONE;
+        let adj = T::Compute::from_f32(adj);
+        let lr = T::Compute::from_f32(lr);
+
+        for i in 0..N {
+            let grad = adj * g.inner[i];
+            let mi = b1 * m.inner[i].to_compute() + (one - b1) * grad;
+            let vi = b2 * v.inner[i].to_compute() + (one - b2) * grad * grad;
+
+            m.inner[i] = T::from_update(mi);
+            v.inner[i] = T::from_update(vi);
+
+            let p = self.inner[i].to_compute();
+            self.inner[i] = T::from_update(p - lr * mi / (vi.sqrt() + eps));
+        }
+    }
+}
+
+fn abs<R: Real>(x: R) -> R {
+    if x < R::ZERO {
+        -x
+    } else {
+        x
+    }
+}
+
+/// Elements shown at each end of a long vector, or rows at each end of
+/// a long matrix, when formatted.
+const EDGE: usize = 3;
+
+/// Indices of the elements of `n` shown when formatted, in order, with
+/// `None` in place of those left out.
+pub(crate) fn shown(n: usize) -> impl Iterator<Item = Option<usize>> {
+    // leaving out a single element would save nothing
+    let elided = n > 2 * EDGE + 1;
+    (0..n).filter_map(move |i| match i {
+        _ if !elided || i < EDGE || i >= n - EDGE => Some(Some(i)),
+        EDGE => Some(None),
+        _ => None,
+    })
+}
+
+/// Counts the bytes written to it.
+struct Width(usize);
 
-        g = adj * g;
-        *m = B1 * *m + (1. - B1) * g;
-        *v = B2 * *v + (1. - B2) * g * g;
-        *self -= lr * *m / (v.sqrt() + 0.000_000_01);
+impl core::fmt::Write for Width {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.len();
+        Ok(())
     }
 }
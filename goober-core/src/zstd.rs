@@ -0,0 +1,158 @@
+//! Streaming [`Read`] adapter over [`ruzstd::FrameDecoder`], for reading
+//! datasets compressed with the `zstd` command line tool without
+//! decompressing them to disk first.
+//! - Concatenated and skippable frames are read in turn, and content
+//!   checksums are verified.
+//!
+//! ```no_run
+//! # use std::{fs::File, io::BufReader};
+//! # use goober::zstd;
+//! # fn main() -> Result<(), std::io::Error> {
+//! let file = BufReader::new(File::open("positions.bin.zst")?);
+//! let mut reader = zstd::Decoder::new(file);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    error::Error,
+    io::{self, Read},
+};
+
+use ruzstd::{BlockDecodingStrategy, FrameDecoder};
+
+use crate::stream::read_full;
+
+const MAGIC: u32 = 0xFD2F_B528;
+
+/// Magic numbers of skippable frames, ignoring the lowest four bits.
+const SKIPPABLE: u32 = 0x184D_2A50;
+
+fn corrupt(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("zstd: {message}"))
+}
+
+/// Error of `ruzstd`, as an [`io::ErrorKind::UnexpectedEof`] if caused
+/// by the input ending early, and otherwise as corrupt input.
+fn decode_error(err: impl Error) -> io::Error {
+    let mut source = err.source();
+    while let Some(err) = source {
+        match err.downcast_ref::<io::Error>() {
+            Some(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return io::ErrorKind::UnexpectedEof.into();
+            }
+            _ => source = err.source(),
+        }
+    }
+    corrupt(&err.to_string())
+}
+
+/// Whether `bytes` starts with the magic number of a zstd frame.
+pub fn is_compressed(bytes: &[u8]) -> bool {
+    bytes.len() >= 4 && u32::from_le_bytes(bytes[..4].try_into().unwrap()) == MAGIC
+}
+
+/// Decompresses every frame of `bytes`.
+pub fn decompress(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    Decoder::new(bytes).read_to_end(&mut out)?;
+    Ok(out)
+}
+
+/// Reader decompressing the zstd frames read from `R`.
+pub struct Decoder<R> {
+    reader: R,
+    frame: FrameDecoder,
+    /// Whether `frame` holds a frame not yet read to its end.
+    in_frame: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            frame: FrameDecoder::new(),
+            in_frame: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Starts the next frame, skipping skippable frames, returning
+    /// `false` at the end of the input.
+    fn next_frame(&mut self) -> io::Result<bool> {
+        loop {
+            let mut magic = [0; 4];
+            let len = read_full(&mut self.reader, &mut magic)?;
+            match len {
+                0 => return Ok(false),
+                4 => {}
+                _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+
+            let magic = u32::from_le_bytes(magic);
+            if magic & !0xF == SKIPPABLE {
+                let mut len = [0; 4];
+                self.reader.read_exact(&mut len)?;
+                let len = u32::from_le_bytes(len).into();
+                let skipped = io::copy(&mut self.reader.by_ref().take(len), &mut io::sink())?;
+                if skipped < len {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                continue;
+            }
+            if magic != MAGIC {
+                return Err(corrupt("not a zstd frame"));
+            }
+
+            let header = magic.to_le_bytes();
+            self.frame
+                .reset(header.chain(&mut self.reader))
+                .map_err(decode_error)?;
+            self.in_frame = true;
+            return Ok(true);
+        }
+    }
+
+    /// Verifies the content checksum of the frame just read, if it has one.
+    fn end_frame(&mut self) -> io::Result<()> {
+        self.in_frame = false;
+        match (
+            self.frame.get_checksum_from_data(),
+            self.frame.get_calculated_checksum(),
+        ) {
+            (Some(expected), Some(actual)) if expected != actual => {
+                Err(corrupt("checksum mismatch"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if !self.in_frame && !self.next_frame()? {
+                return Ok(0);
+            }
+
+            while self.frame.can_collect() < buf.len() && !self.frame.is_finished() {
+                let wanted = buf.len() - self.frame.can_collect();
+                self.frame
+                    .decode_blocks(&mut self.reader, BlockDecodingStrategy::UptoBytes(wanted))
+                    .map_err(decode_error)?;
+            }
+
+            match self.frame.read(buf)? {
+                0 => self.end_frame()?,
+                n => return Ok(n),
+            }
+        }
+    }
+}
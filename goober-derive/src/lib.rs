@@ -1,5 +1,5 @@
 use proc_macro2::{Ident, Span, TokenStream};
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
 #[proc_macro_derive(FeedForwardNetwork)]
@@ -7,43 +7,108 @@ pub fn network_utils(input: proc_macro::TokenStream) -> proc_macro::TokenStream
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
     let layer_name = Ident::new((name.to_string() + "Layer").as_str(), Span::call_site());
+    let grad_name = Ident::new((name.to_string() + "Grad").as_str(), Span::call_site());
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     let add_impl = gen_add_impl(&input.data);
     let layer_fields = gen_layer_fields(&input.data);
+    let grad_fields = gen_grad_fields(&input.data);
 
     let input_type = gen_input_type(&input.data);
     let output_type = gen_output_type(&input.data);
     let output_layer = gen_output_layer(&input.data);
 
     let adam_expr = gen_adam_expr(&input.data);
+    let visit_expr = gen_visit_expr(&input.data, quote!(visit_params));
+    let visit_mut_expr = gen_visit_expr(&input.data, quote!(visit_params_mut));
+    let visit_grads_expr = gen_visit_expr(&input.data, quote!(visit_grads));
+    let visit_grads_mut_expr = gen_visit_expr(&input.data, quote!(visit_grads_mut));
+    let trace_expr = gen_trace_expr(&input.data);
+    let visit_activations_expr = gen_visit_activations_expr(&input.data);
+    let summarize_expr = gen_summarize_expr(&input.data);
     let layer_exprs = gen_layer_exprs(&input.data);
     let layer_exprs_fields = gen_layer_exprs_fields(&input.data);
     let backprop_exprs = gen_backprop_exprs(&input.data);
+    let out_into_exprs = gen_out_into_exprs(&input.data);
+    let backprop_into_exprs = gen_backprop_into_exprs(&input.data);
+    let out_into_qat_exprs = gen_out_into_qat_exprs(&input.data);
+    let clamp_qat_expr = gen_clamp_qat_expr(&input.data);
+    let check_input_expr = gen_check_input_expr(&input.data);
 
     let expanded = quote! {
-        impl std::ops::AddAssign<& #name> for #name {
-            fn add_assign(&mut self, rhs: & #name) {
+        impl #impl_generics ::core::ops::AddAssign<& #name #ty_generics> for #name #ty_generics #where_clause {
+            fn add_assign(&mut self, rhs: & #name #ty_generics) {
                 #add_impl
             }
         }
 
-        pub struct #layer_name {
+        pub struct #layer_name #generics #where_clause {
             #layer_fields
         }
 
-        impl goober::OutputLayer<#output_type> for #layer_name {
+        impl #impl_generics goober::OutputLayer<#output_type> for #layer_name #ty_generics #where_clause {
             #output_layer
         }
 
-        impl goober::FeedForwardNetwork for #name {
+        pub struct #grad_name #generics #where_clause {
+            #grad_fields
+        }
+
+        impl #impl_generics goober::Gradient for #grad_name #ty_generics #where_clause {
+            fn visit_grads<__InternalVisitor: goober::ParamVisitor>(&self, prefix: &str, visitor: &mut __InternalVisitor) {
+                use goober::Gradient as __InternalGradient;
+                #visit_grads_expr
+            }
+
+            fn visit_grads_mut<__InternalVisitor: goober::ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut __InternalVisitor) {
+                use goober::Gradient as __InternalGradient;
+                #visit_grads_mut_expr
+            }
+        }
+
+        impl #impl_generics goober::FeedForwardNetwork for #name #ty_generics #where_clause {
             type InputType = #input_type;
             type OutputType = #output_type;
-            type Layers = #layer_name;
+            type Layers = #layer_name #ty_generics;
+            type Grad = #grad_name #ty_generics;
 
-            fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+            fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
                 #adam_expr
             }
 
+            fn visit_params<__InternalVisitor: goober::ParamVisitor>(&self, prefix: &str, visitor: &mut __InternalVisitor) {
+                #visit_expr
+            }
+
+            fn visit_params_mut<__InternalVisitor: goober::ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut __InternalVisitor) {
+                #visit_mut_expr
+            }
+
+            fn trace(
+                &self,
+                prefix: &str,
+                input: &str,
+                graph: &mut goober::Graph,
+            ) -> Result<goober::__alloc::string::String, goober::Unsupported> {
+                let out = goober::__alloc::string::String::from(input);
+                #trace_expr
+                Ok(out)
+            }
+
+            fn summarize(&self, prefix: &str, summary: &mut goober::summary::Summary) {
+                #summarize_expr
+            }
+
+            fn visit_activations<__InternalVisitor: goober::ActivationVisitor>(
+                &self,
+                prefix: &str,
+                layers: &Self::Layers,
+                visitor: &mut __InternalVisitor,
+            ) {
+                #visit_activations_expr
+            }
+
             fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
                 use goober::OutputLayer as __InternalOutputLayer;
                 #layer_exprs
@@ -52,16 +117,52 @@ pub fn network_utils(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 }
             }
 
+            fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+                use goober::OutputLayer as __InternalOutputLayer;
+                #out_into_exprs
+            }
+
             fn backprop(
                 &self,
                 input: &Self::InputType,
-                grad: &mut Self,
+                grad: &mut Self::Grad,
                 err: Self::OutputType,
                 layers: &Self::Layers,
             ) -> Self::InputType {
                 use goober::OutputLayer as __InternalOutputLayer;
                 #backprop_exprs
             }
+
+            fn backprop_into(
+                &self,
+                input: &Self::InputType,
+                grad: &mut Self::Grad,
+                err: &Self::OutputType,
+                layers: &mut Self::Layers,
+                input_err: &mut Self::InputType,
+            ) {
+                use goober::OutputLayer as __InternalOutputLayer;
+                #backprop_into_exprs
+            }
+
+            fn out_into_qat(
+                &self,
+                prefix: &str,
+                input: &Self::InputType,
+                layers: &mut Self::Layers,
+                qat: &goober::qat::Qat,
+            ) {
+                use goober::OutputLayer as __InternalOutputLayer;
+                #out_into_qat_exprs
+            }
+
+            fn clamp_qat(&mut self, prefix: &str, qat: &goober::qat::Qat) {
+                #clamp_qat_expr
+            }
+
+            fn check_input(&self, input: &Self::InputType) -> Result<(), goober::FeatureOutOfBounds> {
+                #check_input_expr
+            }
         }
     };
 
@@ -97,6 +198,29 @@ fn gen_layer_fields(data: &Data) -> TokenStream {
             let ty = &f.ty;
             quote!(#name: <#ty as goober::FeedForwardNetwork>::Layers,)
         });
+        // the error of the output of every layer but the last,
+        // written in place by the next layer in `backprop_into`
+        let errs = fields.named.iter().rev().skip(1).map(|f| {
+            let err = err_ident(f);
+            let ty = &f.ty;
+            quote!(#err: <#ty as goober::FeedForwardNetwork>::OutputType,)
+        });
+        quote!(#(#recurse)* #(#errs)*)
+    })
+}
+
+/// Field of the derived layers holding the error of the output of `f`.
+fn err_ident(f: &syn::Field) -> Ident {
+    format_ident!("__err_{}", f.ident.as_ref().unwrap())
+}
+
+fn gen_grad_fields(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let ty = &f.ty;
+            quote!(#name: <#ty as goober::FeedForwardNetwork>::Grad,)
+        });
         quote!(#(#recurse)*)
     })
 }
@@ -117,8 +241,8 @@ fn gen_output_layer(data: &Data) -> TokenStream {
         let name = &f1.ident;
         let ty = &f1.ty;
         quote! {
-            fn output_layer(&self) -> <#ty as goober::FeedForwardNetwork>::OutputType {
-                self.#name.output_layer()
+            fn output_layer_ref(&self) -> &<#ty as goober::FeedForwardNetwork>::OutputType {
+                self.#name.output_layer_ref()
             }
         }
     })
@@ -132,6 +256,13 @@ fn gen_input_type(data: &Data) -> TokenStream {
     })
 }
 
+fn gen_check_input_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let name = &fields.named.first().unwrap().ident;
+        quote!(self.#name.check_input(input))
+    })
+}
+
 fn gen_adam_expr(data: &Data) -> TokenStream {
     struct_with_fields_only!(|data, fields| {
         let recurse = fields.named.iter().map(|f| {
@@ -142,13 +273,61 @@ fn gen_adam_expr(data: &Data) -> TokenStream {
     })
 }
 
+fn gen_visit_expr(data: &Data, method: TokenStream) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let name_str = name.as_ref().unwrap().to_string();
+            quote!(self.#name.#method(&goober::param_name(prefix, #name_str), visitor);)
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_trace_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let name_str = name.as_ref().unwrap().to_string();
+            quote! {
+                let out = self.#name.trace(&goober::param_name(prefix, #name_str), &out, graph)?;
+            }
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_summarize_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let name_str = name.as_ref().unwrap().to_string();
+            quote!(self.#name.summarize(&goober::param_name(prefix, #name_str), summary);)
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_visit_activations_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let name_str = name.as_ref().unwrap().to_string();
+            quote! {
+                self.#name.visit_activations(&goober::param_name(prefix, #name_str), &layers.#name, visitor);
+            }
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
 fn gen_layer_exprs(data: &Data) -> TokenStream {
     struct_with_fields_only!(|data, fields| {
         let mut prev = &None;
         let recurse = fields.named.iter().enumerate().map(|(i, f)| {
             let name = &f.ident;
             let res = if i > 0 {
-                quote!(let #name = self.#name.out_with_layers(&#prev.output_layer());)
+                quote!(let #name = self.#name.out_with_layers(#prev.output_layer_ref());)
             } else {
                 quote!(let #name = self.#name.out_with_layers(input);)
             };
@@ -161,11 +340,18 @@ fn gen_layer_exprs(data: &Data) -> TokenStream {
 
 fn gen_layer_exprs_fields(data: &Data) -> TokenStream {
     struct_with_fields_only!(|data, fields| {
+        // errors first, as they are initialised from the outputs
+        // of layers which are then moved
+        let errs = fields.named.iter().rev().skip(1).map(|f| {
+            let name = &f.ident;
+            let err = err_ident(f);
+            quote!(#err: #name.output_layer(),)
+        });
         let recurse = fields.named.iter().map(|f| {
             let name = &f.ident;
             quote!(#name,)
         });
-        quote!(#(#recurse)*)
+        quote!(#(#errs)* #(#recurse)*)
     })
 }
 
@@ -175,7 +361,7 @@ fn gen_backprop_exprs(data: &Data) -> TokenStream {
         let mut list = fields.named.iter().enumerate().map(|(i, f)| {
                 let name = &f.ident;
                 let res = if i > 0 {
-                    quote!(let err = self.#name.backprop(&layers.#prev.output_layer(), &mut grad.#name, err, &layers.#name);)
+                    quote!(let err = self.#name.backprop(layers.#prev.output_layer_ref(), &mut grad.#name, err, &layers.#name);)
                 } else {
                     quote!(self.#name.backprop(input, &mut grad.#name, err, &layers.#name))
                 };
@@ -188,3 +374,77 @@ fn gen_backprop_exprs(data: &Data) -> TokenStream {
         quote!(#(#recurse)*)
     })
 }
+
+fn gen_out_into_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let mut prev = &None;
+        let recurse = fields.named.iter().enumerate().map(|(i, f)| {
+            let name = &f.ident;
+            let res = if i > 0 {
+                quote!(self.#name.out_into(layers.#prev.output_layer_ref(), &mut layers.#name);)
+            } else {
+                quote!(self.#name.out_into(input, &mut layers.#name);)
+            };
+            prev = name;
+            res
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_backprop_into_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let fields: Vec<_> = fields.named.iter().collect();
+        let last = fields.len() - 1;
+        let recurse = (0..fields.len()).rev().map(|i| {
+            let name = &fields[i].ident;
+            // each layer reads the error of its output from the field the
+            // next layer wrote it to, and writes the error of its input to
+            // the field of the previous layer, so nothing is copied
+            let err = if i == last {
+                quote!(err)
+            } else {
+                let err = err_ident(fields[i]);
+                quote!(&layers.#err)
+            };
+            if i > 0 {
+                let prev = &fields[i - 1].ident;
+                let prev_err = err_ident(fields[i - 1]);
+                quote!(self.#name.backprop_into(layers.#prev.output_layer_ref(), &mut grad.#name, #err, &mut layers.#name, &mut layers.#prev_err);)
+            } else {
+                quote!(self.#name.backprop_into(input, &mut grad.#name, #err, &mut layers.#name, input_err);)
+            }
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_out_into_qat_exprs(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let mut prev = &None;
+        let recurse = fields.named.iter().enumerate().map(|(i, f)| {
+            let name = &f.ident;
+            let name_str = name.as_ref().unwrap().to_string();
+            let prefix = quote!(&goober::param_name_cow(prefix, #name_str));
+            let res = if i > 0 {
+                quote!(self.#name.out_into_qat(#prefix, layers.#prev.output_layer_ref(), &mut layers.#name, qat);)
+            } else {
+                quote!(self.#name.out_into_qat(#prefix, input, &mut layers.#name, qat);)
+            };
+            prev = name;
+            res
+        });
+        quote!(#(#recurse)*)
+    })
+}
+
+fn gen_clamp_qat_expr(data: &Data) -> TokenStream {
+    struct_with_fields_only!(|data, fields| {
+        let recurse = fields.named.iter().map(|f| {
+            let name = &f.ident;
+            let name_str = name.as_ref().unwrap().to_string();
+            quote!(self.#name.clamp_qat(&goober::param_name(prefix, #name_str), qat);)
+        });
+        quote!(#(#recurse)*)
+    })
+}
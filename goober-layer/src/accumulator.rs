@@ -0,0 +1,86 @@
+use goober_core::{activation::Activation, Float, SparseVector, Vector};
+
+use crate::SparseConnected;
+
+/// Pre-activation output of a [`SparseConnected`] layer, which can be
+/// updated incrementally as features are added and removed, rather
+/// than recomputed from scratch.
+#[derive(Clone, Copy)]
+pub struct Accumulator<'a, T: Activation, const M: usize, const N: usize, F: Float = f32> {
+    layer: &'a SparseConnected<T, M, N, F>,
+    values: Vector<N, F>,
+}
+
+impl<'a, T: Activation, const M: usize, const N: usize, F: Float> Accumulator<'a, T, M, N, F> {
+    /// Accumulator with no active features.
+    pub fn new(layer: &'a SparseConnected<T, M, N, F>) -> Self {
+        Self {
+            layer,
+            values: layer.bias(),
+        }
+    }
+
+    pub fn from_features(layer: &'a SparseConnected<T, M, N, F>, feats: &SparseVector) -> Self {
+        let mut res = Self::new(layer);
+        res.refresh(feats);
+        res
+    }
+
+    pub fn add_feature(&mut self, idx: usize) {
+        self.values += self.layer.weights_row(idx);
+    }
+
+    pub fn remove_feature(&mut self, idx: usize) {
+        self.values -= self.layer.weights_row(idx);
+    }
+
+    /// Recomputes the accumulator from the full list of active features.
+    pub fn refresh(&mut self, feats: &SparseVector) {
+        self.values = self.layer.bias();
+        for &feat in feats.iter() {
+            self.add_feature(feat);
+        }
+    }
+
+    pub fn values(&self) -> Vector<N, F> {
+        self.values
+    }
+
+    /// Activated output, equivalent to the output of the layer
+    /// given the currently active features.
+    pub fn out(&self) -> Vector<N, F> {
+        self.values.activate::<T>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Accumulator;
+
+    #[test]
+    fn accumulator() {
+        use crate::SparseConnected;
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector};
+
+        let layer: SparseConnected<ReLU, 8, 4> =
+            SparseConnected::from_fn(|i, j| ((i * 4 + j) % 5) as f32 - 2.0, |i| i as f32);
+
+        let mut feats = SparseVector::with_capacity(8);
+        feats.push(1);
+        feats.push(6);
+
+        let mut acc = Accumulator::from_features(&layer, &feats);
+        assert_eq!(acc.out(), layer.out(&feats));
+
+        acc.add_feature(3);
+        acc.remove_feature(1);
+
+        let mut updated = SparseVector::with_capacity(8);
+        updated.push(6);
+        updated.push(3);
+        assert_eq!(acc.out(), layer.out(&updated));
+
+        acc.refresh(&feats);
+        assert_eq!(acc.out(), layer.out(&feats));
+    }
+}
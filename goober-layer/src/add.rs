@@ -1,17 +1,24 @@
-use goober_core::{FeedForwardNetwork, OutputLayer};
+use alloc::string::String;
+
+use goober_core::{
+    param_name, param_name_cow, qat::Qat, summary::Summary, ActivationVisitor, FeatureOutOfBounds,
+    FeedForwardNetwork, Gradient, Graph, Op, OutputLayer, ParamVisitor, ParamVisitorMut,
+    Unsupported,
+};
 
 /// Adds two sub-networks that have common inputs and outputs.
 #[repr(C)]
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Add<A, B> {
     a: A,
     b: B,
 }
 
-impl<A, B> std::ops::AddAssign<&Add<A, B>> for Add<A, B>
+impl<A, B> core::ops::AddAssign<&Add<A, B>> for Add<A, B>
 where
-    for<'a> A: FeedForwardNetwork + std::ops::AddAssign<&'a A>,
-    for<'a> B: FeedForwardNetwork + std::ops::AddAssign<&'a B>,
+    for<'a> A: FeedForwardNetwork + core::ops::AddAssign<&'a A>,
+    for<'a> B: FeedForwardNetwork + core::ops::AddAssign<&'a B>,
 {
     fn add_assign(&mut self, rhs: &Add<A, B>) {
         self.a += &rhs.a;
@@ -26,16 +33,44 @@ where
 {
     a: A::Layers,
     b: B::Layers,
+    /// Sum of the outputs of both sub-networks.
+    out: A::OutputType,
 }
 
 impl<A, B> OutputLayer<A::OutputType> for AddLayers<A, B>
 where
     A: FeedForwardNetwork,
     B: FeedForwardNetwork<OutputType = A::OutputType>,
-    A::OutputType: std::ops::Add<A::OutputType, Output = A::OutputType>,
+    A::OutputType: core::ops::Add<A::OutputType, Output = A::OutputType>,
 {
-    fn output_layer(&self) -> A::OutputType {
-        self.a.output_layer() + self.b.output_layer()
+    fn output_layer_ref(&self) -> &A::OutputType {
+        &self.out
+    }
+}
+
+/// Gradients of an [`Add`].
+pub struct AddGrad<A, B>
+where
+    A: FeedForwardNetwork,
+    B: FeedForwardNetwork,
+{
+    a: A::Grad,
+    b: B::Grad,
+}
+
+impl<A, B> Gradient for AddGrad<A, B>
+where
+    A: FeedForwardNetwork,
+    B: FeedForwardNetwork,
+{
+    fn visit_grads<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.a.visit_grads(&param_name(prefix, "a"), visitor);
+        self.b.visit_grads(&param_name(prefix, "b"), visitor);
+    }
+
+    fn visit_grads_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.a.visit_grads_mut(&param_name(prefix, "a"), visitor);
+        self.b.visit_grads_mut(&param_name(prefix, "b"), visitor);
     }
 }
 
@@ -43,29 +78,94 @@ impl<A, B> FeedForwardNetwork for Add<A, B>
 where
     A: FeedForwardNetwork,
     B: FeedForwardNetwork<InputType = A::InputType, OutputType = A::OutputType>,
-    A::OutputType: std::ops::Add<A::OutputType, Output = A::OutputType>,
-    A::InputType: std::ops::Add<A::InputType, Output = A::InputType>,
+    A::OutputType: core::ops::Add<A::OutputType, Output = A::OutputType>,
+    A::InputType: core::ops::Add<A::InputType, Output = A::InputType>,
 {
     type InputType = A::InputType;
     type OutputType = A::OutputType;
     type Layers = AddLayers<A, B>;
+    type Grad = AddGrad<A, B>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
         self.a.adam(&g.a, &mut m.a, &mut v.a, adj, lr);
         self.b.adam(&g.b, &mut m.b, &mut v.b, adj, lr);
     }
 
+    fn trace(&self, prefix: &str, input: &str, graph: &mut Graph) -> Result<String, Unsupported> {
+        let a = self.a.trace(&param_name(prefix, "a"), input, graph)?;
+        let b = self.b.trace(&param_name(prefix, "b"), input, graph)?;
+        let size = graph.nodes().last().unwrap().size;
+        Ok(graph.push(Op::Add, &[&a, &b], size))
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.a.visit_params(&param_name(prefix, "a"), visitor);
+        self.b.visit_params(&param_name(prefix, "b"), visitor);
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.a.visit_params_mut(&param_name(prefix, "a"), visitor);
+        self.b.visit_params_mut(&param_name(prefix, "b"), visitor);
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        self.a.summarize(&param_name(prefix, "a"), summary);
+        self.b.summarize(&param_name(prefix, "b"), summary);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        self.a
+            .visit_activations(&param_name(prefix, "a"), &layers.a, visitor);
+        self.b
+            .visit_activations(&param_name(prefix, "b"), &layers.b, visitor);
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        self.a.check_input(input)?;
+        self.b.check_input(input)
+    }
+
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
-        Self::Layers {
-            a: self.a.out_with_layers(input),
-            b: self.b.out_with_layers(input),
-        }
+        let a = self.a.out_with_layers(input);
+        let b = self.b.out_with_layers(input);
+        let out = a.output_layer() + b.output_layer();
+        Self::Layers { a, b, out }
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        self.a.out_into(input, &mut layers.a);
+        self.b.out_into(input, &mut layers.b);
+        layers.out = layers.a.output_layer() + layers.b.output_layer();
+    }
+
+    fn out_into_qat(
+        &self,
+        prefix: &str,
+        input: &Self::InputType,
+        layers: &mut Self::Layers,
+        qat: &Qat,
+    ) {
+        self.a
+            .out_into_qat(&param_name_cow(prefix, "a"), input, &mut layers.a, qat);
+        self.b
+            .out_into_qat(&param_name_cow(prefix, "b"), input, &mut layers.b, qat);
+        layers.out = layers.a.output_layer() + layers.b.output_layer();
+    }
+
+    fn clamp_qat(&mut self, prefix: &str, qat: &Qat) {
+        self.a.clamp_qat(&param_name(prefix, "a"), qat);
+        self.b.clamp_qat(&param_name(prefix, "b"), qat);
     }
 
     fn backprop(
         &self,
         input: &Self::InputType,
-        grad: &mut Self,
+        grad: &mut Self::Grad,
         out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType {
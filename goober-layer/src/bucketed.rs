@@ -0,0 +1,299 @@
+use alloc::{format, string::ToString};
+
+use goober_core::{
+    activation::Activation,
+    init::Distribution,
+    param_name,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeatureOutOfBounds, FeedForwardNetwork, Float, OutputLayer, ParamVisitor,
+    ParamVisitorMut, Rand, SparseVector, Vector,
+};
+
+use crate::{sparse::SparseConnectedLayers, SparseConnected};
+
+/// Set of [`SparseConnected`] layers, of which one is selected
+/// per input by a runtime bucket index.
+/// - `T` is the activation function used.
+/// - `M` is the size of the input vector.
+/// - `N` is the size of the output vector.
+/// - `B` is the number of buckets.
+/// - `F` is the type weights and activations are stored as.
+///
+/// The input is a pair of the bucket index (e.g. the king
+/// square bucket) and the active features.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct BucketedSparse<
+    T: Activation,
+    const M: usize,
+    const N: usize,
+    const B: usize,
+    F: Float = f32,
+> {
+    #[cfg_attr(feature = "serde", serde(with = "goober_core::serde_array"))]
+    buckets: [SparseConnected<T, M, N, F>; B],
+}
+
+/// Every parameter, those of each bucket in turn, as [`SparseConnected`].
+impl<T: Activation, const M: usize, const N: usize, const B: usize, F: Float> AsRef<[F]>
+    for BucketedSparse<T, M, N, B, F>
+{
+    fn as_ref(&self) -> &[F] {
+        // SAFETY: `#[repr(C)]`, with every field an array of `F`s, so the
+        // parameters are laid out contiguously, without padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), B * (M * N + N)) }
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const B: usize, F: Float> AsMut<[F]>
+    for BucketedSparse<T, M, N, B, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        // SAFETY: see `as_ref`.
+        unsafe { core::slice::from_raw_parts_mut((self as *mut Self).cast(), B * (M * N + N)) }
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        const M: usize,
+        const N: usize,
+        const B: usize,
+        F: Float + bytemuck::Zeroable,
+    > bytemuck::Zeroable for BucketedSparse<T, M, N, B, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, const B: usize, F: Float + bytemuck::Pod>
+    bytemuck::Pod for BucketedSparse<T, M, N, B, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, const M: usize, const N: usize, const B: usize, F: Float>
+    core::ops::AddAssign<&BucketedSparse<T, M, N, B, F>> for BucketedSparse<T, M, N, B, F>
+{
+    fn add_assign(&mut self, rhs: &BucketedSparse<T, M, N, B, F>) {
+        for (a, b) in self.buckets.iter_mut().zip(rhs.buckets.iter()) {
+            *a += b;
+        }
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const B: usize, F: Float>
+    BucketedSparse<T, M, N, B, F>
+{
+    pub const fn zeroed() -> Self {
+        Self::from_raw([SparseConnected::zeroed(); B])
+    }
+
+    pub const fn from_raw(buckets: [SparseConnected<T, M, N, F>; B]) -> Self {
+        Self { buckets }
+    }
+
+    /// Weights `w(bucket, i, j)` from input `i` to output `j` of each
+    /// bucket, and biases `b(bucket, j)`.
+    pub fn from_fn<WeightFn, BiasFn>(mut w: WeightFn, mut b: BiasFn) -> Self
+    where
+        WeightFn: FnMut(usize, usize, usize) -> F,
+        BiasFn: FnMut(usize, usize) -> F,
+    {
+        Self::from_raw(core::array::from_fn(|bucket| {
+            SparseConnected::from_fn(|i, j| w(bucket, i, j), |j| b(bucket, j))
+        }))
+    }
+
+    /// Weights of every bucket drawn from `dist`, such as
+    /// [`Normal`](goober_core::init::Normal), and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(core::array::from_fn(|_| {
+            SparseConnected::random(rng, &dist)
+        }))
+    }
+
+    /// Weights of every bucket drawn from
+    /// [`glorot_uniform`](goober_core::init::glorot_uniform), and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(core::array::from_fn(|_| {
+            SparseConnected::glorot_uniform(rng)
+        }))
+    }
+
+    /// Weights of every bucket drawn from
+    /// [`glorot_normal`](goober_core::init::glorot_normal), and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(core::array::from_fn(|_| {
+            SparseConnected::glorot_normal(rng)
+        }))
+    }
+
+    /// Weights of every bucket drawn from [`he_uniform`](goober_core::init::he_uniform),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(core::array::from_fn(|_| SparseConnected::he_uniform(rng)))
+    }
+
+    /// Weights of every bucket drawn from [`he_normal`](goober_core::init::he_normal),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(core::array::from_fn(|_| SparseConnected::he_normal(rng)))
+    }
+
+    pub fn bucket(&self, idx: usize) -> &SparseConnected<T, M, N, F> {
+        &self.buckets[idx]
+    }
+
+    pub fn bucket_mut(&mut self, idx: usize) -> &mut SparseConnected<T, M, N, F> {
+        &mut self.buckets[idx]
+    }
+}
+
+pub struct BucketedSparseLayers<const N: usize, F: Float = f32> {
+    out: SparseConnectedLayers<N, F>,
+}
+
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for BucketedSparseLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        self.out.output_layer_ref()
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const B: usize, F: Float> FeedForwardNetwork
+    for BucketedSparse<T, M, N, B, F>
+{
+    type InputType = (usize, SparseVector);
+    type OutputType = Vector<N, F>;
+    type Layers = BucketedSparseLayers<N, F>;
+    type Grad = BucketedSparse<T, M, N, B, F::Compute>;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+        for i in 0..B {
+            self.buckets[i].adam(&g.buckets[i], &mut m.buckets[i], &mut v.buckets[i], adj, lr);
+        }
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            bucket.visit_params(&param_name(prefix, &i.to_string()), visitor);
+        }
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        for (i, bucket) in self.buckets.iter_mut().enumerate() {
+            bucket.visit_params_mut(&param_name(prefix, &i.to_string()), visitor);
+        }
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("(bucket, sparse [{M}])"), format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        self.buckets[0].visit_activations(prefix, &layers.out, visitor);
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        if input.0 >= B {
+            return Err(FeatureOutOfBounds {
+                index: input.0,
+                size: B,
+            });
+        }
+
+        self.buckets[input.0].check_input(&input.1)
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            out: self.buckets[input.0].out_with_layers(&input.1),
+        }
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        self.buckets[input.0].out_into(&input.1, &mut layers.out);
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        let bucket = input.0;
+        self.buckets[bucket].backprop(&input.1, &mut grad.buckets[bucket], out_err, &layers.out);
+        (bucket, SparseVector::with_capacity(0))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BucketedSparse;
+
+    #[test]
+    fn bucketed_sparse() {
+        use crate::SparseConnected;
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector, Vector};
+
+        let layer: BucketedSparse<ReLU, 4, 2, 2> = BucketedSparse::from_raw([
+            SparseConnected::from_fn(|i, j| (i + j) as f32, |_| 0.0),
+            SparseConnected::from_fn(|i, j| (10 * i + j) as f32, |_| 1.0),
+        ]);
+
+        let mut input = SparseVector::with_capacity(2);
+        input.push(1);
+        input.push(3);
+
+        assert_eq!(layer.out(&(0, input.clone())), Vector::from_raw([4.0, 6.0]));
+        assert_eq!(
+            layer.out(&(1, input.clone())),
+            Vector::from_raw([41.0, 43.0])
+        );
+
+        let mut grad = BucketedSparse::zeroed();
+        let input = (1, input);
+        let layers = layer.out_with_layers(&input);
+        layer.backprop(&input, &mut grad, Vector::from_raw([1.0, 1.0]), &layers);
+
+        assert_eq!(grad.bucket(0).weights_row(1), Vector::from_raw([0.0, 0.0]));
+        assert_eq!(grad.bucket(1).weights_row(1), Vector::from_raw([1.0, 1.0]));
+        assert_eq!(grad.bucket(1).bias(), Vector::from_raw([1.0, 1.0]));
+    }
+
+    #[test]
+    fn check_input() {
+        use goober_core::{activation::ReLU, FeatureOutOfBounds, FeedForwardNetwork, SparseVector};
+
+        let layer = BucketedSparse::<ReLU, 4, 2, 2>::zeroed();
+        let input = SparseVector::from_slice(&[1, 3]);
+        assert_eq!(layer.check_input(&(1, input.clone())), Ok(()));
+        assert_eq!(
+            layer.check_input(&(2, input)),
+            Err(FeatureOutOfBounds { index: 2, size: 2 })
+        );
+        assert_eq!(
+            layer.check_input(&(0, SparseVector::from_slice(&[4]))),
+            Err(FeatureOutOfBounds { index: 4, size: 4 })
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use goober_core::{ActivationVisitor, FeedForwardNetwork, Float, Param, ParamVisitor};
+
+use crate::quantized::largest_scale;
+
+/// Largest magnitudes of the weights and outputs of a layer, as recorded
+/// by [`calibrate`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LayerRange {
+    pub name: String,
+    pub max_weight: f32,
+    pub max_output: f32,
+}
+
+impl LayerRange {
+    /// Largest scale at which the weights are representable as `i16`s.
+    pub fn weight_scale(&self) -> i16 {
+        largest_scale(self.max_weight, i16::MAX.into()) as i16
+    }
+
+    /// Largest scale at which the outputs are representable as `i16`s.
+    pub fn output_scale(&self) -> i16 {
+        largest_scale(self.max_output, i16::MAX.into()) as i16
+    }
+
+    /// Largest scale at which both the weights and the outputs are
+    /// representable, as a sparse layer stores both at one scale.
+    pub fn scale(&self) -> i16 {
+        self.weight_scale().min(self.output_scale())
+    }
+}
+
+/// Ranges of each layer of a network, in the order their outputs
+/// are computed, from which to choose quantization scales.
+/// - Accumulators are not recorded, so a layer's own `calibrate`, such as
+///   [`SparseConnected::calibrate`](crate::SparseConnected::calibrate),
+///   which also keeps them in range, may choose a smaller scale.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Calibration {
+    layers: Vec<LayerRange>,
+}
+
+impl Calibration {
+    pub fn layers(&self) -> &[LayerRange] {
+        &self.layers
+    }
+
+    pub fn get(&self, layer: &str) -> Option<&LayerRange> {
+        self.layers.iter().find(|range| range.name == layer)
+    }
+
+    /// [`scale`](LayerRange::scale) of each layer, by name.
+    pub fn scales(&self) -> Vec<(String, i16)> {
+        self.layers
+            .iter()
+            .map(|range| (range.name.clone(), range.scale()))
+            .collect()
+    }
+
+    fn layer_mut(&mut self, layer: &str) -> &mut LayerRange {
+        let idx = match self.layers.iter().position(|range| range.name == layer) {
+            Some(idx) => idx,
+            None => {
+                self.layers.push(LayerRange {
+                    name: layer.to_string(),
+                    max_weight: 0.0,
+                    max_output: 0.0,
+                });
+                self.layers.len() - 1
+            }
+        };
+
+        &mut self.layers[idx]
+    }
+}
+
+/// Runs `inputs` through `net`, recording the largest magnitude of the
+/// outputs of each layer which visits its activations, and of its weights.
+/// - Outputs are recorded against the innermost layer enclosing them with
+///   parameters, so both sides of a perspective layer share its range.
+pub fn calibrate<T: FeedForwardNetwork>(net: &T, inputs: &[T::InputType]) -> Calibration {
+    let mut weights = MaxWeights::default();
+    net.visit_params("", &mut weights);
+
+    let mut record = Record {
+        weights: &weights.0,
+        calibration: Calibration::default(),
+    };
+
+    let mut layers = None;
+    for input in inputs {
+        let layers = match &mut layers {
+            Some(layers) => {
+                net.out_into(input, layers);
+                layers
+            }
+            None => layers.insert(net.out_with_layers(input)),
+        };
+        net.visit_activations("", layers, &mut record);
+    }
+
+    record.calibration
+}
+
+/// Largest magnitude of the parameters of each layer, by name.
+#[derive(Default)]
+struct MaxWeights(Vec<(String, f32)>);
+
+impl ParamVisitor for MaxWeights {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let layer = param.name.rsplit_once('.').map_or("", |(layer, _)| layer);
+        let max = param
+            .values
+            .iter()
+            .fold(0f32, |max, x| max.max(libm::fabsf(x.to_f32())));
+
+        match self.0.iter_mut().find(|(name, _)| name == layer) {
+            Some((_, prev)) => *prev = prev.max(max),
+            None => self.0.push((layer.to_string(), max)),
+        }
+    }
+}
+
+struct Record<'a> {
+    weights: &'a [(String, f32)],
+    calibration: Calibration,
+}
+
+impl Record<'_> {
+    /// Innermost layer with parameters enclosing the outputs named
+    /// `layer`, and the largest magnitude of its parameters.
+    fn owner<'a>(&self, layer: &'a str) -> (&'a str, f32) {
+        let mut prefix = layer;
+        loop {
+            if let Some(&(_, max)) = self.weights.iter().find(|(name, _)| name == prefix) {
+                return (prefix, max);
+            }
+            match prefix.rsplit_once('.') {
+                Some((parent, _)) => prefix = parent,
+                None => return (layer, 0.0),
+            }
+        }
+    }
+}
+
+impl ActivationVisitor for Record<'_> {
+    fn visit<F: Float>(&mut self, name: &str, values: &[F]) {
+        let (layer, max_weight) = self.owner(name);
+        let max = values
+            .iter()
+            .fold(0f32, |max, x| max.max(libm::fabsf(x.to_f32())));
+
+        let range = self.calibration.layer_mut(layer);
+        range.max_weight = max_weight;
+        range.max_output = range.max_output.max(max);
+    }
+}
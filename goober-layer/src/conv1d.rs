@@ -1,98 +1,248 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
-use goober_core::{FeedForwardNetwork, OutputLayer, Vector, activation::Activation};
+use alloc::{format, string::String};
 
-/// Applies a 1D Convolution from input dimension `M` to output dimension `N`.
+use goober_core::{
+    activation::Activation,
+    init::{self, Distribution},
+    param_name,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeedForwardNetwork, Float, Graph, Op, OutputLayer, Param, ParamMut,
+    ParamVisitor, ParamVisitorMut, Rand, Unsupported, Vector,
+};
+
+/// Applies a 1D Convolution from input dimension `M` to output dimension `N`,
+/// storing weights and activations as `F`.
 #[repr(C)]
 #[derive(Clone, Copy)]
-pub struct Conv1D<T, const M: usize, const N: usize> {
-    weights: Vector<M>,
-    bias: Vector<N>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct Conv1D<T, const M: usize, const N: usize, F: Float = f32> {
+    weights: Vector<M, F>,
+    bias: Vector<N, F>,
     phantom: PhantomData<T>,
 }
 
-impl<T, const M: usize, const N: usize> std::ops::AddAssign<&Conv1D<T, M, N>> for Conv1D<T, M, N> {
-    fn add_assign(&mut self, rhs: &Conv1D<T, M, N>) {
+/// Every parameter, the kernel, then the biases.
+impl<T, const M: usize, const N: usize, F: Float> AsRef<[F]> for Conv1D<T, M, N, F> {
+    fn as_ref(&self) -> &[F] {
+        // SAFETY: `#[repr(C)]`, with every field an array of `F`s, so the
+        // parameters are laid out contiguously, without padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), M + N) }
+    }
+}
+
+impl<T, const M: usize, const N: usize, F: Float> AsMut<[F]> for Conv1D<T, M, N, F> {
+    fn as_mut(&mut self) -> &mut [F] {
+        // SAFETY: see `as_ref`.
+        unsafe { core::slice::from_raw_parts_mut((self as *mut Self).cast(), M + N) }
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<T, const M: usize, const N: usize, F: Float + bytemuck::Zeroable> bytemuck::Zeroable
+    for Conv1D<T, M, N, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T, const M: usize, const N: usize, F: Float + bytemuck::Pod> bytemuck::Pod
+    for Conv1D<T, M, N, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T, const M: usize, const N: usize, F: Float> core::ops::AddAssign<&Conv1D<T, M, N, F>>
+    for Conv1D<T, M, N, F>
+{
+    fn add_assign(&mut self, rhs: &Conv1D<T, M, N, F>) {
         self.weights += rhs.weights;
         self.bias += rhs.bias;
     }
 }
 
-impl<T, const M: usize, const N: usize> Conv1D<T, M, N> {
-    pub fn from_raw(weights: Vector<M>, bias: Vector<N>) -> Self {
+impl<T, const M: usize, const N: usize, F: Float> Conv1D<T, M, N, F> {
+    pub fn from_raw(weights: Vector<M, F>, bias: Vector<N, F>) -> Self {
         Self { weights, bias, phantom: PhantomData }
     }
 
     pub const fn zeroed() -> Self {
         Self { weights: Vector::zeroed(), bias: Vector::zeroed(), phantom: PhantomData }
     }
+
+    /// Kernel drawn from [`glorot_uniform`](init::glorot_uniform), with
+    /// each of its `M - N + 1` weights as an input and an output, and zero
+    /// biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::random(rng, init::glorot_uniform(M - N + 1, M - N + 1))
+    }
+
+    /// Kernel drawn from [`glorot_normal`](init::glorot_normal), as
+    /// [`glorot_uniform`](Self::glorot_uniform).
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::random(rng, init::glorot_normal(M - N + 1, M - N + 1))
+    }
+
+    /// Kernel drawn from [`he_uniform`](init::he_uniform), with a fan-in
+    /// of its `M - N + 1` weights, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::random(rng, init::he_uniform(M - N + 1))
+    }
+
+    /// Kernel drawn from [`he_normal`](init::he_normal), as
+    /// [`he_uniform`](Self::he_uniform).
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::random(rng, init::he_normal(M - N + 1))
+    }
+
+    /// Kernel weights `w(j)`, for each of its `M - N + 1` weights, and
+    /// biases `b(i)`, with the unused weights beyond the kernel zero.
+    pub fn from_fn<W: FnMut(usize) -> F, B: FnMut(usize) -> F>(mut w: W, b: B) -> Self {
+        let k = M - N + 1;
+        let weights = Vector::from_fn(|j| if j < k { w(j) } else { F::ZERO });
+        Self::from_raw(weights, Vector::from_fn(b))
+    }
+
+    /// Kernel drawn from `dist`, such as [`Normal`](init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_fn(|_| F::from_f32(dist.sample(rng)), |_| F::ZERO)
+    }
 }
 
-pub struct Conv1DLayers<const N: usize> {
-    out: Vector<N>,
+pub struct Conv1DLayers<const N: usize, F: Float = f32> {
+    out: Vector<N, F>,
 }
 
-impl<const N: usize> OutputLayer<Vector<N>> for Conv1DLayers<N> {
-    fn output_layer(&self) -> Vector<N> {
-        self.out
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for Conv1DLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        &self.out
     }
 }
 
-impl<T, const M: usize, const N: usize> FeedForwardNetwork for Conv1D<T, M, N>
+impl<T, const M: usize, const N: usize, F: Float> FeedForwardNetwork for Conv1D<T, M, N, F>
 where T: Activation
 {
-    type InputType = Vector<M>;
-    type OutputType = Vector<N>;
-    type Layers = Conv1DLayers<N>;
+    type InputType = Vector<M, F>;
+    type OutputType = Vector<N, F>;
+    type Layers = Conv1DLayers<N, F>;
+    type Grad = Conv1D<T, M, N, F::Compute>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
         self.weights.adam(g.weights, &mut m.weights, &mut v.weights, adj, lr);
         self.bias.adam(g.bias, &mut m.bias, &mut v.bias, adj, lr);
     }
 
+    fn trace(&self, prefix: &str, input: &str, graph: &mut Graph) -> Result<String, Unsupported> {
+        let op = Op::Conv1D {
+            weight: param_name(prefix, "weight"),
+            bias: param_name(prefix, "bias"),
+            kernel: M - N + 1,
+        };
+        let out = graph.push(op, &[input], N);
+        Ok(graph.push(Op::Activation(T::ID), &[&out], N))
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        visitor.visit(Param {
+            name: &param_name(prefix, "weight"),
+            shape: &[M],
+            activation: T::ID,
+            values: self.weights.as_slice(),
+        });
+        visitor.visit(Param {
+            name: &param_name(prefix, "bias"),
+            shape: &[N],
+            activation: T::ID,
+            values: self.bias.as_slice(),
+        });
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "weight"),
+            shape: &[M],
+            activation: T::ID,
+            values: self.weights.as_mut_slice(),
+        });
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "bias"),
+            shape: &[N],
+            activation: T::ID,
+            values: self.bias.as_mut_slice(),
+        });
+    }
+
     fn backprop(
         &self,
-        input: &Vector<M>,
-        grad: &mut Self,
-        mut out_err: Vector<N>,
-        layers: &Conv1DLayers<N>,
-    ) -> Vector<M> {
+        input: &Vector<M, F>,
+        grad: &mut Self::Grad,
+        mut out_err: Vector<N, F>,
+        layers: &Conv1DLayers<N, F>,
+    ) -> Vector<M, F> {
         let k = M - N + 1;
         out_err = out_err * layers.out.derivative::<T>();
 
-        grad.bias += out_err;
+        grad.bias += out_err.to_compute();
 
         for i in 0..N {
             for j in 0..k {
-                grad.weights[j] += out_err[i] * input[i + j];
+                grad.weights[j] += out_err[i].to_compute() * input[i + j].to_compute();
             }
         }
 
         Vector::from_fn(|i| {
-            let mut val = 0.0;
+            let mut val = F::Compute::ZERO;
             for j in 0..k {
-                let elem = if i < k - 1 || i >= N + k - 1 {
-                    0.0
-                } else {
-                    out_err[i - k + 1] * self.weights[j]
-                };
-                val += elem;
+                if i >= j && i - j < N {
+                    val += out_err[i - j].to_compute() * self.weights[j].to_compute();
+                }
             }
-            val
+            F::from_compute(val)
         })
     }
 
-    fn out_with_layers(&self, input: &Vector<M>) -> Conv1DLayers<N> {
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("[{M}]"), format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        visitor.visit(prefix, layers.out.as_slice());
+    }
+
+    fn out_with_layers(&self, input: &Vector<M, F>) -> Conv1DLayers<N, F> {
+        let mut layers = Conv1DLayers {
+            out: Vector::zeroed(),
+        };
+        self.out_into(input, &mut layers);
+        layers
+    }
+
+    fn out_into(&self, input: &Vector<M, F>, layers: &mut Conv1DLayers<N, F>) {
         let k = M - N + 1;
 
-        let out = Vector::from_fn(|i| {
-            let mut val = self.bias[i];
+        for i in 0..N {
+            let mut val = self.bias[i].to_compute();
             for j in 0..k {
-                val += input[i + j] * self.weights[j];
+                val += input[i + j].to_compute() * self.weights[j].to_compute();
             }
-            val
-        });
-
-        Conv1DLayers { out: out.activate::<T>() }
+            layers.out[i] = F::from_compute(T::activate(val));
+        }
     }
 }
@@ -1,45 +1,104 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
-use goober_core::{activation::Activation, FeedForwardNetwork, Matrix, OutputLayer, Vector};
+use alloc::{boxed::Box, format, string::String};
+
+use goober_core::{
+    activation::Activation,
+    init::{self, Distribution},
+    param_name,
+    qat::Qat,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeedForwardNetwork, Float, Graph, Matrix, Op, OutputLayer, Param, ParamMut,
+    ParamVisitor, ParamVisitorMut, Rand, Unsupported, Vector,
+};
+
+use crate::{
+    quantized::{clamp_quantized, fake_quantize, fake_quantize_accumulator, largest_scale},
+    QuantizedDense,
+};
 
 /// Fully-Connected layer.
 /// - `T` is the activation function used.
 /// - `M` is the size of the input vector.
 /// - `N` is the size of the output vector.
+/// - `F` is the type weights and activations are stored as.
 #[repr(C)]
 #[derive(Clone, Copy)]
-pub struct DenseConnected<T: Activation, const M: usize, const N: usize> {
-    weights: Matrix<N, M>,
-    bias: Vector<N>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct DenseConnected<T: Activation, const M: usize, const N: usize, F: Float = f32> {
+    weights: Matrix<N, M, F>,
+    bias: Vector<N, F>,
     phantom: PhantomData<T>,
 }
 
-impl<T: Activation, const M: usize, const N: usize> std::ops::AddAssign<&DenseConnected<T, M, N>>
-    for DenseConnected<T, M, N>
+/// Every parameter, the weights, in row-major order, then the biases.
+impl<T: Activation, const M: usize, const N: usize, F: Float> AsRef<[F]>
+    for DenseConnected<T, M, N, F>
+{
+    fn as_ref(&self) -> &[F] {
+        // SAFETY: `#[repr(C)]`, with every field an array of `F`s, so the
+        // parameters are laid out contiguously, without padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), N * M + N) }
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float> AsMut<[F]>
+    for DenseConnected<T, M, N, F>
 {
-    fn add_assign(&mut self, rhs: &DenseConnected<T, M, N>) {
+    fn as_mut(&mut self) -> &mut [F] {
+        // SAFETY: see `as_ref`.
+        unsafe { core::slice::from_raw_parts_mut((self as *mut Self).cast(), N * M + N) }
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, F: Float + bytemuck::Zeroable>
+    bytemuck::Zeroable for DenseConnected<T, M, N, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, F: Float + bytemuck::Pod> bytemuck::Pod
+    for DenseConnected<T, M, N, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float>
+    core::ops::AddAssign<&DenseConnected<T, M, N, F>> for DenseConnected<T, M, N, F>
+{
+    fn add_assign(&mut self, rhs: &DenseConnected<T, M, N, F>) {
         self.weights += &rhs.weights;
         self.bias += rhs.bias;
     }
 }
 
-impl<T: Activation, const M: usize, const N: usize> DenseConnected<T, M, N> {
+impl<T: Activation, const M: usize, const N: usize, F: Float> DenseConnected<T, M, N, F> {
     pub const INPUT_SIZE: usize = M;
     pub const OUTPUT_SIZE: usize = N;
 
-    pub fn weights_row(&self, idx: usize) -> Vector<M> {
+    pub fn weights_row(&self, idx: usize) -> Vector<M, F> {
         self.weights[idx]
     }
 
-    pub fn weights_row_mut(&mut self, idx: usize) -> &mut Vector<M> {
+    pub fn weights_row_mut(&mut self, idx: usize) -> &mut Vector<M, F> {
         &mut self.weights[idx]
     }
 
-    pub fn bias(&self) -> Vector<N> {
+    pub fn bias(&self) -> Vector<N, F> {
         self.bias
     }
 
-    pub fn bias_mut(&mut self) -> &mut Vector<N> {
+    pub fn bias_mut(&mut self) -> &mut Vector<N, F> {
         &mut self.bias
     }
 
@@ -47,7 +106,7 @@ impl<T: Activation, const M: usize, const N: usize> DenseConnected<T, M, N> {
         Self::from_raw(Matrix::zeroed(), Vector::zeroed())
     }
 
-    pub const fn from_raw(weights: Matrix<N, M>, bias: Vector<N>) -> Self {
+    pub const fn from_raw(weights: Matrix<N, M, F>, bias: Vector<N, F>) -> Self {
         Self {
             weights,
             bias,
@@ -55,7 +114,7 @@ impl<T: Activation, const M: usize, const N: usize> DenseConnected<T, M, N> {
         }
     }
 
-    pub fn from_fn<W: FnMut(usize, usize) -> f32, B: FnMut(usize) -> f32>(w: W, b: B) -> Self {
+    pub fn from_fn<W: FnMut(usize, usize) -> F, B: FnMut(usize) -> F>(w: W, b: B) -> Self {
         Self {
             weights: Matrix::from_fn(w),
             bias: Vector::from_fn(b),
@@ -63,53 +122,316 @@ impl<T: Activation, const M: usize, const N: usize> DenseConnected<T, M, N> {
         }
     }
 
-    pub fn transpose_mul(&self, out: Vector<N>) -> Vector<M> {
+    /// Weights drawn from [`glorot_uniform`](init::glorot_uniform),
+    /// and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::random(rng, init::glorot_uniform(M, N))
+    }
+
+    /// Weights drawn from [`glorot_normal`](init::glorot_normal),
+    /// and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::random(rng, init::glorot_normal(M, N))
+    }
+
+    /// Weights drawn from [`he_uniform`](init::he_uniform), with
+    /// a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::random(rng, init::he_uniform(M))
+    }
+
+    /// Weights drawn from [`he_normal`](init::he_normal), with
+    /// a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::random(rng, init::he_normal(M))
+    }
+
+    /// Weights of a random orthogonal matrix, by
+    /// [`orthogonal`](init::orthogonal), scaled by `gain`, and zero biases,
+    /// which keeps deep stacks of layers from shrinking or growing the
+    /// signal, with a `gain` suited to the activation, such as `sqrt(2)`
+    /// for `ReLU`.
+    pub fn orthogonal(rng: &mut Rand, gain: f32) -> Self {
+        let weights = init::orthogonal(rng, N, M);
+        Self::from_fn(|i, j| F::from_f32(gain * weights[i * M + j]), |_| F::ZERO)
+    }
+
+    /// Sparse initialisation, after Sutskever et al., with the weights from
+    /// `k` inputs to each output, chosen at random, drawn from `dist`, and
+    /// the rest zero, along with the biases, so that units start out
+    /// different from each other without the scale of their outputs
+    /// depending on the number of inputs.
+    pub fn sparse_random(rng: &mut Rand, k: usize, dist: impl Distribution) -> Self {
+        let mut weights = Matrix::<N, M, F>::zeroed();
+        for row in weights.iter_mut() {
+            for i in init::choose(rng, M, k) {
+                row[i] = F::from_f32(dist.sample(rng));
+            }
+        }
+        Self::from_raw(weights, Vector::zeroed())
+    }
+
+    /// Weights drawn from `dist`, such as [`Normal`](init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(Matrix::random(rng, dist), Vector::zeroed())
+    }
+
+    pub fn transpose_mul(&self, out: Vector<N, F>) -> Vector<M, F> {
         self.weights.transpose_mul(out)
     }
+
+    /// Converts to a quantized layer for inference, taking inputs
+    /// at `input_scale` and storing weights at `weight_scale`.
+    pub fn quantize(&self, input_scale: i16, weight_scale: i16) -> Box<QuantizedDense<T, M, N>> {
+        QuantizedDense::from_fn(
+            |i, j| self.weights[i][j].to_f32(),
+            |i| self.bias[i].to_f32(),
+            input_scale,
+            weight_scale,
+        )
+    }
+
+    /// Converts to a quantized layer for inference, taking inputs
+    /// at `input_scale` and storing each row of weights at its
+    /// own scale.
+    pub fn quantize_per_row(
+        &self,
+        input_scale: i16,
+        weight_scales: [i16; N],
+    ) -> Box<QuantizedDense<T, M, N>> {
+        QuantizedDense::from_fn_per_row(
+            |i, j| self.weights[i][j].to_f32(),
+            |i| self.bias[i].to_f32(),
+            input_scale,
+            weight_scales,
+        )
+    }
+
+    /// Runs `inputs` through the layer, and chooses the largest weight
+    /// scale at which both the weights and the pre-activations remain
+    /// representable after [`quantize`](Self::quantize).
+    pub fn calibrate<'a, I: IntoIterator<Item = &'a Vector<M, F>>>(
+        &self,
+        inputs: I,
+        input_scale: i16,
+    ) -> i16 {
+        let scales = self.calibrate_per_row(inputs, input_scale);
+        scales.into_iter().min().unwrap_or(i16::MAX)
+    }
+
+    /// As [`calibrate`](Self::calibrate), but choosing a scale
+    /// for each row, for use with [`quantize_per_row`](Self::quantize_per_row).
+    pub fn calibrate_per_row<'a, I: IntoIterator<Item = &'a Vector<M, F>>>(
+        &self,
+        inputs: I,
+        input_scale: i16,
+    ) -> [i16; N] {
+        let mut max_acc = [0f32; N];
+
+        for input in inputs {
+            // row by row, as `out_into`, without copying the weights
+            for (i, row) in self.weights.rows().enumerate() {
+                let acc = row.dot(input) + self.bias[i].to_compute();
+                max_acc[i] = max_acc[i].max(acc.to_f32().abs());
+            }
+        }
+
+        core::array::from_fn(|i| {
+            let row = self.weights[i];
+            let max_weight = (0..M).fold(0f32, |max, j| max.max(row[j].to_f32().abs()));
+
+            let weight_scale = largest_scale(max_weight, i16::MAX.into());
+            let acc_scale = largest_scale(max_acc[i] * f32::from(input_scale), i32::MAX);
+            weight_scale.min(acc_scale) as i16
+        })
+    }
+
+    /// Rounds weights and biases to the nearest values representable
+    /// after [`quantize`](Self::quantize), simulating quantization
+    /// for quantization-aware training.
+    pub fn fake_quantize(&mut self, input_scale: i16, weight_scale: i16) {
+        let weight_scale = i32::from(weight_scale);
+        let bias_scale = i32::from(input_scale) * weight_scale;
+
+        for row in self.weights.iter_mut() {
+            *row = Vector::from_fn(|j| {
+                F::from_f32(fake_quantize::<i16>(row[j].to_f32(), weight_scale))
+            });
+        }
+
+        self.bias = Vector::from_fn(|i| {
+            F::from_f32(fake_quantize_accumulator::<i16>(
+                self.bias[i].to_f32(),
+                bias_scale,
+            ))
+        });
+    }
+
+    /// Clamps weights to the range representable after
+    /// [`quantize`](Self::quantize), so that they cannot drift
+    /// out of range during quantization-aware training.
+    pub fn clamp_quantized(&mut self, weight_scale: i16) {
+        let weight_scale = i32::from(weight_scale);
+
+        for row in self.weights.iter_mut() {
+            *row = Vector::from_fn(|j| {
+                F::from_f32(clamp_quantized::<i16>(row[j].to_f32(), weight_scale))
+            });
+        }
+    }
 }
 
-pub struct DenseConnectedLayers<const N: usize> {
-    out: Vector<N>,
+pub struct DenseConnectedLayers<const N: usize, F: Float = f32> {
+    out: Vector<N, F>,
 }
 
-impl<const N: usize> OutputLayer<Vector<N>> for DenseConnectedLayers<N> {
-    fn output_layer(&self) -> Vector<N> {
-        self.out
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for DenseConnectedLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        &self.out
     }
 }
 
-impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork for DenseConnected<T, M, N> {
-    type InputType = Vector<M>;
-    type OutputType = Vector<N>;
-    type Layers = DenseConnectedLayers<N>;
+impl<T: Activation, const M: usize, const N: usize, F: Float> FeedForwardNetwork
+    for DenseConnected<T, M, N, F>
+{
+    type InputType = Vector<M, F>;
+    type OutputType = Vector<N, F>;
+    type Layers = DenseConnectedLayers<N, F>;
+    type Grad = DenseConnected<T, M, N, F::Compute>;
 
-    fn adam(&mut self, g: &Self, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
         self.weights
             .adam(&g.weights, &mut m.weights, &mut v.weights, adj, lr);
 
         self.bias.adam(g.bias, &mut m.bias, &mut v.bias, adj, lr);
     }
 
+    fn trace(&self, prefix: &str, input: &str, graph: &mut Graph) -> Result<String, Unsupported> {
+        let op = Op::Linear {
+            weight: param_name(prefix, "weight"),
+            bias: param_name(prefix, "bias"),
+        };
+        let out = graph.push(op, &[input], N);
+        Ok(graph.push(Op::Activation(T::ID), &[&out], N))
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        visitor.visit(Param {
+            name: &param_name(prefix, "weight"),
+            shape: &[N, M],
+            activation: T::ID,
+            values: self.weights.as_slice(),
+        });
+        visitor.visit(Param {
+            name: &param_name(prefix, "bias"),
+            shape: &[N],
+            activation: T::ID,
+            values: self.bias.as_slice(),
+        });
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "weight"),
+            shape: &[N, M],
+            activation: T::ID,
+            values: self.weights.as_mut_slice(),
+        });
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "bias"),
+            shape: &[N],
+            activation: T::ID,
+            values: self.bias.as_mut_slice(),
+        });
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("[{M}]"), format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        visitor.visit(prefix, layers.out.as_slice());
+    }
+
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
-        Self::Layers {
-            out: (self.weights * *input + self.bias).activate::<T>(),
+        let mut layers = Self::Layers {
+            out: Vector::zeroed(),
+        };
+        self.out_into(input, &mut layers);
+        layers
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        // row by row, without copying the weights, rounding
+        // each output once
+        for (i, row) in self.weights.rows().enumerate() {
+            let x = row.dot(input) + self.bias[i].to_compute();
+            layers.out[i] = F::from_compute(T::activate(x));
+        }
+    }
+
+    fn out_into_qat(
+        &self,
+        prefix: &str,
+        input: &Self::InputType,
+        layers: &mut Self::Layers,
+        qat: &Qat,
+    ) {
+        let Some(scale) = qat.scale(prefix) else {
+            return self.out_into(input, layers);
+        };
+        let input_scale = i32::from(scale.input);
+        let weight_scale = i32::from(scale.weight);
+        let bias_scale = input_scale * weight_scale;
+
+        // as `QuantizedDense::out`, with the inputs and outputs rounded
+        // to multiples of `1 / input_scale`, the weights to multiples
+        // of `1 / weight_scale`, and the biases to multiples of both
+        let input: [f32; M] =
+            core::array::from_fn(|j| fake_quantize::<i16>(input[j].to_f32(), input_scale));
+
+        for (i, row) in self.weights.rows().enumerate() {
+            let mut acc = fake_quantize_accumulator::<i16>(self.bias[i].to_f32(), bias_scale);
+            for (j, &x) in input.iter().enumerate() {
+                acc += fake_quantize::<i16>(row[j].to_f32(), weight_scale) * x;
+            }
+
+            let acc = fake_quantize_accumulator::<i16>(acc, input_scale);
+            layers.out[i] = F::from_f32(fake_quantize::<i16>(T::activate(acc), input_scale));
+        }
+    }
+
+    fn clamp_qat(&mut self, prefix: &str, qat: &Qat) {
+        if let Some(scale) = qat.scale(prefix) {
+            self.clamp_quantized(scale.weight);
         }
     }
 
     fn backprop(
         &self,
         input: &Self::InputType,
-        grad: &mut Self,
+        grad: &mut Self::Grad,
         mut out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType {
         out_err = out_err * layers.out.derivative::<T>();
 
+        let input_c = input.to_compute();
+        let err_c = out_err.to_compute();
         for (i, row) in grad.weights.iter_mut().enumerate() {
-            *row += out_err[i] * *input;
+            *row += input_c * err_c[i];
         }
 
-        grad.bias += out_err;
+        grad.bias += err_c;
         self.transpose_mul(out_err)
     }
 }
@@ -0,0 +1,161 @@
+use alloc::vec::Vec;
+
+use goober_core::activation::Activation;
+
+use crate::{Quantized, QuantizedDense, QuantizedSparse};
+
+/// Quantized layer that can be written by [`QuantizedExport`].
+pub trait Export {
+    fn export(&self, out: &mut Vec<u8>);
+}
+
+/// Builds a versioned binary file from a sequence of quantized layers,
+/// for consumption by inference code outside of Rust.
+///
+/// All values are little-endian, and the file is laid out as:
+/// - header
+///   - magic `b"GBQN"`
+///   - `u32` format version, currently [`QuantizedExport::VERSION`]
+///   - `u32` number of layers
+/// - for each layer, in the order they were added
+///   - `u8` kind, `0` for dense, `1` for sparse
+///   - `u8` bytes per weight, `1` for `i8`, `2` for `i16`
+///   - `u8` activation id, see [`Activation::ID`]
+///   - `u8` reserved, always `0`
+///   - `u32` input size `M`
+///   - `u32` output size `N`
+///   - scales
+///     - dense: `i32` input scale, then `N` × `i32` weight scales, one per row
+///     - sparse: `i32` scale
+///   - weights, row-major
+///     - dense: `N` rows of `M` × `i16`, one row per output
+///     - sparse: `M` rows of `N` weights, one row per input feature
+///   - biases
+///     - dense: `N` × `i32`
+///     - sparse: `N` accumulators, `i32` for `i16` weights, `i16` for `i8` weights
+#[derive(Clone, Debug, Default)]
+pub struct QuantizedExport {
+    layers: u32,
+    body: Vec<u8>,
+}
+
+impl QuantizedExport {
+    pub const MAGIC: [u8; 4] = *b"GBQN";
+    pub const VERSION: u32 = 1;
+
+    pub fn layer<L: Export>(&mut self, layer: &L) -> &mut Self {
+        layer.export(&mut self.body);
+        self.layers += 1;
+        self
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + self.body.len());
+        bytes.extend_from_slice(&Self::MAGIC);
+        bytes.extend_from_slice(&Self::VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.layers.to_le_bytes());
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+
+    #[cfg(feature = "std")]
+    pub fn write_to_bin(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize> Export for QuantizedDense<T, M, N> {
+    fn export(&self, out: &mut Vec<u8>) {
+        write_layer_header::<T, i16>(out, 0, M, N);
+
+        write_int(out, self.input_scale());
+        for i in 0..N {
+            write_int(out, self.weight_scale(i));
+        }
+
+        for i in 0..N {
+            for &weight in self.weights_row(i) {
+                write_int(out, weight);
+            }
+        }
+
+        for &bias in self.bias() {
+            write_int(out, bias);
+        }
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, Q: Quantized> Export
+    for QuantizedSparse<T, M, N, Q>
+{
+    fn export(&self, out: &mut Vec<u8>) {
+        write_layer_header::<T, Q>(out, 1, M, N);
+
+        write_int(out, self.scale());
+
+        for i in 0..M {
+            for &weight in self.weights_row(i) {
+                write_int(out, weight);
+            }
+        }
+
+        for &bias in self.bias() {
+            write_int(out, bias);
+        }
+    }
+}
+
+fn write_layer_header<T: Activation, Q>(out: &mut Vec<u8>, kind: u8, m: usize, n: usize) {
+    out.extend_from_slice(&[kind, core::mem::size_of::<Q>() as u8, T::ID, 0]);
+    out.extend_from_slice(&(m as u32).to_le_bytes());
+    out.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+/// Writes the little-endian bytes of `x`, using the width of `I`.
+fn write_int<I: Into<i32>>(out: &mut Vec<u8>, x: I) {
+    let bytes = x.into().to_le_bytes();
+    out.extend_from_slice(&bytes[..core::mem::size_of::<I>()]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::QuantizedExport;
+
+    #[test]
+    fn quantized_export() {
+        use crate::{DenseConnected, SparseConnected};
+        use goober_core::activation::{ReLU, SCReLU};
+
+        let sparse: SparseConnected<SCReLU, 3, 2> =
+            SparseConnected::from_fn(|i, j| (i * 2 + j) as f32 / 8.0 - 0.25, |_| 0.5);
+        let dense: DenseConnected<ReLU, 2, 1> = DenseConnected::from_fn(|_, j| j as f32, |_| -1.0);
+
+        let bytes = QuantizedExport::default()
+            .layer(&*sparse.quantize_i8(64))
+            .layer(&*dense.quantize(64, 32))
+            .to_bytes();
+
+        let sparse_len = 12 + 4 + 3 * 2 + 2 * 2;
+        let dense_len = 12 + 4 + 4 + 2 * 2 + 4;
+        assert_eq!(bytes.len(), 12 + sparse_len + dense_len);
+
+        assert_eq!(&bytes[..4], b"GBQN");
+        assert_eq!(bytes[4..8], 1u32.to_le_bytes());
+        assert_eq!(bytes[8..12], 2u32.to_le_bytes());
+
+        let sparse = &bytes[12..12 + sparse_len];
+        assert_eq!(sparse[..4], [1, 1, 2, 0]);
+        assert_eq!(sparse[4..8], 3u32.to_le_bytes());
+        assert_eq!(sparse[8..12], 2u32.to_le_bytes());
+        assert_eq!(sparse[12..16], 64i32.to_le_bytes());
+        assert_eq!(sparse[16..22], [-16i8 as u8, -8i8 as u8, 0, 8, 16, 24]);
+        assert_eq!(sparse[22..], [32, 0, 32, 0]);
+
+        let dense = &bytes[12 + sparse_len..];
+        assert_eq!(dense[..4], [0, 2, 1, 0]);
+        assert_eq!(dense[12..16], 64i32.to_le_bytes());
+        assert_eq!(dense[16..20], 32i32.to_le_bytes());
+        assert_eq!(dense[20..24], [0, 0, 32, 0]);
+        assert_eq!(dense[24..], (-64i32 * 32).to_le_bytes());
+    }
+}
@@ -0,0 +1,375 @@
+use core::marker::PhantomData;
+
+use alloc::format;
+
+use goober_core::{
+    activation::Activation,
+    init::Distribution,
+    param_name,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeatureOutOfBounds, FeedForwardNetwork, Float, Matrix, OutputLayer, Param,
+    ParamMut, ParamVisitor, ParamVisitorMut, Rand, SparseVector, Vector,
+};
+
+use crate::{sparse::debug_assert_in_bounds, SparseConnected};
+
+/// Maps each concrete feature to the coarser virtual
+/// feature that it is factorized into.
+pub trait Factorizer: Copy {
+    fn factor(feat: usize) -> usize;
+}
+
+/// Fully-Connected layer with sparse input, trained with
+/// an additional set of virtual features.
+/// - `T` is the activation function used.
+/// - `Z` maps each feature to its virtual feature.
+/// - `M` is the size of the input vector.
+/// - `V` is the number of virtual features.
+/// - `N` is the size of the output vector.
+/// - `F` is the type weights and activations are stored as.
+///
+/// Every active feature also activates its virtual feature, so the
+/// virtual weights are shared between, and learn from, all features
+/// that factorize into them. They are folded into the concrete
+/// weights by [`fold`](Self::fold) for inference.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct FactorizedSparse<
+    T: Activation,
+    Z: Factorizer,
+    const M: usize,
+    const V: usize,
+    const N: usize,
+    F: Float = f32,
+> {
+    layer: SparseConnected<T, M, N, F>,
+    factors: Matrix<V, N, F>,
+    phantom: PhantomData<Z>,
+}
+
+/// Every parameter, those of the layer, as [`SparseConnected`], then the factors.
+impl<T: Activation, Z: Factorizer, const M: usize, const V: usize, const N: usize, F: Float>
+    AsRef<[F]> for FactorizedSparse<T, Z, M, V, N, F>
+{
+    fn as_ref(&self) -> &[F] {
+        // SAFETY: `#[repr(C)]`, with every field an array of `F`s, so the
+        // parameters are laid out contiguously, without padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), M * N + N + V * N) }
+    }
+}
+
+impl<T: Activation, Z: Factorizer, const M: usize, const V: usize, const N: usize, F: Float>
+    AsMut<[F]> for FactorizedSparse<T, Z, M, V, N, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        // SAFETY: see `as_ref`.
+        unsafe { core::slice::from_raw_parts_mut((self as *mut Self).cast(), M * N + N + V * N) }
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        Z: Factorizer,
+        const M: usize,
+        const V: usize,
+        const N: usize,
+        F: Float + bytemuck::Zeroable,
+    > bytemuck::Zeroable for FactorizedSparse<T, Z, M, V, N, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        Z: Factorizer,
+        const M: usize,
+        const V: usize,
+        const N: usize,
+        F: Float + bytemuck::Pod,
+    > bytemuck::Pod for FactorizedSparse<T, Z, M, V, N, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, Z: Factorizer, const M: usize, const V: usize, const N: usize, F: Float>
+    core::ops::AddAssign<&FactorizedSparse<T, Z, M, V, N, F>>
+    for FactorizedSparse<T, Z, M, V, N, F>
+{
+    fn add_assign(&mut self, rhs: &FactorizedSparse<T, Z, M, V, N, F>) {
+        self.layer += &rhs.layer;
+        self.factors += &rhs.factors;
+    }
+}
+
+impl<T: Activation, Z: Factorizer, const M: usize, const V: usize, const N: usize, F: Float>
+    FactorizedSparse<T, Z, M, V, N, F>
+{
+    pub const fn zeroed() -> Self {
+        Self::from_raw(SparseConnected::zeroed(), Matrix::zeroed())
+    }
+
+    pub const fn from_raw(layer: SparseConnected<T, M, N, F>, factors: Matrix<V, N, F>) -> Self {
+        Self {
+            layer,
+            factors,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Concrete weights `w(i, j)` from input `i` to output `j`, virtual
+    /// weights `v(i, j)` from virtual feature `i`, and biases `b(j)`.
+    pub fn from_fn<WeightFn, FactorFn, BiasFn>(w: WeightFn, v: FactorFn, b: BiasFn) -> Self
+    where
+        WeightFn: FnMut(usize, usize) -> F,
+        FactorFn: FnMut(usize, usize) -> F,
+        BiasFn: FnMut(usize) -> F,
+    {
+        Self::from_raw(SparseConnected::from_fn(w, b), Matrix::from_fn(v))
+    }
+
+    /// Concrete weights drawn from `dist`, such as
+    /// [`Normal`](goober_core::init::Normal), and zero factors and biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(SparseConnected::random(rng, dist), Matrix::zeroed())
+    }
+
+    /// As [`out`](FeedForwardNetwork::out), but returns an error
+    /// rather than panicking if a feature index is out of bounds.
+    pub fn try_out(
+        &self,
+        input: &SparseVector,
+    ) -> Result<<Self as FeedForwardNetwork>::OutputType, FeatureOutOfBounds> {
+        input.check_bounds(M)?;
+        Ok(self.out(input))
+    }
+
+    /// As [`backprop`](FeedForwardNetwork::backprop), but returns an error,
+    /// leaving `grad` unchanged, rather than panicking if a feature index
+    /// is out of bounds.
+    pub fn try_backprop(
+        &self,
+        input: &SparseVector,
+        grad: &mut <Self as FeedForwardNetwork>::Grad,
+        out_err: <Self as FeedForwardNetwork>::OutputType,
+        layers: &<Self as FeedForwardNetwork>::Layers,
+    ) -> Result<SparseVector, FeatureOutOfBounds> {
+        input.check_bounds(M)?;
+        Ok(self.backprop(input, grad, out_err, layers))
+    }
+
+    /// Concrete weights drawn from
+    /// [`glorot_uniform`](goober_core::init::glorot_uniform), and zero
+    /// factors and biases, so the virtual weights start adding nothing.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_uniform(rng), Matrix::zeroed())
+    }
+
+    /// Concrete weights drawn from
+    /// [`glorot_normal`](goober_core::init::glorot_normal), and zero
+    /// factors and biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_normal(rng), Matrix::zeroed())
+    }
+
+    /// Concrete weights drawn from [`he_uniform`](goober_core::init::he_uniform),
+    /// with a fan-in of `M`, and zero factors and biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_uniform(rng), Matrix::zeroed())
+    }
+
+    /// Concrete weights drawn from [`he_normal`](goober_core::init::he_normal),
+    /// with a fan-in of `M`, and zero factors and biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_normal(rng), Matrix::zeroed())
+    }
+
+    pub fn layer(&self) -> &SparseConnected<T, M, N, F> {
+        &self.layer
+    }
+
+    pub fn layer_mut(&mut self) -> &mut SparseConnected<T, M, N, F> {
+        &mut self.layer
+    }
+
+    pub fn factors_row(&self, idx: usize) -> Vector<N, F> {
+        self.factors[idx]
+    }
+
+    pub fn factors_row_mut(&mut self, idx: usize) -> &mut Vector<N, F> {
+        &mut self.factors[idx]
+    }
+
+    /// Folds the virtual weights into the concrete weights,
+    /// producing an equivalent layer without virtual features.
+    pub fn fold(&self) -> SparseConnected<T, M, N, F> {
+        let mut res = self.layer;
+
+        for feat in 0..M {
+            *res.weights_row_mut(feat) += self.factors[Z::factor(feat)];
+        }
+
+        res
+    }
+}
+
+pub struct FactorizedSparseLayers<const N: usize, F: Float = f32> {
+    out: Vector<N, F>,
+}
+
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for FactorizedSparseLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        &self.out
+    }
+}
+
+impl<T: Activation, Z: Factorizer, const M: usize, const V: usize, const N: usize, F: Float>
+    FeedForwardNetwork for FactorizedSparse<T, Z, M, V, N, F>
+{
+    type InputType = SparseVector;
+    type OutputType = Vector<N, F>;
+    type Layers = FactorizedSparseLayers<N, F>;
+    type Grad = FactorizedSparse<T, Z, M, V, N, F::Compute>;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+        self.layer
+            .adam(&g.layer, &mut m.layer, &mut v.layer, adj, lr);
+        self.factors
+            .adam(&g.factors, &mut m.factors, &mut v.factors, adj, lr);
+    }
+
+    fn visit_params<P: ParamVisitor>(&self, prefix: &str, visitor: &mut P) {
+        self.layer.visit_params(prefix, visitor);
+        visitor.visit(Param {
+            name: &param_name(prefix, "factors"),
+            shape: &[V, N],
+            activation: T::ID,
+            values: self.factors.as_slice(),
+        });
+    }
+
+    fn visit_params_mut<P: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut P) {
+        self.layer.visit_params_mut(prefix, visitor);
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "factors"),
+            shape: &[V, N],
+            activation: T::ID,
+            values: self.factors.as_mut_slice(),
+        });
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("sparse [{M}]"), format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<P: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut P,
+    ) {
+        visitor.visit(prefix, layers.out.as_slice());
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        input.check_bounds(M)
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let mut layers = Self::Layers {
+            out: Vector::zeroed(),
+        };
+        self.out_into(input, &mut layers);
+        layers
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        let mut res = self.layer.bias().to_compute();
+
+        for &feat in input.iter() {
+            debug_assert_in_bounds(feat, M);
+            res += self.layer.weights_row(feat).to_compute();
+            res += self.factors[Z::factor(feat)].to_compute();
+        }
+
+        layers.out = Vector::from_compute(&res.activate::<T>());
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        mut out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        out_err = out_err * layers.out.derivative::<T>();
+
+        let err = out_err.to_compute();
+        for &feat in input.iter() {
+            debug_assert_in_bounds(feat, M);
+            *grad.layer.weights_row_mut(feat) += err;
+            grad.factors[Z::factor(feat)] += err;
+        }
+
+        *grad.layer.bias_mut() += err;
+        SparseVector::with_capacity(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FactorizedSparse, Factorizer};
+
+    #[derive(Clone, Copy)]
+    struct Square;
+    impl Factorizer for Square {
+        fn factor(feat: usize) -> usize {
+            feat % 4
+        }
+    }
+
+    #[test]
+    fn factorized_sparse() {
+        use crate::SparseConnected;
+        use goober_core::{activation::ReLU, FeedForwardNetwork, Matrix, SparseVector, Vector};
+
+        let mut layer: FactorizedSparse<ReLU, Square, 8, 4, 2> = FactorizedSparse::from_raw(
+            SparseConnected::from_fn(|i, j| (i * 2 + j) as f32, |_| 0.5),
+            Matrix::from_fn(|i, j| (i + j) as f32 / 2.0),
+        );
+
+        let mut input = SparseVector::with_capacity(2);
+        input.push(1);
+        input.push(6);
+
+        let out = layer.out(&input);
+        assert_eq!(out, Vector::from_raw([16.0, 19.0]));
+        assert_eq!(out, layer.fold().out(&input));
+
+        let mut grad = FactorizedSparse::zeroed();
+        let layers = layer.out_with_layers(&input);
+        layer.backprop(&input, &mut grad, Vector::from_raw([1.0, 1.0]), &layers);
+
+        assert_eq!(grad.layer().weights_row(6), Vector::from_raw([1.0, 1.0]));
+        assert_eq!(grad.factors_row(2), Vector::from_raw([1.0, 1.0]));
+        assert_eq!(grad.factors_row(3), Vector::from_raw([0.0, 0.0]));
+
+        let mut m = FactorizedSparse::zeroed();
+        let mut v = FactorizedSparse::zeroed();
+        layer.adam(&grad, &mut m, &mut v, 1.0, 0.1);
+        assert_eq!(layer.out(&input), layer.fold().out(&input));
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::format;
+
+use goober_core::{
+    activation::Activation,
+    init::Distribution,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeedForwardNetwork, Float, OutputLayer, ParamVisitor, ParamVisitorMut, Rand,
+    SparseVector, Vector,
+};
+
+use crate::SparseConnected;
+
+/// Sparse layer taking arbitrarily large feature IDs, which
+/// are hashed into `M` buckets of a [`SparseConnected`] layer.
+/// - `T` is the activation function used.
+/// - `M` is the number of buckets.
+/// - `N` is the size of the output vector.
+/// - `SIGNED` also hashes each feature to a sign, so that
+///   collisions tend to cancel out rather than accumulate.
+/// - `F` is the type weights and activations are stored as.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct HashedSparse<
+    T: Activation,
+    const M: usize,
+    const N: usize,
+    const SIGNED: bool = false,
+    F: Float = f32,
+> {
+    layer: SparseConnected<T, M, N, F>,
+}
+
+/// Every parameter, as [`SparseConnected`].
+impl<T: Activation, const M: usize, const N: usize, const SIGNED: bool, F: Float> AsRef<[F]>
+    for HashedSparse<T, M, N, SIGNED, F>
+{
+    fn as_ref(&self) -> &[F] {
+        self.layer.as_ref()
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const SIGNED: bool, F: Float> AsMut<[F]>
+    for HashedSparse<T, M, N, SIGNED, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        self.layer.as_mut()
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        const M: usize,
+        const N: usize,
+        const SIGNED: bool,
+        F: Float + bytemuck::Zeroable,
+    > bytemuck::Zeroable for HashedSparse<T, M, N, SIGNED, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        const M: usize,
+        const N: usize,
+        const SIGNED: bool,
+        F: Float + bytemuck::Pod,
+    > bytemuck::Pod for HashedSparse<T, M, N, SIGNED, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, const M: usize, const N: usize, const SIGNED: bool, F: Float>
+    core::ops::AddAssign<&HashedSparse<T, M, N, SIGNED, F>> for HashedSparse<T, M, N, SIGNED, F>
+{
+    fn add_assign(&mut self, rhs: &HashedSparse<T, M, N, SIGNED, F>) {
+        self.layer += &rhs.layer;
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const SIGNED: bool, F: Float>
+    HashedSparse<T, M, N, SIGNED, F>
+{
+    pub const fn zeroed() -> Self {
+        Self::from_raw(SparseConnected::zeroed())
+    }
+
+    pub const fn from_raw(layer: SparseConnected<T, M, N, F>) -> Self {
+        Self { layer }
+    }
+
+    /// Weights `w(i, j)` from input `i` to output `j`, and biases `b(j)`,
+    /// as [`SparseConnected::from_fn`].
+    pub fn from_fn<W: FnMut(usize, usize) -> F, B: FnMut(usize) -> F>(w: W, b: B) -> Self {
+        Self::from_raw(SparseConnected::from_fn(w, b))
+    }
+
+    /// Weights drawn from `dist`, such as [`Normal`](goober_core::init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(SparseConnected::random(rng, dist))
+    }
+
+    /// Weights drawn from [`glorot_uniform`](goober_core::init::glorot_uniform),
+    /// and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_uniform(rng))
+    }
+
+    /// Weights drawn from [`glorot_normal`](goober_core::init::glorot_normal),
+    /// and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_normal(rng))
+    }
+
+    /// Weights drawn from [`he_uniform`](goober_core::init::he_uniform),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_uniform(rng))
+    }
+
+    /// Weights drawn from [`he_normal`](goober_core::init::he_normal),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_normal(rng))
+    }
+
+    pub fn layer(&self) -> &SparseConnected<T, M, N, F> {
+        &self.layer
+    }
+
+    pub fn layer_mut(&mut self) -> &mut SparseConnected<T, M, N, F> {
+        &mut self.layer
+    }
+
+    /// Bucket that a feature ID hashes to, and whether
+    /// its contribution is negated.
+    pub fn bucket(id: usize) -> (usize, bool) {
+        let mut x = (id as u64).wrapping_add(0x9E3779B97F4A7C15);
+        x = (x ^ (x >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        x = (x ^ (x >> 27)).wrapping_mul(0x94D049BB133111EB);
+        x ^= x >> 31;
+
+        ((x % M as u64) as usize, SIGNED && x >> 63 == 1)
+    }
+}
+
+pub struct HashedSparseLayers<const N: usize, F: Float = f32> {
+    out: Vector<N, F>,
+}
+
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for HashedSparseLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        &self.out
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const SIGNED: bool, F: Float> FeedForwardNetwork
+    for HashedSparse<T, M, N, SIGNED, F>
+{
+    type InputType = SparseVector;
+    type OutputType = Vector<N, F>;
+    type Layers = HashedSparseLayers<N, F>;
+    type Grad = HashedSparse<T, M, N, SIGNED, F::Compute>;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+        self.layer
+            .adam(&g.layer, &mut m.layer, &mut v.layer, adj, lr);
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params(prefix, visitor);
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params_mut(prefix, visitor);
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes("sparse ids", format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        visitor.visit(prefix, layers.out.as_slice());
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let mut layers = Self::Layers {
+            out: Vector::zeroed(),
+        };
+        self.out_into(input, &mut layers);
+        layers
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        let mut res = self.layer.bias().to_compute();
+
+        for &id in input.iter() {
+            let (bucket, negated) = Self::bucket(id);
+            if negated {
+                res -= self.layer.weights_row(bucket).to_compute();
+            } else {
+                res += self.layer.weights_row(bucket).to_compute();
+            }
+        }
+
+        layers.out = Vector::from_compute(&res.activate::<T>());
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        mut out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        out_err = out_err * layers.out.derivative::<T>();
+
+        let err = out_err.to_compute();
+        for &id in input.iter() {
+            let (bucket, negated) = Self::bucket(id);
+            if negated {
+                *grad.layer.weights_row_mut(bucket) -= err;
+            } else {
+                *grad.layer.weights_row_mut(bucket) += err;
+            }
+        }
+
+        *grad.layer.bias_mut() += err;
+        SparseVector::with_capacity(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::HashedSparse;
+
+    #[test]
+    fn hashed_sparse() {
+        use crate::SparseConnected;
+        use goober_core::{activation::Identity, FeedForwardNetwork, SparseVector, Vector};
+
+        type Unsigned = HashedSparse<Identity, 16, 1>;
+        type Signed = HashedSparse<Identity, 16, 1, true>;
+
+        let ids = [3, 1 << 40, usize::MAX, 123_456_789];
+        let input = SparseVector::from_slice(&ids);
+
+        let unsigned = Unsigned::from_raw(SparseConnected::from_fn(|i, _| i as f32, |_| 0.0));
+        let expected: usize = ids.iter().map(|&id| Unsigned::bucket(id).0).sum();
+        assert_eq!(unsigned.out(&input), Vector::from_raw([expected as f32]));
+
+        let signed = Signed::from_raw(SparseConnected::from_fn(|_, _| 1.0, |_| 0.0));
+        let expected: f32 = ids
+            .iter()
+            .map(|&id| if Signed::bucket(id).1 { -1.0 } else { 1.0 })
+            .sum();
+        assert_eq!(signed.out(&input), Vector::from_raw([expected]));
+        assert!(ids.iter().any(|&id| Signed::bucket(id).1));
+        assert!(ids.iter().all(|&id| !Unsigned::bucket(id).1));
+
+        let mut grad = Signed::zeroed();
+        let layers = signed.out_with_layers(&input);
+        signed.backprop(&input, &mut grad, Vector::from_raw([1.0]), &layers);
+        for &id in &ids {
+            let (bucket, _) = Signed::bucket(id);
+            assert_ne!(grad.layer().weights_row(bucket), Vector::from_raw([0.0]));
+        }
+    }
+}
@@ -1,9 +1,37 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod accumulator;
 mod add;
+mod bucketed;
+mod calibrate;
 mod conv1d;
 mod dense;
+mod export;
+mod factorized;
+mod hashed;
+mod mapped;
+mod net2net;
+mod perspective;
+mod quantized;
+mod report;
 mod sparse;
+mod weighted;
 
-pub use add::Add;
+pub use accumulator::Accumulator;
+pub use add::{Add, AddGrad};
+pub use bucketed::BucketedSparse;
+pub use calibrate::{calibrate, Calibration, LayerRange};
 pub use conv1d::Conv1D;
 pub use dense::DenseConnected;
+pub use export::{Export, QuantizedExport};
+pub use factorized::{FactorizedSparse, Factorizer};
+pub use hashed::HashedSparse;
+pub use mapped::{FeatureMap, MappedAccumulator, MappedSparse};
+pub use net2net::{widen, WidenInput, WidenOutput};
+pub use perspective::{PerspectiveAccumulator, SparsePerspective};
+pub use quantized::{Quantized, QuantizedDense, QuantizedSparse};
+pub use report::{Compare, ErrorStats, QuantizationReport};
 pub use sparse::SparseConnected;
+pub use weighted::WeightedSparse;
@@ -0,0 +1,356 @@
+use core::marker::PhantomData;
+
+use alloc::format;
+
+use goober_core::{
+    activation::Activation,
+    init::Distribution,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeatureOutOfBounds, FeedForwardNetwork, Float, OutputLayer, ParamVisitor,
+    ParamVisitorMut, Rand, SparseVector, Vector,
+};
+
+use crate::{sparse::SparseConnectedLayers, Accumulator, SparseConnected};
+
+/// Remaps feature indices given a runtime context, such
+/// as mirroring features horizontally based on king file.
+pub trait FeatureMap: Copy {
+    fn map(ctx: usize, feat: usize) -> usize;
+}
+
+/// [`SparseConnected`] layer whose input features are remapped
+/// by `R` before use, allowing symmetric features to share weights.
+/// - `T` is the activation function used.
+/// - `R` remaps the feature indices.
+/// - `M` is the size of the input vector, after remapping.
+/// - `N` is the size of the output vector.
+/// - `F` is the type weights and activations are stored as.
+///
+/// The input is a pair of the context passed to `R` (e.g. the
+/// king square) and the active features.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct MappedSparse<
+    T: Activation,
+    R: FeatureMap,
+    const M: usize,
+    const N: usize,
+    F: Float = f32,
+> {
+    layer: SparseConnected<T, M, N, F>,
+    phantom: PhantomData<R>,
+}
+
+/// Every parameter, as [`SparseConnected`].
+impl<T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float> AsRef<[F]>
+    for MappedSparse<T, R, M, N, F>
+{
+    fn as_ref(&self) -> &[F] {
+        self.layer.as_ref()
+    }
+}
+
+impl<T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float> AsMut<[F]>
+    for MappedSparse<T, R, M, N, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        self.layer.as_mut()
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        R: FeatureMap,
+        const M: usize,
+        const N: usize,
+        F: Float + bytemuck::Zeroable,
+    > bytemuck::Zeroable for MappedSparse<T, R, M, N, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float + bytemuck::Pod>
+    bytemuck::Pod for MappedSparse<T, R, M, N, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float>
+    core::ops::AddAssign<&MappedSparse<T, R, M, N, F>> for MappedSparse<T, R, M, N, F>
+{
+    fn add_assign(&mut self, rhs: &MappedSparse<T, R, M, N, F>) {
+        self.layer += &rhs.layer;
+    }
+}
+
+impl<T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float>
+    MappedSparse<T, R, M, N, F>
+{
+    pub const fn zeroed() -> Self {
+        Self::from_raw(SparseConnected::zeroed())
+    }
+
+    pub const fn from_raw(layer: SparseConnected<T, M, N, F>) -> Self {
+        Self {
+            layer,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Weights `w(i, j)` from input `i` to output `j`, and biases `b(j)`,
+    /// as [`SparseConnected::from_fn`].
+    pub fn from_fn<W: FnMut(usize, usize) -> F, B: FnMut(usize) -> F>(w: W, b: B) -> Self {
+        Self::from_raw(SparseConnected::from_fn(w, b))
+    }
+
+    /// Weights drawn from `dist`, such as [`Normal`](goober_core::init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(SparseConnected::random(rng, dist))
+    }
+
+    /// Weights drawn from [`glorot_uniform`](goober_core::init::glorot_uniform),
+    /// and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_uniform(rng))
+    }
+
+    /// Weights drawn from [`glorot_normal`](goober_core::init::glorot_normal),
+    /// and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_normal(rng))
+    }
+
+    /// Weights drawn from [`he_uniform`](goober_core::init::he_uniform),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_uniform(rng))
+    }
+
+    /// Weights drawn from [`he_normal`](goober_core::init::he_normal),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_normal(rng))
+    }
+
+    pub fn layer(&self) -> &SparseConnected<T, M, N, F> {
+        &self.layer
+    }
+
+    pub fn layer_mut(&mut self) -> &mut SparseConnected<T, M, N, F> {
+        &mut self.layer
+    }
+
+    /// Features as seen by the underlying layer in the given context.
+    pub fn map(ctx: usize, feats: &SparseVector) -> SparseVector {
+        let mut res = SparseVector::with_capacity(feats.len());
+        for &feat in feats.iter() {
+            res.push(R::map(ctx, feat));
+        }
+        res
+    }
+}
+
+pub struct MappedSparseLayers<const N: usize, F: Float = f32> {
+    out: SparseConnectedLayers<N, F>,
+}
+
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for MappedSparseLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        self.out.output_layer_ref()
+    }
+}
+
+impl<T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float> FeedForwardNetwork
+    for MappedSparse<T, R, M, N, F>
+{
+    type InputType = (usize, SparseVector);
+    type OutputType = Vector<N, F>;
+    type Layers = MappedSparseLayers<N, F>;
+    type Grad = MappedSparse<T, R, M, N, F::Compute>;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+        self.layer
+            .adam(&g.layer, &mut m.layer, &mut v.layer, adj, lr);
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params(prefix, visitor);
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params_mut(prefix, visitor);
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes("(usize, sparse)", format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        self.layer.visit_activations(prefix, &layers.out, visitor);
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        self.layer.check_input(&Self::map(input.0, &input.1))
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        Self::Layers {
+            out: self.layer.out_with_layers(&Self::map(input.0, &input.1)),
+        }
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        self.layer
+            .out_into(&Self::map(input.0, &input.1), &mut layers.out);
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        self.layer.backprop(
+            &Self::map(input.0, &input.1),
+            &mut grad.layer,
+            out_err,
+            &layers.out,
+        );
+        (input.0, SparseVector::with_capacity(0))
+    }
+}
+
+/// [`Accumulator`] over a [`MappedSparse`] layer, remapping
+/// features added and removed with the current context.
+///
+/// Changing the context requires a [`refresh`](Self::refresh).
+#[derive(Clone, Copy)]
+pub struct MappedAccumulator<
+    'a,
+    T: Activation,
+    R: FeatureMap,
+    const M: usize,
+    const N: usize,
+    F: Float = f32,
+> {
+    acc: Accumulator<'a, T, M, N, F>,
+    ctx: usize,
+    phantom: PhantomData<R>,
+}
+
+impl<'a, T: Activation, R: FeatureMap, const M: usize, const N: usize, F: Float>
+    MappedAccumulator<'a, T, R, M, N, F>
+{
+    /// Accumulator with no active features.
+    pub fn new(layer: &'a MappedSparse<T, R, M, N, F>, ctx: usize) -> Self {
+        Self {
+            acc: Accumulator::new(&layer.layer),
+            ctx,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn ctx(&self) -> usize {
+        self.ctx
+    }
+
+    pub fn add_feature(&mut self, idx: usize) {
+        self.acc.add_feature(R::map(self.ctx, idx));
+    }
+
+    pub fn remove_feature(&mut self, idx: usize) {
+        self.acc.remove_feature(R::map(self.ctx, idx));
+    }
+
+    /// Recomputes the accumulator from the full list of
+    /// active features, in the given context.
+    pub fn refresh(&mut self, ctx: usize, feats: &SparseVector) {
+        self.ctx = ctx;
+        self.acc
+            .refresh(&MappedSparse::<T, R, M, N, F>::map(ctx, feats));
+    }
+
+    pub fn values(&self) -> Vector<N, F> {
+        self.acc.values()
+    }
+
+    pub fn out(&self) -> Vector<N, F> {
+        self.acc.out()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FeatureMap, MappedAccumulator, MappedSparse};
+
+    #[derive(Clone, Copy)]
+    struct Mirror;
+    impl FeatureMap for Mirror {
+        fn map(ctx: usize, feat: usize) -> usize {
+            if ctx % 8 > 3 {
+                feat ^ 7
+            } else {
+                feat
+            }
+        }
+    }
+
+    #[test]
+    fn mapped_sparse() {
+        use crate::SparseConnected;
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector, Vector};
+
+        let layer: MappedSparse<ReLU, Mirror, 16, 2> =
+            MappedSparse::from_raw(SparseConnected::from_fn(|i, j| (i * 2 + j) as f32, |_| 0.0));
+
+        let mut feats = SparseVector::with_capacity(2);
+        feats.push(1);
+        feats.push(8);
+
+        assert_eq!(
+            layer.out(&(0, feats.clone())),
+            Vector::from_raw([18.0, 20.0])
+        );
+        assert_eq!(
+            layer.out(&(4, feats.clone())),
+            Vector::from_raw([42.0, 44.0])
+        );
+
+        let mut grad = MappedSparse::zeroed();
+        let input = (4, feats.clone());
+        let layers = layer.out_with_layers(&input);
+        layer.backprop(&input, &mut grad, Vector::from_raw([1.0, 1.0]), &layers);
+        assert_eq!(grad.layer().weights_row(1), Vector::from_raw([0.0, 0.0]));
+        assert_eq!(grad.layer().weights_row(6), Vector::from_raw([1.0, 1.0]));
+        assert_eq!(grad.layer().weights_row(15), Vector::from_raw([1.0, 1.0]));
+
+        let mut acc = MappedAccumulator::new(&layer, 4);
+        acc.add_feature(1);
+        acc.add_feature(8);
+        assert_eq!(acc.out(), layer.out(&input));
+
+        acc.refresh(0, &feats);
+        assert_eq!(acc.out(), layer.out(&(0, feats)));
+    }
+}
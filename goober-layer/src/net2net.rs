@@ -0,0 +1,177 @@
+//! Growing trained networks while preserving the function they compute,
+//! after [Net2Net](https://arxiv.org/abs/1511.05641), so that changes of
+//! architecture can continue from a trained network.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh},
+//! #     layer::{widen, DenseConnected, SparseConnected},
+//! #     FeedForwardNetwork, Rand,
+//! # };
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct Net {
+//! #     l1: SparseConnected<ReLU, 768, 256>,
+//! #     l2: DenseConnected<Tanh, 256, 1>,
+//! # }
+//! # #[derive(FeedForwardNetwork)]
+//! # pub struct WideNet {
+//! #     l1: SparseConnected<ReLU, 768, 512>,
+//! #     l2: DenseConnected<Tanh, 512, 1>,
+//! # }
+//! # let old = Net::boxed_and_zeroed();
+//! let (l1, l2) = widen::<_, _, 512>(&old.l1, &old.l2, &mut Rand::default(), 0.1);
+//! let mut wide = WideNet::boxed_and_zeroed();
+//! wide.l1 = *l1;
+//! wide.l2 = *l2;
+//! ```
+
+use alloc::{boxed::Box, vec};
+
+use goober_core::{activation::Activation, FeedForwardNetwork, Float, Rand};
+
+use crate::{DenseConnected, SparseConnected};
+
+/// Layer whose outputs can be widened to `W` units,
+/// each a copy of one of its existing units.
+pub trait WidenOutput<const W: usize> {
+    /// Number of output units before widening.
+    const UNITS: usize;
+
+    type Wider;
+
+    /// Layer with unit `j` computing the same as unit `source[j]` of `self`.
+    fn widen_output(&self, source: &[usize; W]) -> Box<Self::Wider>;
+}
+
+/// Layer whose inputs can be widened to `W`, consuming
+/// the outputs of a layer widened by [`WidenOutput`].
+pub trait WidenInput<const W: usize> {
+    type Wider;
+
+    /// Layer taking input `j` in place of input `source[j]` of `self`,
+    /// with the weights from it scaled by `share[j]`.
+    fn widen_input(&self, source: &[usize; W], share: &[f32; W]) -> Box<Self::Wider>;
+}
+
+impl<T: Activation, const M: usize, const N: usize, const W: usize, F: Float> WidenOutput<W>
+    for SparseConnected<T, M, N, F>
+{
+    const UNITS: usize = N;
+
+    type Wider = SparseConnected<T, M, W, F>;
+
+    fn widen_output(&self, source: &[usize; W]) -> Box<Self::Wider> {
+        let mut wider = Self::Wider::boxed_and_zeroed();
+        for i in 0..M {
+            let row = self.weights_row(i);
+            for (j, &src) in source.iter().enumerate() {
+                wider.weights_row_mut(i)[j] = row[src];
+            }
+        }
+
+        let bias = self.bias();
+        for (j, &src) in source.iter().enumerate() {
+            wider.bias_mut()[j] = bias[src];
+        }
+
+        wider
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const W: usize, F: Float> WidenOutput<W>
+    for DenseConnected<T, M, N, F>
+{
+    const UNITS: usize = N;
+
+    type Wider = DenseConnected<T, M, W, F>;
+
+    fn widen_output(&self, source: &[usize; W]) -> Box<Self::Wider> {
+        let mut wider = Self::Wider::boxed_and_zeroed();
+        let bias = self.bias();
+        for (j, &src) in source.iter().enumerate() {
+            *wider.weights_row_mut(j) = self.weights_row(src);
+            wider.bias_mut()[j] = bias[src];
+        }
+
+        wider
+    }
+}
+
+impl<T: Activation, const N: usize, const O: usize, const W: usize, F: Float> WidenInput<W>
+    for DenseConnected<T, N, O, F>
+{
+    type Wider = DenseConnected<T, W, O, F>;
+
+    fn widen_input(&self, source: &[usize; W], share: &[f32; W]) -> Box<Self::Wider> {
+        let mut wider = Self::Wider::boxed_and_zeroed();
+        for i in 0..O {
+            let row = self.weights_row(i);
+            for (j, &src) in source.iter().enumerate() {
+                wider.weights_row_mut(i)[j] = F::from_f32(row[src].to_f32() * share[j]);
+            }
+        }
+
+        *wider.bias_mut() = self.bias();
+        wider
+    }
+}
+
+/// Widens the `A::UNITS` units between `a` and `b` to `W`, by copying
+/// units chosen at random with `rng` and splitting the outgoing weights
+/// of each unit between its copies, so that the network still computes
+/// the same function.
+/// - Each copy takes a random share of the outgoing weights, varying by
+///   up to `noise` either side of an even split, so that the copies
+///   are trained differently, while the shares still sum to one.
+/// - Panics if `W` is less than `A::UNITS`.
+pub fn widen<A, B, const W: usize>(
+    a: &A,
+    b: &B,
+    rng: &mut Rand,
+    noise: f32,
+) -> (Box<A::Wider>, Box<B::Wider>)
+where
+    A: WidenOutput<W>,
+    B: WidenInput<W>,
+{
+    let units = A::UNITS;
+    assert!(W >= units, "cannot widen {units} units to {W}");
+
+    let mut source = [0; W];
+    for (j, src) in source.iter_mut().enumerate() {
+        *src = if j < units {
+            j
+        } else {
+            (rng.rand_u64() % units as u64) as usize
+        };
+    }
+
+    let mut share = [0.0; W];
+    for s in &mut share {
+        *s = 1.0 + noise * (2.0 * rng.rand_f32() - 1.0);
+    }
+
+    let mut totals = vec![0.0; units];
+    for (&src, &s) in source.iter().zip(&share) {
+        totals[src] += s;
+    }
+    for (s, &src) in share.iter_mut().zip(&source) {
+        *s /= totals[src];
+    }
+
+    (a.widen_output(&source), b.widen_input(&source, &share))
+}
+
+impl<T: Activation, const N: usize, F: Float> DenseConnected<T, N, N, F> {
+    /// Layer passing its input through unchanged, up to its activation,
+    /// for inserting into a trained network to deepen it.
+    /// - Preserves the function of the network when the activation
+    ///   leaves the input unchanged, such as ReLU after a ReLU layer.
+    pub fn identity() -> Box<Self> {
+        let mut layer = Self::boxed_and_zeroed();
+        for i in 0..N {
+            layer.weights_row_mut(i)[i] = F::from_f32(1.0);
+        }
+        layer
+    }
+}
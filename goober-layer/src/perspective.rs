@@ -0,0 +1,416 @@
+use alloc::format;
+
+use goober_core::{
+    activation::Activation,
+    init::Distribution,
+    param_name,
+    qat::Qat,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeatureOutOfBounds, FeedForwardNetwork, Float, OutputLayer, ParamVisitor,
+    ParamVisitorMut, Rand, SparseVector, Vector,
+};
+
+use crate::{sparse::SparseConnectedLayers, Accumulator, SparseConnected};
+
+/// Applies a shared [`SparseConnected`] layer to the features from
+/// the perspective of each side, concatenating the two outputs with
+/// the side to move first.
+/// - `T` is the activation function used.
+/// - `M` is the size of the input vector.
+/// - `N` is the size of the output of each perspective.
+/// - `O` is the size of the output vector, and must be `2 * N`.
+/// - `F` is the type weights and activations are stored as.
+///
+/// The input is a pair of the side to move's features and the
+/// other side's features.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct SparsePerspective<
+    T: Activation,
+    const M: usize,
+    const N: usize,
+    const O: usize,
+    F: Float = f32,
+> {
+    layer: SparseConnected<T, M, N, F>,
+}
+
+/// Every parameter, as [`SparseConnected`].
+impl<T: Activation, const M: usize, const N: usize, const O: usize, F: Float> AsRef<[F]>
+    for SparsePerspective<T, M, N, O, F>
+{
+    fn as_ref(&self) -> &[F] {
+        self.layer.as_ref()
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const O: usize, F: Float> AsMut<[F]>
+    for SparsePerspective<T, M, N, O, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        self.layer.as_mut()
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<
+        T: Activation,
+        const M: usize,
+        const N: usize,
+        const O: usize,
+        F: Float + bytemuck::Zeroable,
+    > bytemuck::Zeroable for SparsePerspective<T, M, N, O, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, const O: usize, F: Float + bytemuck::Pod>
+    bytemuck::Pod for SparsePerspective<T, M, N, O, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, const M: usize, const N: usize, const O: usize, F: Float>
+    core::ops::AddAssign<&SparsePerspective<T, M, N, O, F>> for SparsePerspective<T, M, N, O, F>
+{
+    fn add_assign(&mut self, rhs: &SparsePerspective<T, M, N, O, F>) {
+        self.layer += &rhs.layer;
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const O: usize, F: Float>
+    SparsePerspective<T, M, N, O, F>
+{
+    const VALID: () = assert!(O == 2 * N, "output size must be twice the perspective size");
+
+    pub const fn zeroed() -> Self {
+        Self::from_raw(SparseConnected::zeroed())
+    }
+
+    pub const fn from_raw(layer: SparseConnected<T, M, N, F>) -> Self {
+        let () = Self::VALID;
+        Self { layer }
+    }
+
+    /// Weights `w(i, j)` from input `i` to output `j`, and biases `b(j)`,
+    /// as [`SparseConnected::from_fn`].
+    pub fn from_fn<W: FnMut(usize, usize) -> F, B: FnMut(usize) -> F>(w: W, b: B) -> Self {
+        Self::from_raw(SparseConnected::from_fn(w, b))
+    }
+
+    /// Weights drawn from `dist`, such as [`Normal`](goober_core::init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(SparseConnected::random(rng, dist))
+    }
+
+    /// As [`out`](FeedForwardNetwork::out), but returns an error
+    /// rather than panicking if a feature index is out of bounds.
+    pub fn try_out(
+        &self,
+        input: &(SparseVector, SparseVector),
+    ) -> Result<<Self as FeedForwardNetwork>::OutputType, FeatureOutOfBounds> {
+        input.0.check_bounds(M)?;
+        input.1.check_bounds(M)?;
+        Ok(self.out(input))
+    }
+
+    /// As [`backprop`](FeedForwardNetwork::backprop), but returns an error,
+    /// leaving `grad` unchanged, rather than panicking if a feature index
+    /// is out of bounds.
+    pub fn try_backprop(
+        &self,
+        input: &(SparseVector, SparseVector),
+        grad: &mut <Self as FeedForwardNetwork>::Grad,
+        out_err: <Self as FeedForwardNetwork>::OutputType,
+        layers: &<Self as FeedForwardNetwork>::Layers,
+    ) -> Result<(SparseVector, SparseVector), FeatureOutOfBounds> {
+        input.0.check_bounds(M)?;
+        input.1.check_bounds(M)?;
+        Ok(self.backprop(input, grad, out_err, layers))
+    }
+
+    /// Weights drawn from [`glorot_uniform`](goober_core::init::glorot_uniform),
+    /// and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_uniform(rng))
+    }
+
+    /// Weights drawn from [`glorot_normal`](goober_core::init::glorot_normal),
+    /// and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_normal(rng))
+    }
+
+    /// Weights drawn from [`he_uniform`](goober_core::init::he_uniform),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_uniform(rng))
+    }
+
+    /// Weights drawn from [`he_normal`](goober_core::init::he_normal),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_normal(rng))
+    }
+
+    pub fn layer(&self) -> &SparseConnected<T, M, N, F> {
+        &self.layer
+    }
+
+    pub fn layer_mut(&mut self) -> &mut SparseConnected<T, M, N, F> {
+        &mut self.layer
+    }
+}
+
+pub struct SparsePerspectiveLayers<const N: usize, const O: usize, F: Float = f32> {
+    stm: SparseConnectedLayers<N, F>,
+    nstm: SparseConnectedLayers<N, F>,
+    /// Outputs of both perspectives, concatenated once per forward pass.
+    out: Vector<O, F>,
+}
+
+impl<const N: usize, const O: usize, F: Float> OutputLayer<Vector<O, F>>
+    for SparsePerspectiveLayers<N, O, F>
+{
+    fn output_layer_ref(&self) -> &Vector<O, F> {
+        &self.out
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, const O: usize, F: Float> FeedForwardNetwork
+    for SparsePerspective<T, M, N, O, F>
+{
+    type InputType = (SparseVector, SparseVector);
+    type OutputType = Vector<O, F>;
+    type Layers = SparsePerspectiveLayers<N, O, F>;
+    type Grad = SparsePerspective<T, M, N, O, F::Compute>;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+        self.layer
+            .adam(&g.layer, &mut m.layer, &mut v.layer, adj, lr);
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params(prefix, visitor);
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params_mut(prefix, visitor);
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("2 × sparse [{M}]"), format!("[{O}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        self.layer
+            .visit_activations(&param_name(prefix, "stm"), &layers.stm, visitor);
+        self.layer
+            .visit_activations(&param_name(prefix, "nstm"), &layers.nstm, visitor);
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        input.0.check_bounds(M)?;
+        input.1.check_bounds(M)
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let () = Self::VALID;
+        let stm = self.layer.out_with_layers(&input.0);
+        let nstm = self.layer.out_with_layers(&input.1);
+        let out = concat(stm.output_layer_ref(), nstm.output_layer_ref());
+        Self::Layers { stm, nstm, out }
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        let () = Self::VALID;
+        self.layer.out_into(&input.0, &mut layers.stm);
+        self.layer.out_into(&input.1, &mut layers.nstm);
+        layers.out = concat(
+            layers.stm.output_layer_ref(),
+            layers.nstm.output_layer_ref(),
+        );
+    }
+
+    fn out_into_qat(
+        &self,
+        prefix: &str,
+        input: &Self::InputType,
+        layers: &mut Self::Layers,
+        qat: &Qat,
+    ) {
+        let () = Self::VALID;
+        // both perspectives share the parameters, and so the name, of `layer`
+        self.layer
+            .out_into_qat(prefix, &input.0, &mut layers.stm, qat);
+        self.layer
+            .out_into_qat(prefix, &input.1, &mut layers.nstm, qat);
+        layers.out = concat(
+            layers.stm.output_layer_ref(),
+            layers.nstm.output_layer_ref(),
+        );
+    }
+
+    fn clamp_qat(&mut self, prefix: &str, qat: &Qat) {
+        self.layer.clamp_qat(prefix, qat);
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        let stm_err = Vector::from_fn(|i| out_err[i]);
+        let nstm_err = Vector::from_fn(|i| out_err[N + i]);
+
+        self.layer
+            .backprop(&input.0, &mut grad.layer, stm_err, &layers.stm);
+        self.layer
+            .backprop(&input.1, &mut grad.layer, nstm_err, &layers.nstm);
+
+        (
+            SparseVector::with_capacity(0),
+            SparseVector::with_capacity(0),
+        )
+    }
+}
+
+/// Pair of [`Accumulator`]s over a shared layer, one from
+/// white's perspective and one from black's perspective.
+#[derive(Clone, Copy)]
+pub struct PerspectiveAccumulator<
+    'a,
+    T: Activation,
+    const M: usize,
+    const N: usize,
+    const O: usize,
+    F: Float = f32,
+> {
+    accs: [Accumulator<'a, T, M, N, F>; 2],
+}
+
+impl<'a, T: Activation, const M: usize, const N: usize, const O: usize, F: Float>
+    PerspectiveAccumulator<'a, T, M, N, O, F>
+{
+    pub const WHITE: usize = 0;
+    pub const BLACK: usize = 1;
+
+    /// Accumulators with no active features.
+    pub fn new(layer: &'a SparsePerspective<T, M, N, O, F>) -> Self {
+        Self {
+            accs: [
+                Accumulator::new(&layer.layer),
+                Accumulator::new(&layer.layer),
+            ],
+        }
+    }
+
+    pub fn accumulator(&self, side: usize) -> &Accumulator<'a, T, M, N, F> {
+        &self.accs[side]
+    }
+
+    pub fn accumulator_mut(&mut self, side: usize) -> &mut Accumulator<'a, T, M, N, F> {
+        &mut self.accs[side]
+    }
+
+    /// Adds a feature, given its index from each side's perspective.
+    pub fn add_feature(&mut self, white: usize, black: usize) {
+        self.accs[Self::WHITE].add_feature(white);
+        self.accs[Self::BLACK].add_feature(black);
+    }
+
+    /// Removes a feature, given its index from each side's perspective.
+    pub fn remove_feature(&mut self, white: usize, black: usize) {
+        self.accs[Self::WHITE].remove_feature(white);
+        self.accs[Self::BLACK].remove_feature(black);
+    }
+
+    pub fn refresh(&mut self, white: &SparseVector, black: &SparseVector) {
+        self.accs[Self::WHITE].refresh(white);
+        self.accs[Self::BLACK].refresh(black);
+    }
+
+    /// Input to the rest of the network, with the perspective of
+    /// `stm`, the side to move, first.
+    pub fn select(&self, stm: usize) -> Vector<O, F> {
+        concat(&self.accs[stm].out(), &self.accs[stm ^ 1].out())
+    }
+}
+
+fn concat<const N: usize, const O: usize, F: Float>(
+    a: &Vector<N, F>,
+    b: &Vector<N, F>,
+) -> Vector<O, F> {
+    Vector::from_fn(|i| if i < N { a[i] } else { b[i - N] })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PerspectiveAccumulator, SparsePerspective};
+
+    #[test]
+    fn sparse_perspective() {
+        use crate::SparseConnected;
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector, Vector};
+
+        let layer: SparsePerspective<ReLU, 8, 2, 4> = SparsePerspective::from_raw(
+            SparseConnected::from_fn(|i, j| (i * 2 + j) as f32 / 4.0, |i| i as f32),
+        );
+
+        let mut white = SparseVector::with_capacity(2);
+        white.push(1);
+        white.push(2);
+
+        let mut black = SparseVector::with_capacity(2);
+        black.push(5);
+        black.push(6);
+
+        let input = (black.clone(), white.clone());
+        let out = layer.out(&input);
+        assert_eq!(out, Vector::from_raw([5.5, 7.0, 1.5, 3.0]));
+
+        let mut acc = PerspectiveAccumulator::new(&layer);
+        acc.refresh(&white, &black);
+        assert_eq!(acc.select(1), out);
+
+        acc.remove_feature(2, 6);
+        acc.add_feature(3, 7);
+        white = SparseVector::with_capacity(2);
+        white.push(1);
+        white.push(3);
+        black = SparseVector::with_capacity(2);
+        black.push(5);
+        black.push(7);
+        assert_eq!(acc.select(0), layer.out(&(white, black)));
+
+        let mut grad = SparsePerspective::zeroed();
+        let layers = layer.out_with_layers(&input);
+        let err = Vector::from_raw([1.0, 2.0, 3.0, 4.0]);
+        layer.backprop(&input, &mut grad, err, &layers);
+
+        assert_eq!(grad.layer().bias(), Vector::from_raw([4.0, 6.0]));
+        assert_eq!(grad.layer().weights_row(5), Vector::from_raw([1.0, 2.0]));
+        assert_eq!(grad.layer().weights_row(1), Vector::from_raw([3.0, 4.0]));
+    }
+}
@@ -0,0 +1,455 @@
+use core::marker::PhantomData;
+
+use alloc::boxed::Box;
+
+use goober_core::{activation::Activation, boxed_zeroed, SparseVector};
+
+/// Integer type that quantized sparse weights can be stored as.
+pub trait Quantized: Copy + Into<i32> + 'static {
+    /// Integer type that weights are summed into.
+    type Accumulator: Copy + Into<i32>;
+
+    const MIN: Self;
+
+    const MAX: Self;
+
+    const ACCUMULATOR_MAX: Self::Accumulator;
+
+    fn quantize(x: f32, scale: i32) -> Self;
+
+    fn quantize_accumulator(x: f32, scale: i32) -> Self::Accumulator;
+
+    /// Adds `x` to `acc`, saturating at the bounds of the accumulator.
+    fn accumulate(acc: Self::Accumulator, x: Self) -> Self::Accumulator;
+}
+
+impl Quantized for i16 {
+    type Accumulator = i32;
+
+    const MIN: Self = i16::MIN;
+
+    const MAX: Self = i16::MAX;
+
+    const ACCUMULATOR_MAX: i32 = i32::MAX;
+
+    fn quantize(x: f32, scale: i32) -> Self {
+        quantize_i16(x, scale)
+    }
+
+    fn quantize_accumulator(x: f32, scale: i32) -> i32 {
+        quantize_i32(x, scale)
+    }
+
+    fn accumulate(acc: i32, x: Self) -> i32 {
+        acc.saturating_add(i32::from(x))
+    }
+}
+
+impl Quantized for i8 {
+    type Accumulator = i16;
+
+    const MIN: Self = i8::MIN;
+
+    const MAX: Self = i8::MAX;
+
+    const ACCUMULATOR_MAX: i16 = i16::MAX;
+
+    fn quantize(x: f32, scale: i32) -> Self {
+        libm::roundf(x * scale as f32).clamp(f32::from(i8::MIN), f32::from(i8::MAX)) as i8
+    }
+
+    fn quantize_accumulator(x: f32, scale: i32) -> i16 {
+        quantize_i16(x, scale)
+    }
+
+    fn accumulate(acc: i16, x: Self) -> i16 {
+        acc.saturating_add(i16::from(x))
+    }
+}
+
+/// Quantized Fully-Connected layer, for inference only.
+/// - `T` is the activation function used.
+/// - `M` is the size of the input vector.
+/// - `N` is the size of the output vector.
+///
+/// Inputs are expected at `input_scale`, each row of weights is stored
+/// at its own weight scale, and outputs are produced at `input_scale`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct QuantizedDense<T: Activation, const M: usize, const N: usize> {
+    weights: [[i16; M]; N],
+    bias: [i32; N],
+    input_scale: i32,
+    weight_scales: [i32; N],
+    phantom: PhantomData<T>,
+}
+
+impl<T: Activation, const M: usize, const N: usize> QuantizedDense<T, M, N> {
+    pub fn from_fn<W: FnMut(usize, usize) -> f32, B: FnMut(usize) -> f32>(
+        w: W,
+        b: B,
+        input_scale: i16,
+        weight_scale: i16,
+    ) -> Box<Self> {
+        Self::from_fn_per_row(w, b, input_scale, [weight_scale; N])
+    }
+
+    pub fn from_fn_per_row<W: FnMut(usize, usize) -> f32, B: FnMut(usize) -> f32>(
+        mut w: W,
+        mut b: B,
+        input_scale: i16,
+        weight_scales: [i16; N],
+    ) -> Box<Self> {
+        // SAFETY: quantized layers are made of integers, for which all zero bits is zero
+        let mut res: Box<Self> = unsafe { boxed_zeroed() };
+        let input_scale = i32::from(input_scale);
+
+        for (i, row) in res.weights.iter_mut().enumerate() {
+            let weight_scale = i32::from(weight_scales[i]);
+            for (j, weight) in row.iter_mut().enumerate() {
+                *weight = quantize_i16(w(i, j), weight_scale);
+            }
+        }
+
+        for (i, bias) in res.bias.iter_mut().enumerate() {
+            *bias = quantize_i32(b(i), input_scale * i32::from(weight_scales[i]));
+        }
+
+        res.input_scale = input_scale;
+        res.weight_scales = weight_scales.map(i32::from);
+        res
+    }
+
+    pub fn weights_row(&self, idx: usize) -> &[i16; M] {
+        &self.weights[idx]
+    }
+
+    pub fn bias(&self) -> &[i32; N] {
+        &self.bias
+    }
+
+    pub fn input_scale(&self) -> i32 {
+        self.input_scale
+    }
+
+    pub fn weight_scale(&self, idx: usize) -> i32 {
+        self.weight_scales[idx]
+    }
+
+    pub fn output_scale(&self) -> i32 {
+        self.input_scale
+    }
+
+    /// Pre-activation accumulators, at `input_scale` multiplied
+    /// by the weight scale of each row, saturating at the bounds of `i32`.
+    pub fn accumulate(&self, input: &[i16; M]) -> [i32; N] {
+        let mut res = self.bias;
+
+        for (acc, row) in res.iter_mut().zip(self.weights.iter()) {
+            for (&w, &x) in row.iter().zip(input.iter()) {
+                *acc = acc.saturating_add(i32::from(w) * i32::from(x));
+            }
+        }
+
+        res
+    }
+
+    pub fn out(&self, input: &[i16; M]) -> [i16; N] {
+        let acc = self.accumulate(input);
+        core::array::from_fn(|i| {
+            activate::<T>(div_round(acc[i], self.weight_scales[i]), self.input_scale)
+        })
+    }
+}
+
+/// Quantized Fully-Connected layer with sparse input, for inference only.
+/// - `T` is the activation function used.
+/// - `M` is the size of the input vector.
+/// - `N` is the size of the output vector.
+/// - `Q` is the type weights are stored as.
+///
+/// Weights are stored at `scale`, and outputs are produced at `scale`.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct QuantizedSparse<T: Activation, const M: usize, const N: usize, Q: Quantized = i16> {
+    weights: [[Q; N]; M],
+    bias: [Q::Accumulator; N],
+    scale: i32,
+    phantom: PhantomData<T>,
+}
+
+impl<T: Activation, const M: usize, const N: usize, Q: Quantized> QuantizedSparse<T, M, N, Q> {
+    pub fn from_fn<W: FnMut(usize, usize) -> f32, B: FnMut(usize) -> f32>(
+        mut w: W,
+        mut b: B,
+        scale: i16,
+    ) -> Box<Self> {
+        // SAFETY: quantized layers are made of integers, for which all zero bits is zero
+        let mut res: Box<Self> = unsafe { boxed_zeroed() };
+        let scale = i32::from(scale);
+
+        for (i, row) in res.weights.iter_mut().enumerate() {
+            for (j, weight) in row.iter_mut().enumerate() {
+                *weight = Q::quantize(w(i, j), scale);
+            }
+        }
+
+        for (i, bias) in res.bias.iter_mut().enumerate() {
+            *bias = Q::quantize_accumulator(b(i), scale);
+        }
+
+        res.scale = scale;
+        res
+    }
+
+    pub fn weights_row(&self, idx: usize) -> &[Q; N] {
+        &self.weights[idx]
+    }
+
+    pub fn bias(&self) -> &[Q::Accumulator; N] {
+        &self.bias
+    }
+
+    pub fn scale(&self) -> i32 {
+        self.scale
+    }
+
+    pub fn output_scale(&self) -> i32 {
+        self.scale
+    }
+
+    /// Pre-activation accumulators, at `scale`.
+    pub fn accumulate(&self, input: &SparseVector) -> [Q::Accumulator; N] {
+        let mut res = self.bias;
+
+        for &feat in input.iter() {
+            for (acc, &w) in res.iter_mut().zip(self.weights[feat].iter()) {
+                *acc = Q::accumulate(*acc, w);
+            }
+        }
+
+        res
+    }
+
+    pub fn out(&self, input: &SparseVector) -> [i16; N] {
+        let acc = self.accumulate(input);
+        core::array::from_fn(|i| activate::<T>(acc[i].into(), self.scale))
+    }
+}
+
+/// Rounds weight `x` to the nearest value representable as a `Q` at `scale`.
+pub(crate) fn fake_quantize<Q: Quantized>(x: f32, scale: i32) -> f32 {
+    Q::quantize(x, scale).into() as f32 / scale as f32
+}
+
+/// Rounds bias `x` to the nearest value representable as a `Q::Accumulator` at `scale`.
+pub(crate) fn fake_quantize_accumulator<Q: Quantized>(x: f32, scale: i32) -> f32 {
+    (f64::from(Q::quantize_accumulator(x, scale).into()) / f64::from(scale)) as f32
+}
+
+/// Clamps weight `x` to the range representable as a `Q` at `scale`.
+pub(crate) fn clamp_quantized<Q: Quantized>(x: f32, scale: i32) -> f32 {
+    let scale = scale as f32;
+    x.clamp(Q::MIN.into() as f32 / scale, Q::MAX.into() as f32 / scale)
+}
+
+/// Largest scale, up to `i16::MAX`, at which values of magnitude
+/// up to `max_abs` are representable below `limit`.
+pub(crate) fn largest_scale(max_abs: f32, limit: i32) -> i32 {
+    if max_abs == 0.0 {
+        return i32::from(i16::MAX);
+    }
+
+    libm::floorf(limit as f32 / max_abs).clamp(1.0, f32::from(i16::MAX)) as i32
+}
+
+fn quantize_i16(x: f32, scale: i32) -> i16 {
+    libm::roundf(x * scale as f32).clamp(f32::from(i16::MIN), f32::from(i16::MAX)) as i16
+}
+
+fn quantize_i32(x: f32, scale: i32) -> i32 {
+    libm::round(f64::from(x) * f64::from(scale)).clamp(f64::from(i32::MIN), f64::from(i32::MAX))
+        as i32
+}
+
+/// Applies `T` to `x`, a value quantized at `scale`, producing
+/// an output quantized at the same scale.
+fn activate<T: Activation>(x: i32, scale: i32) -> i16 {
+    quantize_i16(T::activate(x as f32 / scale as f32), scale)
+}
+
+/// `x / d`, for positive `d`, rounded to the nearest integer,
+/// with ties away from zero.
+fn div_round(x: i32, d: i32) -> i32 {
+    let (x, d) = (i64::from(x), i64::from(d));
+    let rounded = (2 * x + x.signum() * d) / (2 * d);
+    rounded as i32
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{DenseConnected, QuantizedDense, QuantizedSparse, SparseConnected};
+
+    #[test]
+    fn quantized() {
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector};
+
+        let sparse: SparseConnected<ReLU, 8, 4> = SparseConnected::from_fn(
+            |i, j| ((i * 4 + j) % 5) as f32 / 10.0 - 0.2,
+            |i| i as f32 / 20.0,
+        );
+        let dense: DenseConnected<ReLU, 4, 2> =
+            DenseConnected::from_fn(|i, j| (i + j) as f32 / 4.0 - 0.3, |i| i as f32 / 10.0);
+
+        let qsparse = sparse.quantize(255);
+        let qdense = dense.quantize(255, 64);
+
+        let mut input = SparseVector::with_capacity(8);
+        input.push(1);
+        input.push(6);
+
+        let expected = dense.out(&sparse.out(&input));
+        let out = qdense.out(&qsparse.out(&input));
+        let scale = qdense.output_scale() as f32;
+
+        for (&q, &e) in out.iter().zip([expected[0], expected[1]].iter()) {
+            assert!((f32::from(q) / scale - e).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn dense_rounds_and_saturates() {
+        use goober_core::activation::Identity;
+
+        let layer = QuantizedDense::<Identity, 3, 1>::from_fn(|_, _| 0.5, |_| 0.0, 1, 2);
+        assert_eq!(layer.out(&[3, 0, 0]), [2]);
+        assert_eq!(layer.out(&[-3, 0, 0]), [-2]);
+
+        let layer = QuantizedDense::<Identity, 3, 1>::from_fn(|_, _| 32767.0, |_| 0.0, 1, 1);
+        assert_eq!(layer.accumulate(&[i16::MAX; 3]), [i32::MAX]);
+        assert_eq!(layer.accumulate(&[i16::MIN; 3]), [i32::MIN]);
+    }
+
+    #[test]
+    fn quantized_i8() {
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector};
+
+        let sparse: SparseConnected<ReLU, 16, 8> = SparseConnected::from_fn(
+            |i, j| ((i * 8 + j) % 9) as f32 / 8.0 - 0.5,
+            |i| i as f32 / 16.0,
+        );
+
+        let qsparse = sparse.quantize_i8(64);
+
+        let mut input = SparseVector::with_capacity(8);
+        for feat in [0, 3, 4, 9, 15] {
+            input.push(feat);
+        }
+
+        let expected = sparse.out(&input);
+        let out = qsparse.out(&input);
+        let scale = qsparse.output_scale() as f32;
+
+        for (i, &q) in out.iter().enumerate() {
+            assert!((f32::from(q) / scale - expected[i]).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn calibrated() {
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector};
+
+        let sparse: SparseConnected<ReLU, 8, 4> = SparseConnected::from_fn(
+            |i, j| ((i * 4 + j) % 5) as f32 / 2.0 - 1.0,
+            |i| i as f32 / 4.0,
+        );
+        let dense: DenseConnected<ReLU, 4, 2> = DenseConnected::from_fn(
+            |i, j| {
+                if i == 0 {
+                    j as f32 / 20.0
+                } else {
+                    -2.0 * (j + 1) as f32
+                }
+            },
+            |_| 0.0,
+        );
+
+        let inputs: Vec<SparseVector> = (0..8)
+            .map(|i| {
+                let mut input = SparseVector::with_capacity(3);
+                input.push(i);
+                input.push((i + 3) % 8);
+                input.push((i * 5 + 1) % 8);
+                input
+            })
+            .collect();
+
+        let hidden: Vec<_> = inputs.iter().map(|input| sparse.out(input)).collect();
+
+        let input_scale = sparse.calibrate(&inputs);
+        let weight_scales = dense.calibrate_per_row(&hidden, input_scale);
+        assert!(weight_scales[0] > weight_scales[1]);
+
+        let qsparse = sparse.quantize(input_scale);
+        let qdense = dense.quantize_per_row(input_scale, weight_scales);
+        let scale = qdense.output_scale() as f32;
+
+        for (input, hidden) in inputs.iter().zip(hidden.iter()) {
+            let expected = dense.out(hidden);
+            let out = qdense.out(&qsparse.out(input));
+
+            for (&q, &e) in out.iter().zip([expected[0], expected[1]].iter()) {
+                assert!((f32::from(q) / scale - e).abs() < 0.001);
+            }
+        }
+    }
+
+    #[test]
+    fn fake_quantized() {
+        use goober_core::{activation::ReLU, FeedForwardNetwork, Vector};
+
+        let mut dense: DenseConnected<ReLU, 4, 2> =
+            DenseConnected::from_fn(|i, j| (i + j) as f32 / 3.0 - 0.3, |_| 0.1);
+
+        dense.clamp_quantized(64);
+        assert_eq!(dense.weights_row(1)[3], 4.0 / 3.0 - 0.3);
+
+        let mut clamped = dense;
+        *clamped.weights_row_mut(0) = Vector::from_raw([1000.0; 4]);
+        clamped.clamp_quantized(64);
+        assert_eq!(clamped.weights_row(0)[0], 32767.0 / 64.0);
+
+        dense.fake_quantize(255, 64);
+        let qdense = dense.quantize(255, 64);
+
+        for i in 0..2 {
+            for j in 0..4 {
+                let w = f32::from(qdense.weights_row(i)[j]) / 64.0;
+                assert_eq!(w, dense.weights_row(i)[j]);
+            }
+        }
+
+        let input = Vector::from_raw([0.5, 0.25, 1.0, 0.0]);
+        let quantized_input = [128, 64, 255, 0];
+        let out = qdense.out(&quantized_input);
+        let expected = dense.out(&input);
+
+        for (&q, &e) in out.iter().zip([expected[0], expected[1]].iter()) {
+            assert!((f32::from(q) / 255.0 - e).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn quantized_i8_saturates() {
+        use goober_core::{activation::Identity, SparseVector};
+
+        let qsparse = QuantizedSparse::<Identity, 4, 1, i8>::from_fn(|_, _| 1.9, |_| 0.0, 64);
+
+        let mut input = SparseVector::with_capacity(1024);
+        for _ in 0..1024 {
+            input.push(0);
+        }
+
+        assert_eq!(qsparse.accumulate(&input), [i16::MAX]);
+    }
+}
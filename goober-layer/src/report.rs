@@ -0,0 +1,273 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use goober_core::{
+    activation::Activation, ActivationVisitor, FeedForwardNetwork, Float, SparseVector, Vector,
+};
+
+use crate::{Quantized, QuantizedDense, QuantizedSparse};
+
+/// Quantized layer which can be run by [`QuantizationReport::compare`].
+pub trait Compare {
+    /// Outputs of the layer, given the input to the network and
+    /// the outputs of the previous layer, empty for the first layer.
+    fn compare_out(&self, input: &SparseVector, prev: &[i16]) -> Vec<i16>;
+
+    fn compare_scale(&self) -> i32;
+}
+
+impl<T: Activation, const M: usize, const N: usize> Compare for QuantizedDense<T, M, N> {
+    fn compare_out(&self, _: &SparseVector, prev: &[i16]) -> Vec<i16> {
+        let prev = prev
+            .try_into()
+            .expect("dense layer input size matches the previous layer");
+        self.out(prev).to_vec()
+    }
+
+    fn compare_scale(&self) -> i32 {
+        self.output_scale()
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, Q: Quantized> Compare
+    for QuantizedSparse<T, M, N, Q>
+{
+    fn compare_out(&self, input: &SparseVector, _: &[i16]) -> Vec<i16> {
+        self.out(input).to_vec()
+    }
+
+    fn compare_scale(&self) -> i32 {
+        self.output_scale()
+    }
+}
+
+/// Running statistics of the absolute error between
+/// float outputs and their quantized counterparts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ErrorStats {
+    max: f32,
+    total: f64,
+    count: usize,
+}
+
+impl ErrorStats {
+    /// Records the error of `quantized`, produced at `scale`,
+    /// against the float reference `expected`.
+    pub fn record<const N: usize, F: Float>(
+        &mut self,
+        expected: &Vector<N, F>,
+        quantized: &[i16; N],
+        scale: i32,
+    ) {
+        self.record_slice(expected.as_slice(), quantized, scale);
+    }
+
+    fn record_slice<F: Float>(&mut self, expected: &[F], quantized: &[i16], scale: i32) {
+        for (x, &q) in expected.iter().zip(quantized) {
+            let err = (f32::from(q) / scale as f32 - x.to_f32()).abs();
+            self.max = self.max.max(err);
+            self.total += f64::from(err);
+            self.count += 1;
+        }
+    }
+
+    pub fn max(&self) -> f32 {
+        self.max
+    }
+
+    pub fn mean(&self) -> f32 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.total / self.count as f64) as f32
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+/// Per-layer error statistics from running a sample set through
+/// a network and its quantized counterpart, in the order that
+/// layers were first recorded - the last being the final output.
+#[derive(Clone, Debug, Default)]
+pub struct QuantizationReport {
+    layers: Vec<(String, ErrorStats)>,
+}
+
+impl QuantizationReport {
+    /// Runs `inputs` through `net` and through `quantized`, its quantized
+    /// layers by name, recording the error of the outputs of each against
+    /// those of the layer of `net` of the same name.
+    /// - Quantized layers are run in order, each taking the outputs of
+    ///   the previous layer, so the last should be the output layer.
+    /// - Layers are named as by [`FeedForwardNetwork::visit_activations`].
+    ///
+    /// # Panics
+    /// If a layer of `net` of the same name does not visit its outputs, or
+    /// if the sizes of consecutive layers do not match.
+    pub fn compare<T: FeedForwardNetwork<InputType = SparseVector>>(
+        net: &T,
+        quantized: &[(&str, &dyn Compare)],
+        inputs: &[SparseVector],
+    ) -> Self {
+        let mut report = Self::default();
+        let mut layers = None;
+        let mut expected = Activations::default();
+
+        for input in inputs {
+            let layers = match &mut layers {
+                Some(layers) => {
+                    net.out_into(input, layers);
+                    layers
+                }
+                None => layers.insert(net.out_with_layers(input)),
+            };
+            expected.0.clear();
+            net.visit_activations("", layers, &mut expected);
+
+            let mut prev = Vec::new();
+            for &(name, layer) in quantized {
+                let (_, values) = expected
+                    .0
+                    .iter()
+                    .find(|(layer, _)| layer == name)
+                    .unwrap_or_else(|| panic!("layer {name} visits its outputs"));
+
+                prev = layer.compare_out(input, &prev);
+                report
+                    .layer_mut(name)
+                    .record_slice(values, &prev, layer.compare_scale());
+            }
+        }
+
+        report
+    }
+
+    pub fn record<const N: usize, F: Float>(
+        &mut self,
+        layer: &str,
+        expected: &Vector<N, F>,
+        quantized: &[i16; N],
+        scale: i32,
+    ) {
+        self.layer_mut(layer).record(expected, quantized, scale);
+    }
+
+    pub fn layer(&self, layer: &str) -> Option<&ErrorStats> {
+        self.layers
+            .iter()
+            .find(|(name, _)| name == layer)
+            .map(|(_, stats)| stats)
+    }
+
+    pub fn layers(&self) -> impl Iterator<Item = (&str, &ErrorStats)> {
+        self.layers
+            .iter()
+            .map(|(name, stats)| (name.as_str(), stats))
+    }
+
+    /// Statistics of the last layer recorded.
+    pub fn output(&self) -> Option<&ErrorStats> {
+        self.layers.last().map(|(_, stats)| stats)
+    }
+
+    fn layer_mut(&mut self, layer: &str) -> &mut ErrorStats {
+        let idx = match self.layers.iter().position(|(name, _)| name == layer) {
+            Some(idx) => idx,
+            None => {
+                self.layers.push((layer.to_string(), ErrorStats::default()));
+                self.layers.len() - 1
+            }
+        };
+
+        &mut self.layers[idx].1
+    }
+}
+
+/// Outputs of each layer of a network, by name.
+#[derive(Default)]
+struct Activations(Vec<(String, Vec<f32>)>);
+
+impl ActivationVisitor for Activations {
+    fn visit<F: Float>(&mut self, name: &str, values: &[F]) {
+        let values = values.iter().map(|x| x.to_f32()).collect();
+        self.0.push((name.to_string(), values));
+    }
+}
+
+impl core::fmt::Display for QuantizationReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let width = self
+            .layers()
+            .map(|(name, _)| name.len())
+            .fold(5, usize::max);
+
+        writeln!(
+            f,
+            "{:width$} | {:>12} | {:>12}",
+            "layer", "max error", "mean error"
+        )?;
+        for (name, stats) in self.layers() {
+            writeln!(
+                f,
+                "{name:width$} | {:>12.6} | {:>12.6}",
+                stats.max(),
+                stats.mean()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::QuantizationReport;
+
+    #[test]
+    fn quantization_report() {
+        use crate::{DenseConnected, SparseConnected};
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector};
+
+        let sparse: SparseConnected<ReLU, 8, 4> = SparseConnected::from_fn(
+            |i, j| ((i * 4 + j) % 5) as f32 / 10.0 - 0.2,
+            |i| i as f32 / 20.0,
+        );
+        let dense: DenseConnected<ReLU, 4, 2> =
+            DenseConnected::from_fn(|i, j| (i + j) as f32 / 4.0 - 0.3, |i| i as f32 / 10.0);
+
+        let qsparse = sparse.quantize(64);
+        let qdense = dense.quantize(64, 64);
+
+        let mut report = QuantizationReport::default();
+        for i in 0..8 {
+            let mut input = SparseVector::with_capacity(2);
+            input.push(i);
+            input.push((i + 3) % 8);
+
+            let hidden = sparse.out(&input);
+            let qhidden = qsparse.out(&input);
+            report.record("l1", &hidden, &qhidden, qsparse.output_scale());
+
+            let out = dense.out(&hidden);
+            let qout = qdense.out(&qhidden);
+            report.record("l2", &out, &qout, qdense.output_scale());
+        }
+
+        let l1 = report.layer("l1").unwrap();
+        let l2 = report.output().unwrap();
+        assert_eq!(l1.count(), 32);
+        assert_eq!(l2.count(), 16);
+        assert!(l1.max() <= 2.0 / 64.0);
+        assert!(l2.max() < 0.05);
+        assert!(l2.mean() <= l2.max());
+
+        let table = report.to_string();
+        assert_eq!(table.lines().count(), 3);
+        assert!(table.lines().nth(2).unwrap().starts_with("l2"));
+    }
+}
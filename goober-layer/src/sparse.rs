@@ -1,44 +1,102 @@
-use std::marker::PhantomData;
+use core::marker::PhantomData;
+
+use alloc::{boxed::Box, format, string::String};
 
 use goober_core::{
-    activation::Activation, FeedForwardNetwork, Matrix, OutputLayer, SparseVector, Vector,
+    activation::Activation,
+    init::{self, Distribution},
+    param_name,
+    qat::Qat,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeatureOutOfBounds, FeedForwardNetwork, Float, Graph, Matrix, Op,
+    OutputLayer, Param, ParamMut, ParamVisitor, ParamVisitorMut, Rand, SparseVector, Unsupported,
+    Vector,
+};
+
+use crate::{
+    quantized::{clamp_quantized, fake_quantize, fake_quantize_accumulator, largest_scale},
+    Quantized, QuantizedSparse,
 };
 
 /// Fully-Connected layer with sparse input.
 /// - `T` is the activation function used.
 /// - `M` is the size of the input vector.
 /// - `N` is the size of the output vector.
+/// - `F` is the type weights and activations are stored as.
 #[repr(C)]
 #[derive(Clone, Copy)]
-pub struct SparseConnected<T: Activation, const M: usize, const N: usize> {
-    weights: Matrix<M, N>,
-    bias: Vector<N>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct SparseConnected<T: Activation, const M: usize, const N: usize, F: Float = f32> {
+    weights: Matrix<M, N, F>,
+    bias: Vector<N, F>,
     phantom: PhantomData<T>,
 }
 
-impl<T: Activation, const M: usize, const N: usize> std::ops::AddAssign<&SparseConnected<T, M, N>>
-    for SparseConnected<T, M, N>
+/// Every parameter, the weights, in row-major order, then the biases.
+impl<T: Activation, const M: usize, const N: usize, F: Float> AsRef<[F]>
+    for SparseConnected<T, M, N, F>
+{
+    fn as_ref(&self) -> &[F] {
+        // SAFETY: `#[repr(C)]`, with every field an array of `F`s, so the
+        // parameters are laid out contiguously, without padding.
+        unsafe { core::slice::from_raw_parts((self as *const Self).cast(), M * N + N) }
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float> AsMut<[F]>
+    for SparseConnected<T, M, N, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        // SAFETY: see `as_ref`.
+        unsafe { core::slice::from_raw_parts_mut((self as *mut Self).cast(), M * N + N) }
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, F: Float + bytemuck::Zeroable>
+    bytemuck::Zeroable for SparseConnected<T, M, N, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, F: Float + bytemuck::Pod> bytemuck::Pod
+    for SparseConnected<T, M, N, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float>
+    core::ops::AddAssign<&SparseConnected<T, M, N, F>> for SparseConnected<T, M, N, F>
 {
-    fn add_assign(&mut self, rhs: &SparseConnected<T, M, N>) {
+    fn add_assign(&mut self, rhs: &SparseConnected<T, M, N, F>) {
         self.weights += &rhs.weights;
         self.bias += rhs.bias;
     }
 }
 
-impl<T: Activation, const M: usize, const N: usize> SparseConnected<T, M, N> {
-    pub fn weights_row(&self, idx: usize) -> Vector<N> {
+impl<T: Activation, const M: usize, const N: usize, F: Float> SparseConnected<T, M, N, F> {
+    pub fn weights_row(&self, idx: usize) -> Vector<N, F> {
         self.weights[idx]
     }
 
-    pub fn weights_row_mut(&mut self, idx: usize) -> &mut Vector<N> {
+    pub fn weights_row_mut(&mut self, idx: usize) -> &mut Vector<N, F> {
         &mut self.weights[idx]
     }
 
-    pub fn bias(&self) -> Vector<N> {
+    pub fn bias(&self) -> Vector<N, F> {
         self.bias
     }
 
-    pub fn bias_mut(&mut self) -> &mut Vector<N> {
+    pub fn bias_mut(&mut self) -> &mut Vector<N, F> {
         &mut self.bias
     }
 
@@ -46,7 +104,7 @@ impl<T: Activation, const M: usize, const N: usize> SparseConnected<T, M, N> {
         Self::from_raw(Matrix::zeroed(), Vector::zeroed())
     }
 
-    pub const fn from_raw(weights: Matrix<M, N>, bias: Vector<N>) -> Self {
+    pub const fn from_raw(weights: Matrix<M, N, F>, bias: Vector<N, F>) -> Self {
         Self {
             weights,
             bias,
@@ -54,33 +112,214 @@ impl<T: Activation, const M: usize, const N: usize> SparseConnected<T, M, N> {
         }
     }
 
-    pub fn from_fn<W: FnMut(usize, usize) -> f32, B: FnMut(usize) -> f32>(w: W, b: B) -> Self {
+    pub fn from_fn<W: FnMut(usize, usize) -> F, B: FnMut(usize) -> F>(w: W, b: B) -> Self {
         Self {
             weights: Matrix::from_fn(w),
             bias: Vector::from_fn(b),
             phantom: PhantomData,
         }
     }
+
+    /// Weights drawn from [`glorot_uniform`](init::glorot_uniform),
+    /// and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::random(rng, init::glorot_uniform(M, N))
+    }
+
+    /// Weights drawn from [`glorot_normal`](init::glorot_normal),
+    /// and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::random(rng, init::glorot_normal(M, N))
+    }
+
+    /// Weights drawn from [`he_uniform`](init::he_uniform), with
+    /// a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::random(rng, init::he_uniform(M))
+    }
+
+    /// Weights drawn from [`he_normal`](init::he_normal), with
+    /// a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::random(rng, init::he_normal(M))
+    }
+
+    /// Sparse initialisation, after Sutskever et al., with the weights from
+    /// `k` inputs to each output, chosen at random, drawn from `dist`, and
+    /// the rest zero, along with the biases, for layers of many more inputs
+    /// than are active at once.
+    pub fn sparse_random(rng: &mut Rand, k: usize, dist: impl Distribution) -> Self {
+        let mut weights = Matrix::<M, N, F>::zeroed();
+        for j in 0..N {
+            for i in init::choose(rng, M, k) {
+                weights[i][j] = F::from_f32(dist.sample(rng));
+            }
+        }
+        Self::from_raw(weights, Vector::zeroed())
+    }
+
+    /// Weights drawn from `dist`, such as [`Normal`](init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(Matrix::random(rng, dist), Vector::zeroed())
+    }
+
+    /// As [`out`](FeedForwardNetwork::out), but returns an error
+    /// rather than panicking if a feature index is out of bounds.
+    pub fn try_out(&self, input: &SparseVector) -> Result<Vector<N, F>, FeatureOutOfBounds> {
+        input.check_bounds(M)?;
+        Ok(self.out(input))
+    }
+
+    /// As [`backprop`](FeedForwardNetwork::backprop), but returns an error,
+    /// leaving `grad` unchanged, rather than panicking if a feature index
+    /// is out of bounds.
+    pub fn try_backprop(
+        &self,
+        input: &SparseVector,
+        grad: &mut <Self as FeedForwardNetwork>::Grad,
+        out_err: Vector<N, F>,
+        layers: &<Self as FeedForwardNetwork>::Layers,
+    ) -> Result<SparseVector, FeatureOutOfBounds> {
+        input.check_bounds(M)?;
+        Ok(self.backprop(input, grad, out_err, layers))
+    }
+
+    /// Converts to a quantized layer for inference, storing
+    /// weights at `scale`.
+    pub fn quantize(&self, scale: i16) -> Box<QuantizedSparse<T, M, N>> {
+        QuantizedSparse::from_fn(
+            |i, j| self.weights[i][j].to_f32(),
+            |i| self.bias[i].to_f32(),
+            scale,
+        )
+    }
+
+    /// Converts to a quantized layer for inference with `i8` weights
+    /// and saturating `i16` accumulation, storing weights at `scale`.
+    pub fn quantize_i8(&self, scale: i16) -> Box<QuantizedSparse<T, M, N, i8>> {
+        QuantizedSparse::from_fn(
+            |i, j| self.weights[i][j].to_f32(),
+            |i| self.bias[i].to_f32(),
+            scale,
+        )
+    }
+
+    /// Runs `inputs` through the layer, and chooses the largest scale
+    /// at which the weights, accumulators and outputs all remain
+    /// representable after [`quantize`](Self::quantize).
+    pub fn calibrate<'a, I: IntoIterator<Item = &'a SparseVector>>(&self, inputs: I) -> i16 {
+        self.calibrate_as::<i16, I>(inputs)
+    }
+
+    /// As [`calibrate`](Self::calibrate), for use with [`quantize_i8`](Self::quantize_i8).
+    pub fn calibrate_i8<'a, I: IntoIterator<Item = &'a SparseVector>>(&self, inputs: I) -> i16 {
+        self.calibrate_as::<i8, I>(inputs)
+    }
+
+    /// Rounds weights and biases to the nearest values representable
+    /// after [`quantize`](Self::quantize), simulating quantization
+    /// for quantization-aware training.
+    pub fn fake_quantize(&mut self, scale: i16) {
+        self.fake_quantize_as::<i16>(scale);
+    }
+
+    /// Rounds weights and biases to the nearest values representable
+    /// after [`quantize_i8`](Self::quantize_i8).
+    pub fn fake_quantize_i8(&mut self, scale: i16) {
+        self.fake_quantize_as::<i8>(scale);
+    }
+
+    /// Clamps weights to the range representable after
+    /// [`quantize`](Self::quantize), so that they cannot drift
+    /// out of range during quantization-aware training.
+    pub fn clamp_quantized(&mut self, scale: i16) {
+        self.clamp_quantized_as::<i16>(scale);
+    }
+
+    /// Clamps weights to the range representable after
+    /// [`quantize_i8`](Self::quantize_i8).
+    pub fn clamp_quantized_i8(&mut self, scale: i16) {
+        self.clamp_quantized_as::<i8>(scale);
+    }
+
+    fn calibrate_as<'a, Q: Quantized, I: IntoIterator<Item = &'a SparseVector>>(
+        &self,
+        inputs: I,
+    ) -> i16 {
+        let mut max_acc = 0f32;
+        let mut max_out = 0f32;
+
+        for input in inputs {
+            let mut acc = self.bias.to_compute();
+            for &feat in input.iter() {
+                acc += self.weights[feat].to_compute();
+            }
+
+            let out = acc.activate::<T>();
+            for i in 0..N {
+                max_acc = max_acc.max(acc[i].to_f32().abs());
+                max_out = max_out.max(out[i].to_f32().abs());
+            }
+        }
+
+        let max_weight = self.weights.iter().fold(0f32, |max, row| {
+            (0..N).fold(max, |max, j| max.max(row[j].to_f32().abs()))
+        });
+
+        let weight_scale = largest_scale(max_weight, Q::MAX.into());
+        let acc_scale = largest_scale(max_acc, Q::ACCUMULATOR_MAX.into());
+        let out_scale = largest_scale(max_out, i16::MAX.into());
+        weight_scale.min(acc_scale).min(out_scale) as i16
+    }
+
+    fn fake_quantize_as<Q: Quantized>(&mut self, scale: i16) {
+        let scale = i32::from(scale);
+
+        for row in self.weights.iter_mut() {
+            *row = Vector::from_fn(|j| F::from_f32(fake_quantize::<Q>(row[j].to_f32(), scale)));
+        }
+
+        self.bias = Vector::from_fn(|i| {
+            F::from_f32(fake_quantize_accumulator::<Q>(self.bias[i].to_f32(), scale))
+        });
+    }
+
+    fn clamp_quantized_as<Q: Quantized>(&mut self, scale: i16) {
+        let scale = i32::from(scale);
+
+        for row in self.weights.iter_mut() {
+            *row = Vector::from_fn(|j| F::from_f32(clamp_quantized::<Q>(row[j].to_f32(), scale)));
+        }
+    }
 }
 
-pub struct SparseConnectedLayers<const N: usize> {
-    out: Vector<N>,
+pub struct SparseConnectedLayers<const N: usize, F: Float = f32> {
+    out: Vector<N, F>,
 }
 
-impl<const N: usize> OutputLayer<Vector<N>> for SparseConnectedLayers<N> {
-    fn output_layer(&self) -> Vector<N> {
-        self.out
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for SparseConnectedLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        &self.out
     }
 }
 
-impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork
-    for SparseConnected<T, M, N>
+impl<T: Activation, const M: usize, const N: usize, F: Float> FeedForwardNetwork
+    for SparseConnected<T, M, N, F>
 {
     type InputType = SparseVector;
-    type OutputType = Vector<N>;
-    type Layers = SparseConnectedLayers<N>;
+    type OutputType = Vector<N, F>;
+    type Layers = SparseConnectedLayers<N, F>;
+    type Grad = SparseConnected<T, M, N, F::Compute>;
 
-    fn adam(&mut self, grad: &Self, momentum: &mut Self, velocity: &mut Self, adj: f32, lr: f32) {
+    fn adam(
+        &mut self,
+        grad: &Self::Grad,
+        momentum: &mut Self,
+        velocity: &mut Self,
+        adj: f32,
+        lr: f32,
+    ) {
         self.weights.adam(
             &grad.weights,
             &mut momentum.weights,
@@ -93,36 +332,149 @@ impl<T: Activation, const M: usize, const N: usize> FeedForwardNetwork
             .adam(grad.bias, &mut momentum.bias, &mut velocity.bias, adj, lr);
     }
 
+    fn trace(&self, prefix: &str, input: &str, graph: &mut Graph) -> Result<String, Unsupported> {
+        let op = Op::SparseLinear {
+            weight: param_name(prefix, "weight"),
+            bias: param_name(prefix, "bias"),
+        };
+        let out = graph.push(op, &[input], N);
+        Ok(graph.push(Op::Activation(T::ID), &[&out], N))
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        visitor.visit(Param {
+            name: &param_name(prefix, "weight"),
+            shape: &[M, N],
+            activation: T::ID,
+            values: self.weights.as_slice(),
+        });
+        visitor.visit(Param {
+            name: &param_name(prefix, "bias"),
+            shape: &[N],
+            activation: T::ID,
+            values: self.bias.as_slice(),
+        });
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "weight"),
+            shape: &[M, N],
+            activation: T::ID,
+            values: self.weights.as_mut_slice(),
+        });
+        visitor.visit(ParamMut {
+            name: &param_name(prefix, "bias"),
+            shape: &[N],
+            activation: T::ID,
+            values: self.bias.as_mut_slice(),
+        });
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("sparse [{M}]"), format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        visitor.visit(prefix, layers.out.as_slice());
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        input.check_bounds(M)
+    }
+
     fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
-        let mut res = self.bias;
+        let mut layers = Self::Layers {
+            out: Vector::zeroed(),
+        };
+        self.out_into(input, &mut layers);
+        layers
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        // summed in compute precision, so that adding many features
+        // to a half-precision accumulator does not round after each
+        let mut res = self.bias.to_compute();
+
+        for &feat in input.iter() {
+            debug_assert_in_bounds(feat, M);
+            res += self.weights[feat].to_compute();
+        }
+
+        layers.out = Vector::from_compute(&res.activate::<T>());
+    }
+
+    fn out_into_qat(
+        &self,
+        prefix: &str,
+        input: &Self::InputType,
+        layers: &mut Self::Layers,
+        qat: &Qat,
+    ) {
+        let Some(scale) = qat.scale(prefix) else {
+            return self.out_into(input, layers);
+        };
+        let scale = i32::from(scale.weight);
+
+        // as `QuantizedSparse::out`, with the weights, biases and
+        // outputs rounded to multiples of `1 / scale`
+        let mut res = Vector::<N>::from_fn(|i| {
+            fake_quantize_accumulator::<i16>(self.bias[i].to_f32(), scale)
+        });
 
         for &feat in input.iter() {
-            res += self.weights[feat];
+            debug_assert_in_bounds(feat, M);
+            let row = &self.weights[feat];
+            res += Vector::from_fn(|j| fake_quantize::<i16>(row[j].to_f32(), scale));
         }
 
-        Self::Layers {
-            out: res.activate::<T>(),
+        layers.out =
+            Vector::from_fn(|i| F::from_f32(fake_quantize::<i16>(T::activate(res[i]), scale)));
+    }
+
+    fn clamp_qat(&mut self, prefix: &str, qat: &Qat) {
+        if let Some(scale) = qat.scale(prefix) {
+            self.clamp_quantized(scale.weight);
         }
     }
 
     fn backprop(
         &self,
         input: &Self::InputType,
-        grad: &mut Self,
+        grad: &mut Self::Grad,
         mut out_err: Self::OutputType,
         layers: &Self::Layers,
     ) -> Self::InputType {
         out_err = out_err * layers.out.derivative::<T>();
 
+        let err = out_err.to_compute();
         for &feat in input.iter() {
-            grad.weights[feat] += out_err;
+            debug_assert_in_bounds(feat, M);
+            grad.weights[feat] += err;
         }
 
-        grad.bias += out_err;
+        grad.bias += err;
         SparseVector::with_capacity(0)
     }
 }
 
+/// Panics, in debug builds, naming the feature, if `feat` is not less
+/// than `size`, rather than only naming the row of weights indexed.
+pub(crate) fn debug_assert_in_bounds(feat: usize, size: usize) {
+    debug_assert!(
+        feat < size,
+        "feature index {feat} out of bounds for input of size {size}"
+    );
+}
+
 #[cfg(test)]
 mod test {
     use super::SparseConnected;
@@ -148,4 +500,83 @@ mod test {
         let expected = Vector::from_raw([3.1, 2.1, 2.2]);
         assert_eq!(expected, layer.out(&input));
     }
+
+    #[test]
+    fn many_features() {
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector, Vector};
+
+        let layer: SparseConnected<ReLU, 128, 1> = SparseConnected::from_fn(|_, _| 1.0, |_| 0.0);
+
+        let mut input = SparseVector::with_capacity(8);
+        for feat in 0..64 {
+            input.push(feat * 2);
+        }
+
+        assert_eq!(layer.out(&input), Vector::from_raw([64.0]));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        use goober_core::{activation::ReLU, FeatureOutOfBounds, SparseVector, Vector};
+
+        let layer: SparseConnected<ReLU, 4, 1> = SparseConnected::from_fn(|_, _| 1.0, |_| 0.0);
+
+        let input = SparseVector::from_slice(&[1, 3]);
+        assert_eq!(layer.try_out(&input), Ok(Vector::from_raw([2.0])));
+
+        let input = SparseVector::from_slice(&[1, 4]);
+        assert_eq!(
+            layer.try_out(&input),
+            Err(FeatureOutOfBounds { index: 4, size: 4 })
+        );
+    }
+
+    #[test]
+    fn try_backprop() {
+        use goober_core::{
+            activation::ReLU, FeatureOutOfBounds, FeedForwardNetwork, SparseVector, Vector,
+        };
+
+        let layer: SparseConnected<ReLU, 4, 1> = SparseConnected::from_fn(|_, _| 1.0, |_| 0.0);
+        let mut grad = SparseConnected::zeroed();
+        let err = Vector::from_raw([1.0]);
+
+        let input = SparseVector::from_slice(&[1, 3]);
+        let layers = layer.out_with_layers(&input);
+        assert!(layer.try_backprop(&input, &mut grad, err, &layers).is_ok());
+        assert_eq!(grad.weights_row(3), Vector::from_raw([1.0]));
+
+        let input = SparseVector::from_slice(&[0, 7]);
+        assert_eq!(
+            layer.try_backprop(&input, &mut grad, err, &layers),
+            Err(FeatureOutOfBounds { index: 7, size: 4 })
+        );
+        assert_eq!(grad.weights_row(0), Vector::zeroed());
+    }
+
+    #[test]
+    fn calibrate_bf16() {
+        use goober_core::{activation::Identity, bf16, SparseVector};
+
+        // 256 + 1 rounds back to 256 in bf16, so summing in storage
+        // precision would see an output of 256 rather than 260
+        let layer: SparseConnected<Identity, 4, 1, bf16> =
+            SparseConnected::from_fn(|_, _| bf16::from_f32(1.0), |_| bf16::from_f32(256.0));
+
+        let input = SparseVector::from_slice(&[0, 1, 2, 3]);
+        assert_eq!(layer.calibrate([&input]), (32767.0 / 260.0) as i16);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "feature index 5 out of bounds for input of size 4")]
+    fn backprop_out_of_bounds() {
+        use goober_core::{activation::ReLU, FeedForwardNetwork, SparseVector, Vector};
+
+        let layer: SparseConnected<ReLU, 4, 1> = SparseConnected::zeroed();
+        let mut grad = SparseConnected::zeroed();
+        let layers = layer.out_with_layers(&SparseVector::from_slice(&[0]));
+        let input = SparseVector::from_slice(&[5]);
+        layer.backprop(&input, &mut grad, Vector::from_raw([1.0]), &layers);
+    }
 }
@@ -0,0 +1,291 @@
+use alloc::format;
+
+use goober_core::{
+    activation::Activation,
+    init::Distribution,
+    summary::{LayerSummary, Summary},
+    ActivationVisitor, FeatureOutOfBounds, FeedForwardNetwork, Float, OutputLayer, ParamVisitor,
+    ParamVisitorMut, Rand, Vector, WeightedSparseVector,
+};
+
+use crate::{sparse::debug_assert_in_bounds, SparseConnected};
+
+/// [`SparseConnected`] layer taking a value for each active
+/// feature, for count-valued or scaled sparse features.
+/// - `T` is the activation function used.
+/// - `M` is the size of the input vector.
+/// - `N` is the size of the output vector.
+/// - `F` is the type weights and activations are stored as.
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "F: serde::Serialize",
+        deserialize = "F: serde::Deserialize<'de>"
+    ))
+)]
+pub struct WeightedSparse<T: Activation, const M: usize, const N: usize, F: Float = f32> {
+    layer: SparseConnected<T, M, N, F>,
+}
+
+/// Every parameter, as [`SparseConnected`].
+impl<T: Activation, const M: usize, const N: usize, F: Float> AsRef<[F]>
+    for WeightedSparse<T, M, N, F>
+{
+    fn as_ref(&self) -> &[F] {
+        self.layer.as_ref()
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float> AsMut<[F]>
+    for WeightedSparse<T, M, N, F>
+{
+    fn as_mut(&mut self) -> &mut [F] {
+        self.layer.as_mut()
+    }
+}
+
+// SAFETY: see `as_ref`, so any bytes which are valid `F`s are valid.
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, F: Float + bytemuck::Zeroable>
+    bytemuck::Zeroable for WeightedSparse<T, M, N, F>
+{
+}
+
+#[cfg(feature = "bytemuck")]
+unsafe impl<T: Activation, const M: usize, const N: usize, F: Float + bytemuck::Pod> bytemuck::Pod
+    for WeightedSparse<T, M, N, F>
+where
+    Self: Copy + 'static,
+{
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float>
+    core::ops::AddAssign<&WeightedSparse<T, M, N, F>> for WeightedSparse<T, M, N, F>
+{
+    fn add_assign(&mut self, rhs: &WeightedSparse<T, M, N, F>) {
+        self.layer += &rhs.layer;
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float> WeightedSparse<T, M, N, F> {
+    pub const fn zeroed() -> Self {
+        Self::from_raw(SparseConnected::zeroed())
+    }
+
+    pub const fn from_raw(layer: SparseConnected<T, M, N, F>) -> Self {
+        Self { layer }
+    }
+
+    /// Weights `w(i, j)` from input `i` to output `j`, and biases `b(j)`,
+    /// as [`SparseConnected::from_fn`].
+    pub fn from_fn<W: FnMut(usize, usize) -> F, B: FnMut(usize) -> F>(w: W, b: B) -> Self {
+        Self::from_raw(SparseConnected::from_fn(w, b))
+    }
+
+    /// Weights drawn from `dist`, such as [`Normal`](goober_core::init::Normal),
+    /// and zero biases.
+    pub fn random(rng: &mut Rand, dist: impl Distribution) -> Self {
+        Self::from_raw(SparseConnected::random(rng, dist))
+    }
+
+    /// As [`out`](FeedForwardNetwork::out), but returns an error
+    /// rather than panicking if a feature index is out of bounds.
+    pub fn try_out(
+        &self,
+        input: &WeightedSparseVector,
+    ) -> Result<<Self as FeedForwardNetwork>::OutputType, FeatureOutOfBounds> {
+        input.check_bounds(M)?;
+        Ok(self.out(input))
+    }
+
+    /// As [`backprop`](FeedForwardNetwork::backprop), but returns an error,
+    /// leaving `grad` unchanged, rather than panicking if a feature index
+    /// is out of bounds.
+    pub fn try_backprop(
+        &self,
+        input: &WeightedSparseVector,
+        grad: &mut <Self as FeedForwardNetwork>::Grad,
+        out_err: <Self as FeedForwardNetwork>::OutputType,
+        layers: &<Self as FeedForwardNetwork>::Layers,
+    ) -> Result<WeightedSparseVector, FeatureOutOfBounds> {
+        input.check_bounds(M)?;
+        Ok(self.backprop(input, grad, out_err, layers))
+    }
+
+    /// Weights drawn from [`glorot_uniform`](goober_core::init::glorot_uniform),
+    /// and zero biases.
+    pub fn glorot_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_uniform(rng))
+    }
+
+    /// Weights drawn from [`glorot_normal`](goober_core::init::glorot_normal),
+    /// and zero biases.
+    pub fn glorot_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::glorot_normal(rng))
+    }
+
+    /// Weights drawn from [`he_uniform`](goober_core::init::he_uniform),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_uniform(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_uniform(rng))
+    }
+
+    /// Weights drawn from [`he_normal`](goober_core::init::he_normal),
+    /// with a fan-in of `M`, and zero biases.
+    pub fn he_normal(rng: &mut Rand) -> Self {
+        Self::from_raw(SparseConnected::he_normal(rng))
+    }
+
+    pub fn layer(&self) -> &SparseConnected<T, M, N, F> {
+        &self.layer
+    }
+
+    pub fn layer_mut(&mut self) -> &mut SparseConnected<T, M, N, F> {
+        &mut self.layer
+    }
+}
+
+pub struct WeightedSparseLayers<const N: usize, F: Float = f32> {
+    out: Vector<N, F>,
+}
+
+impl<const N: usize, F: Float> OutputLayer<Vector<N, F>> for WeightedSparseLayers<N, F> {
+    fn output_layer_ref(&self) -> &Vector<N, F> {
+        &self.out
+    }
+}
+
+impl<T: Activation, const M: usize, const N: usize, F: Float> FeedForwardNetwork
+    for WeightedSparse<T, M, N, F>
+{
+    type InputType = WeightedSparseVector;
+    type OutputType = Vector<N, F>;
+    type Layers = WeightedSparseLayers<N, F>;
+    type Grad = WeightedSparse<T, M, N, F::Compute>;
+
+    fn adam(&mut self, g: &Self::Grad, m: &mut Self, v: &mut Self, adj: f32, lr: f32) {
+        self.layer
+            .adam(&g.layer, &mut m.layer, &mut v.layer, adj, lr);
+    }
+
+    fn visit_params<V: ParamVisitor>(&self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params(prefix, visitor);
+    }
+
+    fn visit_params_mut<V: ParamVisitorMut>(&mut self, prefix: &str, visitor: &mut V) {
+        self.layer.visit_params_mut(prefix, visitor);
+    }
+
+    fn summarize(&self, prefix: &str, summary: &mut Summary) {
+        let layer = LayerSummary::new(self, prefix)
+            .with_shapes(format!("weighted [{M}]"), format!("[{N}]"))
+            .with_activation::<T>();
+        summary.push(layer);
+    }
+
+    fn visit_activations<V: ActivationVisitor>(
+        &self,
+        prefix: &str,
+        layers: &Self::Layers,
+        visitor: &mut V,
+    ) {
+        visitor.visit(prefix, layers.out.as_slice());
+    }
+
+    fn check_input(&self, input: &Self::InputType) -> Result<(), FeatureOutOfBounds> {
+        input.check_bounds(M)
+    }
+
+    fn out_with_layers(&self, input: &Self::InputType) -> Self::Layers {
+        let mut layers = Self::Layers {
+            out: Vector::zeroed(),
+        };
+        self.out_into(input, &mut layers);
+        layers
+    }
+
+    fn out_into(&self, input: &Self::InputType, layers: &mut Self::Layers) {
+        let mut res = self.layer.bias().to_compute();
+
+        for &(feat, val) in input.iter() {
+            debug_assert_in_bounds(feat, M);
+            res += val * self.layer.weights_row(feat).to_compute();
+        }
+
+        layers.out = Vector::from_compute(&res.activate::<T>());
+    }
+
+    fn backprop(
+        &self,
+        input: &Self::InputType,
+        grad: &mut Self::Grad,
+        mut out_err: Self::OutputType,
+        layers: &Self::Layers,
+    ) -> Self::InputType {
+        out_err = out_err * layers.out.derivative::<T>();
+
+        let err = out_err.to_compute();
+        for &(feat, val) in input.iter() {
+            debug_assert_in_bounds(feat, M);
+            *grad.layer.weights_row_mut(feat) += val * err;
+        }
+
+        *grad.layer.bias_mut() += err;
+        WeightedSparseVector::with_capacity(0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::WeightedSparse;
+
+    #[test]
+    fn weighted_sparse() {
+        use crate::SparseConnected;
+        use goober_core::{
+            activation::ReLU, FeedForwardNetwork, SparseVector, Vector, WeightedSparseVector,
+        };
+
+        let layer: WeightedSparse<ReLU, 4, 2> =
+            WeightedSparse::from_raw(SparseConnected::from_fn(|i, j| (i * 2 + j) as f32, |_| 1.0));
+
+        let mut input = WeightedSparseVector::with_capacity(2);
+        input.push(1, 2.0);
+        input.push(3, 0.5);
+        assert_eq!(layer.out(&input), Vector::from_raw([8.0, 10.5]));
+
+        let mut unweighted = SparseVector::with_capacity(2);
+        unweighted.push(1);
+        unweighted.push(3);
+        assert_eq!(
+            layer.out(&unweighted.clone().into()),
+            layer.layer().out(&unweighted)
+        );
+
+        let mut grad = WeightedSparse::zeroed();
+        let layers = layer.out_with_layers(&input);
+        layer.backprop(&input, &mut grad, Vector::from_raw([1.0, 1.0]), &layers);
+        assert_eq!(grad.layer().weights_row(1), Vector::from_raw([2.0, 2.0]));
+        assert_eq!(grad.layer().weights_row(3), Vector::from_raw([0.5, 0.5]));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        use goober_core::{activation::ReLU, FeatureOutOfBounds, Vector, WeightedSparseVector};
+
+        let layer: WeightedSparse<ReLU, 4, 1> = WeightedSparse::zeroed();
+
+        let mut input = WeightedSparseVector::with_capacity(2);
+        input.push(3, 1.0);
+        assert_eq!(layer.try_out(&input), Ok(Vector::zeroed()));
+
+        input.push(4, 1.0);
+        assert_eq!(
+            layer.try_out(&input),
+            Err(FeatureOutOfBounds { index: 4, size: 4 })
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! Python bindings for goober networks, for driving training and inference
+//! from Python while computation stays in Rust.
+//!
+//! Architectures are fixed at compile time, so each network is exposed
+//! as a Python class generated by [`python_network!`], and added to a
+//! module in the usual way with `pyo3`.
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh},
+//! #     layer::{DenseConnected, SparseConnected},
+//! #     FeedForwardNetwork,
+//! # };
+//! # use pyo3::prelude::*;
+//! #[derive(FeedForwardNetwork)]
+//! pub struct Net {
+//!     l1: SparseConnected<ReLU, 768, 32>,
+//!     l2: DenseConnected<Tanh, 32, 1>,
+//! }
+//!
+//! goober_py::python_network!(PyNet, Net, "Net");
+//!
+//! #[pymodule]
+//! fn nets(m: &Bound<'_, PyModule>) -> PyResult<()> {
+//!     m.add_class::<PyNet>()
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! ```python
+//! net = nets.Net()
+//! net.randomize(seed=1, scale=0.1)
+//! loss = net.train_step([[0, 5], [3]], [[1.0], [0.0]], lr=0.001)
+//! net.forward([0, 5])
+//! ```
+
+use std::collections::HashMap;
+
+use goober::{
+    init::{Randomize, Uniform},
+    FeedForwardNetwork, Float, Gradient, OutputLayer, Param, ParamMut, ParamVisitor,
+    ParamVisitorMut, Rand, SparseVector, Vector,
+};
+use pyo3::{
+    exceptions::{PyIOError, PyValueError},
+    prelude::*,
+};
+
+pub use pyo3;
+
+/// Input to a network which can be converted from a Python object.
+pub trait PyInput: Sized {
+    fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Self>;
+}
+
+/// Output of a network which can be converted to and from a list of floats.
+pub trait PyOutput: Sized {
+    fn to_vec(&self) -> Vec<f32>;
+
+    fn from_slice(values: &[f32]) -> Option<Self>;
+}
+
+/// Sparse inputs are lists of active feature indices.
+impl PyInput for SparseVector {
+    fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(Self::from_slice(&obj.extract::<Vec<usize>>()?))
+    }
+}
+
+/// Dense inputs are lists of floats.
+impl<const N: usize, F: Float> PyInput for Vector<N, F> {
+    fn from_py(obj: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let values = obj.extract::<Vec<f32>>()?;
+        <Self as PyOutput>::from_slice(&values).ok_or_else(|| {
+            PyValueError::new_err(format!("expected {N} inputs, got {}", values.len()))
+        })
+    }
+}
+
+impl<const N: usize, F: Float> PyOutput for Vector<N, F> {
+    fn to_vec(&self) -> Vec<f32> {
+        self.as_slice().iter().map(|x| x.to_f32()).collect()
+    }
+
+    fn from_slice(values: &[f32]) -> Option<Self> {
+        (values.len() == N).then(|| Self::from_fn(|i| F::from_f32(values[i])))
+    }
+}
+
+struct Zero;
+
+impl ParamVisitorMut for Zero {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        param.values.fill(F::ZERO);
+    }
+}
+
+struct Collect(HashMap<String, Vec<f32>>);
+
+impl ParamVisitor for Collect {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let values = param.values.iter().map(|x| x.to_f32()).collect();
+        self.0.insert(param.name.to_string(), values);
+    }
+}
+
+/// Network with its gradient and Adam optimiser state,
+/// trained on the mean squared error.
+pub struct Session<T: FeedForwardNetwork> {
+    net: Box<T>,
+    grad: Box<T::Grad>,
+    m: Box<T>,
+    v: Box<T>,
+}
+
+impl<T: FeedForwardNetwork> Default for Session<T> {
+    fn default() -> Self {
+        Self {
+            net: T::boxed_and_zeroed(),
+            grad: T::zeroed_grad(),
+            m: T::boxed_and_zeroed(),
+            v: T::boxed_and_zeroed(),
+        }
+    }
+}
+
+impl<T: FeedForwardNetwork> Session<T>
+where
+    T::InputType: PyInput,
+    T::OutputType: PyOutput,
+{
+    pub fn net(&self) -> &T {
+        &self.net
+    }
+
+    pub fn net_mut(&mut self) -> &mut T {
+        &mut self.net
+    }
+
+    /// Sets every parameter uniformly at random in `[-scale, scale]`.
+    pub fn randomize(&mut self, seed: u64, scale: f32) {
+        let mut init = Randomize::new(Rand::with_seed(seed), Uniform::symmetric(scale));
+        self.net.visit_params_mut("", &mut init);
+    }
+
+    pub fn forward(&self, input: &Bound<'_, PyAny>) -> PyResult<Vec<f32>> {
+        Ok(self.net.out(&self.input(input)?).to_vec())
+    }
+
+    /// Takes one step of Adam on the gradient of the mean squared error
+    /// over a batch of `inputs` and `targets`, returning the error before
+    /// the step.
+    pub fn train_step(
+        &mut self,
+        inputs: &[Bound<'_, PyAny>],
+        targets: &[Vec<f32>],
+        lr: f32,
+    ) -> PyResult<f32> {
+        if inputs.len() != targets.len() || inputs.is_empty() {
+            return Err(PyValueError::new_err(
+                "expected a non-empty batch with one target per input",
+            ));
+        }
+
+        let inputs = inputs
+            .iter()
+            .map(|input| self.input(input))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        self.grad.visit_grads_mut("", &mut Zero);
+        let mut loss = 0.0;
+
+        for (input, target) in inputs.iter().zip(targets) {
+            let layers = self.net.out_with_layers(input);
+            let out = layers.output_layer().to_vec();
+
+            if out.len() != target.len() {
+                let msg = format!("expected {} targets, got {}", out.len(), target.len());
+                return Err(PyValueError::new_err(msg));
+            }
+
+            let scale = 1.0 / out.len() as f32;
+            let err: Vec<f32> = out.iter().zip(target).map(|(x, y)| x - y).collect();
+            loss += scale * err.iter().map(|x| x * x).sum::<f32>();
+
+            let grad: Vec<f32> = err.iter().map(|x| 2.0 * scale * x).collect();
+            let grad = T::OutputType::from_slice(&grad).unwrap();
+            self.net.backprop(input, &mut self.grad, grad, &layers);
+        }
+
+        let adj = 1.0 / inputs.len() as f32;
+        self.net.adam(&self.grad, &mut self.m, &mut self.v, adj, lr);
+
+        Ok(adj * loss)
+    }
+
+    /// Converts `obj` to an input, checking that it is valid for the network
+    /// so that a bad input raises a `ValueError` rather than a panic.
+    fn input(&self, obj: &Bound<'_, PyAny>) -> PyResult<T::InputType> {
+        let input = T::InputType::from_py(obj)?;
+        self.net
+            .check_input(&input)
+            .map_err(|err| PyValueError::new_err(err.to_string()))?;
+        Ok(input)
+    }
+
+    pub fn save(&self, path: &str) -> PyResult<()> {
+        self.net
+            .save(path)
+            .map_err(|err| PyIOError::new_err(err.to_string()))
+    }
+
+    pub fn load(&mut self, path: &str) -> PyResult<()> {
+        self.net
+            .load(path)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    /// Values of every tensor of parameters by name, flattened in row-major order.
+    pub fn parameters(&self) -> HashMap<String, Vec<f32>> {
+        let mut collect = Collect(HashMap::new());
+        self.net.visit_params("", &mut collect);
+        collect.0
+    }
+}
+
+/// Generates the Python class `$py_name`, as the Rust type `$name`,
+/// wrapping a [`Session`] of the network `$t`.
+#[macro_export]
+macro_rules! python_network {
+    ($name:ident, $t:ty, $py_name:literal) => {
+        #[$crate::pyo3::pyclass(crate = "goober_py::pyo3", name = $py_name)]
+        pub struct $name($crate::Session<$t>);
+
+        #[$crate::pyo3::pymethods(crate = "goober_py::pyo3")]
+        impl $name {
+            #[new]
+            fn new() -> Self {
+                Self(Default::default())
+            }
+
+            fn randomize(&mut self, seed: u64, scale: f32) {
+                self.0.randomize(seed, scale)
+            }
+
+            fn forward(
+                &self,
+                input: &$crate::pyo3::Bound<'_, $crate::pyo3::PyAny>,
+            ) -> $crate::pyo3::PyResult<Vec<f32>> {
+                self.0.forward(input)
+            }
+
+            fn train_step(
+                &mut self,
+                inputs: Vec<$crate::pyo3::Bound<'_, $crate::pyo3::PyAny>>,
+                targets: Vec<Vec<f32>>,
+                lr: f32,
+            ) -> $crate::pyo3::PyResult<f32> {
+                self.0.train_step(&inputs, &targets, lr)
+            }
+
+            fn save(&self, path: &str) -> $crate::pyo3::PyResult<()> {
+                self.0.save(path)
+            }
+
+            fn load(&mut self, path: &str) -> $crate::pyo3::PyResult<()> {
+                self.0.load(path)
+            }
+
+            fn parameters(&self) -> ::std::collections::HashMap<String, Vec<f32>> {
+                self.0.parameters()
+            }
+        }
+    };
+}
@@ -0,0 +1,69 @@
+use goober::{
+    activation::{Identity, ReLU},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork,
+};
+use pyo3::{ffi::c_str, prelude::*, types::IntoPyDict};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 8>,
+    l2: DenseConnected<Identity, 8, 1>,
+}
+
+goober_py::python_network!(PyTestNet, TestNet, "TestNet");
+
+#[test]
+fn python() {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let module = PyModule::new(py, "nets").unwrap();
+        module.add_class::<PyTestNet>().unwrap();
+        let locals = [("nets", module)].into_py_dict(py).unwrap();
+
+        py.run(
+            c_str!(
+                r#"
+net = nets.TestNet()
+net.randomize(1, 0.5)
+
+inputs = [[0], [1], [2, 3]]
+targets = [[1.0], [-1.0], [0.5]]
+
+first = net.train_step(inputs, targets, 0.01)
+for _ in range(500):
+    last = net.train_step(inputs, targets, 0.01)
+
+assert last < first / 10, (first, last)
+assert abs(net.forward([0])[0] - 1.0) < 0.1
+
+params = net.parameters()
+assert len(params["l1.weight"]) == 32
+assert len(params["l2.bias"]) == 1
+
+try:
+    net.train_step([[0]], [[1.0, 2.0]], 0.01)
+    assert False
+except ValueError:
+    pass
+
+try:
+    net.forward([4])
+    assert False
+except ValueError as err:
+    assert "out of bounds" in str(err), err
+
+try:
+    net.train_step([[0], [1, 7]], [[1.0], [0.0]], 0.01)
+    assert False
+except ValueError as err:
+    assert "out of bounds" in str(err), err
+"#
+            ),
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
@@ -0,0 +1,463 @@
+//! Command line driver running a whole training run from a
+//! [`Config`] file and a dataset, with resuming, logging and export.
+//!
+//! The `goober-train` binary trains one of a few built-in architectures,
+//! see `goober-train --help`. As architectures are fixed at compile time,
+//! any other is trained by a binary of its own, with the same options:
+//!
+//! ```no_run
+//! # use goober::{
+//! #     activation::{ReLU, Tanh},
+//! #     layer::{DenseConnected, SparseConnected},
+//! #     FeedForwardNetwork,
+//! # };
+//! #[derive(FeedForwardNetwork)]
+//! pub struct Net {
+//!     l1: SparseConnected<ReLU, 768, 32>,
+//!     l2: DenseConnected<Tanh, 32, 1>,
+//! }
+//!
+//! fn main() -> std::process::ExitCode {
+//!     goober_train::main::<Net, 1>()
+//! }
+//! ```
+//!
+//! Datasets are text, with a sample on each line, being its targets,
+//! then `|`, then the indices of its active features:
+//!
+//! ```text
+//! 0.25 | 3 17 200
+//! -1 | 5 9
+//! ```
+//!
+//! With `--sweep`, each trial of a [`Sweep`] is trained by running the
+//! binary again with the configuration of the trial, and its `args.`
+//! parameters as options, such as `--hidden`, and the final losses
+//! of the trials are printed as a table.
+
+use std::{
+    cell::Cell,
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+    rc::Rc,
+};
+
+use goober::{
+    callback::{Callback, Control, CsvLogger, Progress},
+    checkpoint::CheckpointManager,
+    config::Config,
+    init::{Randomize, Uniform},
+    json, onnx, safetensors,
+    sweep::Sweep,
+    FeedForwardNetwork, Rand, SparseVector, Vector,
+};
+
+pub const USAGE: &str = "\
+usage: goober-train --config <file> [options]
+       goober-train --sweep <file> [--jobs <n>] [options]
+
+options:
+  --config <file>       training configuration, in TOML
+  --sweep <file>        trains every trial of a sweep, writing their
+                        configurations and networks to `<file>-trials`
+  --jobs <n>            number of trials to train at once [default: 1]
+  --data <file>         training data, instead of `data.train` of the config
+  --validation <file>   validation data, instead of `data.validation`
+  --out <file>          where to export the trained network, as .gbnn,
+                        .json, .safetensors or .onnx [default: net.gbnn]
+  --log <file>          appends the loss of each epoch to a CSV file
+  --resume              continues from the latest checkpoint
+  --init-scale <x>      scale of the random initial weights [default: 0.1]
+  --help                prints this message";
+
+/// Options of a run, as given on the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Args {
+    /// Configuration to train with, empty if sweeping.
+    pub config: PathBuf,
+    pub sweep: Option<PathBuf>,
+    pub jobs: usize,
+    pub data: Option<PathBuf>,
+    pub validation: Option<PathBuf>,
+    pub out: PathBuf,
+    pub log: Option<PathBuf>,
+    pub resume: bool,
+    pub init_scale: f32,
+    /// Options not recognised here, left for the binary.
+    pub rest: Vec<(String, String)>,
+}
+
+impl Args {
+    /// Parses arguments, not including the name of the program,
+    /// or fails with a description of the problem.
+    /// - Any other `--option <value>` pairs are collected into
+    ///   [`rest`](Self::rest), for the binary to interpret.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut args = args.into_iter();
+        let mut config = None;
+        let mut parsed = Self {
+            config: PathBuf::new(),
+            sweep: None,
+            jobs: 1,
+            data: None,
+            validation: None,
+            out: "net.gbnn".into(),
+            log: None,
+            resume: false,
+            init_scale: 0.1,
+            rest: Vec::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            if arg == "--resume" {
+                parsed.resume = true;
+                continue;
+            }
+
+            let Some(name) = arg.strip_prefix("--") else {
+                return Err(format!("unexpected argument `{arg}`"));
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| format!("`{arg}` requires a value"))?;
+
+            match name {
+                "config" => config = Some(value.into()),
+                "sweep" => parsed.sweep = Some(value.into()),
+                "jobs" => {
+                    parsed.jobs = value
+                        .parse()
+                        .ok()
+                        .filter(|&jobs| jobs > 0)
+                        .ok_or_else(|| format!("invalid number of jobs `{value}`"))?;
+                }
+                "data" => parsed.data = Some(value.into()),
+                "validation" => parsed.validation = Some(value.into()),
+                "out" => parsed.out = value.into(),
+                "log" => parsed.log = Some(value.into()),
+                "init-scale" => {
+                    parsed.init_scale = value
+                        .parse()
+                        .map_err(|_| format!("invalid scale `{value}`"))?;
+                }
+                _ => parsed.rest.push((name.into(), value)),
+            }
+        }
+
+        match (config, &parsed.sweep) {
+            (Some(_), Some(_)) => return Err("`--config` and `--sweep` are exclusive".into()),
+            (Some(config), None) => parsed.config = config,
+            (None, Some(_)) if parsed.resume => {
+                return Err("`--resume` does not apply to sweeps".into())
+            }
+            (None, Some(_)) => {}
+            (None, None) => return Err("`--config` or `--sweep` is required".into()),
+        }
+        Ok(parsed)
+    }
+
+    /// Value of an option collected into [`rest`](Self::rest).
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.rest
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Reads a dataset of samples with `N` targets, in the format above,
+/// checking the features of each against the inputs of `net`.
+pub fn read_data<T, const N: usize>(
+    path: &Path,
+    net: &T,
+) -> io::Result<Vec<(SparseVector, Vector<N>)>>
+where
+    T: FeedForwardNetwork<InputType = SparseVector, OutputType = Vector<N>>,
+{
+    let invalid = |line: usize, message: String| {
+        let message = format!("{}:{line}: {message}", path.display());
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    };
+
+    let mut data = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (targets, features) = line
+            .split_once('|')
+            .ok_or_else(|| invalid(i + 1, "expected `|`".into()))?;
+
+        let targets = targets
+            .split_whitespace()
+            .map(|x| x.parse::<f32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| invalid(i + 1, format!("invalid target: {err}")))?;
+        if targets.len() != N {
+            return Err(invalid(i + 1, format!("expected {N} targets")));
+        }
+
+        let features = features
+            .split_whitespace()
+            .map(|x| x.parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| invalid(i + 1, format!("invalid feature: {err}")))?;
+        let features = SparseVector::from_slice(&features);
+        net.check_input(&features)
+            .map_err(|err| invalid(i + 1, err.to_string()))?;
+
+        data.push((features, Vector::from_fn(|j| targets[j])));
+    }
+
+    Ok(data)
+}
+
+/// Prints the losses at the end of each epoch to stderr,
+/// keeping the last validation loss.
+struct Report(Rc<Cell<Option<f32>>>);
+
+impl<T> Callback<T> for Report {
+    fn on_epoch_end(&mut self, progress: &Progress<'_, T>) -> Control {
+        eprint!(
+            "epoch {:>4}  step {:>8}  lr {:.3e}  loss {:.6}",
+            progress.epoch, progress.step, progress.lr, progress.loss
+        );
+        match progress.validation {
+            Some(metrics) => {
+                eprintln!("  val loss {:.6}", metrics.loss);
+                self.0.set(Some(metrics.loss));
+            }
+            None => eprintln!(),
+        }
+        Control::Continue
+    }
+}
+
+/// Saves `net` to `path`, in the format given by its extension.
+pub fn export<T: FeedForwardNetwork>(net: &T, path: &Path) -> io::Result<()> {
+    let name = path
+        .to_str()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path is not valid UTF-8"))?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => json::save(net, name),
+        Some("safetensors") => safetensors::save(net, name),
+        Some("onnx") => onnx::save(net, name),
+        _ => net.save(name),
+    }
+}
+
+/// Runs training as configured by `args`, returning the validation
+/// loss of the last epoch, if there is a validation set, and otherwise
+/// the mean training loss of the last epoch.
+/// - With `--resume`, training continues from the latest checkpoint,
+///   for the epochs remaining of those configured.
+/// - The network exported is the moving average, if the configuration
+///   keeps one, and otherwise the network as trained.
+pub fn run<T, const N: usize>(args: &Args) -> Result<f32, Box<dyn Error>>
+where
+    T: FeedForwardNetwork<InputType = SparseVector, OutputType = Vector<N>> + Sync + 'static,
+{
+    let config = Config::load(&args.config)?;
+
+    let train = match &args.data {
+        Some(path) => vec![path.clone()],
+        None => config.data.train.clone(),
+    };
+    if train.is_empty() {
+        return Err("no training data, set `data.train` or pass `--data`".into());
+    }
+
+    let mut net = T::boxed_and_zeroed();
+    let seed = config.seed.unwrap_or(0);
+    let mut init = Randomize::new(Rand::with_seed(seed), Uniform::symmetric(args.init_scale));
+    net.visit_params_mut("", &mut init);
+
+    let mut data = Vec::new();
+    for path in &train {
+        data.extend(read_data(path, &*net)?);
+    }
+    eprintln!("read {} samples", data.len());
+
+    let validation = match args.validation.as_ref().or(config.data.validation.as_ref()) {
+        Some(path) => Some(read_data(path, &*net)?),
+        None => None,
+    };
+
+    let val_loss = Rc::new(Cell::new(None));
+    let mut trainer = config
+        .trainer(net, data)?
+        .with_callback(Report(val_loss.clone()));
+    if let Some(validation) = validation {
+        trainer = trainer.with_validation(validation);
+    }
+    if let Some(path) = &args.log {
+        trainer = trainer.with_callback(CsvLogger::new(path)?);
+    }
+
+    if args.resume {
+        let ckpt = config
+            .checkpoint
+            .as_ref()
+            .ok_or("`--resume` requires `checkpoint.dir`")?;
+        let manager = CheckpointManager::new(&ckpt.dir, ckpt.keep)?;
+
+        if let Some(path) = manager.latest()? {
+            trainer.checkpoint_mut().load(path.to_str().unwrap())?;
+            eprintln!(
+                "resumed from {} at epoch {}",
+                path.display(),
+                trainer.epoch()
+            );
+
+            let ema = ckpt.dir.join("ema.gbnn");
+            if let (Some(net), true) = (trainer.ema_net_mut(), ema.exists()) {
+                net.load(ema.to_str().unwrap())?;
+            }
+        }
+    }
+
+    let remaining = (config.epochs as u64).saturating_sub(trainer.epoch());
+    let loss = trainer.run(remaining as usize)?;
+
+    match trainer.ema_net() {
+        Some(net) => export(net, &args.out)?,
+        None => export(trainer.net(), &args.out)?,
+    }
+    eprintln!("saved {}", args.out.display());
+
+    Ok(val_loss.get().unwrap_or(loss))
+}
+
+/// Trains every trial of the sweep given by `--sweep`, on `--jobs`
+/// processes at a time, each running the current executable with the
+/// configuration of the trial, then prints the final loss of each
+/// trial and returns the lowest.
+/// - The configuration, network and log of each trial are written
+///   to a directory named after the sweep, with `-trials` appended.
+/// - Other options are passed on to each trial, unless the trial
+///   sets them with `args.` parameters.
+pub fn sweep(args: &Args) -> Result<f32, Box<dyn Error>> {
+    let path = args.sweep.as_ref().ok_or("`--sweep` is required")?;
+    // trials are configured from another directory, so need data at absolute paths
+    let sweep = Sweep::load(path.canonicalize()?)?;
+    let exe = std::env::current_exe()?;
+
+    let mut dir = path.clone().into_os_string();
+    dir.push("-trials");
+    let dir = PathBuf::from(dir);
+    std::fs::create_dir_all(&dir)?;
+
+    let report = sweep.run(args.jobs, |trial| -> Result<f32, Box<dyn Error>> {
+        let config = trial.path(&dir, "toml");
+        std::fs::write(&config, trial.to_toml())?;
+
+        let mut command = Command::new(&exe);
+        command
+            .arg("--config")
+            .arg(&config)
+            .arg("--out")
+            .arg(trial.path(&dir, "gbnn"))
+            .arg("--init-scale")
+            .arg(args.init_scale.to_string());
+        for (name, path) in [("--data", &args.data), ("--validation", &args.validation)] {
+            if let Some(path) = path {
+                command.arg(name).arg(path);
+            }
+        }
+
+        let mut options = args.rest.clone();
+        for (name, value) in trial.args() {
+            match options.iter_mut().find(|(n, _)| *n == name) {
+                Some(option) => option.1 = value,
+                None => options.push((name, value)),
+            }
+        }
+        for (name, value) in options {
+            command.arg(format!("--{name}")).arg(value);
+        }
+
+        command.stderr(File::create(trial.path(&dir, "log"))?);
+        let output = command.output()?;
+        if !output.status.success() {
+            let log = trial.path(&dir, "log");
+            return Err(format!("{}, see {}", output.status, log.display()).into());
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let loss = stdout
+            .lines()
+            .rev()
+            .find_map(|line| line.strip_prefix("loss "))
+            .and_then(|loss| loss.trim().parse().ok())
+            .ok_or("no final loss")?;
+        eprintln!("trial {} finished with loss {loss:.6}", trial.index);
+        Ok(loss)
+    });
+
+    print!("{report}");
+    let (best, loss) = report.best().ok_or("every trial failed")?;
+    let params: Vec<String> = best
+        .params()
+        .iter()
+        .map(|(key, value)| format!("{key} = {value}"))
+        .collect();
+    eprintln!("best trial {}: {}", best.index, params.join(", "));
+    Ok(loss)
+}
+
+/// Parses the arguments of the process and runs training with
+/// the architecture `T`, as the entry point of a binary.
+pub fn main<T, const N: usize>() -> ExitCode
+where
+    T: FeedForwardNetwork<InputType = SparseVector, OutputType = Vector<N>> + Sync + 'static,
+{
+    let args = match parse_args(USAGE) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+
+    if let Some((name, _)) = args.rest.first() {
+        eprintln!("error: unknown option `--{name}`\n\n{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    if args.sweep.is_some() {
+        return exit(sweep(&args));
+    }
+    exit(run::<T, N>(&args))
+}
+
+/// Parses the arguments of the process, or prints `usage`, and returns
+/// the code to exit with, if they ask for help or are invalid.
+pub fn parse_args(usage: &str) -> Result<Args, ExitCode> {
+    if std::env::args().any(|arg| arg == "--help") {
+        println!("{usage}");
+        return Err(ExitCode::SUCCESS);
+    }
+
+    Args::parse(std::env::args().skip(1)).map_err(|err| {
+        eprintln!("error: {err}\n\n{usage}");
+        ExitCode::FAILURE
+    })
+}
+
+/// Code to exit with after [`run`] or [`sweep`], printing
+/// the final loss to stdout, as `loss <x>`, or any error.
+pub fn exit(result: Result<f32, Box<dyn Error>>) -> ExitCode {
+    match result {
+        Ok(loss) => {
+            println!("loss {loss}");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
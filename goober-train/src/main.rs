@@ -0,0 +1,48 @@
+use std::process::ExitCode;
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork,
+};
+use goober_train::{exit, parse_args, run, sweep, USAGE};
+
+/// Sparse inputs, a hidden layer of `H` with ReLU, and
+/// a single output with tanh, for targets in `[-1, 1]`.
+#[derive(FeedForwardNetwork)]
+pub struct Net<const H: usize> {
+    l1: SparseConnected<ReLU, 768, H>,
+    l2: DenseConnected<Tanh, H, 1>,
+}
+
+fn main() -> ExitCode {
+    let usage = format!(
+        "{USAGE}
+
+built-in architecture, 768 sparse inputs -> H (ReLU) -> 1 (tanh):
+  --hidden <H>          32, 64, 128, 256, 512 or 1024 [default: 256]"
+    );
+
+    let args = match parse_args(&usage) {
+        Ok(args) => args,
+        Err(code) => return code,
+    };
+
+    if let Some((name, _)) = args.rest.iter().find(|(name, _)| name != "hidden") {
+        eprintln!("error: unknown option `--{name}`\n\n{usage}");
+        return ExitCode::FAILURE;
+    }
+
+    if args.sweep.is_some() {
+        return exit(sweep(&args));
+    }
+    exit(match args.get("hidden").unwrap_or("256") {
+        "32" => run::<Net<32>, 1>(&args),
+        "64" => run::<Net<64>, 1>(&args),
+        "128" => run::<Net<128>, 1>(&args),
+        "256" => run::<Net<256>, 1>(&args),
+        "512" => run::<Net<512>, 1>(&args),
+        "1024" => run::<Net<1024>, 1>(&args),
+        hidden => Err(format!("unsupported hidden size `{hidden}`").into()),
+    })
+}
@@ -0,0 +1,200 @@
+use std::{path::PathBuf, process::Command};
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, SparseVector,
+};
+use goober_train::{read_data, run, Args};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+fn args(args: &[&str]) -> Result<Args, String> {
+    Args::parse(args.iter().map(|arg| arg.to_string()))
+}
+
+fn setup(name: &str, epochs: usize) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("goober-train-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let data: String = (0..8)
+        .flat_map(|i| (0..i).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            let target = if (i + j) % 3 == 0 { 0.5 } else { -0.5 };
+            format!("{target} | {i} {j}\n")
+        })
+        .collect();
+    std::fs::write(dir.join("train.txt"), data).unwrap();
+
+    let config = format!(
+        "epochs = {epochs}\nbatch_size = 7\nseed = 1\n\n\
+         [schedule]\nkind = \"constant\"\nlr = 0.01\n\n\
+         [data]\ntrain = \"train.txt\"\n\n\
+         [checkpoint]\ndir = \"ckpt\"\nkeep = 1\n"
+    );
+    std::fs::write(dir.join("run.toml"), config).unwrap();
+    dir
+}
+
+#[test]
+fn parse_args() {
+    let parsed = args(&["--config", "a.toml", "--resume", "--hidden", "64"]).unwrap();
+    assert_eq!(parsed.config, PathBuf::from("a.toml"));
+    assert!(parsed.resume);
+    assert_eq!(parsed.out, PathBuf::from("net.gbnn"));
+    assert_eq!(parsed.get("hidden"), Some("64"));
+
+    assert_eq!(args(&[]), Err("`--config` or `--sweep` is required".into()));
+    assert_eq!(
+        args(&["--config", "a.toml", "--sweep", "b.toml"]),
+        Err("`--config` and `--sweep` are exclusive".into())
+    );
+    assert_eq!(
+        args(&["--sweep", "b.toml", "--jobs", "0"]),
+        Err("invalid number of jobs `0`".into())
+    );
+
+    let parsed = args(&["--sweep", "b.toml", "--jobs", "4"]).unwrap();
+    assert_eq!(parsed.sweep, Some(PathBuf::from("b.toml")));
+    assert_eq!(parsed.jobs, 4);
+    assert_eq!(
+        args(&["--config"]),
+        Err("`--config` requires a value".into())
+    );
+    assert_eq!(
+        args(&["run.toml"]),
+        Err("unexpected argument `run.toml`".into())
+    );
+}
+
+#[test]
+fn train_and_resume() {
+    let dir = setup("resume", 40);
+    let config = dir.join("run.toml");
+    let out = dir.join("net.json");
+    let path = |p: &PathBuf| p.to_str().unwrap().to_string();
+
+    let net = TestNet::boxed_and_zeroed();
+    let data = read_data(&dir.join("train.txt"), &*net).unwrap();
+    assert_eq!(data.len(), 28);
+    assert_eq!(data[0].0, SparseVector::from_slice(&[1, 0]));
+
+    let bad = dir.join("bad.txt");
+    std::fs::write(&bad, "0.5 | 1 2\n-0.5 | 3 8\n").unwrap();
+    let err = read_data(&bad, &*net).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "{}:2: feature index 8 out of bounds for input of size 8",
+            bad.display()
+        )
+    );
+
+    let full = args(&["--config", &path(&config), "--out", &path(&out)]).unwrap();
+    let loss = run::<TestNet, 1>(&full).unwrap();
+    assert!(loss < 0.05, "{loss}");
+    let mut trained = TestNet::boxed_and_zeroed();
+    goober::json::load(&mut *trained, out.to_str().unwrap()).unwrap();
+
+    // stop half way, then resume for the remaining epochs
+    let resumed = setup("halves", 20);
+    let first = args(&["--config", &path(&resumed.join("run.toml"))]).unwrap();
+    let first = Args {
+        out: resumed.join("net.gbnn"),
+        ..first
+    };
+    run::<TestNet, 1>(&first).unwrap();
+
+    std::fs::write(
+        resumed.join("run.toml"),
+        std::fs::read_to_string(&config).unwrap(),
+    )
+    .unwrap();
+    let second = Args {
+        resume: true,
+        ..first
+    };
+    run::<TestNet, 1>(&second).unwrap();
+
+    let mut net = TestNet::boxed_and_zeroed();
+    net.load(resumed.join("net.gbnn").to_str().unwrap())
+        .unwrap();
+    assert_eq!(net.fingerprint(), trained.fingerprint());
+
+    std::fs::remove_dir_all(dir).unwrap();
+    std::fs::remove_dir_all(resumed).unwrap();
+}
+
+#[test]
+fn binary() {
+    let bin = env!("CARGO_BIN_EXE_goober-train");
+    let help = Command::new(bin).arg("--help").output().unwrap();
+    assert!(help.status.success());
+    assert!(String::from_utf8_lossy(&help.stdout).contains("--hidden <H>"));
+
+    let dir = setup("binary", 2);
+    let status = Command::new(bin)
+        .args(["--config", "run.toml", "--hidden", "32", "--log", "log.csv"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(status.status.success(), "{status:?}");
+    assert!(dir.join("net.gbnn").exists());
+    assert_eq!(
+        std::fs::read_to_string(dir.join("log.csv"))
+            .unwrap()
+            .lines()
+            .count(),
+        3
+    );
+
+    let bad = Command::new(bin)
+        .args(["--config", "run.toml", "--hidden", "33"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(!bad.status.success());
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("unsupported hidden size `33`"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn sweep() {
+    let bin = env!("CARGO_BIN_EXE_goober-train");
+    let dir = setup("sweep", 2);
+    std::fs::copy(dir.join("train.txt"), dir.join("val.txt")).unwrap();
+
+    let config = std::fs::read_to_string(dir.join("run.toml")).unwrap();
+    let config = config.replace("[data]\n", "[data]\nvalidation = \"val.txt\"\n");
+    let sweep =
+        format!("{config}\n[sweep.params]\nschedule.lr = [0.001, 0.01]\nargs.hidden = [32, 64]\n");
+    std::fs::write(dir.join("sweep.toml"), sweep).unwrap();
+
+    let output = Command::new(bin)
+        .args(["--sweep", "sweep.toml", "--jobs", "2"])
+        .current_dir(&dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 6, "{stdout}");
+    assert!(lines[0].contains("schedule.lr") && lines[0].contains("args.hidden"));
+    assert!(lines[5].starts_with("loss "));
+
+    let trials = dir.join("sweep.toml-trials");
+    for i in 0..4 {
+        assert!(trials.join(format!("trial-{i}.toml")).exists());
+        assert!(trials.join(format!("trial-{i}.gbnn")).exists());
+    }
+    assert!(!dir.join("ckpt").exists());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
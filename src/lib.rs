@@ -1,3 +1,30 @@
-pub use goober_core::{activation, FeedForwardNetwork, Matrix, OutputLayer, SparseVector, Vector};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "chess")]
+pub use goober_core::chess;
+#[cfg(feature = "progress")]
+pub use goober_core::progress;
+#[cfg(feature = "tensorboard")]
+pub use goober_core::tensorboard;
+#[cfg(feature = "zstd")]
+pub use goober_core::zstd;
+pub use goober_core::{
+    activation, augment, bf16, dataset, diff, dot, f16, gradcheck, init, loss, merge, param_name,
+    param_name_cow, prune, qat, schedule, stats, summary, ActivationVisitor, FeatureOutOfBounds,
+    FeedForwardNetwork, Float, GooberError, Gradient, Graph, LengthMismatch, Matrix, Node, Op,
+    OutputLayer, Param, ParamMut, ParamVisitor, ParamVisitorMut, Rand, Real, SparseVector,
+    Stochastic, Unsupported, Vector, WeightedSparseVector,
+};
+#[cfg(feature = "std")]
+pub use goober_core::{
+    callback, checkpoint, codegen, config, crc32, csv, include_net, json, libsvm, lr_finder,
+    metrics, mmap, numpy, onnx, safetensors, seed_stochastic_rounding, stream, sweep, trainer,
+    LoadError,
+};
+#[cfg(feature = "ffi")]
+pub use goober_core::{export_c_api, ffi};
 pub use goober_derive::FeedForwardNetwork;
 pub use goober_layer as layer;
+
+#[doc(hidden)]
+pub extern crate alloc as __alloc;
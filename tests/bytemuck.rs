@@ -0,0 +1,41 @@
+#![cfg(feature = "bytemuck")]
+
+use goober::{
+    activation::{ReLU, Tanh},
+    f16,
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, Matrix, SparseVector, Vector,
+};
+
+#[test]
+fn cast() {
+    let vector = Vector::<3>::from_raw([1.0, -2.0, 0.5]);
+    let bytes = bytemuck::bytes_of(&vector);
+    assert_eq!(bytes.len(), 12);
+    assert_eq!(bytes[4..8], (-2f32).to_le_bytes());
+    assert_eq!(*bytemuck::from_bytes::<Vector<3>>(bytes), vector);
+
+    let matrix = Matrix::<2, 3>::from_fn(|i, j| (i * 3 + j) as f32);
+    let floats: &[f32] = bytemuck::cast_slice(bytemuck::bytes_of(&matrix));
+    assert_eq!(floats, matrix.as_ref());
+
+    let zeroed: Vector<4, f16> = bytemuck::Zeroable::zeroed();
+    assert_eq!(zeroed, Vector::zeroed());
+}
+
+#[test]
+fn layers() {
+    let layer = SparseConnected::<ReLU, 5, 3>::from_fn(|i, j| (i + 2 * j) as f32, |j| -(j as f32));
+    assert_eq!(
+        bytemuck::cast_slice::<u8, f32>(bytemuck::bytes_of(&layer)),
+        layer.as_ref()
+    );
+
+    let mut loaded: SparseConnected<ReLU, 5, 3> = bytemuck::Zeroable::zeroed();
+    bytemuck::bytes_of_mut(&mut loaded).copy_from_slice(bytemuck::bytes_of(&layer));
+    let input = SparseVector::from_slice(&[1, 4]);
+    assert_eq!(loaded.out(&input), layer.out(&input));
+
+    let dense: DenseConnected<Tanh, 3, 1, f16> = bytemuck::Zeroable::zeroed();
+    assert_eq!(bytemuck::bytes_of(&dense), [0; 8]);
+}
@@ -0,0 +1,47 @@
+use goober::{
+    activation::{Identity, ReLU},
+    layer::{calibrate, DenseConnected, SparseConnected},
+    FeedForwardNetwork, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 2>,
+    l2: DenseConnected<Identity, 2, 1>,
+}
+
+#[test]
+fn calibrate_network() {
+    let net = TestNet {
+        l1: SparseConnected::from_fn(|i, j| (i + j) as f32 / 4.0, |_| 0.0),
+        l2: DenseConnected::from_fn(|_, j| j as f32 - 2.0, |_| 0.5),
+    };
+
+    let inputs = [
+        SparseVector::from_slice(&[0, 1]),
+        SparseVector::from_slice(&[2, 3]),
+    ];
+    let calibration = calibrate(&net, &inputs);
+
+    let names: Vec<_> = calibration.layers().iter().map(|l| &l.name).collect();
+    assert_eq!(names, ["l1", "l2"]);
+
+    // features 2 and 3 of the second neuron sum to 0.75 + 1.0
+    let l1 = calibration.get("l1").unwrap();
+    assert_eq!(l1.max_weight, 1.0);
+    assert_eq!(l1.max_output, 1.75);
+    assert_eq!(l1.weight_scale(), i16::MAX);
+    assert_eq!(l1.scale(), 18724);
+
+    // 1.25 * -2.0 + 1.75 * -1.0 + 0.5
+    let l2 = calibration.get("l2").unwrap();
+    assert_eq!(l2.max_weight, 2.0);
+    assert_eq!(l2.max_output, 3.75);
+    assert_eq!(l2.weight_scale(), 16383);
+    assert_eq!(l2.output_scale(), 8737);
+
+    assert_eq!(
+        calibration.scales(),
+        [("l1".to_string(), 18724), ("l2".to_string(), 8737)]
+    );
+}
@@ -0,0 +1,133 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    checkpoint::{Checkpoint, CheckpointManager},
+    layer::{DenseConnected, SparseConnected},
+    schedule::{Constant, Plateau, Scheduler},
+    FeedForwardNetwork, LoadError, OutputLayer, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+fn initial() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    for i in 0..8 {
+        *net.l1.weights_row_mut(i) = Vector::from_fn(|j| ((i * 4 + j) % 7) as f32 / 10.0 - 0.3);
+    }
+    *net.l2.weights_row_mut(0) = Vector::from_raw([0.1, -0.2, 0.3, 0.4]);
+    net
+}
+
+fn train(ckpt: &mut Checkpoint<TestNet, Plateau>, steps: u64) {
+    for _ in 0..steps {
+        let step = ckpt.step as usize;
+        let input = SparseVector::from_slice(&[
+            ckpt.rng.rand_u64() as usize % 8,
+            ckpt.rng.rand_u64() as usize % 8,
+        ]);
+        let target = if step.is_multiple_of(2) { 0.5 } else { -0.5 };
+
+        let mut grad = TestNet::zeroed_grad();
+        let layers = ckpt.net.out_with_layers(&input);
+        let err = layers.output_layer()[0] - target;
+        ckpt.net
+            .backprop(&input, &mut grad, Vector::from_raw([2.0 * err]), &layers);
+
+        ckpt.adam(&grad, 1.0);
+        ckpt.scheduler.observe(err * err);
+    }
+}
+
+#[test]
+fn resume_matches_uninterrupted() {
+    let mut full = Checkpoint::new(initial(), Plateau::new(0.01, 0.5, 2));
+    train(&mut full, 40);
+
+    let mut first = Checkpoint::new(initial(), Plateau::new(0.01, 0.5, 2));
+    train(&mut first, 17);
+
+    let path = std::env::temp_dir().join("goober_resume_matches_uninterrupted.ckpt");
+    let path = path.to_str().unwrap();
+    first.save(path).unwrap();
+    drop(first);
+
+    let mut resumed = Checkpoint::new(TestNet::boxed_and_zeroed(), Plateau::new(0.01, 0.5, 2));
+    resumed.load(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(resumed.step, 17);
+
+    train(&mut resumed, 23);
+    assert_eq!(resumed.step, 40);
+    assert_eq!(resumed.rng, full.rng);
+    assert_eq!(resumed.lr(), full.lr());
+    assert_eq!(resumed.to_bytes(), full.to_bytes());
+}
+
+#[test]
+fn load_failures() {
+    let ckpt = Checkpoint::new(initial(), Constant(0.01));
+    let bytes = ckpt.to_bytes();
+
+    let mut other = Checkpoint::new(TestNet::boxed_and_zeroed(), Constant(0.01));
+
+    let mut corrupt = bytes.clone();
+    corrupt[20] ^= 1;
+    assert!(matches!(
+        other.load_bytes(&corrupt),
+        Err(LoadError::BadChecksum)
+    ));
+
+    let mut plateau = Checkpoint::new(TestNet::boxed_and_zeroed(), Plateau::new(0.01, 0.5, 2));
+    assert!(matches!(
+        plateau.load_bytes(&bytes),
+        Err(LoadError::Malformed(_))
+    ));
+
+    other.load_bytes(&bytes).unwrap();
+    assert_eq!(other.to_bytes(), bytes);
+}
+
+#[test]
+fn rotation() {
+    let dir = std::env::temp_dir().join("goober_checkpoint_rotation");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let manager = CheckpointManager::new(&dir, 2).unwrap();
+    assert!(manager.latest().unwrap().is_none());
+    assert!(manager.best().is_none());
+
+    let mut ckpt = Checkpoint::new(initial(), Plateau::new(0.01, 0.5, 2));
+    for _ in 0..4 {
+        train(&mut ckpt, 5);
+        manager.save(&ckpt).unwrap();
+        if ckpt.step == 10 {
+            manager.save_best(&ckpt).unwrap();
+        }
+    }
+
+    let steps: Vec<u64> = manager
+        .checkpoints()
+        .unwrap()
+        .into_iter()
+        .map(|(step, _)| step)
+        .collect();
+    assert_eq!(steps, [15, 20]);
+
+    let mut resumed = Checkpoint::new(TestNet::boxed_and_zeroed(), Plateau::new(0.01, 0.5, 2));
+    resumed
+        .load(manager.latest().unwrap().unwrap().to_str().unwrap())
+        .unwrap();
+    assert_eq!(resumed.to_bytes(), ckpt.to_bytes());
+
+    resumed
+        .load(manager.best().unwrap().to_str().unwrap())
+        .unwrap();
+    assert_eq!(resumed.step, 10);
+
+    let leftover = std::fs::read_dir(&dir).unwrap().count();
+    assert_eq!(leftover, 3);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
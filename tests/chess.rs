@@ -0,0 +1,86 @@
+#![cfg(feature = "chess")]
+
+use goober::{
+    chess::{BulletFormat, MarlinFormat, Position},
+    dataset::{Dataset, Record, Records},
+    SparseVector,
+};
+
+/// White king on e1 and pawn on e2, black king on e8 and queen on d8,
+/// evaluated at 150 centipawns for white, with `stm` the side to move
+/// in the top bit, which goes on to win.
+fn marlin(stm: u8) -> [u8; 32] {
+    let mut record = [0; 32];
+    let occupancy: u64 = 1 << 4 | 1 << 12 | 1 << 59 | 1 << 60;
+    record[..8].copy_from_slice(&occupancy.to_le_bytes());
+    record[8] = 0x05;
+    record[9] = 0xDC;
+    record[24] = stm | 64;
+    record[26] = 1;
+    record[28..30].copy_from_slice(&150i16.to_le_bytes());
+    record[30] = 2;
+    record
+}
+
+/// The position of `marlin(0x80)`, flipped so that black is to move.
+fn bullet() -> [u8; 32] {
+    let mut record = [0; 32];
+    let occupancy: u64 = 1 << 3 | 1 << 4 | 1 << 52 | 1 << 60;
+    record[..8].copy_from_slice(&occupancy.to_le_bytes());
+    record[8] = 0x54;
+    record[9] = 0xD8;
+    record[24..26].copy_from_slice(&(-150i16).to_le_bytes());
+    record[27] = 4;
+    record[28] = 4;
+    record
+}
+
+fn features(position: &Position) -> Vec<usize> {
+    let mut features: Vec<usize> = position.features.iter().copied().collect();
+    features.sort();
+    features
+}
+
+#[test]
+fn marlinformat() {
+    let white = MarlinFormat::<400>::position(&marlin(0));
+    assert_eq!(features(&white), [12, 324, 699, 764]);
+    assert_eq!((white.score, white.result), (150, 1.0));
+
+    let black = MarlinFormat::<400>::position(&marlin(0x80));
+    assert_eq!(features(&black), [259, 324, 436, 764]);
+    assert_eq!((black.score, black.result), (-150, 0.0));
+
+    let mut castling = marlin(0);
+    // the pawn becomes a rook that may still castle
+    castling[8] = 0x65;
+    let rook = MarlinFormat::<400>::position(&castling);
+    assert_eq!(features(&rook), [204, 324, 699, 764]);
+}
+
+#[test]
+fn bulletformat() {
+    let position = BulletFormat::<400>::position(&bullet());
+    let marlin = MarlinFormat::<400>::position(&marlin(0x80));
+    assert_eq!(features(&position), features(&marlin));
+    assert_eq!((position.score, position.result), (-150, 0.0));
+}
+
+#[test]
+fn targets() {
+    let position = BulletFormat::<400>::position(&bullet());
+    let score = 1.0 / (1.0 + 0.375f32.exp());
+    assert!((position.target(400.0, 0.0) - score).abs() < 1e-6);
+    assert!((position.target(400.0, 0.25) - 0.75 * score).abs() < 1e-6);
+
+    let (_, target) = BulletFormat::<400, 25>::decode(&bullet());
+    assert!((target[0] - 0.75 * score).abs() < 1e-6);
+    let (_, target) = MarlinFormat::<200, 100>::decode(&marlin(0));
+    assert_eq!(target[0], 1.0);
+
+    let records = Records::<MarlinFormat>::new([marlin(0), marlin(0x80)].concat());
+    assert_eq!(records.len(), 2);
+    let (input, target) = records.get(1).into_owned();
+    assert_eq!(input, SparseVector::from_slice(&[764, 436, 259, 324]));
+    assert!((target[0] - score).abs() < 1e-6);
+}
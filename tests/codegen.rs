@@ -0,0 +1,38 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    codegen,
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 3>,
+    l2: DenseConnected<Tanh, 3, 1>,
+}
+
+fn test_net() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    for i in 0..4 {
+        *net.l1.weights_row_mut(i) = Vector::from_fn(|j| (i as f32 - j as f32) / 4.0);
+    }
+    *net.l2.weights_row_mut(0) = Vector::from_raw([0.5, -1.0, 0.1]);
+    net
+}
+
+mod generated {
+    include!("data/codegen.rs");
+}
+
+#[test]
+fn codegen() {
+    let net = test_net();
+    let source = codegen::to_rust(&*net).unwrap();
+    assert_eq!(source, include_str!("data/codegen.rs"));
+
+    for feats in [&[0, 3][..], &[1, 2, 3], &[]] {
+        let expected = net.out(&SparseVector::from_slice(feats));
+        let out = generated::evaluate(feats);
+        assert!((out[0] - expected[0]).abs() < 1e-6);
+    }
+}
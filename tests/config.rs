@@ -0,0 +1,142 @@
+use std::path::PathBuf;
+
+use goober::{
+    activation::{ReLU, Tanh},
+    config::{CheckpointConfig, Config, ConfigError, Schedule},
+    layer::{DenseConnected, SparseConnected},
+    schedule::{Cosine, Plateau},
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+const CONFIG: &str = r#"
+epochs = 3
+batch_size = 4
+seed = 42
+deterministic = true
+
+[optimizer]
+kind = "adam"
+ema = 0.9
+
+[schedule]
+kind = "cosine"
+lr = 0.01
+min_lr = 1e-4
+warmup = 2
+steps = 12
+
+[data]
+train = [
+    "a.bin",
+    "b.bin",
+]
+validation = "val.bin"
+
+[checkpoint]
+dir = "ckpt"
+keep = 2
+"#;
+
+#[test]
+fn parse() {
+    let config = Config::parse(CONFIG).unwrap();
+    assert_eq!(config.epochs, 3);
+    assert_eq!(config.batch_size, 4);
+    assert_eq!(config.seed, Some(42));
+    assert!(config.deterministic && config.shuffle && !config.nan_guard);
+    assert_eq!(config.ema, Some(0.9));
+    assert_eq!(config.swa, None);
+    assert_eq!(
+        config.schedule,
+        Schedule::Cosine(Cosine {
+            lr: 0.01,
+            min_lr: 1e-4,
+            warmup: 2,
+            steps: 12,
+        })
+    );
+    assert_eq!(config.data.train, [PathBuf::from("a.bin"), "b.bin".into()]);
+    assert_eq!(config.data.validation, Some("val.bin".into()));
+    assert_eq!(
+        config.checkpoint,
+        Some(CheckpointConfig {
+            dir: "ckpt".into(),
+            every: 1,
+            keep: 2,
+        })
+    );
+
+    let plateau = "[schedule]\nkind = 'plateau'\nlr = 1\nfactor = 0.5\npatience = 3";
+    assert_eq!(
+        Config::parse(plateau).unwrap().schedule,
+        Schedule::Plateau(Plateau::new(1.0, 0.5, 3))
+    );
+    assert_eq!(Config::parse("").unwrap(), Config::default());
+}
+
+#[test]
+fn errors() {
+    let err = |text: &str| Config::parse(text).unwrap_err().to_string();
+
+    assert_eq!(err("epochs = 1\nepoch = 2"), "`epoch` on line 2 is unknown");
+    assert_eq!(
+        err("epochs = -1"),
+        "`epochs` should be a non-negative integer"
+    );
+    assert_eq!(
+        err("[schedule]\nkind = \"cosine\"\nlr = 1"),
+        "`schedule.steps` is missing"
+    );
+    assert_eq!(
+        err("[loss]\nkind = \"l1\""),
+        "`loss.kind` is `l1`, expected one of mse"
+    );
+    assert_eq!(err("seed = 1\nseed = 2"), "line 2: duplicate key `seed`");
+    assert_eq!(
+        err("deterministic = true"),
+        "`deterministic` requires `seed`"
+    );
+    assert!(matches!(
+        Config::load("does/not/exist.toml"),
+        Err(ConfigError::Io(_))
+    ));
+}
+
+fn data() -> Vec<(SparseVector, Vector<1>)> {
+    (0..8)
+        .map(|i| {
+            let target = if i % 2 == 0 { 0.5 } else { -0.5 };
+            (SparseVector::from_slice(&[i]), Vector::from_raw([target]))
+        })
+        .collect()
+}
+
+#[test]
+fn load_and_train() {
+    let dir = std::env::temp_dir().join(format!("goober-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("run.toml");
+    std::fs::write(&path, CONFIG).unwrap();
+
+    let config = Config::load(&path).unwrap();
+    assert_eq!(config.data.train[0], dir.join("a.bin"));
+    assert_eq!(config.checkpoint.as_ref().unwrap().dir, dir.join("ckpt"));
+
+    let run = || {
+        let mut trainer = config.trainer(TestNet::boxed_and_zeroed(), data()).unwrap();
+        trainer.run(config.epochs).unwrap();
+        assert_eq!(trainer.step(), 6);
+        assert!(trainer.ema_net().is_some());
+        trainer.checkpoint().to_bytes()
+    };
+
+    assert_eq!(run(), run());
+    assert_eq!(std::fs::read_dir(dir.join("ckpt")).unwrap().count(), 3);
+    std::fs::remove_dir_all(dir).unwrap();
+}
@@ -1,7 +1,12 @@
 use goober::{
-    activation::ReLU,
-    layer::{DenseConnected, SparseConnected},
-    FeedForwardNetwork, SparseVector,
+    activation::{ReLU, Tanh},
+    init::Normal,
+    layer::{
+        Add, BucketedSparse, Conv1D, DenseConnected, FactorizedSparse, Factorizer, HashedSparse,
+        SparseConnected, SparsePerspective, WeightedSparse,
+    },
+    ActivationVisitor, FeedForwardNetwork, Float, Param, ParamVisitor, Rand, SparseVector, Vector,
+    WeightedSparseVector,
 };
 
 #[derive(FeedForwardNetwork)]
@@ -24,3 +29,202 @@ fn connected() {
     input.push(5);
     let _ = net.out(&input);
 }
+
+/// Every parameter, in the order visited.
+#[derive(Default)]
+struct Concat(Vec<f32>);
+
+impl ParamVisitor for Concat {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.0.extend(param.values.iter().map(|x| x.to_f32()));
+    }
+}
+
+fn assert_as_ref<T: FeedForwardNetwork + AsRef<[f32]> + AsMut<[f32]>>(layer: &mut T) {
+    let mut concat = Concat::default();
+    layer.visit_params("", &mut concat);
+    assert_eq!(layer.as_ref(), concat.0);
+
+    layer.as_mut().iter_mut().for_each(|x| *x = 1.0);
+    let mut concat = Concat::default();
+    layer.visit_params("", &mut concat);
+    assert!(concat.0.iter().all(|&x| x == 1.0));
+}
+
+#[derive(Clone, Copy)]
+struct Halves;
+
+impl Factorizer for Halves {
+    fn factor(feat: usize) -> usize {
+        feat / 2
+    }
+}
+
+#[test]
+fn as_ref() {
+    let mut rng = Rand::with_seed(7);
+    let dist = Normal::new(0.0, 1.0);
+
+    assert_as_ref(&mut DenseConnected::<ReLU, 5, 3>::random(&mut rng, dist));
+    assert_as_ref(&mut SparseConnected::<ReLU, 5, 3>::random(&mut rng, dist));
+    assert_as_ref(&mut SparsePerspective::<ReLU, 5, 3, 6>::random(
+        &mut rng, dist,
+    ));
+    assert_as_ref(&mut BucketedSparse::<ReLU, 5, 3, 2>::random(&mut rng, dist));
+    assert_as_ref(&mut FactorizedSparse::<ReLU, Halves, 6, 3, 2>::random(
+        &mut rng, dist,
+    ));
+    assert_as_ref(&mut Conv1D::<ReLU, 6, 4>::random(&mut rng, dist));
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DenseNet {
+    l1: DenseConnected<ReLU, 4, 8>,
+    l2: DenseConnected<Tanh, 8, 2>,
+}
+
+/// Every output of every layer, in the order visited.
+#[derive(Default)]
+struct Outputs(Vec<f32>);
+
+impl ActivationVisitor for Outputs {
+    fn visit<F: Float>(&mut self, _: &str, values: &[F]) {
+        self.0.extend(values.iter().map(|x| x.to_f32()));
+    }
+}
+
+fn outputs<T: FeedForwardNetwork>(net: &T, layers: &T::Layers) -> Vec<f32> {
+    let mut outputs = Outputs::default();
+    net.visit_activations("", layers, &mut outputs);
+    outputs.0
+}
+
+#[test]
+fn out_into() {
+    let mut rng = Rand::with_seed(5);
+    let dist = Normal::new(0.0, 0.5);
+
+    let mut net = TestNet::boxed_and_zeroed();
+    net.l1 = SparseConnected::random(&mut rng, dist);
+    net.l2.l1 = DenseConnected::random(&mut rng, dist);
+    net.l2.l2 = DenseConnected::random(&mut rng, dist);
+
+    let inputs = [
+        SparseVector::from_slice(&[5, 100, 700]),
+        SparseVector::from_slice(&[1]),
+        SparseVector::from_slice(&[]),
+    ];
+    let mut layers = net.out_with_layers(&inputs[0]);
+    for input in &inputs {
+        net.out_into(input, &mut layers);
+        let expected = net.out_with_layers(input);
+        assert_eq!(outputs(&*net, &layers), outputs(&*net, &expected));
+    }
+}
+
+/// Runs `layer` on each of `inputs` in turn, reusing the same layers.
+fn assert_out_into<T: FeedForwardNetwork>(layer: &T, inputs: &[T::InputType]) {
+    let mut layers = layer.out_with_layers(&inputs[0]);
+    for input in inputs.iter().rev() {
+        layer.out_into(input, &mut layers);
+        let expected = layer.out_with_layers(input);
+        assert_eq!(outputs(layer, &layers), outputs(layer, &expected));
+    }
+}
+
+#[test]
+fn out_into_layers() {
+    let mut rng = Rand::with_seed(8);
+    let dist = Normal::new(0.0, 0.5);
+    let a = SparseVector::from_slice(&[0, 3, 4]);
+    let b = SparseVector::from_slice(&[1]);
+
+    let layer = SparsePerspective::<ReLU, 6, 3, 6>::random(&mut rng, dist);
+    assert_out_into(&layer, &[(a.clone(), b.clone()), (b.clone(), a.clone())]);
+    let layer = BucketedSparse::<ReLU, 6, 3, 2>::random(&mut rng, dist);
+    assert_out_into(&layer, &[(0, a.clone()), (1, b.clone())]);
+    let layer = FactorizedSparse::<ReLU, Halves, 6, 3, 3>::random(&mut rng, dist);
+    assert_out_into(&layer, &[a.clone(), b.clone()]);
+    let layer = HashedSparse::<ReLU, 3, 4>::random(&mut rng, dist);
+    assert_out_into(&layer, &[a.clone(), b.clone()]);
+    let layer = Add::from_raw(
+        SparseConnected::<ReLU, 6, 3>::random(&mut rng, dist),
+        SparseConnected::<ReLU, 6, 3>::random(&mut rng, dist),
+    );
+    assert_out_into(&layer, &[a, b]);
+
+    let mut weighted = WeightedSparseVector::with_capacity(2);
+    weighted.push(2, 0.5);
+    weighted.push(5, -1.5);
+    let layer = WeightedSparse::<ReLU, 6, 3>::random(&mut rng, dist);
+    assert_out_into(&layer, &[weighted, WeightedSparseVector::with_capacity(0)]);
+
+    let layer = Conv1D::<Tanh, 6, 4>::random(&mut rng, dist);
+    assert_out_into(
+        &layer,
+        &[
+            Vector::from_raw([1.0, -1.0, 0.5, 0.0, 2.0, 0.25]),
+            Vector::zeroed(),
+        ],
+    );
+}
+
+#[test]
+fn backprop_into() {
+    let mut rng = Rand::with_seed(6);
+    let dist = Normal::new(0.0, 0.5);
+    let net = DenseNet {
+        l1: DenseConnected::random(&mut rng, dist),
+        l2: DenseConnected::random(&mut rng, dist),
+    };
+
+    let input = Vector::from_raw([0.5, -1.0, 2.0, 0.25]);
+    let err = Vector::from_raw([1.0, -0.5]);
+    let mut layers = net.out_with_layers(&input);
+
+    let mut grad = DenseNet::zeroed_grad();
+    let expected = net.backprop(&input, &mut grad, err, &layers);
+
+    let mut grad_into = DenseNet::zeroed_grad();
+    let mut input_err = Vector::zeroed();
+    net.backprop_into(&input, &mut grad_into, &err, &mut layers, &mut input_err);
+
+    assert_eq!(input_err, expected);
+    assert_eq!(grad_into.l1.fingerprint(), grad.l1.fingerprint());
+    assert_eq!(grad_into.l2.fingerprint(), grad.l2.fingerprint());
+}
+
+#[test]
+fn backprop_into_nested() {
+    let mut rng = Rand::with_seed(7);
+    let dist = Normal::new(0.0, 0.5);
+    let net = TestNet {
+        l1: SparseConnected::random(&mut rng, dist),
+        l2: SubTestNet {
+            l1: DenseConnected::random(&mut rng, dist),
+            l2: DenseConnected::random(&mut rng, dist),
+        },
+    };
+
+    let inputs = [
+        SparseVector::from_slice(&[1, 50, 700]),
+        SparseVector::from_slice(&[3, 50]),
+    ];
+    let errs = [Vector::from_raw([1.0]), Vector::from_raw([-0.5])];
+
+    // the layers, and so the errors kept in them, of the first
+    // sample are reused for the second
+    let mut grad = TestNet::zeroed_grad();
+    let mut grad_into = TestNet::zeroed_grad();
+    let mut layers = net.out_with_layers(&inputs[0]);
+    let mut input_err = SparseVector::with_capacity(0);
+    for (input, err) in inputs.iter().zip(errs) {
+        net.backprop(input, &mut grad, err, &net.out_with_layers(input));
+        net.out_into(input, &mut layers);
+        net.backprop_into(input, &mut grad_into, &err, &mut layers, &mut input_err);
+    }
+
+    assert_eq!(grad_into.l1.fingerprint(), grad.l1.fingerprint());
+    assert_eq!(grad_into.l2.l1.fingerprint(), grad.l2.l1.fingerprint());
+    assert_eq!(grad_into.l2.l2.fingerprint(), grad.l2.l2.fingerprint());
+}
@@ -0,0 +1,80 @@
+use std::io::{self, Cursor};
+
+use goober::{csv::Csv, Vector};
+
+const HOUSES: &str = "\
+rooms,area,\"age, in years\",price
+3,120.5,10,250
+
+4, 150,\"2\",  400
+";
+
+#[test]
+fn columns() {
+    let data: Vec<(Vector<3>, Vector<1>)> = Csv::new().read(Cursor::new(HOUSES)).unwrap();
+    assert_eq!(
+        data,
+        [
+            (
+                Vector::from_raw([3.0, 120.5, 10.0]),
+                Vector::from_raw([250.0])
+            ),
+            (
+                Vector::from_raw([4.0, 150.0, 2.0]),
+                Vector::from_raw([400.0])
+            ),
+        ]
+    );
+
+    let data: Vec<(Vector<2>, Vector<2>)> = Csv::new()
+        .with_inputs(&["age, in years", "rooms"])
+        .with_targets(&["price", "area"])
+        .read(Cursor::new(HOUSES))
+        .unwrap();
+    assert_eq!(data[1].0, Vector::from_raw([2.0, 4.0]));
+    assert_eq!(data[1].1, Vector::from_raw([400.0, 150.0]));
+
+    let data: Vec<(Vector<3>, Vector<1>)> = Csv::new()
+        .with_targets(&["rooms"])
+        .with_delimiter(';')
+        .read(Cursor::new("rooms;area;age;price\n3;120;10;250\n"))
+        .unwrap();
+    assert_eq!(
+        data,
+        [(
+            Vector::from_raw([120.0, 10.0, 250.0]),
+            Vector::from_raw([3.0])
+        )]
+    );
+}
+
+#[test]
+fn errors() {
+    let error = |csv: Csv, text: &str| {
+        let err = csv.read::<2, 1>(Cursor::new(text)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.to_string()
+    };
+
+    assert_eq!(error(Csv::new(), ""), "line 1: expected a header");
+    assert_eq!(
+        error(Csv::new(), HOUSES),
+        "line 1: expected 2 input columns, found 3"
+    );
+    assert_eq!(
+        error(Csv::new().with_targets(&["cost"]), HOUSES),
+        "line 1: no column `cost`"
+    );
+    assert_eq!(
+        error(Csv::new(), "a,b,c\n1,2,3\n1,2\n"),
+        "line 3: expected 3 fields, found 2"
+    );
+    assert_eq!(
+        error(Csv::new(), "a,b,c\n1,two,3\n"),
+        "line 2: invalid value `two` in column `b`"
+    );
+    assert_eq!(
+        error(Csv::new(), "a,b,c\n1,\"2,3\n"),
+        "line 2: unterminated quote"
+    );
+}
@@ -0,0 +1,53 @@
+// Generated by goober, do not edit.
+
+type T = f32;
+
+static L1_WEIGHT: [[T; 3]; 4] = [
+    [0.0, -0.25, -0.5],
+    [0.25, 0.0, -0.25],
+    [0.5, 0.25, 0.0],
+    [0.75, 0.5, 0.25],
+];
+
+static L1_BIAS: [T; 3] = [0.0, 0.0, 0.0];
+
+static L2_WEIGHT: [[T; 3]; 1] = [
+    [0.5, -1.0, 0.1],
+];
+
+static L2_BIAS: [T; 1] = [0.0];
+
+pub fn evaluate(input: &[usize]) -> [T; 1] {
+    let t0 = sparse_linear(&L1_WEIGHT, &L1_BIAS, input);
+    let t1 = relu(t0);
+    let t2 = linear(&L2_WEIGHT, &L2_BIAS, &t1);
+    tanh(t2)
+}
+
+fn sparse_linear<const M: usize, const N: usize>(w: &[[T; N]; M], b: &[T; N], x: &[usize]) -> [T; N] {
+    let mut out = *b;
+    for &feat in x {
+        for (out, w) in out.iter_mut().zip(&w[feat]) {
+            *out += w;
+        }
+    }
+    out
+}
+
+fn relu<const N: usize>(x: [T; N]) -> [T; N] {
+    x.map(|x| x.max(0.0))
+}
+
+fn linear<const M: usize, const N: usize>(w: &[[T; M]; N], b: &[T; N], x: &[T; M]) -> [T; N] {
+    let mut out = *b;
+    for (out, row) in out.iter_mut().zip(w) {
+        for (w, x) in row.iter().zip(x) {
+            *out += w * x;
+        }
+    }
+    out
+}
+
+fn tanh<const N: usize>(x: [T; N]) -> [T; N] {
+    x.map(T::tanh)
+}
@@ -0,0 +1,195 @@
+use goober::{
+    dataset::{
+        split, split_by_key, Dataset, Encoded, FeatureEncoder, Record, Records, Sample, Subset,
+    },
+    SparseVector, Vector, WeightedSparseVector,
+};
+
+/// Up to four features as `u16`, padded with `u16::MAX`, then the target.
+struct Position;
+
+impl Record for Position {
+    type Input = SparseVector;
+    type Output = Vector<1>;
+
+    const SIZE: usize = 12;
+
+    fn decode(bytes: &[u8]) -> (SparseVector, Vector<1>) {
+        let features: Vec<usize> = bytes[..8]
+            .chunks(2)
+            .map(|b| u16::from_le_bytes([b[0], b[1]]))
+            .filter(|&f| f != u16::MAX)
+            .map(usize::from)
+            .collect();
+        let target = f32::from_le_bytes(bytes[8..].try_into().unwrap());
+        (
+            SparseVector::from_slice(&features),
+            Vector::from_raw([target]),
+        )
+    }
+}
+
+fn encode(features: &[u16], target: f32) -> Vec<u8> {
+    let mut out = Vec::new();
+    for i in 0..4 {
+        let feature = features.get(i).copied().unwrap_or(u16::MAX);
+        out.extend_from_slice(&feature.to_le_bytes());
+    }
+    out.extend_from_slice(&target.to_le_bytes());
+    out
+}
+
+fn bytes() -> Vec<u8> {
+    [
+        encode(&[1, 5], 0.5),
+        encode(&[], -1.0),
+        encode(&[0, 2, 4, 6], 0.25),
+    ]
+    .concat()
+}
+
+#[test]
+fn in_memory() {
+    let data = vec![(SparseVector::from_slice(&[2]), Vector::from_raw([1.0]))];
+    assert_eq!(Dataset::len(&data), 1);
+    assert!(!Dataset::is_empty(&data));
+
+    let sample = Dataset::get(&data, 0);
+    assert!(matches!(sample, Sample::Borrowed(..)));
+    assert_eq!(sample.input(), &data[0].0);
+    assert_eq!(sample.into_owned(), data[0]);
+}
+
+#[test]
+fn records() {
+    let records = Records::<Position>::new(bytes());
+    assert_eq!(records.len(), 3);
+    assert_eq!(records.record(1), &encode(&[], -1.0)[..]);
+
+    let sample = records.get(2);
+    assert!(matches!(sample, Sample::Owned(..)));
+    assert_eq!(sample.input(), &SparseVector::from_slice(&[0, 2, 4, 6]));
+    assert_eq!(sample.target(), &Vector::from_raw([0.25]));
+    assert_eq!(records.get(1).input(), &SparseVector::from_slice(&[]));
+}
+
+/// Features of the board mirrored, out of eight.
+struct Mirror;
+
+impl FeatureEncoder<SparseVector> for Mirror {
+    type Input = SparseVector;
+
+    fn encode(&self, raw: &SparseVector) -> SparseVector {
+        let features: Vec<usize> = raw.iter().map(|f| 7 - f).collect();
+        SparseVector::from_slice(&features)
+    }
+}
+
+#[test]
+fn encoded() {
+    let data = Encoded::new(Records::<Position>::new(bytes()), Mirror);
+    assert_eq!(data.len(), 3);
+    let sample = data.get(0);
+    assert_eq!(sample.input(), &SparseVector::from_slice(&[6, 2]));
+    assert_eq!(sample.target(), &Vector::from_raw([0.5]));
+
+    let halved = |raw: &SparseVector| {
+        let mut features = WeightedSparseVector::with_capacity(raw.len());
+        for &f in raw {
+            features.push(f, 0.5);
+        }
+        features
+    };
+    let data = Encoded::new(data.into_inner(), halved);
+    assert_eq!(
+        &*data.get(2).into_owned().0,
+        &[(0, 0.5), (2, 0.5), (4, 0.5), (6, 0.5)]
+    );
+}
+
+#[test]
+#[should_panic(expected = "not a whole number of records of 12 bytes")]
+fn partial_record() {
+    Records::<Position, _>::new(&bytes()[..30]);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn mapped() {
+    let dir = std::env::temp_dir().join(format!("goober-records-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("positions.bin");
+    std::fs::write(&path, bytes()).unwrap();
+
+    let mapped = unsafe { Records::<Position, _>::map(&path) }.unwrap();
+    let records = Records::<Position>::new(bytes());
+    assert_eq!(mapped.len(), 3);
+    for i in 0..3 {
+        assert_eq!(mapped.get(i), records.get(i));
+    }
+
+    std::fs::write(&path, &bytes()[..13]).unwrap();
+    let err = unsafe { Records::<Position, _>::map(&path) }.err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// Samples of a feature each, from `games` games of ten positions.
+fn games(games: usize) -> Vec<(SparseVector, Vector<1>)> {
+    (0..games * 10)
+        .map(|i| (SparseVector::from_slice(&[i]), Vector::from_raw([i as f32])))
+        .collect()
+}
+
+#[test]
+fn subset() {
+    let data = Subset::new(games(1), vec![7, 2]);
+    assert_eq!(data.len(), 2);
+    assert_eq!(data.indices(), &[7, 2]);
+    assert_eq!(data.get(0).target(), &Vector::from_raw([7.0]));
+    assert_eq!(
+        data.to_vec(),
+        vec![games(1)[7].clone(), games(1)[2].clone()]
+    );
+}
+
+#[test]
+fn split_by_ratio() {
+    let (train, validation) = split(games(100), 0.1, 1);
+    assert_eq!((train.len(), validation.len()), (900, 100));
+
+    let mut all = [train.indices(), validation.indices()].concat();
+    all.sort_unstable();
+    assert_eq!(all, (0..1000).collect::<Vec<_>>());
+    assert!(train.indices().is_sorted());
+
+    let (_, again) = split(games(100), 0.1, 1);
+    assert_eq!(again.indices(), validation.indices());
+    let (_, other) = split(games(100), 0.1, 2);
+    assert_ne!(other.indices(), validation.indices());
+
+    let (train, validation) = split(games(1), 0.0, 1);
+    assert_eq!((train.len(), validation.len()), (10, 0));
+}
+
+#[test]
+fn split_by_game() {
+    let game = |input: &SparseVector, _: &Vector<1>| input.iter().next().unwrap() / 10;
+    let (train, validation) = split_by_key(games(1000), 0.2, game);
+    assert_eq!(train.len() + validation.len(), 10000);
+    assert!((1500..2500).contains(&validation.len()));
+
+    let held: Vec<usize> = validation
+        .to_vec()
+        .iter()
+        .map(|(input, target)| game(input, target))
+        .collect();
+    for (input, target) in train.to_vec() {
+        assert!(!held.contains(&game(&input, &target)));
+    }
+
+    // a game stays on the same side as more are added
+    let (_, more) = split_by_key(games(2000), 0.2, game);
+    assert_eq!(&more.indices()[..validation.len()], validation.indices());
+}
@@ -0,0 +1,50 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    diff::Diff,
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 2>,
+    l2: DenseConnected<Tanh, 2, 1>,
+}
+
+#[test]
+fn diff() {
+    let mut a = TestNet::boxed_and_zeroed();
+    *a.l1.weights_row_mut(1) = Vector::from_raw([1.0, 1.0]);
+    *a.l2.weights_row_mut(0) = Vector::from_raw([0.5, 0.5]);
+
+    let mut b = TestNet::boxed_and_zeroed();
+    b.load_bytes(&a.save_bytes()).unwrap();
+    assert!(Diff::of(&*a, &*b, 2).is_identical());
+
+    *b.l1.weights_row_mut(1) = Vector::from_raw([1.0, 2.0]);
+    *b.l1.weights_row_mut(3) = Vector::from_raw([3.0, 4.0]);
+    b.l2.bias_mut()[0] = -0.5;
+
+    let diff = Diff::of(&*a, &*b, 2);
+    assert!(!diff.is_identical());
+
+    let l1 = diff.get("l1.weight").unwrap();
+    assert_eq!(l1.l2, 26f32.sqrt());
+    assert_eq!(l1.linf, 4.0);
+    assert_eq!(l1.rel_l2, 13f32.sqrt());
+    assert_eq!(l1.rows, [(3, 5.0), (1, 1.0)]);
+
+    let bias = diff.get("l2.bias").unwrap();
+    assert_eq!((bias.l2, bias.rel_l2), (0.5, f32::INFINITY));
+    assert!(bias.rows.is_empty());
+    assert_eq!(diff.get("l2.weight").unwrap().l2, 0.0);
+
+    let dir = std::env::temp_dir().join(format!("goober-diff-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (pa, pb) = (dir.join("a.gbnn"), dir.join("b.gbnn"));
+    a.save(pa.to_str().unwrap()).unwrap();
+    b.save(pb.to_str().unwrap()).unwrap();
+    let loaded = Diff::load::<TestNet>(pa.to_str().unwrap(), pb.to_str().unwrap(), 2).unwrap();
+    assert_eq!(loaded, diff);
+    std::fs::remove_dir_all(dir).unwrap();
+}
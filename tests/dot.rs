@@ -0,0 +1,39 @@
+use goober::{
+    activation::{Identity, ReLU, Tanh},
+    dot::to_dot,
+    layer::{Add, DenseConnected, SparseConnected},
+    FeedForwardNetwork,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 768, 16>,
+    l2: Add<DenseConnected<ReLU, 16, 4>, DenseConnected<Identity, 16, 4>>,
+    l3: DenseConnected<Tanh, 4, 1>,
+}
+
+#[test]
+fn dot() {
+    let net = TestNet::boxed_and_zeroed();
+    let dot = to_dot(&*net).unwrap();
+
+    let expected = r#"digraph network {
+    node [shape=box];
+    input [shape=ellipse, label="input\nsparse [768]"];
+    n0 [label="l1\nSparseLinear + ReLU\n[16]"];
+    n1 [label="l2.a\nLinear + ReLU\n[4]"];
+    n2 [label="l2.b\nLinear\n[4]"];
+    n3 [label="Add\n[4]"];
+    n4 [label="l3\nLinear + Tanh\n[1]"];
+    input -> n0;
+    n0 -> n1 [label="[16]"];
+    n0 -> n2 [label="[16]"];
+    n1 -> n3 [label="[4]"];
+    n2 -> n3 [label="[4]"];
+    n3 -> n4 [label="[4]"];
+    output [shape=ellipse];
+    n4 -> output [label="[1]"];
+}
+"#;
+    assert_eq!(dot, expected);
+}
@@ -0,0 +1,92 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    gradcheck::GradCheck,
+    layer::{DenseConnected, SparseConnected},
+    loss::Mse,
+    FeedForwardNetwork, Float, Gradient, Param, ParamVisitor, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet<F: Float> {
+    l1: SparseConnected<ReLU, 32, 8, F>,
+    l2: DenseConnected<Tanh, 8, 1, F>,
+}
+
+fn init<F: Float>() -> TestNet<F> {
+    TestNet {
+        l1: SparseConnected::from_fn(
+            |i, j| F::from_f32(((i * 8 + j) % 7) as f32 / 10.0 - 0.3),
+            |i| F::from_f32(i as f32 / 100.0),
+        ),
+        l2: DenseConnected::from_fn(
+            |_, j| F::from_f32((j % 3) as f32 / 10.0 - 0.1),
+            |_| F::from_f32(0.05),
+        ),
+    }
+}
+
+#[test]
+fn double() {
+    let single = init::<f32>();
+    let double = init::<f64>();
+
+    let mut input = SparseVector::with_capacity(8);
+    input.push(3);
+    input.push(17);
+    input.push(30);
+
+    let a = single.out(&input)[0];
+    let b = double.out(&input)[0];
+    assert!((f64::from(a) - b).abs() < 1e-6);
+
+    let mut single_grad = TestNet::<f32>::zeroed_grad();
+    let layers = single.out_with_layers(&input);
+    single.backprop(
+        &input,
+        &mut single_grad,
+        Vector::from_raw([a - 0.5]),
+        &layers,
+    );
+
+    let mut double_grad = TestNet::<f64>::zeroed_grad();
+    let layers = double.out_with_layers(&input);
+    double.backprop(
+        &input,
+        &mut double_grad,
+        Vector::from_raw([b - 0.5]),
+        &layers,
+    );
+
+    let single_grad = values(&*single_grad);
+    let double_grad = values(&*double_grad);
+    assert_eq!(single_grad.len(), double_grad.len());
+    assert!(double_grad.iter().any(|&x| x != 0.0));
+    for (x, y) in single_grad.iter().zip(&double_grad) {
+        assert!((x - y).abs() < 1e-6, "{x} != {y}");
+    }
+}
+
+#[test]
+fn double_gradcheck() {
+    let mut net = init::<f64>();
+    let input = SparseVector::from_slice(&[3, 17, 30]);
+    let target = Vector::from_raw([0.5]);
+
+    let report = GradCheck::default().check(&mut net, &input, &target, &Mse);
+    assert!(report.passed(), "{report}");
+}
+
+/// Every gradient, in the order visited, as `f64`s.
+fn values<G: Gradient>(grad: &G) -> Vec<f64> {
+    struct Collect(Vec<f64>);
+
+    impl ParamVisitor for Collect {
+        fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+            self.0.extend(param.values.iter().map(|x| x.to_f64()));
+        }
+    }
+
+    let mut collect = Collect(Vec::new());
+    grad.visit_grads("", &mut collect);
+    collect.0
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "ffi")]
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 2>,
+}
+
+goober::export_c_api!(TestNet);
+
+#[test]
+fn ffi() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(3) = Vector::from_fn(|i| i as f32 / 4.0);
+    *net.l2.weights_row_mut(1) = Vector::from_fn(|i| 1.0 - i as f32 / 8.0);
+    let bytes = net.save_bytes();
+
+    unsafe {
+        let ptr = goober_create(bytes.as_ptr(), bytes.len());
+        assert!(!ptr.is_null());
+
+        let feats = [3, 9];
+        let mut out = [0.0; 4];
+        let written = goober_forward_sparse(ptr, feats.as_ptr(), 2, out.as_mut_ptr(), 4);
+        assert_eq!(written, 2);
+        assert_eq!(out[..2], net.out(&SparseVector::from_slice(&feats)).as_slice()[..]);
+
+        // too small an output buffer
+        let written = goober_forward_sparse(ptr, feats.as_ptr(), 2, out.as_mut_ptr(), 1);
+        assert_eq!(written, -1);
+
+        // out of bounds features panic, which is caught at the boundary
+        let written = goober_forward_sparse(ptr, [99].as_ptr(), 1, out.as_mut_ptr(), 4);
+        assert_eq!(written, -1);
+
+        // dense input to a sparse network
+        let dense = [0.0; 16];
+        let written = goober_forward_dense(ptr, dense.as_ptr(), 16, out.as_mut_ptr(), 4);
+        assert_eq!(written, -1);
+
+        goober_destroy(ptr);
+
+        assert!(goober_create(bytes.as_ptr(), bytes.len() - 1).is_null());
+    }
+}
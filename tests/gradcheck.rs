@@ -0,0 +1,67 @@
+use goober::{
+    activation::{Identity, ReLU},
+    gradcheck::GradCheck,
+    layer::{Conv1D, DenseConnected, SparseConnected},
+    loss::{Loss, Mse},
+    FeedForwardNetwork, Float, ParamMut, ParamVisitorMut, Rand, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 8>,
+    l2: DenseConnected<Identity, 8, 4>,
+    l3: Conv1D<Identity, 4, 2>,
+}
+
+struct Randomize(Rand);
+
+impl ParamVisitorMut for Randomize {
+    fn visit<F: Float>(&mut self, param: ParamMut<'_, F>) {
+        for x in param.values {
+            *x = F::from_f32(self.0.rand_f32() - 0.5);
+        }
+    }
+}
+
+/// Mean squared error with a gradient twice as large as it should be.
+struct Wrong;
+
+impl Loss<Vector<2>> for Wrong {
+    fn loss(&self, out: &Vector<2>, target: &Vector<2>) -> (f32, Vector<2>) {
+        let (loss, grad) = Mse.loss(out, target);
+        (loss, grad * 2.0)
+    }
+}
+
+fn net() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    net.visit_params_mut("", &mut Randomize(Rand::with_seed(5)));
+    net
+}
+
+#[test]
+fn layers_pass() {
+    let mut net = net();
+    let before = net.save_bytes();
+
+    let input = SparseVector::from_slice(&[1, 4, 6]);
+    let target = Vector::from_raw([0.3, -0.2]);
+    let report = GradCheck::default().check(&mut *net, &input, &target, &Mse);
+
+    assert!(report.passed(), "{report}");
+    assert_eq!(report.tensors().len(), 6);
+    assert_eq!(report.get("l1.weight").unwrap().checked, 64);
+    assert_eq!(report.get("l3.bias").unwrap().checked, 2);
+    assert_eq!(net.save_bytes(), before);
+}
+
+#[test]
+fn wrong_gradient_fails() {
+    let mut net = net();
+    let input = SparseVector::from_slice(&[0, 2]);
+    let target = Vector::from_raw([1.0, -1.0]);
+    let report = GradCheck::default().check(&mut *net, &input, &target, &Wrong);
+
+    assert!(!report.passed());
+    assert!(report.get("l3.bias").unwrap().failed > 0);
+}
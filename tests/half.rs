@@ -0,0 +1,107 @@
+use goober::{
+    activation::ReLU,
+    bf16, f16,
+    layer::{DenseConnected, SparseConnected},
+    seed_stochastic_rounding, FeedForwardNetwork, Float, SparseVector, Stochastic, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 768, 16, f16>,
+    l2: DenseConnected<ReLU, 16, 1, f16>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct BFloatTestNet {
+    l1: SparseConnected<ReLU, 768, 16, bf16>,
+    l2: DenseConnected<ReLU, 16, 1, bf16>,
+}
+
+#[test]
+fn half() {
+    let net = TestNet {
+        l1: SparseConnected::from_fn(|_, _| f16::from_f32(0.5), |_| f16::ZERO),
+        l2: DenseConnected::from_fn(|_, _| f16::from_f32(0.25), |_| f16::ZERO),
+    };
+
+    let mut grad = TestNet::zeroed_grad();
+
+    let mut input = SparseVector::with_capacity(8);
+    input.push(5);
+
+    let err = net.out(&input);
+    assert_eq!(err[0].to_f32(), 2.0);
+
+    let layers = net.out_with_layers(&input);
+    net.backprop(&input, &mut grad, err, &layers);
+    assert_eq!(grad.l2.bias()[0].to_f32(), 2.0);
+    assert_eq!(grad.l1.weights_row(5)[0].to_f32(), 0.5);
+}
+
+#[test]
+fn bfloat() {
+    let net = BFloatTestNet {
+        l1: SparseConnected::from_fn(|_, _| bf16::from_f32(0.5), |_| bf16::ZERO),
+        l2: DenseConnected::from_fn(|_, _| bf16::from_f32(0.25), |_| bf16::ZERO),
+    };
+
+    let mut grad = BFloatTestNet::zeroed_grad();
+
+    let mut input = SparseVector::with_capacity(8);
+    input.push(5);
+
+    let err = net.out(&input);
+    assert_eq!(err[0].to_f32(), 2.0);
+
+    let layers = net.out_with_layers(&input);
+    net.backprop(&input, &mut grad, err, &layers);
+    assert_eq!(grad.l2.bias()[0].to_f32(), 2.0);
+    assert_eq!(grad.l1.weights_row(5)[0].to_f32(), 0.5);
+}
+
+#[test]
+fn accumulate_in_compute_precision() {
+    // summed in `f16`, adding 0.1 stops changing a running total once
+    // the spacing of `f16`s around it is over 0.2, from 64 upwards
+    type Layer = SparseConnected<ReLU, 768, 1, f16>;
+    let layer = Layer::from_fn(|_, _| f16::from_f32(0.1), |_| f16::ZERO);
+    let tenth = f16::from_f32(0.1).to_f32();
+
+    let input = (0..768).collect::<SparseVector>();
+    let out = layer.out(&input);
+    assert_eq!(out[0], f16::from_f32(768.0 * tenth));
+
+    let mut grad = Layer::zeroed_grad();
+    let layers = layer.out_with_layers(&input);
+    for _ in 0..4096 {
+        let err = Vector::from_raw([f16::from_f32(0.1)]);
+        layer.backprop(&input, &mut grad, err, &layers);
+    }
+    assert_eq!(grad.bias()[0], 4096.0 * tenth);
+    assert_eq!(grad.weights_row(767)[0], 4096.0 * tenth);
+}
+
+fn train_small_updates<T: Float>() -> f32 {
+    let mut weight = Vector::from_raw([T::from_f32(1.0)]);
+    let mut m = Vector::zeroed();
+    let mut v = Vector::zeroed();
+    let grad = Vector::from_raw([T::Compute::from_f32(-1.0)]);
+
+    for _ in 0..5000 {
+        weight.adam(grad, &mut m, &mut v, 1.0, 0.0001);
+    }
+
+    weight[0].to_f32()
+}
+
+#[test]
+fn stochastic() {
+    seed_stochastic_rounding(42);
+
+    let nearest = train_small_updates::<bf16>();
+    let stochastic = train_small_updates::<Stochastic<bf16>>();
+    let exact = train_small_updates::<f32>();
+
+    assert_eq!(nearest, 1.0);
+    assert!((stochastic - exact).abs() < 0.2);
+}
@@ -0,0 +1,386 @@
+use goober::{
+    activation::{Identity, ReLU},
+    init::{
+        choose, glorot_normal, glorot_uniform, he_normal, he_uniform, orthogonal, Distribution,
+        Lsuv, Normal, Randomize, Uniform,
+    },
+    layer::{
+        BucketedSparse, Conv1D, DenseConnected, FactorizedSparse, Factorizer, HashedSparse,
+        SparseConnected,
+    },
+    FeedForwardNetwork, Float, Matrix, Param, ParamVisitor, Rand, Vector,
+};
+
+/// Values of every tensor of parameters, by name.
+#[derive(Default)]
+struct Values(Vec<(String, Vec<f32>)>);
+
+impl ParamVisitor for Values {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        let values = param.values.iter().map(|x| x.to_f32()).collect();
+        self.0.push((param.name.to_string(), values));
+    }
+}
+
+fn values<T: FeedForwardNetwork>(layer: &T) -> Vec<(String, Vec<f32>)> {
+    let mut values = Values::default();
+    layer.visit_params("", &mut values);
+    values.0
+}
+
+fn moments(values: &[f32]) -> (f32, f32) {
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let var = values.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / values.len() as f32;
+    (mean, var.sqrt())
+}
+
+#[test]
+fn distributions() {
+    let mut rng = Rand::with_seed(3);
+    let samples: Vec<f32> = (0..100_000)
+        .map(|_| Uniform::new(1.0, 3.0).sample(&mut rng))
+        .collect();
+    assert!(samples.iter().all(|x| (1.0..3.0).contains(x)));
+    let (mean, std) = moments(&samples);
+    assert!((mean - 2.0).abs() < 0.01, "{mean}");
+    assert!((std - 1.0 / 3f32.sqrt()).abs() < 0.01, "{std}");
+
+    let samples: Vec<f32> = (0..100_000)
+        .map(|_| Normal::new(-1.0, 0.5).sample(&mut rng))
+        .collect();
+    let (mean, std) = moments(&samples);
+    assert!((mean + 1.0).abs() < 0.01, "{mean}");
+    assert!((std - 0.5).abs() < 0.01, "{std}");
+
+    assert_eq!(glorot_uniform(4, 8), Uniform::symmetric(0.5_f32.sqrt()));
+    assert_eq!(glorot_normal(12, 4), Normal::new(0.0, 0.125_f32.sqrt()));
+    assert_eq!(he_uniform(24), Uniform::symmetric(0.5));
+    assert_eq!(he_normal(8), Normal::new(0.0, 0.5));
+}
+
+#[test]
+fn randomize() {
+    let mut layer = DenseConnected::<Identity, 4, 3>::zeroed();
+    layer.visit_params_mut(
+        "",
+        &mut Randomize::new(Rand::with_seed(5), Uniform::new(1.0, 2.0)),
+    );
+
+    let values = values(&layer);
+    assert_eq!(values.len(), 2);
+    assert!(values
+        .iter()
+        .flat_map(|(_, v)| v)
+        .all(|x| (1.0..2.0).contains(x)));
+
+    let mut rng = Rand::with_seed(5);
+    assert_eq!(values[0].1[0], Uniform::new(1.0, 2.0).sample(&mut rng));
+}
+
+#[test]
+fn glorot() {
+    let layer = DenseConnected::<ReLU, 64, 32>::glorot_uniform(&mut Rand::with_seed(1));
+    let params = values(&layer);
+    let limit = (6.0 / 96.0f32).sqrt();
+    assert!(params[0].1.iter().all(|w| w.abs() <= limit));
+    assert!(params[0].1.iter().filter(|w| w.abs() > limit / 2.0).count() > 900);
+    assert!(params[1].1.iter().all(|&b| b == 0.0));
+
+    let again = DenseConnected::<ReLU, 64, 32>::glorot_uniform(&mut Rand::with_seed(1));
+    assert_eq!(again.fingerprint(), layer.fingerprint());
+
+    let layer = SparseConnected::<ReLU, 256, 128>::glorot_normal(&mut Rand::with_seed(1));
+    let (mean, std) = moments(&values(&layer)[0].1);
+    assert!(mean.abs() < 0.005, "{mean}");
+    assert!((std - (2.0 / 384.0f32).sqrt()).abs() < 0.002, "{std}");
+}
+
+#[test]
+fn he() {
+    let layer = DenseConnected::<ReLU, 64, 32>::he_uniform(&mut Rand::with_seed(1));
+    let params = values(&layer);
+    let limit = (6.0 / 64.0f32).sqrt();
+    assert!(params[0].1.iter().all(|w| w.abs() <= limit));
+    assert!(params[0].1.iter().any(|w| w.abs() > (6.0 / 96.0f32).sqrt()));
+    assert!(params[1].1.iter().all(|&b| b == 0.0));
+
+    let layer = SparseConnected::<ReLU, 512, 64>::he_normal(&mut Rand::with_seed(1));
+    let (mean, std) = moments(&values(&layer)[0].1);
+    assert!(mean.abs() < 0.005, "{mean}");
+    assert!((std - (2.0 / 512.0f32).sqrt()).abs() < 0.002, "{std}");
+
+    // activations keep their scale through a stack of `ReLU` layers
+    let mut rng = Rand::with_seed(2);
+    let layers: Vec<_> = (0..8)
+        .map(|_| DenseConnected::<ReLU, 256, 256>::he_normal(&mut rng))
+        .collect();
+    let mut x = Vector::<256>::from_fn(|_| rng.rand_normal().abs());
+    let scale = x.as_slice().iter().map(|v| v * v).sum::<f32>();
+    for layer in &layers {
+        x = layer.out(&x);
+    }
+    let ratio = x.as_slice().iter().map(|v| v * v).sum::<f32>() / scale;
+    assert!((0.25..4.0).contains(&ratio), "{ratio}");
+}
+
+/// Largest difference of the products of pairs of rows of the
+/// `rows` by `cols` matrix `m` from those of the identity.
+fn from_orthonormal(m: &[f32], rows: usize, cols: usize) -> f32 {
+    let mut max: f32 = 0.0;
+    for i in 0..rows {
+        for j in 0..rows {
+            let dot: f32 = (0..cols).map(|k| m[i * cols + k] * m[j * cols + k]).sum();
+            let expected = if i == j { 1.0 } else { 0.0 };
+            max = max.max((dot - expected).abs());
+        }
+    }
+    max
+}
+
+fn transpose(m: &[f32], rows: usize, cols: usize) -> Vec<f32> {
+    (0..rows * cols)
+        .map(|i| m[(i % rows) * cols + i / rows])
+        .collect()
+}
+
+#[test]
+fn orthogonal_matrix() {
+    let mut rng = Rand::with_seed(5);
+    let wide = orthogonal(&mut rng, 16, 64);
+    assert!(from_orthonormal(&wide, 16, 64) < 1e-5);
+
+    let tall = orthogonal(&mut rng, 64, 16);
+    assert!(from_orthonormal(&transpose(&tall, 64, 16), 16, 64) < 1e-5);
+
+    let square = orthogonal(&mut rng, 32, 32);
+    assert!(from_orthonormal(&square, 32, 32) < 1e-5);
+    assert!(from_orthonormal(&transpose(&square, 32, 32), 32, 32) < 1e-5);
+
+    let again = orthogonal(&mut Rand::with_seed(5), 16, 64);
+    assert_eq!(again, wide);
+}
+
+#[test]
+fn orthogonal_dense() {
+    let layer = DenseConnected::<Identity, 32, 32>::orthogonal(&mut Rand::default(), 2.0);
+    let params = values(&layer);
+    let halved: Vec<f32> = params[0].1.iter().map(|w| w / 2.0).collect();
+    assert!(from_orthonormal(&halved, 32, 32) < 1e-5);
+    assert!(params[1].1.iter().all(|&b| b == 0.0));
+
+    // lengths are preserved through a deep stack of layers
+    let mut rng = Rand::with_seed(9);
+    let layers: Vec<_> = (0..32)
+        .map(|_| DenseConnected::<Identity, 32, 32>::orthogonal(&mut rng, 1.0))
+        .collect();
+    let mut x = Vector::<32>::from_fn(|i| i as f32 / 32.0);
+    let length = x.as_slice().iter().map(|v| v * v).sum::<f32>();
+    for layer in &layers {
+        x = layer.out(&x);
+    }
+    let ratio = x.as_slice().iter().map(|v| v * v).sum::<f32>() / length;
+    assert!((ratio - 1.0).abs() < 1e-3, "{ratio}");
+
+    let tall = DenseConnected::<Identity, 8, 24>::orthogonal(&mut rng, 1.0);
+    let columns = transpose(&values(&tall)[0].1, 24, 8);
+    assert!(from_orthonormal(&columns, 8, 24) < 1e-5);
+}
+
+#[test]
+fn conv1d() {
+    let layer = Conv1D::<Identity, 8, 6>::glorot_uniform(&mut Rand::with_seed(1));
+    let kernel = &values(&layer)[0].1;
+    assert!(kernel[..3].iter().all(|&w| w != 0.0 && w.abs() <= 1.0));
+    assert!(kernel[3..].iter().all(|&w| w == 0.0));
+
+    let layer = Conv1D::<Identity, 8, 6>::he_normal(&mut Rand::with_seed(1));
+    assert!(values(&layer)[0].1[3..].iter().all(|&w| w == 0.0));
+}
+
+#[derive(Clone, Copy)]
+struct Halves;
+
+impl Factorizer for Halves {
+    fn factor(feat: usize) -> usize {
+        feat / 2
+    }
+}
+
+#[test]
+fn composite() {
+    let layer = FactorizedSparse::<ReLU, Halves, 8, 4, 4>::glorot_uniform(&mut Rand::default());
+    let params = values(&layer);
+    assert!(params.iter().any(|(_, v)| v.iter().any(|&w| w != 0.0)));
+    let factors = params.iter().find(|(name, _)| name.ends_with("factors"));
+    assert!(factors.unwrap().1.iter().all(|&w| w == 0.0));
+
+    let layer = BucketedSparse::<ReLU, 8, 4, 2>::glorot_normal(&mut Rand::default());
+    assert_ne!(
+        layer.bucket(0).weights_row(0),
+        layer.bucket(1).weights_row(0)
+    );
+}
+
+#[test]
+fn random() {
+    let dist = Normal::new(0.0, 1.0);
+    let mut rng = Rand::with_seed(4);
+    let expected = Matrix::<3, 5>::from_fn(|_, _| dist.sample(&mut rng));
+    assert_eq!(
+        Matrix::<3, 5>::random(&mut Rand::with_seed(4), dist),
+        expected
+    );
+    let vector = Vector::<15>::random(&mut Rand::with_seed(4), dist);
+    assert_eq!(vector.as_slice(), expected.as_slice());
+
+    let shared: &dyn Distribution = &dist;
+    let layer = DenseConnected::<ReLU, 5, 3>::random(&mut Rand::with_seed(4), shared);
+    for i in 0..3 {
+        assert_eq!(layer.weights_row(i), expected[i]);
+    }
+    assert_eq!(layer.bias(), Vector::zeroed());
+
+    let layer = HashedSparse::<ReLU, 3, 5>::random(&mut Rand::with_seed(4), dist);
+    assert_eq!(layer.layer().weights_row(2), expected[2]);
+
+    let layer = BucketedSparse::<ReLU, 3, 5, 2>::random(&mut Rand::with_seed(4), dist);
+    assert_eq!(layer.bucket(0).weights_row(1), expected[1]);
+    assert_ne!(layer.bucket(1).weights_row(1), expected[1]);
+}
+
+#[test]
+fn from_fn() {
+    let layer = HashedSparse::<ReLU, 3, 5>::from_fn(|i, j| (i * 5 + j) as f32, |j| j as f32);
+    assert_eq!(layer.layer().weights_row(2)[4], 14.0);
+    assert_eq!(layer.layer().bias()[3], 3.0);
+
+    let layer = BucketedSparse::<ReLU, 3, 5, 2>::from_fn(
+        |bucket, i, j| (bucket * 100 + i * 5 + j) as f32,
+        |bucket, j| (bucket * 10 + j) as f32,
+    );
+    assert_eq!(layer.bucket(1).weights_row(2)[4], 114.0);
+    assert_eq!(layer.bucket(1).bias()[3], 13.0);
+
+    let layer = FactorizedSparse::<ReLU, Halves, 4, 2, 3>::from_fn(
+        |i, j| (i * 3 + j) as f32,
+        |i, j| -((i * 3 + j) as f32),
+        |_| 0.5,
+    );
+    assert_eq!(layer.layer().weights_row(3)[1], 10.0);
+    assert_eq!(layer.factors_row(1)[2], -5.0);
+
+    let layer = Conv1D::<Identity, 8, 6>::from_fn(|j| j as f32 + 1.0, |i| i as f32);
+    assert_eq!(
+        values(&layer)[0].1,
+        [1.0, 2.0, 3.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+    );
+    assert_eq!(values(&layer)[1].1, [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+}
+
+#[test]
+fn sparse() {
+    let mut rng = Rand::with_seed(8);
+    for (n, k) in [(100, 15), (10, 10), (5, 8), (1, 0)] {
+        let mut chosen = choose(&mut rng, n, k);
+        assert_eq!(chosen.len(), k.min(n));
+        chosen.sort_unstable();
+        chosen.dedup();
+        assert_eq!(chosen.len(), k.min(n));
+        assert!(chosen.iter().all(|&i| i < n));
+    }
+
+    // every index is about as likely to be chosen
+    let mut counts = [0; 10];
+    for _ in 0..10_000 {
+        for i in choose(&mut rng, 10, 3) {
+            counts[i] += 1;
+        }
+    }
+    assert!(
+        counts.iter().all(|&c| (2700..3300).contains(&c)),
+        "{counts:?}"
+    );
+
+    let dist = Normal::new(0.0, 1.0);
+    let layer = DenseConnected::<ReLU, 64, 16>::sparse_random(&mut rng, 5, dist);
+    for j in 0..16 {
+        let row = layer.weights_row(j);
+        assert_eq!(row.as_slice().iter().filter(|&&w| w != 0.0).count(), 5);
+    }
+    assert_eq!(layer.bias(), Vector::zeroed());
+
+    let layer = SparseConnected::<ReLU, 768, 8>::sparse_random(&mut rng, 15, dist);
+    for j in 0..8 {
+        let nonzero = (0..768).filter(|&i| layer.weights_row(i)[j] != 0.0);
+        assert_eq!(nonzero.count(), 15);
+    }
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DeepNet {
+    l1: DenseConnected<Identity, 16, 32>,
+    l2: DenseConnected<ReLU, 32, 32>,
+    l3: DenseConnected<Identity, 32, 4>,
+}
+
+#[test]
+fn lsuv() {
+    let mut rng = Rand::with_seed(21);
+    let mut net = DeepNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::random(&mut rng, Normal::new(0.0, 5.0));
+    net.l2 = DenseConnected::random(&mut rng, Normal::new(0.0, 0.01));
+    net.l3 = DenseConnected::random(&mut rng, Normal::new(0.0, 3.0));
+    *net.l2.bias_mut() = Vector::from_fn(|i| i as f32 / 100.0);
+
+    let normal = Normal::new(0.0, 1.0);
+    let inputs: Vec<Vector<16>> = (0..256).map(|_| Vector::random(&mut rng, normal)).collect();
+    let output = values(&net.l3);
+    let lsuv = Lsuv::default();
+    let variances = lsuv.run(&mut *net, &inputs);
+
+    let names: Vec<&str> = variances.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["l1", "l2", "l3"]);
+    for (name, variance) in &variances[..2] {
+        assert!(
+            (variance - 1.0).abs() <= lsuv.tolerance,
+            "{name}: {variance}"
+        );
+    }
+    assert_eq!(net.l2.bias(), Vector::from_fn(|i| i as f32 / 100.0));
+
+    // the output layer is measured, but left as it is
+    assert!(variances[2].1 > 2.0, "{}", variances[2].1);
+    assert_eq!(values(&net.l3), output);
+
+    assert!(lsuv.run(&mut *net, &[]).is_empty());
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct BiasedNet {
+    l1: DenseConnected<ReLU, 16, 32>,
+    l2: DenseConnected<Identity, 32, 1>,
+}
+
+#[test]
+fn lsuv_overshoot() {
+    let mut rng = Rand::with_seed(5);
+    let mut net = BiasedNet::boxed_and_zeroed();
+    net.l1 = DenseConnected::random(&mut rng, Normal::new(0.0, 0.5));
+    *net.l1.bias_mut() = Vector::from_raw([-3.0; 32]);
+
+    let normal = Normal::new(0.0, 1.0);
+    let inputs: Vec<Vector<16>> = (0..256).map(|_| Vector::random(&mut rng, normal)).collect();
+    let before = Lsuv {
+        max_iterations: 0,
+        ..Lsuv::default()
+    }
+    .run(&mut *net, &inputs)[0]
+        .1;
+
+    // rescaling to unit variance activates many more units, overshooting,
+    // so the rescale is undone and retried smaller, rather than oscillating
+    let lsuv = Lsuv::default();
+    let after = lsuv.run(&mut *net, &inputs)[0].1;
+    assert!(before < 0.2, "{before}");
+    assert!((after - 1.0).abs() <= lsuv.tolerance, "{after}");
+}
@@ -0,0 +1,69 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    f16,
+    layer::{DenseConnected, SparseConnected},
+    json, FeedForwardNetwork, LoadError, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 2>,
+    l2: DenseConnected<Tanh, 2, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct HalfNet {
+    l1: SparseConnected<ReLU, 4, 2, f16>,
+    l2: DenseConnected<Tanh, 2, 1, f16>,
+}
+
+#[test]
+fn json() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(1) = Vector::from_raw([0.1, -0.25]);
+    *net.l2.weights_row_mut(0) = Vector::from_raw([0.5, 1.0]);
+
+    let text = json::to_string(&*net);
+    let expected = r#"{
+  "l1": {
+    "weight": [
+      [0,0],
+      [0.1,-0.25],
+      [0,0],
+      [0,0]
+    ],
+    "bias": [0,0]
+  },
+  "l2": {
+    "weight": [
+      [0.5,1]
+    ],
+    "bias": [0]
+  }
+}
+"#;
+    assert_eq!(text, expected);
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    json::from_str(&mut *loaded, &text).unwrap();
+
+    let input = SparseVector::from_slice(&[1, 2]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+
+    let mut half = HalfNet::boxed_and_zeroed();
+    json::from_str(&mut *half, &text).unwrap();
+    assert!((half.out(&input)[0].to_f32() - net.out(&input)[0]).abs() < 0.01);
+    assert_eq!(json::to_string(&*half), text.replace("0.1,", "0.099975586,"));
+
+    let ragged = text.replace("[0.1,-0.25]", "[0.1]");
+    assert!(matches!(
+        json::from_str(&mut *loaded, &ragged),
+        Err(LoadError::Malformed(_))
+    ));
+
+    let missing = text.replace("\"bias\": [0]", "\"other\": [0]");
+    assert!(matches!(
+        json::from_str(&mut *loaded, &missing),
+        Err(LoadError::Mismatch(_))
+    ));
+}
@@ -0,0 +1,58 @@
+use std::io::{self, Cursor};
+
+use goober::{libsvm, WeightedSparseVector};
+
+fn weighted(features: &[(usize, f32)]) -> WeightedSparseVector {
+    let mut vec = WeightedSparseVector::with_capacity(features.len());
+    for &(index, value) in features {
+        vec.push(index, value);
+    }
+    vec
+}
+
+#[test]
+fn read() {
+    let text = "\
+# label index:value ...
++1 3:0.5 10:1 2048:-0.25
+-1 qid:4 7:1 # comment
+
+0.5
+";
+    let data = libsvm::read(Cursor::new(text)).unwrap();
+    assert_eq!(data.len(), 3);
+
+    assert_eq!(data[0].0, weighted(&[(2, 0.5), (9, 1.0), (2047, -0.25)]));
+    assert_eq!(data[0].1[0], 1.0);
+    assert_eq!(data[1].0, weighted(&[(6, 1.0)]));
+    assert_eq!(data[1].1[0], -1.0);
+    assert_eq!(data[2].0, weighted(&[]));
+    assert_eq!(data[2].1[0], 0.5);
+}
+
+#[test]
+fn errors() {
+    let error = |text: &str| {
+        let err = libsvm::read(Cursor::new(text)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        err.to_string()
+    };
+
+    assert_eq!(error("1 2:1\nyes 1:1"), "line 2: invalid label `yes`");
+    assert_eq!(error("1 2"), "line 1: expected `index:value`, found `2`");
+    assert_eq!(
+        error("1 0:1"),
+        "line 1: index 0, though indices count from 1"
+    );
+    assert_eq!(error("1 x:1"), "line 1: invalid index `x`");
+    assert_eq!(error("1 1:x"), "line 1: invalid value `x`");
+
+    let path = std::env::temp_dir().join(format!("goober-libsvm-{}.txt", std::process::id()));
+    std::fs::write(&path, "1 1:1\n1 1:1:1\n").unwrap();
+    let err = libsvm::load(&path).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("{}: line 2: invalid value `1:1`", path.display())
+    );
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,113 @@
+use goober::{LengthMismatch, Matrix, Vector};
+
+#[test]
+fn from_rows() {
+    let m = Matrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+    assert_eq!(m.as_slice(), [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    assert_eq!(m, Matrix::from([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]));
+    assert_eq!(
+        m,
+        Matrix::from([
+            Vector::from_raw([1.0, 2.0, 3.0]),
+            Vector::from_raw([4.0, 5.0, 6.0])
+        ])
+    );
+}
+
+#[test]
+fn transpose() {
+    let m: Matrix<2, 3> = Matrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+    let t = m.transpose();
+    assert_eq!(t, Matrix::from_rows([[1.0, 4.0], [2.0, 5.0], [3.0, 6.0]]));
+    assert_eq!(t.transpose(), m);
+
+    let v = Vector::from_raw([1.0, -1.0]);
+    assert_eq!(t * v, m.transpose_mul(v));
+}
+
+#[test]
+fn identity() {
+    let id: Matrix<3, 3> = Matrix::identity();
+    assert_eq!(
+        id,
+        Matrix::from_rows([[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]])
+    );
+    assert_eq!(id.transpose(), id);
+
+    let v = Vector::from_raw([1.0, 2.0, 3.0]);
+    assert_eq!(id * v, v);
+}
+
+#[test]
+fn format() {
+    let m = Matrix::from_rows([[1.0, -2.0], [30.0, 4.5]]);
+    assert_eq!(format!("{m:?}"), "Matrix<2, 2> [[1.0, -2.0], [30.0, 4.5]]");
+    assert_eq!(
+        format!("{m:.1}"),
+        "Matrix<2, 2>\n[ 1.0, -2.0]\n[30.0,  4.5]"
+    );
+
+    let large = Matrix::<128, 64>::from_fn(|i, j| (i * j) as f32);
+    let shown = format!("{large:.0}");
+    let lines: Vec<&str> = shown.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0], "Matrix<128, 64>");
+    assert_eq!(lines[1], "[   0,    0,    0, ...,    0,    0,    0]");
+    assert_eq!(lines[4], "...");
+    assert_eq!(lines[7], "[   0,  127,  254, ..., 7747, 7874, 8001]");
+}
+
+#[test]
+fn as_ref() {
+    let mut m = Matrix::from_rows([[1.0, 2.0], [3.0, 4.0]]);
+    assert_eq!(m.as_ref(), [1.0, 2.0, 3.0, 4.0]);
+    m.as_mut()[2] = 5.0;
+    assert_eq!(m[1][0], 5.0);
+}
+
+#[test]
+fn try_from() {
+    let m = Matrix::<2, 3>::try_from([1.0, 2.0, 3.0, 4.0, 5.0, 6.0].as_slice()).unwrap();
+    assert_eq!(m, Matrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]));
+    assert_eq!(Matrix::try_from(m.as_slice().to_vec()), Ok(m));
+
+    assert_eq!(
+        Matrix::<2, 3>::try_from(vec![0.0; 7]),
+        Err(LengthMismatch {
+            expected: 6,
+            found: 7
+        })
+    );
+}
+
+#[test]
+fn rows_and_cols() {
+    let mut m = Matrix::from_rows([[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+    assert_eq!(m[(1, 2)], 6.0);
+    assert_eq!(m[1][2], 6.0);
+    m[(0, 1)] = 7.0;
+    assert_eq!(m[0][1], 7.0);
+
+    let rows: Vec<Vector<3>> = m.rows().copied().collect();
+    assert_eq!(
+        rows,
+        [
+            Vector::from_raw([1.0, 7.0, 3.0]),
+            Vector::from_raw([4.0, 5.0, 6.0])
+        ]
+    );
+    assert_eq!(m.col(1).collect::<Vec<_>>(), [7.0, 5.0]);
+    assert!(m.col(2).eq(m.transpose()[2].as_slice().iter().copied()));
+
+    for (i, row) in m.rows_mut().enumerate() {
+        *row = Vector::from_fn(|j| (i * 3 + j) as f32);
+    }
+    assert_eq!(m.as_slice(), [0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+}
+
+#[test]
+#[should_panic(expected = "column 3 out of bounds")]
+fn col_out_of_bounds() {
+    let m: Matrix<2, 3> = Matrix::zeroed();
+    let _ = m.col(3);
+}
@@ -0,0 +1,44 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    merge::{average, lerp, lerp_in_place, weighted_average},
+    FeedForwardNetwork, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 2>,
+    l2: DenseConnected<Tanh, 2, 1>,
+}
+
+fn net(x: f32) -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(2) = Vector::from_raw([x, -x]);
+    *net.l2.bias_mut() = Vector::from_raw([2.0 * x]);
+    net
+}
+
+#[test]
+fn merge() {
+    let (a, b, c) = (net(1.0), net(3.0), net(8.0));
+
+    let mid = lerp(&*a, &*b, 0.5);
+    assert_eq!(mid.l1.weights_row(2), Vector::from_raw([2.0, -2.0]));
+    assert_eq!(mid.l2.bias(), Vector::from_raw([4.0]));
+
+    assert_eq!(lerp(&*a, &*b, 0.0).fingerprint(), a.fingerprint());
+    assert_eq!(lerp(&*a, &*b, 1.0).fingerprint(), b.fingerprint());
+
+    let mean = average(&[&*a, &*b, &*c]);
+    assert_eq!(mean.l1.weights_row(2), Vector::from_raw([4.0, -4.0]));
+
+    let weighted = weighted_average(&[(&*a, 0.25), (&*c, 0.75)]);
+    assert_eq!(weighted.l2.bias(), Vector::from_raw([12.5]));
+}
+
+#[test]
+fn in_place() {
+    let (mut a, b) = (net(1.0), net(3.0));
+    lerp_in_place(&mut *a, &*b, 0.25);
+    assert_eq!(a.fingerprint(), lerp(&*net(1.0), &*b, 0.25).fingerprint());
+}
@@ -0,0 +1,100 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    mmap, FeedForwardNetwork, LoadError, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct SwappedNet {
+    l2: SparseConnected<ReLU, 16, 8>,
+    l1: DenseConnected<Tanh, 8, 1>,
+}
+
+fn test_net() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(3) = Vector::from_fn(|i| i as f32 / 4.0);
+    *net.l2.weights_row_mut(0) = Vector::from_fn(|i| 1.0 - i as f32 / 8.0);
+    net
+}
+
+#[test]
+fn view() {
+    let net = test_net();
+    let image = mmap::to_bytes(&*net);
+    assert_eq!(&image[..4], b"GBMM");
+
+    let viewed = unsafe { mmap::view::<TestNet>(&image) }.unwrap();
+
+    let input = SparseVector::from_slice(&[3, 9]);
+    assert_eq!(net.out(&input), viewed.out(&input));
+
+    assert!(matches!(
+        unsafe { mmap::view::<SwappedNet>(&image) },
+        Err(LoadError::Mismatch(_))
+    ));
+
+    let layer = mmap::to_bytes(&net.l2);
+    assert!(matches!(
+        unsafe { mmap::view::<TestNet>(&layer) },
+        Err(LoadError::Mismatch(_))
+    ));
+
+    assert!(matches!(
+        unsafe { mmap::view::<TestNet>(&image[..image.len() - 4]) },
+        Err(LoadError::Truncated)
+    ));
+
+    let mut corrupt = image.clone();
+    corrupt[0] = b'X';
+    assert!(matches!(
+        unsafe { mmap::view::<TestNet>(&corrupt) },
+        Err(LoadError::BadMagic)
+    ));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn map() {
+    let net = test_net();
+    let path = std::env::temp_dir().join("goober_mmap.bin");
+    let path = path.to_str().unwrap();
+    mmap::save(&*net, path).unwrap();
+
+    let mapped = unsafe { mmap::map::<TestNet>(path) }.unwrap();
+
+    let input = SparseVector::from_slice(&[3, 9]);
+    assert_eq!(net.out(&input), mapped.out(&input));
+
+    drop(mapped);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn read() {
+    let net = test_net();
+    let image = mmap::to_bytes(&*net);
+
+    // misaligned copy of the image
+    let mut shifted = vec![0; image.len() + 1];
+    shifted[1..].copy_from_slice(&image);
+
+    let read = unsafe { mmap::read::<TestNet>(&shifted[1..]) }.unwrap();
+
+    let input = SparseVector::from_slice(&[3, 9]);
+    assert_eq!(net.out(&input), read.out(&input));
+}
+
+#[test]
+fn include_net() {
+    let layer: &'static DenseConnected<ReLU, 4, 2> =
+        unsafe { goober::include_net!(DenseConnected<ReLU, 4, 2>, "data/dense.bin") };
+
+    let out = layer.out(&Vector::from_raw([1.0, 1.0, 1.0, 1.0]));
+    assert_eq!(out, Vector::from_raw([0.85, 2.4]));
+}
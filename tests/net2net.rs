@@ -0,0 +1,76 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{widen, DenseConnected, SparseConnected},
+    FeedForwardNetwork, Rand, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct WideNet {
+    l1: SparseConnected<ReLU, 8, 7>,
+    l2: DenseConnected<Tanh, 7, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DeepNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    mid: DenseConnected<ReLU, 4, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+fn net() -> Box<TestNet> {
+    let w = |i: usize, j: usize| ((i * 7 + j * 13) % 11) as f32 / 11.0 - 0.5;
+    let b = |i: usize| ((i * 5) % 7) as f32 / 7.0 - 0.5;
+    let mut net = TestNet::boxed_and_zeroed();
+    net.l1 = SparseConnected::from_fn(w, b);
+    net.l2 = DenseConnected::from_fn(w, b);
+    net
+}
+
+fn inputs() -> Vec<SparseVector> {
+    (0..8u8)
+        .map(|i| {
+            let feats = [i % 8, (i * 3 + 1) % 8, (i * 5 + 2) % 8];
+            let feats = feats.map(usize::from);
+            SparseVector::from_slice(&feats)
+        })
+        .collect()
+}
+
+#[test]
+fn widen_preserves_function() {
+    let net = net();
+    let (l1, l2) = widen::<_, _, 7>(&net.l1, &net.l2, &mut Rand::default(), 0.2);
+
+    let mut wide = WideNet::boxed_and_zeroed();
+    wide.l1 = *l1;
+    wide.l2 = *l2;
+
+    for input in inputs() {
+        let (a, b) = (net.out(&input)[0], wide.out(&input)[0]);
+        assert!((a - b).abs() < 1e-6, "{a} != {b}");
+    }
+
+    for i in 0..8 {
+        assert_eq!(wide.l1.weights_row(i)[2], net.l1.weights_row(i)[2]);
+    }
+}
+
+#[test]
+fn deepen_preserves_function() {
+    let net = net();
+
+    let mut deep = DeepNet::boxed_and_zeroed();
+    deep.l1 = net.l1;
+    deep.mid = *DenseConnected::identity();
+    deep.l2 = net.l2;
+
+    for input in inputs() {
+        assert_eq!(net.out(&input), deep.out(&input));
+    }
+}
@@ -0,0 +1,119 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    numpy, FeedForwardNetwork, LoadError, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DoubleNet {
+    l1: SparseConnected<ReLU, 16, 8, f64>,
+    l2: DenseConnected<Tanh, 8, 1, f64>,
+}
+
+fn test_net() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(5) = Vector::from_fn(|i| i as f32 / 4.0 - 1.0);
+    *net.l2.weights_row_mut(0) = Vector::from_fn(|i| 0.5 - i as f32 / 8.0);
+    net
+}
+
+#[test]
+fn npz() {
+    let net = test_net();
+    let bytes = numpy::to_npz(&*net);
+
+    let header = b"\x93NUMPY\x01\x00";
+    let start = bytes.windows(8).position(|w| w == header).unwrap();
+    let len = u16::from_le_bytes([bytes[start + 8], bytes[start + 9]]) as usize;
+    let text = std::str::from_utf8(&bytes[start + 10..start + 10 + len]).unwrap();
+    assert_eq!((10 + len) % 64, 0);
+    assert!(text.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (16, 8), }"));
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    numpy::from_npz(&mut *loaded, &bytes).unwrap();
+
+    let input = SparseVector::from_slice(&[5]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+
+    let mut double = DoubleNet::boxed_and_zeroed();
+    numpy::from_npz(&mut *double, &bytes).unwrap();
+    assert!((double.out(&input)[0] as f32 - net.out(&input)[0]).abs() < 1e-6);
+}
+
+#[test]
+fn npy_dir() {
+    let net = test_net();
+    let dir = std::env::temp_dir().join("goober_npy_dir");
+    let dir = dir.to_str().unwrap();
+
+    numpy::save_dir(&net.l2, dir).unwrap();
+
+    let mut layer = DenseConnected::<Tanh, 8, 1>::zeroed();
+    numpy::load_dir(&mut layer, dir).unwrap();
+    assert_eq!(layer.weights_row(0), net.l2.weights_row(0));
+
+    let mut wider = DenseConnected::<Tanh, 8, 2>::zeroed();
+    assert!(matches!(
+        numpy::load_dir(&mut wider, dir),
+        Err(LoadError::Mismatch(_))
+    ));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+/// An `.npy` array of `descr` and `shape`, holding `data`.
+fn npy(descr: &str, shape: &str, data: &[u8]) -> Vec<u8> {
+    let header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}\n");
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DenseNet {
+    l1: DenseConnected<ReLU, 3, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+#[test]
+fn dataset() {
+    let x: Vec<u8> = (0..6).flat_map(|i| f64::from(i).to_le_bytes()).collect();
+    let x = npy("<f8", "(2, 3)", &x);
+    let y: Vec<u8> = [1i64, 0].iter().flat_map(|i| i.to_le_bytes()).collect();
+
+    let data = numpy::dataset_from_npy::<3, 1>(&x, &npy("<i8", "(2,)", &y)).unwrap();
+    assert_eq!(
+        data,
+        [
+            (Vector::from_raw([0.0, 1.0, 2.0]), Vector::from_raw([1.0])),
+            (Vector::from_raw([3.0, 4.0, 5.0]), Vector::from_raw([0.0])),
+        ]
+    );
+    let trainer = goober::trainer::Trainer::new(DenseNet::boxed_and_zeroed(), data);
+    assert_eq!(trainer.data().len(), 2);
+
+    let y = npy("|u1", "(2, 2)", &[1, 0, 0, 1]);
+    let data = numpy::dataset_from_npy::<3, 2>(&x, &y).unwrap();
+    assert_eq!(data[1].1, Vector::from_raw([0.0, 1.0]));
+
+    let mismatch = |result: Result<Vec<(Vector<3>, Vector<2>)>, LoadError>| match result {
+        Err(LoadError::Mismatch(message)) => message,
+        other => panic!("expected a mismatch, got {other:?}"),
+    };
+    assert_eq!(
+        mismatch(numpy::dataset_from_npy(&y, &y)),
+        "inputs of shape (2, 2), expected (_, 3)"
+    );
+    assert_eq!(
+        mismatch(numpy::dataset_from_npy(&x, &npy("|u1", "(2,)", &[1, 0]))),
+        "targets of shape (2,), expected (2, 2)"
+    );
+}
@@ -0,0 +1,233 @@
+use goober::{
+    activation::{ReLU, SCReLU, Tanh},
+    layer::{Add, Conv1D, DenseConnected, SparseConnected, WeightedSparse},
+    onnx, FeedForwardNetwork, Graph, Op,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: Add<DenseConnected<SCReLU, 8, 4>, Conv1D<ReLU, 8, 4>>,
+    l3: DenseConnected<Tanh, 4, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct UnsupportedNet {
+    l1: WeightedSparse<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+/// Fields of a protobuf message, as `(field, varint or bytes)`.
+fn fields(mut bytes: &[u8]) -> Vec<(u64, Result<u64, &[u8]>)> {
+    fn varint(bytes: &mut &[u8]) -> u64 {
+        let mut res = 0;
+        for shift in (0..).step_by(7) {
+            let byte = bytes[0];
+            *bytes = &bytes[1..];
+            res |= u64::from(byte & 0x7F) << shift;
+            if byte < 0x80 {
+                break;
+            }
+        }
+        res
+    }
+
+    let mut res = Vec::new();
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes);
+        let value = match key & 7 {
+            0 => Ok(varint(&mut bytes)),
+            2 => {
+                let len = varint(&mut bytes) as usize;
+                let (value, rest) = bytes.split_at(len);
+                bytes = rest;
+                Err(value)
+            }
+            _ => panic!("unexpected wire type"),
+        };
+        res.push((key >> 3, value));
+    }
+    res
+}
+
+fn submessages(bytes: &[u8], field: u64) -> Vec<&[u8]> {
+    fields(bytes)
+        .into_iter()
+        .filter(|&(f, _)| f == field)
+        .map(|(_, v)| v.unwrap_err())
+        .collect()
+}
+
+fn string(bytes: &[u8], field: u64) -> String {
+    String::from_utf8(submessages(bytes, field)[0].to_vec()).unwrap()
+}
+
+#[test]
+fn trace() {
+    let net = TestNet::boxed_and_zeroed();
+
+    let mut graph = Graph::default();
+    let out = net.trace("", Graph::INPUT, &mut graph).unwrap();
+    assert_eq!(out, graph.output());
+
+    let ops: Vec<&Op> = graph.nodes().iter().map(|node| &node.op).collect();
+    assert!(matches!(ops[0], Op::SparseLinear { weight, .. } if weight == "l1.weight"));
+    assert!(matches!(ops[4], Op::Conv1D { kernel: 5, .. }));
+    assert_eq!(ops[6], &Op::Add);
+    assert_eq!(graph.nodes()[6].size, 4);
+    assert_eq!(ops.len(), 9);
+
+    assert!(onnx::to_bytes(&*UnsupportedNet::boxed_and_zeroed()).is_err());
+}
+
+#[test]
+fn onnx() {
+    let net = TestNet::boxed_and_zeroed();
+    let bytes = onnx::to_bytes(&*net).unwrap();
+
+    let graph = submessages(&bytes, 7)[0];
+    let ops: Vec<String> = submessages(graph, 1)
+        .into_iter()
+        .map(|node| string(node, 4))
+        .collect();
+    assert_eq!(
+        ops,
+        [
+            "Gather",
+            "ReduceSum",
+            "Add",
+            "Relu",
+            "MatMul",
+            "Add",
+            "Clip",
+            "Mul",
+            "Reshape",
+            "Conv",
+            "Reshape",
+            "Add",
+            "Relu",
+            "Add",
+            "MatMul",
+            "Add",
+            "Tanh"
+        ]
+    );
+
+    let initializers: Vec<String> = submessages(graph, 5)
+        .into_iter()
+        .map(|tensor| string(tensor, 8))
+        .collect();
+    assert!(initializers.contains(&"l2.a.weight".to_string()));
+    assert!(initializers.contains(&"l3.bias".to_string()));
+
+    let input = submessages(graph, 11)[0];
+    assert_eq!(string(input, 1), Graph::INPUT);
+
+    let output = submessages(graph, 12)[0];
+    assert_eq!(string(output, 1), "t8");
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct DenseNet {
+    l1: DenseConnected<ReLU, 3, 2>,
+    l2: DenseConnected<Tanh, 2, 1>,
+}
+
+#[test]
+fn onnx_roundtrip() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(3) = goober::Vector::from_fn(|i| i as f32 / 8.0);
+    *net.l1.bias_mut() = goober::Vector::from_fn(|i| 0.5 - i as f32 / 16.0);
+    *net.l3.weights_row_mut(0) = goober::Vector::from_raw([0.5, -0.25, 0.125, 1.0]);
+
+    let bytes = onnx::to_bytes(&*net).unwrap();
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    onnx::from_bytes(&mut *loaded, &bytes).unwrap();
+
+    let input = goober::SparseVector::from_slice(&[3, 11]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+
+    let mut dense = DenseNet::boxed_and_zeroed();
+    assert!(onnx::from_bytes(&mut *dense, &bytes).is_err());
+}
+
+/// Encodes a protobuf message from `(field, value)` pairs.
+fn encode(fields: &[(u64, Result<u64, Vec<u8>>)]) -> Vec<u8> {
+    fn varint(out: &mut Vec<u8>, mut x: u64) {
+        while x >= 0x80 {
+            out.push(x as u8 | 0x80);
+            x >>= 7;
+        }
+        out.push(x as u8);
+    }
+
+    let mut out = Vec::new();
+    for (field, value) in fields {
+        match value {
+            Ok(x) => {
+                varint(&mut out, field << 3);
+                varint(&mut out, *x);
+            }
+            Err(bytes) => {
+                varint(&mut out, field << 3 | 2);
+                varint(&mut out, bytes.len() as u64);
+                out.extend_from_slice(bytes);
+            }
+        }
+    }
+    out
+}
+
+fn text(s: &str) -> Result<u64, Vec<u8>> {
+    Err(s.as_bytes().to_vec())
+}
+
+fn node(op: &str, inputs: &[&str], output: &str) -> Result<u64, Vec<u8>> {
+    let mut fields: Vec<_> = inputs.iter().map(|x| (1, text(x))).collect();
+    fields.push((2, text(output)));
+    fields.push((4, text(op)));
+    Err(encode(&fields))
+}
+
+fn init(name: &str, dims: &[u64], values: &[f32]) -> Result<u64, Vec<u8>> {
+    let mut fields: Vec<_> = dims.iter().map(|&d| (1, Ok(d))).collect();
+    fields.push((2, Ok(1)));
+    fields.push((8, text(name)));
+    fields.push((
+        9,
+        Err(values.iter().flat_map(|x| x.to_le_bytes()).collect()),
+    ));
+    Err(encode(&fields))
+}
+
+#[test]
+fn onnx_import_transposed() {
+    // as exported by PyTorch for `Linear(3, 2), ReLU, Linear(2, 1), Tanh`
+    let graph = encode(&[
+        (1, node("MatMul", &["x", "w1"], "a")),
+        (1, node("Add", &["a", "b1"], "b")),
+        (1, node("Relu", &["b"], "c")),
+        (1, node("MatMul", &["c", "w2"], "d")),
+        (1, node("Add", &["d", "b2"], "e")),
+        (1, node("Tanh", &["e"], "y")),
+        (5, init("w1", &[3, 2], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0])),
+        (5, init("b1", &[2], &[0.5, -0.5])),
+        (5, init("w2", &[2, 1], &[0.25, -0.125])),
+        (5, init("b2", &[1], &[0.1])),
+    ]);
+    let model = encode(&[(1, Ok(7)), (7, Err(graph))]);
+
+    let mut net = DenseNet::boxed_and_zeroed();
+    onnx::from_bytes(&mut *net, &model).unwrap();
+
+    assert_eq!(
+        net.l1.weights_row(0),
+        goober::Vector::from_raw([1.0, 3.0, 5.0])
+    );
+    assert_eq!(
+        net.l1.weights_row(1),
+        goober::Vector::from_raw([2.0, 4.0, 6.0])
+    );
+    assert_eq!(net.l2.bias(), goober::Vector::from_raw([0.1]));
+}
@@ -0,0 +1,30 @@
+#![cfg(feature = "progress")]
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    progress::ProgressBar,
+    trainer::Trainer,
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+#[test]
+fn progress_bar() {
+    let data: Vec<_> = (0..10)
+        .map(|i| (SparseVector::from_slice(&[i % 8]), Vector::from_raw([0.1])))
+        .collect();
+
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), data.clone())
+        .with_batch_size(4)
+        .with_validation(data)
+        .with_callback(ProgressBar::new());
+
+    trainer.run(3).unwrap();
+    assert_eq!(trainer.step(), 9);
+}
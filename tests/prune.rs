@@ -0,0 +1,47 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{Add, DenseConnected, SparseConnected},
+    prune::Prune,
+    FeedForwardNetwork, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 5>,
+    l2: Add<DenseConnected<ReLU, 5, 2>, DenseConnected<Tanh, 5, 2>>,
+}
+
+fn net() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    for i in 0..4 {
+        *net.l1.weights_row_mut(i) = Vector::from_fn(|j| (i * 5 + j) as f32 - 9.5);
+    }
+    *net.l1.bias_mut() = Vector::from_raw([0.1; 5]);
+    net.l2 = Add::from_raw(
+        DenseConnected::from_fn(|i, j| (i * 5 + j + 1) as f32, |_| 0.1),
+        DenseConnected::from_fn(|i, j| -((i * 5 + j + 1) as f32), |_| 0.1),
+    );
+    net
+}
+
+#[test]
+fn prune() {
+    let mut net = net();
+    let mask = Prune::new(0.5).with_layer("l2.b", 0.8).apply(&mut *net);
+
+    assert_eq!(mask.tensor_sparsity("l1.weight"), Some(0.5));
+    assert_eq!(mask.tensor_sparsity("l2.a.weight"), Some(0.5));
+    assert_eq!(mask.tensor_sparsity("l2.b.weight"), Some(0.8));
+    assert_eq!(mask.tensor_sparsity("l1.bias"), None);
+    assert_eq!(mask.sparsity(), (10.0 + 5.0 + 8.0) / 40.0);
+
+    // from -9.5 to 9.5, so the middle two rows are nearest zero
+    assert_eq!(net.l1.weights_row(1), Vector::from_raw([0.0; 5]));
+    assert_eq!(net.l1.weights_row(2), Vector::from_raw([0.0; 5]));
+    assert_eq!(net.l1.weights_row(0)[0], -9.5);
+    assert_eq!(net.l1.bias(), Vector::from_raw([0.1; 5]));
+
+    let mut other = self::net();
+    mask.apply(&mut *other);
+    assert_eq!(other.fingerprint(), net.fingerprint());
+}
@@ -0,0 +1,65 @@
+use goober::{
+    activation::{Identity, ReLU},
+    layer::{DenseConnected, SparseConnected},
+    qat::{Qat, QatScale},
+    schedule::Constant,
+    trainer::Trainer,
+    FeedForwardNetwork, OutputLayer, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<Identity, 4, 1>,
+}
+
+const QA: i16 = 255;
+const QB: i16 = 64;
+
+fn qat() -> Qat {
+    Qat::new()
+        .with_layer("l1", QatScale::sparse(QA))
+        .with_layer("l2", QatScale::dense(QA, QB))
+}
+
+#[test]
+fn qat_trains() {
+    let net = TestNet {
+        l1: SparseConnected::from_fn(|i, j| ((i + j) % 3) as f32 / 10.0, |_| 0.0),
+        l2: DenseConnected::from_fn(|_, j| j as f32 / 10.0, |_| 0.0),
+    };
+
+    let input = SparseVector::from_slice(&[1, 2]);
+    let data = vec![(input.clone(), Vector::from_raw([0.7]))];
+
+    let mut trainer = Trainer::new(Box::new(net), data)
+        .with_scheduler(Constant(0.01))
+        .with_batch_size(1)
+        .with_qat(qat());
+    trainer.run(500).unwrap();
+    let net = trainer.into_net();
+
+    let l1 = net.l1.quantize(QA);
+    let l2 = net.l2.quantize(QA, QB);
+    let out = f32::from(l2.out(&l1.out(&input))[0]) / f32::from(QA);
+    assert!((out - 0.7).abs() < 0.01);
+
+    // the simulated forward pass is the quantized one
+    let mut layers = net.out_with_layers(&input);
+    net.out_into_qat("", &input, &mut layers, &qat());
+    assert_eq!(layers.output_layer()[0], out);
+}
+
+#[test]
+fn qat_clamps() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l2.weights_row_mut(0) = Vector::from_raw([1000.0, -1000.0, 0.5, 0.0]);
+
+    let data = vec![(SparseVector::from_slice(&[0]), Vector::from_raw([0.0]))];
+    let trainer = Trainer::new(net, data).with_qat(qat());
+
+    let max = f32::from(i16::MAX) / f32::from(QB);
+    let min = f32::from(i16::MIN) / f32::from(QB);
+    let row = trainer.net().l2.weights_row(0);
+    assert_eq!(row, Vector::from_raw([max, min, 0.5, 0.0]));
+}
@@ -0,0 +1,50 @@
+use goober::{
+    activation::ReLU,
+    layer::{Compare, DenseConnected, QuantizationReport, SparseConnected},
+    FeedForwardNetwork, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<ReLU, 4, 2>,
+}
+
+#[test]
+fn compare_networks() {
+    let net = TestNet {
+        l1: SparseConnected::from_fn(
+            |i, j| ((i * 4 + j) % 5) as f32 / 10.0 - 0.2,
+            |i| i as f32 / 20.0,
+        ),
+        l2: DenseConnected::from_fn(|i, j| (i + j) as f32 / 4.0 - 0.3, |i| i as f32 / 10.0),
+    };
+
+    let l1 = net.l1.quantize(64);
+    let l2 = net.l2.quantize(64, 64);
+
+    let inputs: Vec<_> = (0..8)
+        .map(|i| SparseVector::from_slice(&[i, (i + 3) % 8]))
+        .collect();
+    let quantized: [(&str, &dyn Compare); 2] = [("l1", &*l1), ("l2", &*l2)];
+    let report = QuantizationReport::compare(&net, &quantized, &inputs);
+
+    let mut expected = QuantizationReport::default();
+    for input in &inputs {
+        let hidden = net.l1.out(input);
+        let qhidden = l1.out(input);
+        expected.record("l1", &hidden, &qhidden, l1.output_scale());
+        expected.record(
+            "l2",
+            &net.l2.out(&hidden),
+            &l2.out(&qhidden),
+            l2.output_scale(),
+        );
+    }
+
+    let names: Vec<_> = report.layers().map(|(name, _)| name).collect();
+    assert_eq!(names, ["l1", "l2"]);
+    assert_eq!(report.layer("l1"), expected.layer("l1"));
+    assert_eq!(report.output(), expected.output());
+    assert_eq!(report.output().unwrap().count(), 16);
+}
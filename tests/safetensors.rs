@@ -0,0 +1,90 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    f16,
+    layer::{Conv1D, DenseConnected, SparseConnected},
+    safetensors, FeedForwardNetwork, LoadError, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct HalfNet {
+    l1: SparseConnected<ReLU, 16, 8, f16>,
+    l2: DenseConnected<Tanh, 8, 1, f16>,
+}
+
+#[test]
+fn safetensors() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(3) = goober::Vector::from_fn(|i| i as f32 / 4.0);
+    *net.l2.weights_row_mut(0) = goober::Vector::from_fn(|i| 1.0 - i as f32 / 8.0);
+
+    let bytes = safetensors::to_bytes(&*net);
+
+    let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap()) as usize;
+    assert!(header_len.is_multiple_of(8));
+
+    let header = std::str::from_utf8(&bytes[8..8 + header_len]).unwrap();
+    assert!(header.contains(r#""l1.weight":{"dtype":"F32","shape":[16,8],"data_offsets":[0,512]}"#));
+    assert!(header.contains(r#""l2.bias":{"dtype":"F32","shape":[1],"data_offsets":[576,580]}"#));
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    safetensors::from_bytes(&mut *loaded, &bytes).unwrap();
+
+    let input = SparseVector::from_slice(&[3, 9]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+
+    let mut half = HalfNet::boxed_and_zeroed();
+    safetensors::from_bytes(&mut *half, &bytes).unwrap();
+    assert!((half.out(&input)[0].to_f32() - net.out(&input)[0]).abs() < 0.01);
+
+    let mut wider = DenseConnected::<ReLU, 8, 2>::zeroed();
+    let dense = safetensors::to_bytes(&net.l2);
+    assert!(matches!(
+        safetensors::from_bytes(&mut wider, &dense),
+        Err(LoadError::Mismatch(_))
+    ));
+}
+
+#[test]
+fn torch_naming() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(3) = goober::Vector::from_fn(|i| i as f32 / 4.0);
+    *net.l2.weights_row_mut(0) = goober::Vector::from_fn(|i| 1.0 - i as f32 / 8.0);
+
+    let bytes = safetensors::to_torch_bytes(&*net).unwrap();
+
+    let header_len = u64::from_le_bytes(bytes[..8].try_into().unwrap()) as usize;
+    let header = std::str::from_utf8(&bytes[8..8 + header_len]).unwrap();
+    assert!(header.contains(r#""0.weight":{"dtype":"F32","shape":[8,16],"data_offsets":[0,512]}"#));
+    assert!(header.contains(r#""0.bias":{"dtype":"F32","shape":[8],"#));
+    assert!(header.contains(r#""2.weight":{"dtype":"F32","shape":[1,8],"#));
+    assert!(header.contains(r#""2.bias":{"dtype":"F32","shape":[1],"#));
+
+    // `0.weight[o][3]` holds the weight from feature 3 to output `o`
+    let data = &bytes[8 + header_len..];
+    let elem = |o: usize, f: usize| {
+        let start = 4 * (16 * o + f);
+        f32::from_le_bytes(data[start..start + 4].try_into().unwrap())
+    };
+    assert_eq!(elem(5, 3), 1.25);
+    assert_eq!(elem(5, 4), 0.0);
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    safetensors::from_torch_bytes(&mut *loaded, &bytes).unwrap();
+
+    let input = SparseVector::from_slice(&[3, 9]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+
+    assert!(matches!(
+        safetensors::from_bytes(&mut *loaded, &bytes),
+        Err(LoadError::Mismatch(_))
+    ));
+
+    let conv = Conv1D::<ReLU, 4, 2>::zeroed();
+    assert!(safetensors::to_torch_bytes(&conv).is_err());
+}
@@ -0,0 +1,188 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, Float, GooberError, LoadError, Param, ParamVisitor, SparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct WiderNet {
+    l1: SparseConnected<ReLU, 16, 8>,
+    l2: DenseConnected<Tanh, 8, 2>,
+}
+
+struct Names(Vec<(String, Vec<usize>)>);
+
+impl ParamVisitor for Names {
+    fn visit<F: Float>(&mut self, param: Param<'_, F>) {
+        self.0.push((param.name.to_string(), param.shape.to_vec()));
+    }
+}
+
+fn test_net() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(3) = goober::Vector::from_fn(|i| i as f32 / 10.0);
+    *net.l2.bias_mut() = goober::Vector::from_raw([0.25]);
+    net
+}
+
+#[test]
+fn save_and_load() {
+    let net = test_net();
+
+    let mut names = Names(Vec::new());
+    net.visit_params("", &mut names);
+    assert_eq!(
+        names.0,
+        vec![
+            ("l1.weight".to_string(), vec![16, 8]),
+            ("l1.bias".to_string(), vec![8]),
+            ("l2.weight".to_string(), vec![1, 8]),
+            ("l2.bias".to_string(), vec![1]),
+        ]
+    );
+
+    let path = std::env::temp_dir().join("goober_save_and_load.bin");
+    let path = path.to_str().unwrap();
+    net.save(path).unwrap();
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    loaded.load(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let input = SparseVector::from_slice(&[3, 7]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+}
+
+#[test]
+fn load_failures() {
+    let bytes = test_net().save_bytes();
+    let mut loaded = TestNet::boxed_and_zeroed();
+
+    let mut corrupt = bytes.clone();
+    corrupt[40] ^= 1;
+    assert!(matches!(
+        loaded.load_bytes(&corrupt),
+        Err(LoadError::BadChecksum)
+    ));
+
+    let mut magic = bytes.clone();
+    magic[0] = b'X';
+    assert!(matches!(
+        loaded.load_bytes(&magic),
+        Err(LoadError::BadMagic)
+    ));
+
+    assert!(matches!(
+        loaded.load_bytes(&bytes[..10]),
+        Err(LoadError::Truncated)
+    ));
+
+    let mut wider = WiderNet::boxed_and_zeroed();
+    assert!(matches!(
+        wider.load_bytes(&bytes),
+        Err(LoadError::Mismatch(_))
+    ));
+    assert!(matches!(
+        loaded.load_bytes(&wider.save_bytes()),
+        Err(LoadError::Mismatch(_))
+    ));
+}
+
+#[test]
+fn raw_little_endian() {
+    let net = test_net();
+
+    let path = std::env::temp_dir().join("goober_raw_little_endian.bin");
+    let path = path.to_str().unwrap();
+    net.write_to_bin(path);
+
+    let bytes = std::fs::read(path).unwrap();
+    assert_eq!(bytes.len(), (16 * 8 + 8 + 8 + 1) * 4);
+    assert_eq!(&bytes[3 * 32 + 4..3 * 32 + 8], &0.1f32.to_le_bytes());
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    loaded.read_from_bin(path);
+    std::fs::remove_file(path).unwrap();
+
+    let input = SparseVector::from_slice(&[3, 7]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+}
+
+#[test]
+fn raw_fallible() {
+    let net = test_net();
+    let mut loaded = TestNet::try_boxed_and_zeroed().unwrap();
+
+    let path = std::env::temp_dir().join("goober_raw_fallible.bin");
+    let path = path.to_str().unwrap();
+    let _ = std::fs::remove_file(path);
+    assert!(matches!(
+        loaded.try_read_from_bin(path),
+        Err(GooberError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound
+    ));
+
+    std::fs::write(path, [0; 7]).unwrap();
+    let err = loaded.try_read_from_bin(path).unwrap_err();
+    assert!(matches!(err, GooberError::Load(LoadError::Mismatch(_))));
+    assert_eq!(
+        err.to_string(),
+        "architecture mismatch: expected 580 bytes of parameters, found 7"
+    );
+    assert_eq!(
+        loaded.fingerprint(),
+        TestNet::boxed_and_zeroed().fingerprint()
+    );
+
+    net.try_write_to_bin(path).unwrap();
+    loaded.try_read_from_bin(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(loaded.fingerprint(), net.fingerprint());
+}
+
+/// Every error converts into [`GooberError`] through `?`.
+fn load_twice(a: &mut TestNet, b: &mut TestNet, bytes: &[u8]) -> Result<(), GooberError> {
+    a.load_bytes(bytes)?;
+    b.load_bytes(bytes)?;
+    Ok(())
+}
+
+#[test]
+fn goober_error() {
+    let net = test_net();
+    let (mut a, mut b) = (TestNet::boxed_and_zeroed(), TestNet::boxed_and_zeroed());
+    assert!(load_twice(&mut a, &mut b, &net.save_bytes()).is_ok());
+    assert_eq!(b.fingerprint(), net.fingerprint());
+
+    let err = load_twice(&mut a, &mut b, b"GBNN").unwrap_err();
+    assert!(matches!(err, GooberError::Load(_)));
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn fingerprint() {
+    let net = test_net();
+    assert_eq!(net.fingerprint_hex(), format!("{:016x}", net.fingerprint()));
+    assert_ne!(net.fingerprint(), TestNet::boxed_and_zeroed().fingerprint());
+
+    let mut loaded = TestNet::boxed_and_zeroed();
+    loaded.load_bytes(&net.save_bytes()).unwrap();
+    assert_eq!(loaded.fingerprint(), net.fingerprint());
+
+    loaded.l2.bias_mut()[0] = 0.5;
+    assert_ne!(loaded.fingerprint(), net.fingerprint());
+
+    // pinned, so that changes to the hash are deliberate
+    assert_eq!(net.fingerprint_hex(), "f3dec2d51ff4a273");
+
+    // zeroed networks of different architectures differ by shape
+    assert_ne!(
+        TestNet::boxed_and_zeroed().fingerprint(),
+        WiderNet::boxed_and_zeroed().fingerprint()
+    );
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "serde")]
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    FeedForwardNetwork, SparseVector, Vector,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(FeedForwardNetwork, Serialize, Deserialize)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 40, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+#[test]
+fn serde() {
+    let net = TestNet {
+        l1: SparseConnected::from_fn(|i, j| (i * 4 + j) as f32 / 100.0, |j| j as f32),
+        l2: DenseConnected::from_fn(|i, j| (i + j) as f32 / 10.0, |_| -0.5),
+    };
+
+    let json = serde_json::to_string(&net).unwrap();
+    let loaded: TestNet = serde_json::from_str(&json).unwrap();
+
+    let input = SparseVector::from_slice(&[1, 33]);
+    assert_eq!(net.out(&input), loaded.out(&input));
+
+    let vector: Vector<3> = serde_json::from_str("[1.0, 2.0, 3.0]").unwrap();
+    assert_eq!(vector, Vector::from_raw([1.0, 2.0, 3.0]));
+    assert!(serde_json::from_str::<Vector<3>>("[1.0, 2.0]").is_err());
+}
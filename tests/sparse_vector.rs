@@ -0,0 +1,44 @@
+use goober::SparseVector;
+
+#[test]
+fn sparse_vector() {
+    let mut pushed = SparseVector::with_capacity(4);
+    pushed.push(1);
+    pushed.push(4);
+    pushed.push(9);
+
+    let collected: SparseVector = [1, 4, 9].into_iter().collect();
+    assert_eq!(collected, pushed);
+    assert_eq!(SparseVector::from_slice(&[1, 4, 9]), pushed);
+
+    let mut extended = SparseVector::from_slice(&[1]);
+    extended.extend([4, 9]);
+    assert_eq!(extended, pushed);
+
+    let borrowed: Vec<usize> = (&pushed).into_iter().copied().collect();
+    assert_eq!(borrowed, vec![1, 4, 9]);
+    assert_eq!(pushed.into_iter().sum::<usize>(), 14);
+}
+
+#[test]
+fn sorted_sparse_vector() {
+    let mut vec = SparseVector::sorted(4);
+    vec.push(9);
+    vec.push(1);
+    vec.push(4);
+    vec.push(1);
+    assert_eq!(&vec[..], &[1, 4, 9]);
+
+    assert!(vec.contains(4));
+    assert!(vec.remove(4));
+    assert!(!vec.contains(4));
+    assert!(!vec.remove(4));
+
+    let mut unsorted = SparseVector::from_slice(&[7, 2, 7, 5]);
+    assert!(!unsorted.is_sorted());
+    unsorted.sort_dedup();
+    assert_eq!(&unsorted[..], &[2, 5, 7]);
+
+    unsorted.extend([3, 5]);
+    assert_eq!(&unsorted[..], &[2, 3, 5, 7]);
+}
@@ -0,0 +1,52 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    stats::Stats,
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 4, 2>,
+    l2: DenseConnected<Tanh, 2, 1>,
+}
+
+#[test]
+fn weights_and_gradients() {
+    let mut net = TestNet::boxed_and_zeroed();
+    *net.l1.weights_row_mut(0) = Vector::from_raw([1.0, -1.0]);
+    *net.l1.weights_row_mut(1) = Vector::from_raw([3.0, f32::NAN]);
+    *net.l2.weights_row_mut(0) = Vector::from_raw([0.5, 0.5]);
+
+    let stats = Stats::of(&*net, 4);
+    let l1 = stats.get("l1.weight").unwrap();
+    assert_eq!(l1.shape, [4, 2]);
+    assert_eq!(l1.count, 8);
+    assert_eq!(l1.non_finite, 1);
+    assert_eq!(l1.zeros, 4);
+    assert_eq!((l1.min, l1.max), (-1.0, 3.0));
+    assert!((l1.mean - 3.0 / 7.0).abs() < 1e-6);
+    assert_eq!(l1.histogram, [1, 4, 1, 1]);
+
+    let bias = stats.get("l1.bias").unwrap();
+    assert_eq!(bias.zero_fraction(), 1.0);
+    assert_eq!(
+        (bias.std, bias.histogram.as_slice()),
+        (0.0, &[2, 0, 0, 0][..])
+    );
+
+    // gradient of an input only reaching the first feature
+    *net.l1.weights_row_mut(1) = Vector::from_raw([0.0, 0.0]);
+    let input = SparseVector::from_slice(&[0]);
+    let mut grad = TestNet::zeroed_grad();
+    let layers = net.out_with_layers(&input);
+    net.backprop(&input, &mut grad, Vector::from_raw([1.0]), &layers);
+
+    let grads = Stats::of(&*grad, 4);
+    assert_eq!(grads.get("l1.weight").unwrap().zeros, 7);
+    assert!(grads.get("l2.weight").unwrap().max > 0.0);
+
+    let table = grads.to_string();
+    assert_eq!(table.lines().count(), 5);
+    assert!(table.lines().nth(1).unwrap().starts_with("l1.weight"));
+}
@@ -0,0 +1,376 @@
+use std::{
+    cell::RefCell,
+    io::{self, Cursor},
+    rc::Rc,
+};
+
+use goober::{
+    activation::{ReLU, Tanh},
+    callback::{Callback, Control, Progress},
+    dataset::{FeatureEncoder, Record},
+    layer::{DenseConnected, SparseConnected},
+    stream::{Batches, Loader, Shuffle, Stream},
+    trainer::Trainer,
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+/// Two features as bytes, then the target as an `i8` in hundredths.
+struct Pair;
+
+impl Record for Pair {
+    type Input = SparseVector;
+    type Output = Vector<1>;
+
+    const SIZE: usize = 3;
+
+    fn decode(bytes: &[u8]) -> (SparseVector, Vector<1>) {
+        let features = [usize::from(bytes[0]), usize::from(bytes[1])];
+        let target = f32::from(bytes[2] as i8) / 100.0;
+        (
+            SparseVector::from_slice(&features),
+            Vector::from_raw([target]),
+        )
+    }
+}
+
+fn bytes(samples: usize) -> Vec<u8> {
+    (0..samples)
+        .flat_map(|i| {
+            let (a, b) = (i % 8, (i / 8 + 1 + i % 8) % 8);
+            let target: i8 = if (a + b) % 3 == 0 { 50 } else { -50 };
+            [a as u8, b as u8, target as u8]
+        })
+        .collect()
+}
+
+#[test]
+fn reads_in_order() {
+    let bytes = bytes(3000);
+    let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 1);
+    let samples: Vec<_> = stream.collect::<io::Result<_>>().unwrap();
+
+    assert_eq!(samples.len(), 3000);
+    for (sample, record) in samples.iter().zip(bytes.chunks(3)) {
+        assert_eq!(*sample, Pair::decode(record));
+    }
+}
+
+#[test]
+fn errors() {
+    let mut stream = Stream::records::<Pair>(Cursor::new(bytes(2)[..5].to_vec()), 2);
+    let err = stream.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(stream.next().is_none());
+
+    let mut chunks = 0;
+    let mut stream = Stream::<u8, u8>::spawn(1, move || {
+        chunks += 1;
+        match chunks {
+            1 => Ok(Some(vec![(1, 2)])),
+            _ => panic!("corrupt"),
+        }
+    });
+    assert_eq!(stream.next().unwrap().unwrap(), (1, 2));
+    assert_eq!(
+        stream.next().unwrap().unwrap_err().to_string(),
+        "reader thread panicked"
+    );
+    assert!(stream.next().is_none());
+}
+
+/// Epoch of each batch and of each end of an epoch, stopping at `stop`.
+struct Epochs {
+    batches: Rc<RefCell<Vec<u64>>>,
+    ends: Rc<RefCell<Vec<u64>>>,
+    stop: u64,
+}
+
+impl Callback<TestNet> for Epochs {
+    fn on_batch_end(&mut self, progress: &Progress<'_, TestNet>) -> Control {
+        self.batches.borrow_mut().push(progress.epoch);
+        match progress.step == self.stop {
+            true => Control::Stop,
+            false => Control::Continue,
+        }
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, TestNet>) -> Control {
+        self.ends.borrow_mut().push(progress.epoch);
+        Control::Continue
+    }
+}
+
+#[test]
+fn stream_epochs() {
+    let bytes = bytes(100);
+    let (batches, ends) = Default::default();
+    let epochs = Epochs {
+        batches: Rc::clone(&batches),
+        ends: Rc::clone(&ends),
+        stop: 10,
+    };
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), Vec::new())
+        .with_batch_size(16)
+        .with_callback(epochs);
+
+    // the first pass is epoch 0 throughout, though its length is unknown
+    let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+    trainer.run_stream(stream).unwrap();
+    assert_eq!(*batches.borrow(), [0; 7]);
+    assert_eq!(*ends.borrow(), [1]);
+    assert_eq!(trainer.epoch(), 1);
+
+    // stopped at the 10th step, so the second pass does not end
+    let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+    trainer.run_stream(stream).unwrap();
+    assert!(trainer.stopped());
+    assert_eq!(batches.borrow().len(), 10);
+    assert_eq!(*ends.borrow(), [1]);
+    assert_eq!(trainer.epoch(), 1);
+
+    // a later pass is not stopped by the earlier stop
+    let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+    trainer.run_stream(stream).unwrap();
+    assert!(!trainer.stopped());
+    assert_eq!(batches.borrow()[10..], [1; 7]);
+    assert_eq!(*ends.borrow(), [1, 2]);
+    assert_eq!(trainer.epoch(), 2);
+}
+
+#[test]
+fn train() {
+    let bytes = bytes(100);
+    let data: Vec<_> = bytes.chunks(3).map(Pair::decode).collect();
+
+    let mut expected = Trainer::new(TestNet::boxed_and_zeroed(), data)
+        .with_batch_size(16)
+        .with_shuffle(false);
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), Vec::new()).with_batch_size(16);
+    assert_eq!(trainer.batches_per_epoch(), 0);
+
+    for _ in 0..3 {
+        let loss = expected.run_epoch().unwrap();
+        let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+        assert_eq!(trainer.run_stream(stream).unwrap(), loss);
+    }
+
+    assert_eq!(trainer.batches_per_epoch(), 7);
+    assert_eq!(trainer.epoch(), 3);
+    assert_eq!(trainer.net().fingerprint(), expected.net().fingerprint());
+
+    let path = std::env::temp_dir().join(format!("goober-stream-{}.bin", std::process::id()));
+    std::fs::write(&path, &bytes[..7]).unwrap();
+    let stream = Stream::open::<Pair>(&path, 2).unwrap();
+    let err = trainer.run_stream(stream).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    std::fs::remove_file(path).unwrap();
+}
+
+/// A zstd frame holding `bytes` in a single uncompressed block.
+fn raw_frame(bytes: &[u8]) -> Vec<u8> {
+    let header = (bytes.len() << 3 | 1) as u32;
+    [
+        &[0x28, 0xB5, 0x2F, 0xFD, 0, 0],
+        &header.to_le_bytes()[..3],
+        bytes,
+    ]
+    .concat()
+}
+
+#[test]
+#[cfg(feature = "zstd")]
+fn compressed() {
+    let bytes = bytes(100);
+    let path = std::env::temp_dir().join(format!("goober-stream-{}.zst", std::process::id()));
+    std::fs::write(&path, raw_frame(&bytes)).unwrap();
+    let samples: Vec<_> = Stream::open::<Pair>(&path, 2)
+        .unwrap()
+        .collect::<io::Result<_>>()
+        .unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let expected: Vec<_> = bytes.chunks(3).map(Pair::decode).collect();
+    assert_eq!(samples, expected);
+}
+
+#[test]
+#[cfg(not(feature = "zstd"))]
+fn compressed_unsupported() {
+    let path = std::env::temp_dir().join(format!("goober-stream-{}.zst", std::process::id()));
+    std::fs::write(&path, raw_frame(&bytes(100))).unwrap();
+    let err = Stream::open::<Pair>(&path, 2).err().unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn shuffle() {
+    let ordered = || (0..1000).map(Ok);
+    let shuffle = |capacity, seed| {
+        Shuffle::new(ordered(), capacity, seed)
+            .collect::<io::Result<Vec<usize>>>()
+            .unwrap()
+    };
+
+    let shuffled = shuffle(64, 1);
+    assert_ne!(shuffled, (0..1000).collect::<Vec<_>>());
+    assert_eq!(shuffled, shuffle(64, 1));
+    assert_ne!(shuffled, shuffle(64, 2));
+    assert_eq!(shuffle(1, 1), (0..1000).collect::<Vec<_>>());
+
+    let mut sorted = shuffled.clone();
+    sorted.sort();
+    assert_eq!(sorted, (0..1000).collect::<Vec<_>>());
+    assert!(shuffled.iter().enumerate().all(|(i, &x)| x < i + 64));
+
+    let failing = (0..10).map(|i| match i {
+        5 => Err(io::Error::other("bad")),
+        i => Ok(i),
+    });
+    let results: Vec<_> = Shuffle::new(failing, 8, 1).collect();
+    assert_eq!(results.len(), 10);
+    assert_eq!(results[0].as_ref().unwrap_err().to_string(), "bad");
+
+    let stream = Stream::records::<Pair>(Cursor::new(bytes(100)), 2).shuffled(16, 3);
+    let mut samples: Vec<_> = stream.collect::<io::Result<_>>().unwrap();
+    let mut expected: Vec<_> = bytes(100).chunks(3).map(Pair::decode).collect();
+    assert_ne!(samples, expected);
+    let key =
+        |(input, target): &(SparseVector, Vector<1>)| (format!("{input:?}"), target[0].to_bits());
+    samples.sort_by_key(key);
+    expected.sort_by_key(key);
+    assert_eq!(samples, expected);
+}
+
+/// The features of a pair, plus the feature of their sum.
+struct WithSum;
+
+impl FeatureEncoder<SparseVector> for WithSum {
+    type Input = SparseVector;
+
+    fn encode(&self, raw: &SparseVector) -> SparseVector {
+        let mut features = raw.clone();
+        features.push(raw.iter().sum::<usize>() % 8);
+        features
+    }
+}
+
+#[test]
+fn encoded() {
+    let bytes = bytes(100);
+    let expected: Vec<_> = bytes
+        .chunks(3)
+        .map(|record| {
+            let (input, target) = Pair::decode(record);
+            (WithSum.encode(&input), target)
+        })
+        .collect();
+
+    let stream = Stream::encoded::<Pair, _>(Cursor::new(bytes.clone()), 2, WithSum);
+    let samples: Vec<_> = stream.collect::<io::Result<_>>().unwrap();
+    assert_eq!(samples, expected);
+    assert_eq!(samples[9].0.len(), 3);
+
+    let path = std::env::temp_dir().join(format!("goober-encoded-{}.bin", std::process::id()));
+    std::fs::write(&path, &bytes).unwrap();
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), Vec::new()).with_batch_size(16);
+    let mut expected = Trainer::new(TestNet::boxed_and_zeroed(), expected)
+        .with_batch_size(16)
+        .with_shuffle(false);
+    let stream = Stream::open_encoded::<Pair, _>(&path, 2, WithSum).unwrap();
+    assert_eq!(
+        trainer.run_stream(stream).unwrap(),
+        expected.run_epoch().unwrap()
+    );
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn filter() {
+    let records = bytes(3000);
+    let keep = |input: &SparseVector, _: &Vector<1>| !input.contains(0);
+    let expected: Vec<_> = records
+        .chunks(3)
+        .map(Pair::decode)
+        .filter(|(input, target)| keep(input, target))
+        .map(|(input, target)| (WithSum.encode(&input), target))
+        .collect();
+
+    let mut stream = Loader::<Pair>::new(2)
+        .with_filter(keep)
+        .with_encoder(WithSum)
+        .read(Cursor::new(records));
+    let samples: Vec<_> = stream.by_ref().collect::<io::Result<_>>().unwrap();
+    assert_eq!(samples, expected);
+    assert_eq!(stream.skipped(), 3000 - expected.len() as u64);
+    assert!(stream.skipped() > 0);
+
+    let stream = Loader::<Pair>::new(2)
+        .with_filter(|_, _| false)
+        .read(Cursor::new(bytes(10)));
+    assert_eq!(stream.count(), 0);
+}
+
+#[test]
+fn threads() {
+    let records = bytes(10000);
+    let keep = |input: &SparseVector, _: &Vector<1>| !input.contains(0);
+    let single = Loader::<Pair>::new(2)
+        .with_filter(keep)
+        .with_encoder(WithSum)
+        .read(Cursor::new(records.clone()));
+    let expected: Vec<_> = single.collect::<io::Result<_>>().unwrap();
+
+    let mut stream = Loader::<Pair>::new(2)
+        .with_filter(keep)
+        .with_encoder(WithSum)
+        .with_threads(4)
+        .read(Cursor::new(records));
+    let samples: Vec<_> = stream.by_ref().collect::<io::Result<_>>().unwrap();
+    assert_eq!(samples, expected);
+    assert_eq!(stream.skipped(), 10000 - expected.len() as u64);
+
+    let mut partial = bytes(10);
+    partial.pop();
+    let result: io::Result<Vec<_>> = Loader::<Pair>::new(2)
+        .with_threads(4)
+        .read(Cursor::new(partial))
+        .collect();
+    assert!(result.is_err());
+}
+
+#[test]
+fn batches() {
+    let bytes = bytes(100);
+    let expected: Vec<_> = bytes.chunks(3).map(Pair::decode).collect();
+
+    let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+    let batches: Vec<_> = Batches::new(stream, 16).collect::<io::Result<_>>().unwrap();
+    assert_eq!(batches.len(), 7);
+    assert!(batches.iter().take(6).all(|batch| batch.len() == 16));
+    assert_eq!(batches.concat(), expected);
+
+    let mut streamed = Trainer::new(TestNet::boxed_and_zeroed(), Vec::new()).with_batch_size(16);
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), Vec::new()).with_batch_size(16);
+    for _ in 0..3 {
+        let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+        let loss = streamed.run_stream(stream).unwrap();
+        let stream = Stream::records::<Pair>(Cursor::new(bytes.clone()), 2);
+        assert_eq!(trainer.run_batches(Batches::new(stream, 16)).unwrap(), loss);
+    }
+    assert_eq!(trainer.batches_per_epoch(), 7);
+    assert_eq!(trainer.net().fingerprint(), streamed.net().fingerprint());
+
+    let stream = Stream::records::<Pair>(Cursor::new(bytes[..7].to_vec()), 2);
+    let mut batches = Batches::new(stream, 16);
+    let err = batches.next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert!(batches.next().is_none());
+}
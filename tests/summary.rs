@@ -0,0 +1,38 @@
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{Add, DenseConnected, SparseConnected},
+    FeedForwardNetwork,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 768, 16>,
+    l2: Add<DenseConnected<ReLU, 16, 4>, DenseConnected<Tanh, 16, 4>>,
+    l3: DenseConnected<Tanh, 4, 1>,
+}
+
+#[test]
+fn summary() {
+    let net = TestNet::boxed_and_zeroed();
+    let summary = net.summary();
+
+    let names: Vec<_> = summary.layers().iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, ["l1", "l2.a", "l2.b", "l3"]);
+
+    let l1 = &summary.layers()[0];
+    assert_eq!(l1.kind, "SparseConnected<ReLU, 768, 16>");
+    assert_eq!(l1.input, "sparse [768]");
+    assert_eq!(l1.output, "[16]");
+    assert_eq!(l1.activation, "ReLU");
+    assert_eq!(l1.params, 768 * 16 + 16);
+    assert_eq!(l1.bytes, 4 * l1.params);
+
+    let l3 = &summary.layers()[3];
+    assert_eq!((l3.params, l3.bytes), (5, 20));
+    assert_eq!(summary.params(), 12304 + 2 * 68 + 5);
+
+    let table = summary.to_string();
+    assert!(table.contains("l2.b   DenseConnected<Tanh, 16, 4>"));
+    assert!(table.contains("total params: 12445"));
+    assert!(table.contains("memory: 48.6 KiB of parameters, 194.5 KiB while training"));
+}
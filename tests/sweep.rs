@@ -0,0 +1,148 @@
+use goober::{
+    config::{Config, ConfigError, Schedule},
+    schedule::{Constant, Scheduler},
+    sweep::{Mode, Sweep},
+};
+
+const SWEEP: &str = r#"
+epochs = 2
+batch_size = 4
+
+[schedule]
+kind = "constant"
+lr = 0.001
+
+[data]
+train = "train.txt"
+
+[checkpoint]
+dir = "ckpt"
+
+[sweep.params]
+schedule.lr = [0.01, 0.1]
+batch_size = [8, 16, 32]
+args.hidden = [32, 64]
+"#;
+
+#[test]
+fn grid() {
+    let sweep = Sweep::parse(SWEEP).unwrap();
+    assert_eq!(sweep.mode(), Mode::Grid);
+
+    let trials = sweep.trials();
+    assert_eq!(trials.len(), 12);
+    assert!(trials.iter().enumerate().all(|(i, trial)| trial.index == i));
+
+    let last = &trials[11];
+    assert_eq!(
+        last.params(),
+        [
+            ("schedule.lr".to_string(), "0.1".to_string()),
+            ("batch_size".to_string(), "32".to_string()),
+            ("args.hidden".to_string(), "64".to_string()),
+        ]
+    );
+    assert_eq!(last.args(), [("hidden".to_string(), "64".to_string())]);
+
+    let config = last.config().unwrap();
+    assert_eq!(config.epochs, 2);
+    assert_eq!(config.batch_size, 32);
+    assert_eq!(config.schedule, Schedule::Constant(Constant(0.1)));
+    assert_eq!(config.checkpoint, None);
+
+    assert_eq!(Config::parse(&last.to_toml()).unwrap(), config);
+}
+
+#[test]
+fn random() {
+    let text = format!("{SWEEP}\n[sweep]\nmode = \"random\"\ntrials = 5\nseed = 7\n");
+    let sweep = Sweep::parse(&text).unwrap();
+    assert_eq!(sweep.mode(), Mode::Random { trials: 5, seed: 7 });
+
+    let trials = sweep.trials();
+    assert_eq!(trials.len(), 5);
+    assert_eq!(trials, sweep.trials());
+
+    let other = Sweep::parse(&text.replace("seed = 7", "seed = 8")).unwrap();
+    assert_ne!(trials, other.trials());
+}
+
+#[test]
+fn run() {
+    let sweep = Sweep::parse(SWEEP).unwrap();
+    let report = sweep.run(3, |trial| {
+        let config = trial.config().map_err(|err| err.to_string())?;
+        match trial.index {
+            5 => Err("diverged".to_string()),
+            _ => Ok(config.batch_size as f32 * config.schedule.lr(0)),
+        }
+    });
+
+    assert_eq!(report.results().len(), 12);
+    assert!(report
+        .results()
+        .iter()
+        .enumerate()
+        .all(|(i, (trial, _))| trial.index == i));
+    assert_eq!(report.results()[5].1, Err("diverged".to_string()));
+
+    let (best, loss) = report.best().unwrap();
+    assert_eq!(best.index, 0);
+    assert_eq!(loss, 8.0 * 0.01);
+
+    let table = report.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+    assert_eq!(lines.len(), 13);
+    assert!(lines[0].contains("schedule.lr") && lines[0].contains("args.hidden"));
+    assert!(lines[1].trim_start().starts_with("0 "));
+    assert!(lines[12].contains("failed") && lines[12].ends_with("diverged"));
+}
+
+#[test]
+fn errors() {
+    let invalid = |text: &str| match Sweep::parse(text) {
+        Err(ConfigError::Invalid { key, message }) => (key, message),
+        other => panic!("expected an invalid key, got {other:?}"),
+    };
+
+    assert_eq!(
+        invalid("[sweep]\nmode = \"best\""),
+        (
+            "sweep.mode".into(),
+            "is `best`, expected one of grid, random".into()
+        )
+    );
+    assert_eq!(
+        invalid("[sweep]\nmode = \"random\""),
+        ("sweep.trials".into(), "is missing".into())
+    );
+    assert_eq!(
+        invalid("[sweep]\ntrials = 3"),
+        (
+            "sweep.trials".into(),
+            "only applies to random sweeps".into()
+        )
+    );
+    assert_eq!(
+        invalid("[sweep]\nrepeats = 3"),
+        ("sweep.repeats".into(), "on line 2 is unknown".into())
+    );
+    assert_eq!(
+        invalid("[sweep.params]\nbatch_size = []"),
+        ("sweep.params.batch_size".into(), "has no values".into())
+    );
+    assert_eq!(
+        invalid("[sweep.params]\nbatch_size = [8, \"many\"]"),
+        (
+            "batch_size".into(),
+            "should be a non-negative integer, in trial 1".into()
+        )
+    );
+    assert_eq!(
+        invalid("[sweep.params]\nlearning_rate = [0.1]"),
+        (
+            "learning_rate".into(),
+            "on line 2 is unknown, in trial 0".into()
+        )
+    );
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "tensorboard")]
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    tensorboard::TensorBoard,
+    trainer::Trainer,
+    FeedForwardNetwork, SparseVector, Vector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+/// Splits an event file into its records, checking the framing.
+fn records(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut records = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let len = u64::from_le_bytes(bytes[pos..pos + 8].try_into().unwrap()) as usize;
+        records.push(&bytes[pos + 12..pos + 12 + len]);
+        pos += 16 + len;
+    }
+
+    assert_eq!(pos, bytes.len());
+    records
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn tensorboard() {
+    let dir = std::env::temp_dir().join("goober_tensorboard");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let data = (0..8)
+        .map(|i| {
+            (
+                SparseVector::from_slice(&[i]),
+                Vector::from_raw([0.1 * i as f32]),
+            )
+        })
+        .collect();
+
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), data)
+        .with_batch_size(4)
+        .with_callback(TensorBoard::new(&dir).unwrap().with_every(1));
+    trainer.run(2).unwrap();
+    drop(trainer);
+
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+
+    let bytes = std::fs::read(files[0].as_ref().unwrap().path()).unwrap();
+    let records = records(&bytes);
+
+    assert!(contains(records[0], b"brain.Event:2"));
+
+    // each epoch has loss and lr after both steps and at its
+    // end, followed by histograms of the 4 tensors of parameters
+    assert_eq!(records.len(), 1 + 2 * (3 * 2 + 4));
+    assert!(contains(records[1], b"loss"));
+    assert!(contains(records[2], b"lr"));
+    assert!(contains(records[7], b"l1.weight"));
+    assert!(contains(records[10], b"l2.bias"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "tracing")]
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use goober::{
+    activation::{ReLU, Tanh},
+    layer::{DenseConnected, SparseConnected},
+    trainer::Trainer,
+    FeedForwardNetwork, SparseVector, Vector,
+};
+use tracing::{span, subscriber::Subscriber, Event, Metadata};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 4>,
+    l2: DenseConnected<Tanh, 4, 1>,
+}
+
+/// Records the names of spans entered and events emitted.
+#[derive(Default)]
+struct Record {
+    next: AtomicU64,
+    names: Mutex<Vec<(u64, &'static str)>>,
+    log: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl Subscriber for Record {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        let id = self.next.fetch_add(1, Ordering::Relaxed) + 1;
+        self.names
+            .lock()
+            .unwrap()
+            .push((id, span.metadata().name()));
+        span::Id::from_u64(id)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        self.log.lock().unwrap().push(event.metadata().name());
+    }
+
+    fn enter(&self, span: &span::Id) {
+        let names = self.names.lock().unwrap();
+        let (_, name) = names.iter().find(|(id, _)| *id == span.into_u64()).unwrap();
+        self.log.lock().unwrap().push(name);
+    }
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+fn spans() {
+    let record = Record::default();
+    let log = record.log.clone();
+
+    let data: Vec<_> = (0..8)
+        .map(|i| (SparseVector::from_slice(&[i]), Vector::from_raw([0.1])))
+        .collect();
+
+    tracing::subscriber::with_default(record, || {
+        let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), data.clone())
+            .with_batch_size(4)
+            .with_validation(data);
+        trainer.run(1).unwrap();
+    });
+
+    let log = log.lock().unwrap();
+    for name in [
+        "run",
+        "epoch",
+        "batch",
+        "forward_backward",
+        "optimiser",
+        "evaluate",
+    ] {
+        assert!(log.contains(&name), "missing `{name}` in {log:?}");
+    }
+    assert_eq!(log.iter().filter(|&&name| name == "batch").count(), 2);
+}
@@ -0,0 +1,774 @@
+use std::{
+    cell::{Cell, RefCell},
+    path::Path,
+    rc::Rc,
+};
+
+use goober::{
+    activation::{ReLU, Tanh},
+    augment::{Augment, FeatureDropout, Mixup},
+    bf16,
+    callback::{BestModel, Callback, Control, CsvLogger, Curriculum, EarlyStopping, Progress},
+    checkpoint::CheckpointManager,
+    dataset::{Dataset, Sample, Weighted},
+    diff::Diff,
+    layer::{DenseConnected, SparseConnected},
+    loss::{Loss, Mse},
+    lr_finder::LrFinder,
+    merge::{average, lerp_in_place},
+    metrics::{evaluate, evaluate_with_threads, Metrics},
+    prune::Prune,
+    schedule::StepDecay,
+    seed_stochastic_rounding,
+    trainer::{NonFinite, NonFiniteKind, Trainer},
+    FeedForwardNetwork, Float, Rand, SparseVector, Stochastic, Vector, WeightedSparseVector,
+};
+
+#[derive(FeedForwardNetwork)]
+pub struct TestNet {
+    l1: SparseConnected<ReLU, 8, 8>,
+    l2: DenseConnected<Tanh, 8, 1>,
+}
+
+fn initial() -> Box<TestNet> {
+    let mut net = TestNet::boxed_and_zeroed();
+    for i in 0..8 {
+        *net.l1.weights_row_mut(i) = Vector::from_fn(|j| ((i * 8 + j) % 7) as f32 / 10.0 - 0.3);
+    }
+    *net.l2.weights_row_mut(0) = Vector::from_fn(|j| (j % 3) as f32 / 10.0 - 0.1);
+    net
+}
+
+fn data() -> Vec<(SparseVector, Vector<1>)> {
+    (0..8)
+        .flat_map(|i| (0..8).map(move |j| (i, j)))
+        .filter(|(i, j)| i < j)
+        .map(|(i, j)| {
+            let target = if (i + j) % 3 == 0 { 0.5 } else { -0.5 };
+            (
+                SparseVector::from_slice(&[i, j]),
+                Vector::from_raw([target]),
+            )
+        })
+        .collect()
+}
+
+fn trainer() -> Trainer<TestNet, StepDecay> {
+    Trainer::new(initial(), data())
+        .with_scheduler(StepDecay {
+            lr: 0.01,
+            gamma: 0.5,
+            every: 500,
+        })
+        .with_batch_size(8)
+        .with_seed(7)
+}
+
+#[test]
+fn loss_decreases() {
+    let mut trainer = trainer();
+    assert_eq!(trainer.batches_per_epoch(), 4);
+
+    let first = trainer.run_epoch().unwrap();
+    let last = trainer.run(199).unwrap();
+
+    assert_eq!(trainer.step(), 800);
+    assert_eq!(trainer.epoch(), 200);
+    assert_eq!(trainer.lr(), 0.005);
+    assert!(last < first / 10.0, "{first} -> {last}");
+}
+
+/// Decodes the samples of [`data`] on demand.
+struct Pairs;
+
+impl Dataset<SparseVector, Vector<1>> for Pairs {
+    fn len(&self) -> usize {
+        28
+    }
+
+    fn get(&self, index: usize) -> Sample<'_, SparseVector, Vector<1>> {
+        let (input, target) = data().swap_remove(index);
+        Sample::Owned(input, target)
+    }
+}
+
+#[test]
+fn custom_dataset() {
+    let data = data();
+    assert_eq!(Dataset::len(&data), 28);
+    assert_eq!(
+        Dataset::get(&data, 3),
+        Sample::Borrowed(&data[3].0, &data[3].1)
+    );
+
+    let mut expected = trainer();
+    expected.run(3).unwrap();
+
+    let mut trainer = Trainer::from_dataset(initial(), Pairs)
+        .with_scheduler(StepDecay {
+            lr: 0.01,
+            gamma: 0.5,
+            every: 500,
+        })
+        .with_batch_size(8)
+        .with_seed(7);
+    assert_eq!(trainer.data().get(3).into_owned(), data[3]);
+    trainer.run(3).unwrap();
+
+    assert_eq!(trainer.net().fingerprint(), expected.net().fingerprint());
+}
+
+#[test]
+fn sample_weights() {
+    // each batch is a pair of the same sample, or it weighted by 2 and another by 0
+    let weighted: Vec<_> = data()
+        .chunks(2)
+        .flat_map(|pair| {
+            [
+                (pair[0].0.clone(), pair[0].1, 2.0),
+                (pair[1].0.clone(), pair[1].1, 0.0),
+            ]
+        })
+        .collect();
+    let repeated: Vec<_> = data()
+        .chunks(2)
+        .flat_map(|pair| [pair[0].clone(), pair[0].clone()])
+        .collect();
+    assert_eq!(Dataset::weight(&weighted, 2), 2.0);
+    assert_eq!(Dataset::weight(&repeated, 2), 1.0);
+
+    let mut expected = Trainer::new(initial(), repeated)
+        .with_batch_size(2)
+        .with_shuffle(false);
+    let mut trainer = Trainer::from_dataset(initial(), weighted)
+        .with_batch_size(2)
+        .with_shuffle(false);
+    for _ in 0..3 {
+        assert_eq!(trainer.run_epoch().unwrap(), expected.run_epoch().unwrap());
+    }
+    assert_eq!(trainer.net().fingerprint(), expected.net().fingerprint());
+
+    let positive = Weighted::new(
+        data(),
+        |_: &SparseVector, target: &Vector<1>| {
+            if target[0] > 0.0 {
+                3.0
+            } else {
+                1.0
+            }
+        },
+    );
+    let weights: Vec<f32> = (0..positive.len()).map(|i| positive.weight(i)).collect();
+    let expected: Vec<f32> = data()
+        .iter()
+        .map(|(_, target)| if target[0] > 0.0 { 3.0 } else { 1.0 })
+        .collect();
+    assert_eq!(weights, expected);
+}
+
+#[test]
+fn curriculum() {
+    let curriculum = Curriculum::new(|epochs| (epochs / 2.0).min(1.0));
+    let stage = curriculum.stage();
+    assert_eq!(stage.get(), 0.0);
+
+    // negative samples are phased in over two epochs
+    let phased = stage.clone();
+    let data = Weighted::new(
+        data(),
+        move |_: &SparseVector, target: &Vector<1>| {
+            if target[0] > 0.0 {
+                1.0
+            } else {
+                phased.get()
+            }
+        },
+    );
+    let mut trainer = Trainer::from_dataset(initial(), data)
+        .with_batch_size(8)
+        .with_callback(curriculum);
+
+    trainer.run_epoch().unwrap();
+    assert_eq!(stage.get(), 0.5);
+    assert_eq!(trainer.data().weight(1), 0.5);
+    trainer.run(3).unwrap();
+    assert_eq!(stage.get(), 1.0);
+    assert_eq!(trainer.data().weight(1), 1.0);
+}
+
+#[test]
+fn resume_between_epochs() {
+    let mut full = trainer();
+    full.run(6).unwrap();
+
+    let mut first = trainer();
+    first.run(2).unwrap();
+    let bytes = first.checkpoint().to_bytes();
+
+    let mut resumed = Trainer::new(TestNet::boxed_and_zeroed(), data())
+        .with_scheduler(StepDecay {
+            lr: 0.01,
+            gamma: 0.5,
+            every: 500,
+        })
+        .with_batch_size(8);
+    resumed.checkpoint_mut().load_bytes(&bytes).unwrap();
+    resumed.run(4).unwrap();
+
+    assert_eq!(
+        resumed.checkpoint().to_bytes(),
+        full.checkpoint().to_bytes()
+    );
+}
+
+#[derive(Default)]
+struct Record {
+    batches: Rc<RefCell<Vec<(u64, u64)>>>,
+    checkpoints: Rc<RefCell<Vec<u64>>>,
+}
+
+impl Callback<TestNet> for Record {
+    fn on_batch_end(&mut self, progress: &Progress<'_, TestNet>) -> Control {
+        self.batches
+            .borrow_mut()
+            .push((progress.epoch, progress.step));
+
+        if progress.step == 10 {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+
+    fn on_checkpoint(&mut self, progress: &Progress<'_, TestNet>, path: &Path) {
+        assert!(path.exists());
+        self.checkpoints.borrow_mut().push(progress.step);
+    }
+}
+
+#[test]
+fn callbacks() {
+    let dir = std::env::temp_dir().join("goober_trainer_callbacks");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let record = Record::default();
+    let batches = record.batches.clone();
+    let checkpoints = record.checkpoints.clone();
+
+    let mut trainer = trainer()
+        .with_callback(record)
+        .with_checkpoints(CheckpointManager::new(&dir, 3).unwrap(), 1);
+
+    trainer.run(5).unwrap();
+    assert!(trainer.stopped());
+    assert_eq!(trainer.step(), 10);
+
+    let batches = batches.borrow();
+    assert_eq!(batches.len(), 10);
+    assert_eq!(batches[3], (1, 4));
+    assert_eq!(batches[9], (2, 10));
+    assert_eq!(*checkpoints.borrow(), [4, 8]);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn early_stopping() {
+    let mut best = trainer();
+    best.run(2).unwrap();
+
+    let metrics = [3.0, 2.0, 2.5, 1.95, 2.2, 1.0];
+    let mut epoch = 0;
+    let mut stopping = EarlyStopping::with_metric(1, move |_: &TestNet| {
+        epoch += 1;
+        metrics[epoch - 1]
+    });
+    stopping.min_delta = 0.1;
+
+    let mut trainer = trainer().with_callback(stopping);
+    trainer.run(10).unwrap();
+
+    assert!(trainer.stopped());
+    assert_eq!(trainer.epoch(), 4);
+    assert!(trainer.net().save_bytes() == best.net().save_bytes());
+}
+
+#[test]
+fn best_model() {
+    let path = std::env::temp_dir().join("goober_trainer_best.bin");
+    let _ = std::fs::remove_file(&path);
+
+    let mut best = trainer();
+    best.run(1).unwrap();
+
+    let mut evals = 0;
+    let saver = BestModel::with_metric(&path, 2, move |_: &TestNet| {
+        evals += 1;
+        [3.0, 1.0, 1.5, 1.0, 2.0, 4.0][evals - 1]
+    });
+
+    let mut trainer = trainer().with_callback(saver);
+    trainer.run(3).unwrap();
+
+    let mut saved = TestNet::boxed_and_zeroed();
+    saved.load(path.to_str().unwrap()).unwrap();
+    assert!(saved.save_bytes() == best.net().save_bytes());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn validation_metric() {
+    let path = std::env::temp_dir().join("goober_trainer_best_validation.bin");
+    let _ = std::fs::remove_file(&path);
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut trainer = trainer()
+        .with_scheduler(StepDecay {
+            lr: 0.5,
+            gamma: 1.0,
+            every: 500,
+        })
+        .with_validation(data()[..10].to_vec())
+        .with_callback(Validated(seen.clone()))
+        .with_callback(EarlyStopping::new(0))
+        .with_callback(BestModel::new(&path));
+    trainer.run(6).unwrap();
+
+    let best = seen
+        .borrow()
+        .iter()
+        .map(|metrics| metrics.loss)
+        .fold(f32::INFINITY, f32::min);
+    assert_eq!(evaluate(trainer.net(), &data()[..10], &Mse).loss, best);
+
+    let mut saved = TestNet::boxed_and_zeroed();
+    saved.load(path.to_str().unwrap()).unwrap();
+    assert!(saved.save_bytes() == trainer.net().save_bytes());
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+struct Validated(Rc<RefCell<Vec<Metrics>>>);
+
+impl Callback<TestNet> for Validated {
+    fn on_batch_end(&mut self, progress: &Progress<'_, TestNet>) -> Control {
+        assert!(progress.validation.is_none());
+        Control::Continue
+    }
+
+    fn on_epoch_end(&mut self, progress: &Progress<'_, TestNet>) -> Control {
+        self.0.borrow_mut().push(progress.validation.unwrap());
+        Control::Continue
+    }
+}
+
+#[test]
+fn validation() {
+    let net = initial();
+    let data = data();
+
+    let expected = data
+        .iter()
+        .map(|(input, target)| Mse.loss(&net.out(input), target).0)
+        .sum::<f32>()
+        / data.len() as f32;
+
+    let single = evaluate_with_threads(&*net, &data, &Mse, 1);
+    assert_eq!(single.samples, 28);
+    assert!((single.loss - expected).abs() < 1e-6);
+
+    let multi = evaluate_with_threads(&*net, &data, &Mse, 3);
+    assert_eq!(multi, single);
+    assert_eq!(evaluate(&*net, &data[..0], &Mse), Metrics::default());
+
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut trainer = trainer()
+        .with_validation(data[..10].to_vec())
+        .with_callback(Validated(seen.clone()));
+    trainer.run(3).unwrap();
+
+    let seen = seen.borrow();
+    assert_eq!(seen.len(), 3);
+    assert_eq!(seen[2], evaluate(trainer.net(), &data[..10], &Mse));
+}
+
+/// Twice the mean squared error.
+struct Double;
+
+impl Loss<Vector<1>> for Double {
+    fn loss(&self, out: &Vector<1>, target: &Vector<1>) -> (f32, Vector<1>) {
+        let (loss, grad) = Mse.loss(out, target);
+        (loss * 2.0, grad * 2.0)
+    }
+}
+
+#[test]
+fn validation_loss() {
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let mut trainer = trainer()
+        .with_validation(data()[..10].to_vec())
+        .with_loss(Double)
+        .with_callback(Validated(seen.clone()));
+    trainer.run(1).unwrap();
+
+    let net = trainer.net();
+    assert_eq!(seen.borrow()[0], evaluate(net, &data()[..10], &Double));
+    assert_ne!(seen.borrow()[0], evaluate(net, &data()[..10], &Mse));
+}
+
+#[test]
+fn csv_logger() {
+    let path = std::env::temp_dir().join("goober_trainer_metrics.tsv");
+    let _ = std::fs::remove_file(&path);
+
+    let logger = CsvLogger::new(&path)
+        .unwrap()
+        .with_delimiter('\t')
+        .with_every(3)
+        .with_column("bias", |net: &TestNet| net.l2.bias()[0]);
+
+    let mut trainer = trainer()
+        .with_validation(data()[..4].to_vec())
+        .with_callback(logger);
+    trainer.run(2).unwrap();
+
+    let csv = std::fs::read_to_string(&path).unwrap();
+    let rows: Vec<Vec<&str>> = csv.lines().map(|l| l.split('\t').collect()).collect();
+
+    assert_eq!(rows[0], ["step", "epoch", "lr", "loss", "val_loss", "bias"]);
+    assert_eq!(rows.len(), 5);
+    assert_eq!(rows[1][..3], ["3", "0", "0.01"]);
+    assert_eq!(rows[1][4..], ["", ""]);
+    assert_eq!(rows[2][..2], ["4", "1"]);
+    assert_eq!(rows[3][..2], ["6", "1"]);
+    assert_eq!(rows[4][..2], ["8", "2"]);
+    assert_eq!(
+        rows[4][4].parse::<f32>().unwrap(),
+        evaluate(trainer.net(), &data()[..4], &Mse).loss
+    );
+    assert_eq!(
+        rows[4][5].parse::<f32>().unwrap(),
+        trainer.net().l2.bias()[0]
+    );
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn nan_guard() {
+    let mut net = initial();
+    net.l1.weights_row_mut(3)[0] = f32::INFINITY;
+
+    let mut trainer = Trainer::new(net, data())
+        .with_batch_size(8)
+        .with_seed(7)
+        .with_nan_guard(true);
+    let err = trainer.run(1).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let found = err.get_ref().unwrap().downcast_ref::<NonFinite>().unwrap();
+    assert_eq!(found.kind, NonFiniteKind::Activation);
+    assert_eq!(found.name, "l1");
+    assert!(trainer
+        .data()
+        .get(found.sample.unwrap())
+        .input()
+        .contains(3));
+    assert_eq!(trainer.step(), found.step - 1);
+
+    let mut data = data();
+    data[5].1 = Vector::from_raw([f32::NAN]);
+    let mut trainer = Trainer::new(initial(), data).with_nan_guard(true);
+    let err = trainer.run(1).unwrap_err().to_string();
+    assert_eq!(err, "non-finite loss at step 1 on sample 5");
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct RoundedNet {
+    l1: SparseConnected<ReLU, 8, 8, Stochastic<bf16>>,
+    l2: DenseConnected<Tanh, 8, 1, Stochastic<bf16>>,
+}
+
+fn rounded(seed: u64) -> Trainer<RoundedNet> {
+    let mut net = RoundedNet::boxed_and_zeroed();
+    for i in 0..8 {
+        *net.l1.weights_row_mut(i) = Vector::from_fn(|j| {
+            Stochastic::<bf16>::from_f32(((i * 8 + j) % 7) as f32 / 10.0 - 0.3)
+        });
+    }
+
+    let data = data();
+    let data = data
+        .into_iter()
+        .map(|(input, target)| {
+            (
+                input,
+                Vector::from_fn(|_| Stochastic::<bf16>::from_f32(target[0])),
+            )
+        })
+        .collect();
+
+    Trainer::new(net, data)
+        .with_batch_size(8)
+        .with_deterministic(seed)
+}
+
+#[test]
+fn deterministic() {
+    let mut first = rounded(3);
+    first.run(3).unwrap();
+
+    seed_stochastic_rounding(12345);
+    let mut second = rounded(3);
+    second.run(1).unwrap();
+    second.run(2).unwrap();
+    assert_eq!(
+        first.checkpoint().to_bytes(),
+        second.checkpoint().to_bytes()
+    );
+
+    let mut other = rounded(4);
+    other.run(3).unwrap();
+    assert_ne!(first.checkpoint().to_bytes(), other.checkpoint().to_bytes());
+}
+
+#[test]
+fn swa() {
+    let mut swa = trainer().with_swa(2);
+    swa.run(1).unwrap();
+    assert!(swa.swa_net().is_none());
+    swa.run(3).unwrap();
+    assert_eq!(swa.swa_count(), 3);
+
+    let mut plain = trainer();
+    let mut snapshots = Vec::new();
+    for epoch in 1..=4 {
+        plain.run_epoch().unwrap();
+        if epoch >= 2 {
+            let mut net = TestNet::boxed_and_zeroed();
+            net.load_bytes(&plain.net().save_bytes()).unwrap();
+            snapshots.push(net);
+        }
+    }
+
+    let nets: Vec<&TestNet> = snapshots.iter().map(|net| &**net).collect();
+    let expected = average(&nets);
+    let diff = Diff::of(&*expected, swa.swa_net().unwrap(), 0);
+    assert!(diff.tensors().iter().all(|t| t.linf < 1e-6), "{diff}");
+    assert!(!Diff::of(&*expected, swa.net(), 0).is_identical());
+
+    let averaged = swa.swa_net().unwrap().fingerprint();
+    assert_eq!(swa.into_swa_net().fingerprint(), averaged);
+}
+
+#[test]
+fn ema() {
+    let dir = std::env::temp_dir().join(format!("goober-ema-{}", std::process::id()));
+    let manager = CheckpointManager::new(&dir, 1).unwrap();
+
+    let mut ema = trainer()
+        .with_batch_size(28)
+        .with_ema(0.5)
+        .with_checkpoints(manager, 3);
+    assert!(ema.ema_net().is_none());
+    ema.run(3).unwrap();
+
+    let mut plain = trainer().with_batch_size(28);
+    plain.run_epoch().unwrap();
+    let mut expected = average(&[plain.net()]);
+    for _ in 0..2 {
+        plain.run_epoch().unwrap();
+        lerp_in_place(&mut *expected, plain.net(), 0.5);
+    }
+
+    assert_eq!(ema.ema_net().unwrap().fingerprint(), expected.fingerprint());
+    assert_ne!(ema.net().fingerprint(), expected.fingerprint());
+
+    let mut saved = TestNet::boxed_and_zeroed();
+    saved.load(dir.join("ema.gbnn").to_str().unwrap()).unwrap();
+    assert_eq!(saved.fingerprint(), expected.fingerprint());
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[derive(FeedForwardNetwork)]
+pub struct Teacher {
+    l1: SparseConnected<ReLU, 8, 32>,
+    l2: DenseConnected<Tanh, 32, 1>,
+}
+
+#[test]
+fn distillation() {
+    let mut teacher = Trainer::new(Teacher::boxed_and_zeroed(), data())
+        .with_batch_size(8)
+        .with_seed(3);
+    for i in 0..8 {
+        *teacher.net_mut().l1.weights_row_mut(i) =
+            Vector::from_fn(|j| ((i * 5 + j) % 11) as f32 / 20.0 - 0.2);
+    }
+    teacher.run(20).unwrap();
+    let teacher = teacher.into_net();
+
+    let mut hard = trainer();
+    hard.run(2).unwrap();
+    let mut ignored = trainer().with_teacher(average(&[&*teacher]), 0.0);
+    ignored.run(2).unwrap();
+    assert_eq!(ignored.net().fingerprint(), hard.net().fingerprint());
+
+    let soft: Vec<_> = data()
+        .into_iter()
+        .map(|(input, _)| {
+            let out = teacher.out(&input);
+            (input, out)
+        })
+        .collect();
+
+    let mut student = trainer().with_teacher(teacher, 1.0);
+    let before = evaluate(student.net(), &soft, &Mse).loss;
+    student.run(50).unwrap();
+    let after = evaluate(student.net(), &soft, &Mse).loss;
+    assert!(after < before / 4.0, "{before} -> {after}");
+}
+
+#[test]
+fn prune_and_fine_tune() {
+    let mut trainer = trainer();
+    trainer.run(50).unwrap();
+
+    let prune = Prune::new(0.5).with_layer("l2", 0.0);
+    let mut pruned = average(&[trainer.net()]);
+    prune.apply(&mut *pruned);
+    let before = evaluate(&*pruned, &data(), &Mse).loss;
+
+    trainer.prune(&prune, 50).unwrap();
+    let after = evaluate(trainer.net(), &data(), &Mse).loss;
+    assert!(after < before / 10.0, "{before} -> {after}");
+
+    let zeros = (0..8)
+        .map(|i| trainer.net().l1.weights_row(i))
+        .chain([trainer.net().l2.weights_row(0)])
+        .flat_map(|row| (0..8).map(move |j| row[j]))
+        .filter(|&x| x == 0.0)
+        .count();
+    assert_eq!(zeros, 32);
+}
+
+#[test]
+fn lr_finder() {
+    let mut trainer = trainer();
+    let before = trainer.net().fingerprint();
+    let finder = LrFinder {
+        min_lr: 1e-5,
+        max_lr: 10.0,
+        steps: 100,
+        ..LrFinder::default()
+    };
+
+    let sweep = trainer.find_lr(&finder);
+    assert_eq!(trainer.net().fingerprint(), before);
+    assert_eq!(trainer.step(), 0);
+
+    let points = sweep.points();
+    assert_eq!(points.len(), 100);
+    assert_eq!(points[0].0, 1e-5);
+    assert!(points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+    let best = sweep.min_loss_lr().unwrap();
+    assert!(best > 1e-3 && best < 1.0, "{sweep}");
+    assert_eq!(sweep.suggested(), Some((best / 100.0, best / 10.0)));
+    assert!(sweep.steepest_lr().unwrap() <= best);
+    assert!(sweep.to_string().contains("suggested:"));
+
+    // same batches, so the same sweep
+    assert_eq!(trainer.find_lr(&finder), sweep);
+}
+
+#[test]
+fn mixup() {
+    let mut batch: Vec<_> = (0..64)
+        .map(|i| {
+            (
+                Vector::<3>::from_raw([i as f32; 3]),
+                Vector::from_raw([i as f32]),
+            )
+        })
+        .collect();
+    Mixup::new(0.4).augment(&mut batch, &mut Rand::with_seed(1));
+
+    for (input, target) in &batch {
+        // inputs and targets are blended by the same fraction
+        assert!((input[0] - target[0]).abs() < 1e-4);
+        assert_eq!(input[0], input[2]);
+        assert!((0.0..=63.0).contains(&target[0]));
+    }
+    let mixed = batch.iter().filter(|(_, target)| target[0].fract() != 0.0);
+    assert!(mixed.count() > 32);
+
+    let mut again: Vec<_> = (0..64)
+        .map(|i| {
+            (
+                Vector::<3>::from_raw([i as f32; 3]),
+                Vector::from_raw([i as f32]),
+            )
+        })
+        .collect();
+    Mixup::new(0.4).augment(&mut again, &mut Rand::with_seed(1));
+    assert_eq!(again, batch);
+}
+
+#[test]
+fn feature_dropout() {
+    let features: Vec<usize> = (0..1000).collect();
+    let mut batch = vec![(SparseVector::from_slice(&features), ())];
+    FeatureDropout::new(0.0).augment(&mut batch, &mut Rand::default());
+    assert_eq!(batch[0].0.len(), 1000);
+
+    FeatureDropout::new(0.25).augment(&mut batch, &mut Rand::default());
+    assert!((650..850).contains(&batch[0].0.len()));
+    assert!(batch[0].0.windows(2).all(|pair| pair[0] < pair[1]));
+
+    let mut sorted = SparseVector::sorted(4);
+    sorted.push(3);
+    let mut batch = vec![(sorted, ())];
+    FeatureDropout::new(0.0).augment(&mut batch, &mut Rand::default());
+    assert!(batch[0].0.is_sorted());
+    FeatureDropout::new(1.0).augment(&mut batch, &mut Rand::default());
+    assert!(batch[0].0.is_empty());
+
+    let mut weighted = WeightedSparseVector::with_capacity(2);
+    weighted.push(1, 0.5);
+    weighted.push(4, 2.0);
+    let mut batch = vec![(weighted.clone(), ())];
+    FeatureDropout::new(0.0).augment(&mut batch, &mut Rand::default());
+    assert_eq!(batch[0].0, weighted);
+}
+
+#[test]
+fn augmentation() {
+    let mut expected = Trainer::new(TestNet::boxed_and_zeroed(), data()).with_batch_size(8);
+    let batches = Rc::new(Cell::new(0));
+    let counted = Rc::clone(&batches);
+    let mut trainer = Trainer::new(TestNet::boxed_and_zeroed(), data())
+        .with_batch_size(8)
+        .with_augmentation(
+            move |batch: &mut [(SparseVector, Vector<1>)], _: &mut Rand| {
+                assert!(batch.len() <= 8);
+                counted.set(counted.get() + 1);
+            },
+        );
+    for _ in 0..3 {
+        assert_eq!(trainer.run_epoch().unwrap(), expected.run_epoch().unwrap());
+    }
+    assert_eq!(batches.get(), 12);
+    assert_eq!(trainer.net().fingerprint(), expected.net().fingerprint());
+
+    // without any features, only the biases are trained
+    let mut trainer = Trainer::new(initial(), data())
+        .with_batch_size(8)
+        .with_augmentation(FeatureDropout::new(1.0));
+    trainer.run(3).unwrap();
+    for i in 0..8 {
+        assert_eq!(trainer.net().l1.weights_row(i), initial().l1.weights_row(i));
+    }
+    assert_ne!(trainer.net().fingerprint(), initial().fingerprint());
+}
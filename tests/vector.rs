@@ -0,0 +1,71 @@
+use goober::{LengthMismatch, Vector};
+
+#[test]
+fn norms() {
+    let v = Vector::from_raw([3.0, -4.0, 0.0]);
+    assert_eq!(v.dot(&v), 25.0);
+    assert_eq!(v.l1_norm(), 7.0);
+    assert_eq!(v.l2_norm(), 5.0);
+    assert_eq!(Vector::<3>::zeroed().l2_norm(), 0.0);
+}
+
+#[test]
+fn element_wise() {
+    let u = Vector::from_raw([-2.0, 0.5, 3.0]);
+    let v = Vector::from_raw([1.0, -1.0, 4.0]);
+
+    assert_eq!(u.abs(), Vector::from_raw([2.0, 0.5, 3.0]));
+    assert_eq!(u.clamp(-1.0, 1.0), Vector::from_raw([-1.0, 0.5, 1.0]));
+    assert_eq!(u.min(v), Vector::from_raw([-2.0, -1.0, 3.0]));
+    assert_eq!(u.max(v), Vector::from_raw([1.0, 0.5, 4.0]));
+}
+
+#[test]
+fn lerp() {
+    let u = Vector::from_raw([0.0, 2.0]);
+    let v = Vector::from_raw([4.0, -2.0]);
+
+    assert_eq!(u.lerp(v, 0.0), u);
+    assert_eq!(u.lerp(v, 1.0), v);
+    assert_eq!(u.lerp(v, 0.25), Vector::from_raw([1.0, 1.0]));
+}
+
+#[test]
+fn format() {
+    let v = Vector::from_raw([1.0, -2.5, 10.0]);
+    assert_eq!(format!("{v:?}"), "Vector<3> [1.0, -2.5, 10.0]");
+    assert_eq!(format!("{v}"), "[ 1.0000, -2.5000, 10.0000]");
+    assert_eq!(format!("{v:.1}"), "[ 1.0, -2.5, 10.0]");
+
+    let long = Vector::<768>::from_fn(|i| i as f32);
+    assert_eq!(
+        format!("{long:?}"),
+        "Vector<768> [0.0, 1.0, 2.0, ..., 765.0, 766.0, 767.0]"
+    );
+    assert_eq!(format!("{long:.0}"), "[  0,   1,   2, ..., 765, 766, 767]");
+}
+
+#[test]
+fn as_ref() {
+    let mut v = Vector::from_raw([1.0, 2.0]);
+    assert_eq!(v.as_ref(), [1.0, 2.0]);
+    v.as_mut()[1] = 3.0;
+    assert_eq!(v, Vector::from_raw([1.0, 3.0]));
+}
+
+#[test]
+fn try_from() {
+    let v = Vector::<3>::try_from([1.0, 2.0, 3.0].as_slice()).unwrap();
+    assert_eq!(v, Vector::from_raw([1.0, 2.0, 3.0]));
+    assert_eq!(Vector::<3>::try_from(vec![1.0, 2.0, 3.0]), Ok(v));
+
+    let err = Vector::<3>::try_from(vec![1.0, 2.0]).unwrap_err();
+    assert_eq!(
+        err,
+        LengthMismatch {
+            expected: 3,
+            found: 2
+        }
+    );
+    assert_eq!(err.to_string(), "expected 3 elements, found 2");
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "zstd")]
+
+use std::io::{self, Read};
+
+use goober::zstd::{self, Decoder};
+
+/// Numbered lines of random numbers, then random bytes, then zeros,
+/// as compressed by the `zstd` tool into the files in `data`.
+fn text() -> Vec<u8> {
+    let mut state = 1u64;
+    let mut next = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        state
+    };
+
+    let mut text = Vec::new();
+    for i in 0..4000 {
+        text.extend(format!("{i} {}\n", (next() >> 33) % 1000).bytes());
+    }
+    text.extend((0..1000).map(|_| (next() >> 56) as u8));
+    text.extend([0; 300000]);
+    text
+}
+
+/// A frame holding `bytes` in a single uncompressed block.
+fn raw_frame(bytes: &[u8]) -> Vec<u8> {
+    let header = (bytes.len() << 3 | 1) as u32;
+    [
+        &[0x28, 0xB5, 0x2F, 0xFD, 0, 0],
+        &header.to_le_bytes()[..3],
+        bytes,
+    ]
+    .concat()
+}
+
+#[test]
+fn decompress() {
+    let text = text();
+    // compressed with `zstd -19`, with the size and checksum of the content
+    let best = include_bytes!("data/text.zst");
+    // compressed with `zstd -1 --no-check` from a pipe, with neither
+    let fast = include_bytes!("data/text-fast.zst");
+
+    assert!(zstd::is_compressed(best));
+    assert!(!zstd::is_compressed(&text));
+    assert!(zstd::decompress(best).unwrap() == text);
+    assert!(zstd::decompress(fast).unwrap() == text);
+
+    let mut decoder = Decoder::new(&fast[..]);
+    let mut out = Vec::new();
+    let mut buf = [0; 1000];
+    loop {
+        match decoder.read(&mut buf[..777]).unwrap() {
+            0 => break,
+            n => out.extend_from_slice(&buf[..n]),
+        }
+    }
+    assert!(out == text);
+}
+
+#[test]
+fn frames() {
+    let skippable = [0x5E, 0x2A, 0x4D, 0x18, 3, 0, 0, 0, 1, 2, 3];
+    let bytes = [&raw_frame(b"abc")[..], &skippable, &raw_frame(b"def")].concat();
+    assert_eq!(zstd::decompress(&bytes).unwrap(), b"abcdef");
+    assert_eq!(zstd::decompress(&[]).unwrap(), b"");
+
+    let best = include_bytes!("data/text.zst");
+    let twice = zstd::decompress(&[&best[..], best].concat()).unwrap();
+    assert!(twice == [text(), text()].concat());
+}
+
+#[test]
+fn errors() {
+    let error = |bytes: &[u8]| {
+        let err = zstd::decompress(bytes).unwrap_err();
+        (err.kind(), err.to_string())
+    };
+
+    let best = include_bytes!("data/text.zst");
+    let mut corrupt = best.to_vec();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        error(&corrupt),
+        (io::ErrorKind::InvalidData, "zstd: checksum mismatch".into())
+    );
+    assert_eq!(error(&best[..5000]).0, io::ErrorKind::UnexpectedEof);
+    assert_eq!(
+        error(b"goober"),
+        (io::ErrorKind::InvalidData, "zstd: not a zstd frame".into())
+    );
+
+    let mut reserved = raw_frame(b"abc");
+    reserved[6] |= 6;
+    let (kind, message) = error(&reserved);
+    assert_eq!(kind, io::ErrorKind::InvalidData);
+    assert!(message.starts_with("zstd: "), "{message}");
+}
+
+#[test]
+fn malformed() {
+    // every truncation and single bit flip of a frame is an error or
+    // some output, never a panic
+    let best = include_bytes!("data/text.zst");
+    for len in (1..best.len()).step_by(97) {
+        assert!(zstd::decompress(&best[..len]).is_err());
+    }
+    for (i, bit) in (0..best.len()).step_by(31).zip((0..8).cycle()) {
+        let mut flipped = best.to_vec();
+        flipped[i] ^= 1 << bit;
+        let _ = zstd::decompress(&flipped);
+    }
+}